@@ -0,0 +1,44 @@
+-- Per-user change sequence counter
+CREATE TABLE IF NOT EXISTS sync_sequences (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    last_seq BIGINT NOT NULL DEFAULT 0
+);
+
+-- Change log written on every create/update/delete
+CREATE TABLE IF NOT EXISTS sync_changes (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    seq BIGINT NOT NULL,
+    entity_type VARCHAR(32) NOT NULL,
+    entity_id UUID NOT NULL,
+    operation VARCHAR(16) NOT NULL,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, seq)
+);
+
+CREATE INDEX idx_sync_changes_entity ON sync_changes(user_id, entity_type, entity_id);
+
+-- Backfill existing rows so a first sync from an empty cursor sees everything
+WITH existing AS (
+    SELECT user_id, 'category' AS entity_type, id AS entity_id, created_at AS changed_at FROM categories
+    UNION ALL
+    SELECT user_id, 'tag', id, created_at FROM tags
+    UNION ALL
+    SELECT user_id, 'bookmark', id, updated_at FROM bookmarks
+    UNION ALL
+    SELECT b.user_id, 'bookmark_tags', b.id, b.updated_at
+    FROM bookmarks b
+    WHERE EXISTS (SELECT 1 FROM bookmark_tags bt WHERE bt.bookmark_id = b.id)
+    UNION ALL
+    SELECT user_id, 'note', id, updated_at FROM notes
+)
+INSERT INTO sync_changes (user_id, seq, entity_type, entity_id, operation, changed_at)
+SELECT user_id,
+       ROW_NUMBER() OVER (PARTITION BY user_id ORDER BY changed_at, entity_type, entity_id),
+       entity_type,
+       entity_id,
+       'upsert',
+       changed_at
+FROM existing;
+
+INSERT INTO sync_sequences (user_id, last_seq)
+SELECT user_id, MAX(seq) FROM sync_changes GROUP BY user_id;
@@ -0,0 +1,21 @@
+-- Deleted entities, kept until the retention window expires
+CREATE TABLE IF NOT EXISTS tombstones (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    entity_type VARCHAR(32) NOT NULL,
+    entity_id UUID NOT NULL,
+    deleted_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, entity_type, entity_id)
+);
+
+CREATE INDEX idx_tombstones_deleted_at ON tombstones(deleted_at);
+
+-- Highest change log sequence removed by garbage collection
+ALTER TABLE sync_sequences
+ADD COLUMN purged_seq BIGINT NOT NULL DEFAULT 0;
+
+INSERT INTO tombstones (user_id, entity_type, entity_id, deleted_at)
+SELECT user_id, entity_type, entity_id, MAX(changed_at)
+FROM sync_changes
+WHERE operation = 'delete'
+GROUP BY user_id, entity_type, entity_id
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,12 @@
+-- Monotonic per-row versions backing ETag / If-Match
+ALTER TABLE bookmarks
+ADD COLUMN version BIGINT NOT NULL DEFAULT 1;
+
+ALTER TABLE notes
+ADD COLUMN version BIGINT NOT NULL DEFAULT 1;
+
+ALTER TABLE tags
+ADD COLUMN version BIGINT NOT NULL DEFAULT 1;
+
+ALTER TABLE categories
+ADD COLUMN version BIGINT NOT NULL DEFAULT 1;
@@ -0,0 +1,29 @@
+-- Note content per version, used as the base for three-way merges
+CREATE TABLE IF NOT EXISTS note_revisions (
+    note_id UUID NOT NULL REFERENCES notes(id) ON DELETE CASCADE,
+    version BIGINT NOT NULL,
+    title VARCHAR(500) NOT NULL,
+    content TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (note_id, version)
+);
+
+INSERT INTO note_revisions (note_id, version, title, content, created_at)
+SELECT id, version, title, content, updated_at FROM notes;
+
+-- Concurrent edits that could not be merged automatically
+CREATE TABLE IF NOT EXISTS note_conflicts (
+    id UUID PRIMARY KEY,
+    note_id UUID NOT NULL REFERENCES notes(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    base_version BIGINT NOT NULL,
+    server_version BIGINT NOT NULL,
+    server_title VARCHAR(500) NOT NULL,
+    server_content TEXT NOT NULL,
+    client_title VARCHAR(500) NOT NULL,
+    client_content TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    resolved_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_note_conflicts_note_id ON note_conflicts(note_id);
@@ -0,0 +1,21 @@
+-- Announce every change log entry so connected clients can be pushed updates.
+-- Notifications are only delivered once the recording transaction commits.
+CREATE OR REPLACE FUNCTION notify_sync_change() RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM pg_notify(
+        'sync_changes',
+        json_build_object(
+            'user_id', NEW.user_id,
+            'seq', NEW.seq,
+            'entity_type', NEW.entity_type,
+            'id', NEW.entity_id,
+            'operation', NEW.operation
+        )::text
+    );
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER sync_changes_notify
+    AFTER INSERT ON sync_changes
+    FOR EACH ROW EXECUTE FUNCTION notify_sync_change();
@@ -0,0 +1,29 @@
+-- Current version of a synced entity, or NULL once it has been deleted
+CREATE OR REPLACE FUNCTION sync_entity_version(kind VARCHAR, entity UUID) RETURNS BIGINT AS $$
+    SELECT CASE kind
+        WHEN 'bookmark' THEN (SELECT version FROM bookmarks WHERE id = entity)
+        WHEN 'bookmark_tags' THEN (SELECT version FROM bookmarks WHERE id = entity)
+        WHEN 'note' THEN (SELECT version FROM notes WHERE id = entity)
+        WHEN 'tag' THEN (SELECT version FROM tags WHERE id = entity)
+        WHEN 'category' THEN (SELECT version FROM categories WHERE id = entity)
+    END;
+$$ LANGUAGE sql STABLE;
+
+CREATE OR REPLACE FUNCTION notify_sync_change() RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM pg_notify(
+        'sync_changes',
+        json_build_object(
+            'user_id', NEW.user_id,
+            'seq', NEW.seq,
+            'entity_type', NEW.entity_type,
+            'id', NEW.entity_id,
+            'operation', NEW.operation,
+            'version', CASE WHEN NEW.operation = 'upsert'
+                THEN sync_entity_version(NEW.entity_type, NEW.entity_id)
+            END
+        )::text
+    );
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
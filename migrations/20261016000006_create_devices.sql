@@ -0,0 +1,15 @@
+-- Devices a user has signed in from; every issued token belongs to one
+CREATE TABLE IF NOT EXISTS devices (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    platform VARCHAR(50),
+    client_version VARCHAR(50),
+    -- Highest change log sequence the device has confirmed applying
+    last_sync_seq BIGINT,
+    last_seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    revoked_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_devices_user_id ON devices(user_id);
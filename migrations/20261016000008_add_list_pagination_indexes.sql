@@ -0,0 +1,5 @@
+-- Keyset pagination over the default list orderings
+CREATE INDEX idx_bookmarks_user_created ON bookmarks(user_id, created_at, id);
+CREATE INDEX idx_bookmarks_user_updated ON bookmarks(user_id, updated_at, id);
+CREATE INDEX idx_notes_user_updated ON notes(user_id, updated_at, id);
+CREATE INDEX idx_notes_user_created ON notes(user_id, created_at, id);
@@ -0,0 +1,21 @@
+-- Full-text search over bookmarks and notes; titles weigh more than bodies
+ALTER TABLE bookmarks
+ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+    setweight(to_tsvector('english', COALESCE(title, '')), 'A') ||
+    setweight(to_tsvector('english', COALESCE(description, '')), 'B') ||
+    setweight(to_tsvector('simple', COALESCE(url, '')), 'C')
+) STORED;
+
+ALTER TABLE notes
+ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+    setweight(to_tsvector('english', COALESCE(title, '')), 'A') ||
+    setweight(to_tsvector('english', COALESCE(content, '')), 'B')
+) STORED;
+
+CREATE INDEX idx_bookmarks_search_vector ON bookmarks USING GIN(search_vector);
+CREATE INDEX idx_notes_search_vector ON notes USING GIN(search_vector);
+
+-- Escapes user text before ts_headline wraps matches in <mark> tags
+CREATE OR REPLACE FUNCTION search_escape_html(input TEXT) RETURNS TEXT AS $$
+    SELECT replace(replace(replace(input, '&', '&amp;'), '<', '&lt;'), '>', '&gt;');
+$$ LANGUAGE sql IMMUTABLE;
@@ -0,0 +1,4 @@
+-- Lowercased host of a URL, skipping the scheme and any userinfo; NULL if there is none
+CREATE OR REPLACE FUNCTION url_host(url TEXT) RETURNS TEXT AS $$
+    SELECT lower(substring(url from '^[A-Za-z][A-Za-z0-9+.-]*://(?:[^@/?#]*@)?([^/:?#]+)'));
+$$ LANGUAGE sql IMMUTABLE;
@@ -0,0 +1,26 @@
+-- Named search queries, evaluated on demand as virtual folders
+CREATE TABLE IF NOT EXISTS saved_searches (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    query TEXT NOT NULL,
+    sort VARCHAR(20) NOT NULL DEFAULT 'updated_at',
+    sort_order VARCHAR(4),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    version BIGINT NOT NULL DEFAULT 1,
+    UNIQUE (user_id, name)
+);
+
+CREATE INDEX idx_saved_searches_user_created ON saved_searches(user_id, created_at, id);
+
+CREATE OR REPLACE FUNCTION sync_entity_version(kind VARCHAR, entity UUID) RETURNS BIGINT AS $$
+    SELECT CASE kind
+        WHEN 'bookmark' THEN (SELECT version FROM bookmarks WHERE id = entity)
+        WHEN 'bookmark_tags' THEN (SELECT version FROM bookmarks WHERE id = entity)
+        WHEN 'note' THEN (SELECT version FROM notes WHERE id = entity)
+        WHEN 'tag' THEN (SELECT version FROM tags WHERE id = entity)
+        WHEN 'category' THEN (SELECT version FROM categories WHERE id = entity)
+        WHEN 'saved_search' THEN (SELECT version FROM saved_searches WHERE id = entity)
+    END;
+$$ LANGUAGE sql STABLE;
@@ -0,0 +1,6 @@
+-- Typo-tolerant matching for fuzzy search and autocomplete
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX idx_bookmarks_title_trgm ON bookmarks USING GIN (title gin_trgm_ops);
+CREATE INDEX idx_bookmarks_url_trgm ON bookmarks USING GIN (url gin_trgm_ops);
+CREATE INDEX idx_notes_title_trgm ON notes USING GIN (title gin_trgm_ops);
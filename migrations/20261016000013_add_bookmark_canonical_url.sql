@@ -0,0 +1,6 @@
+-- Normalized URL for duplicate detection; filled in by the application,
+-- which also backfills existing rows at startup
+ALTER TABLE bookmarks
+ADD COLUMN canonical_url TEXT;
+
+CREATE INDEX idx_bookmarks_user_canonical_url ON bookmarks(user_id, canonical_url);
@@ -0,0 +1,14 @@
+-- Page metadata fetched in the background after a bookmark is saved
+ALTER TABLE bookmarks
+ADD COLUMN preview_title TEXT,
+ADD COLUMN preview_description TEXT,
+ADD COLUMN preview_image TEXT,
+ADD COLUMN favicon TEXT,
+ADD COLUMN preview_status VARCHAR(20) NOT NULL DEFAULT 'pending',
+ADD COLUMN preview_fetched_at TIMESTAMPTZ,
+ADD COLUMN preview_attempts INTEGER NOT NULL DEFAULT 0,
+-- Also serves as the lease while a worker is fetching the page
+ADD COLUMN preview_next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+CREATE INDEX idx_bookmarks_preview_due ON bookmarks(preview_next_attempt_at)
+WHERE preview_status = 'pending';
@@ -0,0 +1,25 @@
+-- Offline copy of each bookmarked page, taken by the preview worker
+CREATE TABLE bookmark_archives (
+    bookmark_id UUID PRIMARY KEY REFERENCES bookmarks(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- Final URL after redirects
+    url TEXT NOT NULL,
+    content_type VARCHAR(255) NOT NULL,
+    raw_content BYTEA NOT NULL,
+    -- Hex-encoded SHA-256 of raw_content
+    content_hash CHAR(64) NOT NULL,
+    truncated BOOLEAN NOT NULL DEFAULT FALSE,
+    title TEXT,
+    -- Main text of the page
+    text TEXT NOT NULL,
+    word_count INTEGER NOT NULL,
+    archived_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    -- Archived text ranks below everything stored on the bookmark itself;
+    -- capped because a tsvector cannot exceed 1MB
+    search_vector TSVECTOR GENERATED ALWAYS AS (
+        setweight(to_tsvector('english', left(text, 100000)), 'D')
+    ) STORED
+);
+
+CREATE INDEX idx_bookmark_archives_user_id ON bookmark_archives(user_id);
+CREATE INDEX idx_bookmark_archives_search_vector ON bookmark_archives USING GIN(search_vector);
@@ -0,0 +1,51 @@
+-- Content-addressed blobs; the bytes live in the configured blob store
+CREATE TABLE blobs (
+    -- Hex-encoded SHA-256 of the content
+    hash CHAR(64) PRIMARY KEY,
+    size BIGINT NOT NULL,
+    -- Sum of user_blobs.ref_count, maintained by trigger
+    ref_count INTEGER NOT NULL DEFAULT 0 CHECK (ref_count >= 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    -- Set while unreferenced; the blob is removed once this is old enough
+    released_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX idx_blobs_released_at ON blobs(released_at) WHERE ref_count = 0;
+
+-- References per user, for quota accounting
+CREATE TABLE user_blobs (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    hash CHAR(64) NOT NULL REFERENCES blobs(hash),
+    ref_count INTEGER NOT NULL CHECK (ref_count > 0),
+    PRIMARY KEY (user_id, hash)
+);
+
+CREATE INDEX idx_user_blobs_hash ON user_blobs(hash);
+
+CREATE OR REPLACE FUNCTION count_blob_references() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP IN ('UPDATE', 'DELETE') THEN
+        UPDATE blobs
+        SET ref_count = ref_count - OLD.ref_count,
+            released_at = CASE WHEN ref_count = OLD.ref_count THEN NOW() END
+        WHERE hash = OLD.hash;
+    END IF;
+
+    IF TG_OP IN ('INSERT', 'UPDATE') THEN
+        UPDATE blobs
+        SET ref_count = ref_count + NEW.ref_count, released_at = NULL
+        WHERE hash = NEW.hash;
+    END IF;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER user_blobs_count_references
+    AFTER INSERT OR UPDATE OR DELETE ON user_blobs
+    FOR EACH ROW EXECUTE FUNCTION count_blob_references();
+
+-- Archived pages move to the blob store, addressed by content_hash;
+-- archives taken before keep their page here until moved at startup
+ALTER TABLE bookmark_archives
+ALTER COLUMN raw_content DROP NOT NULL;
@@ -0,0 +1,19 @@
+-- Opaque refresh tokens; each refresh uses one up and issues its successor in the same family
+CREATE TABLE IF NOT EXISTS refresh_tokens (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    device_id UUID NOT NULL REFERENCES devices(id) ON DELETE CASCADE,
+    -- Shared by every token descended from the same sign-in
+    family_id UUID NOT NULL,
+    -- SHA-256 of the token; the token itself is never stored
+    token_hash CHAR(64) NOT NULL UNIQUE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    -- Set when the token is exchanged; presenting it again revokes the family
+    used_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_refresh_tokens_family_id ON refresh_tokens(family_id);
+CREATE INDEX idx_refresh_tokens_device_id ON refresh_tokens(device_id);
+CREATE INDEX idx_refresh_tokens_expires_at ON refresh_tokens(expires_at);
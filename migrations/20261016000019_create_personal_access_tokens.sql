@@ -0,0 +1,15 @@
+-- Long-lived tokens users create for scripts and integrations, limited to their scopes
+CREATE TABLE IF NOT EXISTS personal_access_tokens (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    -- SHA-256 of the token; the token itself is only shown once, on creation
+    token_hash CHAR(64) NOT NULL UNIQUE,
+    scopes VARCHAR(50)[] NOT NULL,
+    -- NULL for tokens that never expire
+    expires_at TIMESTAMPTZ,
+    last_used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_personal_access_tokens_user_id ON personal_access_tokens(user_id);
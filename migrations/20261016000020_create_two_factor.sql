@@ -0,0 +1,36 @@
+-- TOTP second factor; a row without confirmed_at is an enrollment awaiting its first code
+CREATE TABLE IF NOT EXISTS two_factor_credentials (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    secret BYTEA NOT NULL,
+    -- Time step of the last accepted code; it and earlier codes cannot be replayed
+    last_used_step BIGINT,
+    confirmed_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Single-use codes for signing in without the authenticator
+CREATE TABLE IF NOT EXISTS recovery_codes (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- SHA-256 of the normalized code
+    code_hash CHAR(64) NOT NULL,
+    used_at TIMESTAMPTZ,
+    UNIQUE (user_id, code_hash)
+);
+
+-- Logins whose password was verified, waiting for the second factor
+CREATE TABLE IF NOT EXISTS login_challenges (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash CHAR(64) NOT NULL UNIQUE,
+    -- Device to register once the challenge is passed
+    device_name VARCHAR(100),
+    device_platform VARCHAR(50),
+    device_client_version VARCHAR(50),
+    -- Wrong codes entered so far; the challenge is dropped after too many
+    attempts INT NOT NULL DEFAULT 0,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_login_challenges_expires_at ON login_challenges(expires_at);
@@ -0,0 +1,3 @@
+-- Bookmarks the user starred, matched by the `is:favorite` search flag
+ALTER TABLE bookmarks
+ADD COLUMN is_favorite BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,5 @@
+-- Wrong second-factor codes across all login challenges, so fresh challenges
+-- don't give unlimited guesses
+ALTER TABLE two_factor_credentials
+ADD COLUMN failed_attempts INT NOT NULL DEFAULT 0,
+ADD COLUMN locked_until TIMESTAMPTZ;
@@ -0,0 +1,2 @@
+-- Device a two-factor login signs in on again, when the client named one
+ALTER TABLE login_challenges ADD COLUMN device_id UUID;
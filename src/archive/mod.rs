@@ -0,0 +1,11 @@
+//! Offline copies of bookmarked pages.
+//!
+//! The raw page is kept as fetched, along with its main text as picked out by
+//! a readability-style pass that drops navigation, sidebars and comments.
+
+mod readable;
+
+#[cfg(test)]
+mod readable_tests;
+
+pub use readable::{Readable, readable};
@@ -0,0 +1,310 @@
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use scraper::{ElementRef, Html, Node, Selector};
+
+static TITLE: LazyLock<Selector> = LazyLock::new(|| selector("title"));
+static BODY: LazyLock<Selector> = LazyLock::new(|| selector("body"));
+static PARAGRAPHS: LazyLock<Selector> = LazyLock::new(|| selector("p, pre, td, blockquote"));
+static LINKS: LazyLock<Selector> = LazyLock::new(|| selector("a"));
+
+fn selector(css: &str) -> Selector {
+    Selector::parse(css).expect("valid selector")
+}
+
+/// Paragraphs shorter than this don't count towards their container's score.
+const MIN_PARAGRAPH_CHARS: usize = 25;
+
+/// Elements that never hold article text.
+const SKIPPED_TAGS: &[&str] = &[
+    "script", "style", "noscript", "template", "svg", "canvas", "iframe", "object", "embed",
+    "form", "button", "input", "select", "textarea", "nav", "header", "footer", "aside",
+];
+
+/// Elements whose text starts a new paragraph.
+const BLOCK_TAGS: &[&str] = &[
+    "address",
+    "article",
+    "blockquote",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "li",
+    "main",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "td",
+    "th",
+    "tr",
+    "ul",
+];
+
+/// Class and id fragments of page furniture.
+const UNLIKELY: &[&str] = &[
+    "banner",
+    "breadcrumb",
+    "comment",
+    "cookie",
+    "disqus",
+    "footer",
+    "header",
+    "menu",
+    "modal",
+    "nav",
+    "pager",
+    "pagination",
+    "popup",
+    "promo",
+    "related",
+    "share",
+    "sidebar",
+    "social",
+    "sponsor",
+    "subscribe",
+];
+
+/// Class and id fragments that keep an element even if it also looks unlikely.
+const LIKELY: &[&str] = &[
+    "article", "body", "content", "entry", "main", "post", "story", "text",
+];
+
+/// Main text of a page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Readable {
+    pub title: Option<String>,
+    /// Paragraphs separated by blank lines; list items start with `- `
+    pub text: String,
+}
+
+/// Picks out the main text of an HTML page.
+///
+/// Every paragraph scores points for its parent and half as many for its
+/// grandparent, by length and number of commas; the best scoring container,
+/// discounted by how much of its text is links, is taken to be the article.
+pub fn readable(html: &str) -> Readable {
+    let document = Html::parse_document(html);
+
+    let title = document
+        .select(&TITLE)
+        .next()
+        .map(|title| collapse(&title.text().collect::<String>()))
+        .filter(|title| !title.is_empty());
+
+    let root = main_content(&document)
+        .or_else(|| document.select(&BODY).next())
+        .unwrap_or_else(|| document.root_element());
+
+    let mut writer = TextWriter::default();
+    writer.walk(root);
+
+    Readable {
+        title,
+        text: writer.finish(),
+    }
+}
+
+fn main_content(document: &Html) -> Option<ElementRef<'_>> {
+    let mut scores = HashMap::new();
+
+    for paragraph in document.select(&PARAGRAPHS) {
+        if paragraph
+            .ancestors()
+            .filter_map(ElementRef::wrap)
+            .any(is_skipped)
+        {
+            continue;
+        }
+
+        let text = collapse(&paragraph.text().collect::<String>());
+        let length = text.chars().count();
+        if length < MIN_PARAGRAPH_CHARS {
+            continue;
+        }
+        let score = 1.0 + text.matches(',').count() as f64 + (length as f64 / 100.0).min(3.0);
+
+        let mut ancestors = paragraph.ancestors().filter_map(ElementRef::wrap);
+        for share in [1.0, 0.5] {
+            let Some(ancestor) = ancestors.next() else {
+                break;
+            };
+            scores
+                .entry(ancestor.id())
+                .or_insert_with(|| (ancestor, initial_score(ancestor)))
+                .1 += score * share;
+        }
+    }
+
+    scores
+        .into_values()
+        .map(|(element, score)| (element, score * (1.0 - link_density(element))))
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(element, _)| element)
+}
+
+fn initial_score(element: ElementRef) -> f64 {
+    let tag_score = match element.value().name() {
+        "article" | "main" => 10.0,
+        "div" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+
+    let names = class_and_id(element);
+    let class_score = if LIKELY.iter().any(|likely| names.contains(likely)) {
+        25.0
+    } else if UNLIKELY.iter().any(|unlikely| names.contains(unlikely)) {
+        -25.0
+    } else {
+        0.0
+    };
+
+    tag_score + class_score
+}
+
+/// Share of an element's text that sits inside links.
+fn link_density(element: ElementRef) -> f64 {
+    let total = text_length(element);
+    if total == 0 {
+        return 0.0;
+    }
+
+    let linked: usize = element.select(&LINKS).map(text_length).sum();
+    linked as f64 / total as f64
+}
+
+fn text_length(element: ElementRef) -> usize {
+    element.text().map(|text| text.trim().chars().count()).sum()
+}
+
+/// Script, navigation and furniture such as sidebars, hidden elements included.
+fn is_skipped(element: ElementRef) -> bool {
+    let value = element.value();
+    let name = value.name();
+
+    if SKIPPED_TAGS.contains(&name)
+        || value.attr("hidden").is_some()
+        || value.attr("aria-hidden") == Some("true")
+    {
+        return true;
+    }
+    if matches!(name, "html" | "body" | "article" | "main") {
+        return false;
+    }
+
+    let names = class_and_id(element);
+    UNLIKELY.iter().any(|unlikely| names.contains(unlikely))
+        && !LIKELY.iter().any(|likely| names.contains(likely))
+}
+
+fn class_and_id(element: ElementRef) -> String {
+    let value = element.value();
+    format!(
+        "{} {}",
+        value.attr("class").unwrap_or_default(),
+        value.attr("id").unwrap_or_default()
+    )
+    .to_ascii_lowercase()
+}
+
+fn collapse(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Flattens an element tree into paragraphs of plain text.
+#[derive(Default)]
+struct TextWriter {
+    paragraphs: Vec<String>,
+    current: String,
+    prefix: &'static str,
+}
+
+impl TextWriter {
+    fn walk(&mut self, element: ElementRef) {
+        if is_skipped(element) {
+            return;
+        }
+
+        let name = element.value().name();
+        match name {
+            "br" => {
+                self.current.push('\n');
+                return;
+            }
+            "pre" => {
+                self.flush();
+                let text: String = element.text().collect();
+                let text = text.trim_matches('\n').trim_end();
+                if !text.is_empty() {
+                    self.paragraphs.push(text.to_string());
+                }
+                return;
+            }
+            _ => {}
+        }
+
+        let is_block = BLOCK_TAGS.contains(&name);
+        if is_block {
+            self.flush();
+        }
+        if name == "li" {
+            self.prefix = "- ";
+        }
+
+        for child in element.children() {
+            match child.value() {
+                Node::Text(text) => {
+                    // Line breaks in the source are just whitespace; only <br> ends a line
+                    self.current.push_str(&text.replace(['\n', '\r'], " "))
+                }
+                Node::Element(_) => {
+                    if let Some(child) = ElementRef::wrap(child) {
+                        self.walk(child);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if is_block {
+            self.flush();
+        }
+    }
+
+    /// Ends the current paragraph, collapsing whitespace within each line.
+    fn flush(&mut self) {
+        let lines: Vec<String> = self
+            .current
+            .lines()
+            .map(collapse)
+            .filter(|line| !line.is_empty())
+            .collect();
+
+        if !lines.is_empty() {
+            self.paragraphs
+                .push(format!("{}{}", self.prefix, lines.join("\n")));
+        }
+
+        self.current.clear();
+        self.prefix = "";
+    }
+
+    fn finish(mut self) -> String {
+        self.flush();
+        self.paragraphs.join("\n\n")
+    }
+}
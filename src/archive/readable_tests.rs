@@ -0,0 +1,91 @@
+#[cfg(test)]
+mod tests {
+    use crate::archive::readable;
+
+    const ARTICLE: &str = r#"<html>
+<head><title>  Understanding   Ownership </title><script>var tracking = 1;</script></head>
+<body>
+  <nav class="site-nav"><a href="/">Home</a> <a href="/blog">Blog</a> <a href="/about">About</a></nav>
+  <div class="sidebar">
+    <p>Subscribe to our newsletter, get updates, offers, and more great things every week.</p>
+  </div>
+  <div class="post-content">
+    <h1>Understanding Ownership</h1>
+    <p>Ownership is Rust's most unique feature, and it enables memory safety
+       without needing a garbage collector.</p>
+    <p>Each value in Rust has an owner, there can only be one owner at a time,
+       and when the owner goes out of scope, the value will be dropped.</p>
+    <ul><li>Moves</li><li>Borrows</li></ul>
+    <pre>let s = String::from("hello");
+let t = s;</pre>
+    <p>First line<br>second line</p>
+    <p hidden>This paragraph is hidden from readers, so it should not be archived at all.</p>
+  </div>
+  <div id="comments">
+    <p>Great post, thanks for writing it, really helped me understand the borrow checker!</p>
+  </div>
+  <footer><p>Copyright 2026, Example Blog, all rights reserved, contact us for details.</p></footer>
+</body>
+</html>"#;
+
+    #[test]
+    fn test_extracts_main_article_text() {
+        let page = readable(ARTICLE);
+
+        assert_eq!(page.title.as_deref(), Some("Understanding Ownership"));
+        assert_eq!(
+            page.text,
+            "Understanding Ownership\n\n\
+             Ownership is Rust's most unique feature, and it enables memory safety without needing a garbage collector.\n\n\
+             Each value in Rust has an owner, there can only be one owner at a time, and when the owner goes out of scope, the value will be dropped.\n\n\
+             - Moves\n\n\
+             - Borrows\n\n\
+             let s = String::from(\"hello\");\nlet t = s;\n\n\
+             First line\nsecond line"
+        );
+    }
+
+    #[test]
+    fn test_drops_navigation_comments_and_scripts() {
+        let text = readable(ARTICLE).text;
+
+        for furniture in [
+            "Home",
+            "newsletter",
+            "Great post",
+            "Copyright",
+            "tracking",
+            "hidden",
+        ] {
+            assert!(!text.contains(furniture), "{furniture} should be dropped");
+        }
+    }
+
+    #[test]
+    fn test_prefers_prose_over_link_lists() {
+        let html = r#"<body>
+            <div class="links">
+                <p><a href="/1">A long list of links to other articles, one, two, three</a></p>
+                <p><a href="/2">Another long link to yet more articles, four, five, six</a></p>
+            </div>
+            <div>
+                <p>Plain prose about the topic of the page, with enough words to count.</p>
+            </div>
+        </body>"#;
+
+        let text = readable(html).text;
+
+        assert_eq!(
+            text,
+            "Plain prose about the topic of the page, with enough words to count."
+        );
+    }
+
+    #[test]
+    fn test_falls_back_to_body_without_paragraphs() {
+        let page = readable("<html><body><div>Short</div><span>page</span></body></html>");
+
+        assert_eq!(page.title, None);
+        assert_eq!(page.text, "Short\n\npage");
+    }
+}
@@ -1,50 +1,135 @@
+use std::sync::Arc;
+
 use chrono::{Duration, Utc};
-use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, decode_header, encode};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use super::{JwkSet, JwtKeys, RevocationCache};
 use crate::error::{AppError, Result};
 
 #[derive(Clone)]
 pub struct JwtManager {
-    encoding_key: EncodingKey,
-    decoding_key: DecodingKey,
-    expiration_hours: i64,
+    keys: Keys,
+    expiration: Duration,
+    refresh_expiration: Duration,
+    revocations: RevocationCache,
+}
+
+#[derive(Clone)]
+enum Keys {
+    /// HS256 with a shared secret
+    Secret {
+        encoding: EncodingKey,
+        decoding: DecodingKey,
+    },
+    /// EdDSA or RS256, with the key named by each token's `kid`
+    Asymmetric(Arc<JwtKeys>),
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: Uuid,
     pub email: String,
+    /// Device the token was issued to; absent on tokens issued before devices existed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub did: Option<Uuid>,
+    /// Unique token id, by which it can be revoked
+    pub jti: Uuid,
     pub exp: i64,
     pub iat: i64,
 }
 
 impl JwtManager {
-    pub fn new(secret: &str, expiration_hours: i64) -> Self {
+    /// Signs with a shared HMAC secret; only holders of the secret can verify tokens.
+    pub fn new(secret: &str, expiration_minutes: i64, refresh_expiration_days: i64) -> Self {
+        let keys = Keys::Secret {
+            encoding: EncodingKey::from_secret(secret.as_bytes()),
+            decoding: DecodingKey::from_secret(secret.as_bytes()),
+        };
+
+        Self::with_keys(keys, expiration_minutes, refresh_expiration_days)
+    }
+
+    /// Signs with private keys whose public halves are published as a JWKS.
+    pub fn from_keys(keys: JwtKeys, expiration_minutes: i64, refresh_expiration_days: i64) -> Self {
+        let keys = Keys::Asymmetric(Arc::new(keys));
+
+        Self::with_keys(keys, expiration_minutes, refresh_expiration_days)
+    }
+
+    fn with_keys(keys: Keys, expiration_minutes: i64, refresh_expiration_days: i64) -> Self {
         Self {
-            encoding_key: EncodingKey::from_secret(secret.as_bytes()),
-            decoding_key: DecodingKey::from_secret(secret.as_bytes()),
-            expiration_hours,
+            keys,
+            expiration: Duration::minutes(expiration_minutes),
+            refresh_expiration: Duration::days(refresh_expiration_days),
+            revocations: RevocationCache::default(),
         }
     }
 
-    pub fn generate_token(&self, user_id: Uuid, email: &str) -> Result<String> {
+    /// Seconds an access token stays valid.
+    pub fn expires_in(&self) -> i64 {
+        self.expiration.num_seconds()
+    }
+
+    /// How long a refresh token stays valid if unused.
+    pub fn refresh_expiration(&self) -> Duration {
+        self.refresh_expiration
+    }
+
+    /// Revocation checks of tokens this manager issued, shared by its clones.
+    pub fn revocations(&self) -> &RevocationCache {
+        &self.revocations
+    }
+
+    /// Public keys tokens can be verified with; empty when signing with a shared secret.
+    pub fn jwks(&self) -> JwkSet {
+        match &self.keys {
+            Keys::Secret { .. } => JwkSet { keys: Vec::new() },
+            Keys::Asymmetric(keys) => keys.jwks(),
+        }
+    }
+
+    pub fn generate_token(&self, user_id: Uuid, email: &str, device_id: Uuid) -> Result<String> {
         let now = Utc::now();
-        let exp = now + Duration::hours(self.expiration_hours);
+        let exp = now + self.expiration;
 
         let claims = Claims {
             sub: user_id,
             email: email.to_string(),
+            did: Some(device_id),
+            jti: Uuid::new_v4(),
             exp: exp.timestamp(),
             iat: now.timestamp(),
         };
 
-        encode(&Header::default(), &claims, &self.encoding_key).map_err(AppError::from)
+        match &self.keys {
+            Keys::Secret { encoding, .. } => encode(&Header::default(), &claims, encoding),
+            Keys::Asymmetric(keys) => {
+                let key = keys.signing_key();
+                let mut header = Header::new(key.algorithm);
+                header.kid = Some(key.kid.clone());
+                encode(&header, &claims, &key.encoding)
+            }
+        }
+        .map_err(AppError::from)
     }
 
     pub fn verify_token(&self, token: &str) -> Result<Claims> {
-        decode::<Claims>(token, &self.decoding_key, &Validation::default())
+        let (decoding, validation) = match &self.keys {
+            Keys::Secret { decoding, .. } => (decoding, Validation::default()),
+            Keys::Asymmetric(keys) => {
+                // Only the key named in the header is tried, with its own algorithm
+                let key = decode_header(token)?
+                    .kid
+                    .and_then(|kid| keys.get(&kid))
+                    .ok_or_else(|| jsonwebtoken::errors::Error::from(ErrorKind::InvalidToken))?;
+                (&key.decoding, Validation::new(key.algorithm))
+            }
+        };
+
+        decode::<Claims>(token, decoding, &validation)
             .map(|data| data.claims)
             .map_err(AppError::from)
     }
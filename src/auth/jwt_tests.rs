@@ -5,11 +5,11 @@ mod tests {
 
     #[test]
     fn test_jwt_token_generation_and_verification() {
-        let jwt = JwtManager::new("test-secret-key", 24);
+        let jwt = JwtManager::new("test-secret-key", 15, 30);
         let user_id = Uuid::new_v4();
         let email = "test@example.com";
 
-        let token = jwt.generate_token(user_id, email).unwrap();
+        let token = jwt.generate_token(user_id, email, Uuid::new_v4()).unwrap();
         assert!(!token.is_empty());
 
         let claims = jwt.verify_token(&token).unwrap();
@@ -19,11 +19,13 @@ mod tests {
 
     #[test]
     fn test_jwt_token_with_invalid_secret() {
-        let jwt1 = JwtManager::new("secret-one", 24);
-        let jwt2 = JwtManager::new("secret-two", 24);
+        let jwt1 = JwtManager::new("secret-one", 15, 30);
+        let jwt2 = JwtManager::new("secret-two", 15, 30);
 
         let user_id = Uuid::new_v4();
-        let token = jwt1.generate_token(user_id, "test@example.com").unwrap();
+        let token = jwt1
+            .generate_token(user_id, "test@example.com", Uuid::new_v4())
+            .unwrap();
 
         let result = jwt2.verify_token(&token);
         assert!(result.is_err());
@@ -31,15 +33,48 @@ mod tests {
 
     #[test]
     fn test_jwt_claims_contain_correct_data() {
-        let jwt = JwtManager::new("test-secret", 48);
+        let jwt = JwtManager::new("test-secret", 60, 30);
         let user_id = Uuid::new_v4();
         let email = "user@domain.com";
 
-        let token = jwt.generate_token(user_id, email).unwrap();
+        let token = jwt.generate_token(user_id, email, Uuid::new_v4()).unwrap();
         let claims = jwt.verify_token(&token).unwrap();
 
         assert_eq!(claims.sub, user_id);
         assert_eq!(claims.email, email);
-        assert!(claims.exp > claims.iat);
+        assert_eq!(claims.exp - claims.iat, 60 * 60);
+        assert_eq!(jwt.expires_in(), 60 * 60);
+    }
+
+    #[test]
+    fn test_jwt_claims_carry_device_id() {
+        let jwt = JwtManager::new("test-secret", 15, 30);
+        let device_id = Uuid::new_v4();
+
+        let token = jwt
+            .generate_token(Uuid::new_v4(), "user@domain.com", device_id)
+            .unwrap();
+        let claims = jwt.verify_token(&token).unwrap();
+
+        assert_eq!(claims.did, Some(device_id));
+    }
+
+    #[test]
+    fn test_jwt_tokens_get_unique_ids() {
+        let jwt = JwtManager::new("test-secret", 15, 30);
+        let user_id = Uuid::new_v4();
+        let device_id = Uuid::new_v4();
+
+        let first = jwt
+            .generate_token(user_id, "user@domain.com", device_id)
+            .unwrap();
+        let second = jwt
+            .generate_token(user_id, "user@domain.com", device_id)
+            .unwrap();
+
+        assert_ne!(
+            jwt.verify_token(&first).unwrap().jti,
+            jwt.verify_token(&second).unwrap().jti
+        );
     }
 }
@@ -0,0 +1,236 @@
+use std::path::{Path, PathBuf};
+
+use base64::{
+    Engine,
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey};
+use ring::rsa::PublicKeyComponents;
+use ring::signature::{Ed25519KeyPair, KeyPair, RsaKeyPair};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Debug, thiserror::Error)]
+pub enum KeyError {
+    #[error("failed to read JWT keys from {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("{0} is not a PEM-encoded private key")]
+    InvalidPem(PathBuf),
+
+    #[error("{0} is neither an Ed25519 nor an RSA private key")]
+    UnsupportedKey(PathBuf),
+
+    #[error("no JWT keys (*.pem) found in {0}")]
+    NoKeys(PathBuf),
+
+    #[error("JWT signing key '{0}' not found")]
+    UnknownSigningKey(String),
+
+    #[error("several JWT keys found; JWT_SIGNING_KID must name the one to sign with")]
+    AmbiguousSigningKey,
+}
+
+/// Public key in JSON Web Key form, as published in the JWKS.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct Jwk {
+    /// `OKP` for Ed25519 keys, `RSA` for RSA keys
+    pub kty: String,
+    pub kid: String,
+    pub alg: String,
+    #[serde(rename = "use")]
+    pub use_: String,
+    /// Curve of an `OKP` key, always `Ed25519`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crv: Option<String>,
+    /// Public key of an `OKP` key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x: Option<String>,
+    /// Modulus of an `RSA` key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<String>,
+    /// Exponent of an `RSA` key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub e: Option<String>,
+}
+
+/// Public keys tokens may be verified with.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct JwkSet {
+    pub keys: Vec<Jwk>,
+}
+
+/// A private key, named by the `kid` header of the tokens it signs.
+#[derive(Clone)]
+pub(super) struct JwtKey {
+    pub kid: String,
+    pub algorithm: Algorithm,
+    pub encoding: EncodingKey,
+    pub decoding: DecodingKey,
+    pub jwk: Jwk,
+}
+
+/// Asymmetric keys for signing and verifying tokens.
+///
+/// New tokens are signed with one key; the others only verify, so tokens
+/// signed before a rotation stay valid until the old key is removed.
+#[derive(Clone)]
+pub struct JwtKeys {
+    keys: Vec<JwtKey>,
+    signing: usize,
+}
+
+impl JwtKeys {
+    /// Loads every `*.pem` private key in `dir`, named by its file stem.
+    ///
+    /// `signing_kid` picks the key new tokens are signed with; it may be
+    /// omitted when the directory holds a single key.
+    pub fn load_dir(dir: impl AsRef<Path>, signing_kid: Option<&str>) -> Result<Self, KeyError> {
+        let dir = dir.as_ref();
+        let io_error = |source| KeyError::Io {
+            path: dir.to_path_buf(),
+            source,
+        };
+
+        let mut paths = Vec::new();
+        for entry in std::fs::read_dir(dir).map_err(io_error)? {
+            let path = entry.map_err(io_error)?.path();
+            if path.extension().is_some_and(|ext| ext == "pem") {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+
+        let mut keys = Vec::with_capacity(paths.len());
+        for path in paths {
+            let pem = std::fs::read(&path).map_err(|source| KeyError::Io {
+                path: path.clone(),
+                source,
+            })?;
+            let kid = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            keys.push(parse_key(&path, kid, &pem)?);
+        }
+
+        if keys.is_empty() {
+            return Err(KeyError::NoKeys(dir.to_path_buf()));
+        }
+        Self::new(keys, signing_kid)
+    }
+
+    /// Builds a key set from PEM-encoded private keys and their ids.
+    pub fn from_pems(keys: &[(&str, &[u8])], signing_kid: Option<&str>) -> Result<Self, KeyError> {
+        let keys = keys
+            .iter()
+            .map(|(kid, pem)| parse_key(Path::new(kid), kid.to_string(), pem))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Self::new(keys, signing_kid)
+    }
+
+    fn new(keys: Vec<JwtKey>, signing_kid: Option<&str>) -> Result<Self, KeyError> {
+        let signing = match signing_kid {
+            Some(kid) => keys
+                .iter()
+                .position(|key| key.kid == kid)
+                .ok_or_else(|| KeyError::UnknownSigningKey(kid.to_string()))?,
+            None if keys.len() == 1 => 0,
+            None => return Err(KeyError::AmbiguousSigningKey),
+        };
+
+        Ok(Self { keys, signing })
+    }
+
+    pub(super) fn signing_key(&self) -> &JwtKey {
+        &self.keys[self.signing]
+    }
+
+    pub(super) fn get(&self, kid: &str) -> Option<&JwtKey> {
+        self.keys.iter().find(|key| key.kid == kid)
+    }
+
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self.keys.iter().map(|key| key.jwk.clone()).collect(),
+        }
+    }
+}
+
+fn parse_key(path: &Path, kid: String, pem: &[u8]) -> Result<JwtKey, KeyError> {
+    let invalid = || KeyError::InvalidPem(path.to_path_buf());
+    let pem_text = std::str::from_utf8(pem).map_err(|_| invalid())?;
+    let (label, der) = decode_pem(pem_text).ok_or_else(invalid)?;
+
+    let ed25519 = match label {
+        "PRIVATE KEY" => Ed25519KeyPair::from_pkcs8_maybe_unchecked(&der).ok(),
+        _ => None,
+    };
+    if let Some(pair) = ed25519 {
+        let x = URL_SAFE_NO_PAD.encode(pair.public_key().as_ref());
+        let decoding = DecodingKey::from_ed_components(&x).map_err(|_| invalid())?;
+        let encoding = EncodingKey::from_ed_pem(pem).map_err(|_| invalid())?;
+
+        return Ok(JwtKey {
+            jwk: Jwk {
+                kty: "OKP".to_string(),
+                kid: kid.clone(),
+                alg: "EdDSA".to_string(),
+                use_: "sig".to_string(),
+                crv: Some("Ed25519".to_string()),
+                x: Some(x),
+                n: None,
+                e: None,
+            },
+            kid,
+            algorithm: Algorithm::EdDSA,
+            encoding,
+            decoding,
+        });
+    }
+
+    let rsa = match label {
+        "PRIVATE KEY" => RsaKeyPair::from_pkcs8(&der),
+        "RSA PRIVATE KEY" => RsaKeyPair::from_der(&der),
+        _ => return Err(invalid()),
+    }
+    .map_err(|_| KeyError::UnsupportedKey(path.to_path_buf()))?;
+
+    let components = PublicKeyComponents::<Vec<u8>>::from(rsa.public());
+    let n = URL_SAFE_NO_PAD.encode(&components.n);
+    let e = URL_SAFE_NO_PAD.encode(&components.e);
+    let decoding = DecodingKey::from_rsa_components(&n, &e).map_err(|_| invalid())?;
+    let encoding = EncodingKey::from_rsa_pem(pem).map_err(|_| invalid())?;
+
+    Ok(JwtKey {
+        jwk: Jwk {
+            kty: "RSA".to_string(),
+            kid: kid.clone(),
+            alg: "RS256".to_string(),
+            use_: "sig".to_string(),
+            crv: None,
+            x: None,
+            n: Some(n),
+            e: Some(e),
+        },
+        kid,
+        algorithm: Algorithm::RS256,
+        encoding,
+        decoding,
+    })
+}
+
+/// Label and contents of the first PEM block in `pem`.
+fn decode_pem(pem: &str) -> Option<(&str, Vec<u8>)> {
+    let start = pem.find("-----BEGIN ")?;
+    let rest = &pem[start + "-----BEGIN ".len()..];
+    let (label, rest) = rest.split_once("-----")?;
+    let end = rest.find(&format!("-----END {label}-----"))?;
+
+    let body: String = rest[..end].split_whitespace().collect();
+    Some((label, STANDARD.decode(body).ok()?))
+}
@@ -1,7 +1,13 @@
-use axum::{extract::FromRequestParts, http::request::Parts};
+use axum::{
+    extract::{FromRef, FromRequestParts},
+    http::request::Parts,
+};
+use sqlx::PgPool;
 use uuid::Uuid;
 
 use crate::error::AppError;
+use crate::models::Scope;
+use crate::services::{DeviceService, PersonalAccessTokenService, TokenRevocationService};
 
 use super::JwtManager;
 
@@ -9,15 +15,119 @@ use super::JwtManager;
 pub struct AuthUser {
     pub user_id: Uuid,
     pub email: String,
+    /// Device the token was issued to, if any
+    pub device_id: Option<Uuid>,
+    /// How the request authenticated
+    pub credential: Credential,
+}
+
+#[derive(Debug, Clone)]
+pub enum Credential {
+    /// An access token from signing in, which may do anything the user can
+    Session {
+        /// Id of the presented token
+        jti: Uuid,
+        /// Expiry of the presented token, as a Unix timestamp
+        exp: i64,
+    },
+    /// A personal access token, limited to its scopes
+    PersonalAccessToken { id: Uuid, scopes: Vec<Scope> },
+}
+
+impl AuthUser {
+    /// Authenticates a bearer token, rejecting revoked tokens and tokens of revoked devices.
+    ///
+    /// Accepts both signed access tokens and personal access tokens.
+    pub async fn from_token(
+        pool: &PgPool,
+        jwt: &JwtManager,
+        token: &str,
+    ) -> Result<Self, AppError> {
+        if PersonalAccessTokenService::is_personal_access_token(token) {
+            return PersonalAccessTokenService::authenticate(pool, token).await;
+        }
+
+        let claims = jwt.verify_token(token)?;
+
+        if TokenRevocationService::is_revoked(pool, jwt.revocations(), claims.jti, claims.exp)
+            .await?
+        {
+            return Err(AppError::Unauthorized);
+        }
+
+        if let Some(device_id) = claims.did {
+            DeviceService::authorize(pool, jwt.revocations(), claims.sub, device_id).await?;
+        }
+
+        Ok(AuthUser {
+            user_id: claims.sub,
+            email: claims.email,
+            device_id: claims.did,
+            credential: Credential::Session {
+                jti: claims.jti,
+                exp: claims.exp,
+            },
+        })
+    }
+
+    /// Fails with `Forbidden` unless the credential grants `scope`.
+    pub fn require(&self, scope: Scope) -> Result<(), AppError> {
+        match &self.credential {
+            Credential::Session { .. } => Ok(()),
+            Credential::PersonalAccessToken { scopes, .. } => {
+                if scopes.iter().any(|granted| granted.grants(scope)) {
+                    Ok(())
+                } else {
+                    Err(AppError::Forbidden)
+                }
+            }
+        }
+    }
+
+    /// Fails with `Forbidden` for personal access tokens, for account
+    /// management that only a signed-in user may do.
+    pub fn require_session(&self) -> Result<(), AppError> {
+        match self.credential {
+            Credential::Session { .. } => Ok(()),
+            Credential::PersonalAccessToken { .. } => Err(AppError::Forbidden),
+        }
+    }
+
+    /// Fails with `Unauthorized` once the credential has expired or been
+    /// revoked, or its device has been revoked, for connections that outlive
+    /// the request that authenticated them.
+    pub async fn revalidate(&self, pool: &PgPool, jwt: &JwtManager) -> Result<(), AppError> {
+        match self.credential {
+            Credential::Session { jti, exp } => {
+                if exp <= chrono::Utc::now().timestamp()
+                    || TokenRevocationService::is_revoked(pool, jwt.revocations(), jti, exp).await?
+                {
+                    return Err(AppError::Unauthorized);
+                }
+            }
+            Credential::PersonalAccessToken { id, .. } => {
+                if !PersonalAccessTokenService::is_active(pool, id).await? {
+                    return Err(AppError::Unauthorized);
+                }
+            }
+        }
+
+        if let Some(device_id) = self.device_id {
+            DeviceService::authorize(pool, jwt.revocations(), self.user_id, device_id).await?;
+        }
+
+        Ok(())
+    }
 }
 
 impl<S> FromRequestParts<S> for AuthUser
 where
     S: Send + Sync,
+    PgPool: FromRef<S>,
 {
     type Rejection = AppError;
 
-    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
         let auth_header = parts
             .headers
             .get("Authorization")
@@ -33,11 +143,6 @@ where
             .get::<JwtManager>()
             .ok_or(AppError::Internal("JWT manager not configured".to_string()))?;
 
-        let claims = jwt_manager.verify_token(token)?;
-
-        Ok(AuthUser {
-            user_id: claims.sub,
-            email: claims.email,
-        })
+        Self::from_token(&PgPool::from_ref(state), jwt_manager, token).await
     }
 }
@@ -1,8 +1,25 @@
 mod jwt;
+mod keys;
 mod middleware;
+mod opaque;
+mod revocation;
+pub mod totp;
 
 #[cfg(test)]
 mod jwt_tests;
+#[cfg(test)]
+mod keys_tests;
+#[cfg(test)]
+mod opaque_tests;
+#[cfg(test)]
+mod revocation_tests;
+#[cfg(test)]
+mod totp_tests;
 
 pub use jwt::{Claims, JwtManager};
-pub use middleware::AuthUser;
+pub use keys::{Jwk, JwkSet, JwtKeys, KeyError};
+pub use middleware::{AuthUser, Credential};
+pub use opaque::{generate_token, hash_token};
+pub use revocation::{
+    NEVER_EXPIRES, REVOCATION_CHANNEL, RevocationCache, start_revocation_listener,
+};
@@ -0,0 +1,20 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use sha2::{Digest, Sha256};
+
+/// Random bytes in an opaque token; 256 bits cannot be guessed.
+const TOKEN_BYTES: usize = 32;
+
+/// Generates a random, URL-safe token for clients to present later.
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; TOKEN_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Hex-encoded SHA-256 of a token, the form it is stored and looked up in.
+///
+/// Tokens carry enough entropy that a fast hash suffices, unlike passwords.
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
@@ -0,0 +1,27 @@
+#[cfg(test)]
+mod tests {
+    use crate::auth::{generate_token, hash_token};
+
+    #[test]
+    fn test_generated_tokens_are_url_safe_and_unique() {
+        let first = generate_token();
+        let second = generate_token();
+
+        assert_eq!(first.len(), 43);
+        assert!(
+            first
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        );
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn test_token_hash_is_stable_hex() {
+        let hash = hash_token("token");
+
+        assert_eq!(hash, hash_token("token"));
+        assert_ne!(hash, hash_token("token2"));
+        assert_eq!(hash.len(), 64);
+    }
+}
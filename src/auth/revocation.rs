@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use sqlx::PgPool;
+use sqlx::postgres::PgListener;
+use uuid::Uuid;
+
+/// Postgres channel revocations are announced on, with `<jti> <exp>` as
+/// payload, or `<device id> <NEVER_EXPIRES>` for signed-out devices.
+pub const REVOCATION_CHANNEL: &str = "token_revocations";
+
+/// How long a token found not revoked is trusted without asking the database.
+///
+/// Revocations through other instances normally arrive as notifications
+/// first; this only bounds the delay while the listener is reconnecting.
+/// Expiry cached for signed-out devices, which stay signed out.
+pub const NEVER_EXPIRES: i64 = i64::MAX;
+
+const NOT_REVOKED_TTL: Duration = Duration::from_secs(30);
+const MAX_ENTRIES: usize = 100_000;
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// In-memory cache of access token revocation checks, keyed by `jti`.
+///
+/// Revoked tokens are remembered until they expire; tokens found valid are
+/// re-checked after a short while. Device checks share the cache, keyed by
+/// device id, with signed-out devices never expiring.
+#[derive(Clone, Default)]
+pub struct RevocationCache {
+    entries: Arc<Mutex<HashMap<Uuid, Entry>>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    revoked: bool,
+    checked_at: Instant,
+    /// The token's `exp`
+    expires_at: i64,
+}
+
+impl RevocationCache {
+    /// Whether the token is revoked, or `None` if it has to be looked up.
+    pub fn get(&self, jti: Uuid) -> Option<bool> {
+        let entries = self.entries.lock().unwrap();
+        let entry = entries.get(&jti)?;
+
+        if entry.revoked || entry.checked_at.elapsed() < NOT_REVOKED_TTL {
+            Some(entry.revoked)
+        } else {
+            None
+        }
+    }
+
+    pub fn insert(&self, jti: Uuid, revoked: bool, expires_at: i64) {
+        let mut entries = self.entries.lock().unwrap();
+
+        if entries.len() >= MAX_ENTRIES {
+            let now = Utc::now().timestamp();
+            entries.retain(|_, entry| {
+                entry.expires_at > now
+                    && (entry.revoked || entry.checked_at.elapsed() < NOT_REVOKED_TTL)
+            });
+            if entries.len() >= MAX_ENTRIES {
+                entries.clear();
+            }
+        }
+
+        entries.insert(
+            jti,
+            Entry {
+                revoked,
+                checked_at: Instant::now(),
+                expires_at,
+            },
+        );
+    }
+
+    pub fn revoke(&self, jti: Uuid, expires_at: i64) {
+        self.insert(jti, true, expires_at);
+    }
+}
+
+/// Starts applying revocations announced by any server instance to `cache`.
+///
+/// Returns once the listener is subscribed.
+pub async fn start_revocation_listener(
+    pool: &PgPool,
+    cache: RevocationCache,
+) -> Result<(), sqlx::Error> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen(REVOCATION_CHANNEL).await?;
+
+    tokio::spawn(async move {
+        loop {
+            match listener.recv().await {
+                Ok(notification) => match parse_payload(notification.payload()) {
+                    Some((jti, expires_at)) => cache.revoke(jti, expires_at),
+                    None => tracing::warn!(
+                        payload = notification.payload(),
+                        "Ignoring malformed revocation notification"
+                    ),
+                },
+                Err(e) => {
+                    // The listener reconnects on the next recv
+                    tracing::error!(error = %e, "Revocation listener failed");
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+fn parse_payload(payload: &str) -> Option<(Uuid, i64)> {
+    let (jti, expires_at) = payload.split_once(' ')?;
+    Some((jti.parse().ok()?, expires_at.parse().ok()?))
+}
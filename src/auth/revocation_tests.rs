@@ -0,0 +1,31 @@
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    use crate::auth::RevocationCache;
+
+    #[test]
+    fn test_unknown_tokens_need_a_lookup() {
+        let cache = RevocationCache::default();
+        assert_eq!(cache.get(Uuid::new_v4()), None);
+    }
+
+    #[test]
+    fn test_cache_remembers_checks() {
+        let cache = RevocationCache::default();
+        let exp = Utc::now().timestamp() + 900;
+        let valid = Uuid::new_v4();
+        let revoked = Uuid::new_v4();
+
+        cache.insert(valid, false, exp);
+        cache.revoke(revoked, exp);
+
+        assert_eq!(cache.get(valid), Some(false));
+        assert_eq!(cache.get(revoked), Some(true));
+
+        // A later revocation overrides an earlier check
+        cache.revoke(valid, exp);
+        assert_eq!(cache.get(valid), Some(true));
+    }
+}
@@ -0,0 +1,115 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use data_encoding::BASE32_NOPAD;
+use ring::hmac;
+
+/// Secret size recommended by RFC 4226 for HMAC-SHA1.
+const SECRET_BYTES: usize = 20;
+/// Seconds each code is valid for.
+pub const STEP_SECONDS: i64 = 30;
+const DIGITS: u32 = 6;
+/// Codes from this many steps before or after the current one are accepted,
+/// allowing for clock drift and slow typing.
+const ALLOWED_DRIFT: i64 = 1;
+/// Name shown next to the account in authenticator apps.
+const ISSUER: &str = "Xync";
+
+/// Random bytes in a recovery code, written as 16 base32 characters.
+const RECOVERY_CODE_BYTES: usize = 10;
+
+/// Generates a random secret for a new enrollment.
+pub fn generate_secret() -> Vec<u8> {
+    let mut secret = vec![0u8; SECRET_BYTES];
+    OsRng.fill_bytes(&mut secret);
+    secret
+}
+
+/// The secret in base32, as authenticator apps expect it typed in.
+pub fn encode_secret(secret: &[u8]) -> String {
+    BASE32_NOPAD.encode(secret)
+}
+
+/// `otpauth://` URI that authenticator apps read from a QR code.
+pub fn otpauth_uri(secret: &[u8], account: &str) -> String {
+    let label: String =
+        url::form_urlencoded::byte_serialize(format!("{ISSUER}:{account}").as_bytes()).collect();
+
+    format!(
+        "otpauth://totp/{label}?secret={}&issuer={ISSUER}&algorithm=SHA1&digits={DIGITS}&period={STEP_SECONDS}",
+        encode_secret(secret)
+    )
+}
+
+/// Time step a Unix timestamp falls in.
+pub fn step_at(unix_time: i64) -> i64 {
+    unix_time.div_euclid(STEP_SECONDS)
+}
+
+/// The RFC 6238 code for a time step.
+pub fn code_at(secret: &[u8], step: i64) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, secret);
+    let tag = hmac::sign(&key, &step.to_be_bytes());
+    let digest = tag.as_ref();
+
+    // Dynamic truncation, RFC 4226 section 5.3
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+
+    format!(
+        "{:0width$}",
+        binary % 10u32.pow(DIGITS),
+        width = DIGITS as usize
+    )
+}
+
+/// Checks a code against the steps around `unix_time`, returning the step it
+/// matched.
+///
+/// Steps up to `last_used_step` are skipped so a code cannot be replayed.
+pub fn verify(
+    secret: &[u8],
+    code: &str,
+    unix_time: i64,
+    last_used_step: Option<i64>,
+) -> Option<i64> {
+    let code = code.trim();
+    if code.len() != DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let current = step_at(unix_time);
+    (current - ALLOWED_DRIFT..=current + ALLOWED_DRIFT)
+        .filter(|step| last_used_step.is_none_or(|last| *step > last))
+        .find(|step| constant_time_eq(code_at(secret, *step).as_bytes(), code.as_bytes()))
+}
+
+/// Generates a one-time recovery code, formatted as `xxxx-xxxx-xxxx-xxxx`.
+pub fn generate_recovery_code() -> String {
+    let mut bytes = [0u8; RECOVERY_CODE_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+
+    let encoded = BASE32_NOPAD.encode(&bytes).to_ascii_lowercase();
+    encoded
+        .as_bytes()
+        .chunks(4)
+        .map(|chunk| std::str::from_utf8(chunk).expect("base32 is ASCII"))
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Recovery code in the form it is hashed in, so case and dashes don't matter
+/// when typing it.
+pub fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
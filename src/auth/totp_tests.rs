@@ -0,0 +1,87 @@
+#[cfg(test)]
+mod tests {
+    use crate::auth::totp;
+
+    /// Secret of the RFC 6238 SHA-1 test vectors.
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn test_codes_match_rfc_6238_vectors() {
+        // The RFC lists 8 digits; 6-digit codes are their last six
+        for (time, code) in [
+            (59, "287082"),
+            (1111111109, "081804"),
+            (1111111111, "050471"),
+            (1234567890, "005924"),
+            (2000000000, "279037"),
+            (20000000000, "353130"),
+        ] {
+            assert_eq!(totp::code_at(RFC_SECRET, totp::step_at(time)), code);
+        }
+    }
+
+    #[test]
+    fn test_verify_allows_one_step_of_drift() {
+        let now = 1111111111;
+        let step = totp::step_at(now);
+        let code = totp::code_at(RFC_SECRET, step);
+
+        assert_eq!(totp::verify(RFC_SECRET, &code, now, None), Some(step));
+        assert_eq!(
+            totp::verify(RFC_SECRET, &code, now + totp::STEP_SECONDS, None),
+            Some(step)
+        );
+        assert_eq!(
+            totp::verify(RFC_SECRET, &code, now - totp::STEP_SECONDS, None),
+            Some(step)
+        );
+        assert_eq!(
+            totp::verify(RFC_SECRET, &code, now + 2 * totp::STEP_SECONDS, None),
+            None
+        );
+    }
+
+    #[test]
+    fn test_verify_rejects_replayed_and_malformed_codes() {
+        let now = 1234567890;
+        let step = totp::step_at(now);
+        let code = totp::code_at(RFC_SECRET, step);
+
+        assert_eq!(totp::verify(RFC_SECRET, &code, now, Some(step)), None);
+        assert_eq!(
+            totp::verify(RFC_SECRET, &code, now, Some(step - 1)),
+            Some(step)
+        );
+        assert_eq!(
+            totp::verify(RFC_SECRET, &format!(" {code} "), now, None),
+            Some(step)
+        );
+        assert_eq!(totp::verify(RFC_SECRET, "12345", now, None), None);
+        assert_eq!(totp::verify(RFC_SECRET, "abcdef", now, None), None);
+        assert_eq!(totp::verify(b"another secret", &code, now, None), None);
+    }
+
+    #[test]
+    fn test_otpauth_uri() {
+        let uri = totp::otpauth_uri(RFC_SECRET, "ada@example.com");
+
+        assert_eq!(
+            uri,
+            "otpauth://totp/Xync%3Aada%40example.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ\
+             &issuer=Xync&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+
+    #[test]
+    fn test_recovery_codes() {
+        let code = totp::generate_recovery_code();
+
+        assert_eq!(code.len(), 19);
+        assert_eq!(code.matches('-').count(), 3);
+        assert_ne!(code, totp::generate_recovery_code());
+        assert_eq!(
+            totp::normalize_recovery_code(&code.to_uppercase().replace('-', " ")),
+            code.replace('-', "")
+        );
+    }
+}
@@ -0,0 +1,71 @@
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use super::{BlobError, BlobStore, check_hash};
+
+/// Blobs as files under a root directory, fanned out by the first two bytes
+/// of their hash: `<root>/ab/cd/abcd…`.
+#[derive(Debug, Clone)]
+pub struct FsBlobStore {
+    root: PathBuf,
+}
+
+impl FsBlobStore {
+    /// Uses `root`, creating it if needed.
+    pub fn new(root: impl Into<PathBuf>) -> Result<Self, BlobError> {
+        let root = root.into();
+        std::fs::create_dir_all(root.join("tmp"))?;
+        Ok(Self { root })
+    }
+
+    fn path(&self, hash: &str) -> Result<PathBuf, BlobError> {
+        check_hash(hash)?;
+        Ok(self.root.join(&hash[..2]).join(&hash[2..4]).join(hash))
+    }
+}
+
+#[async_trait]
+impl BlobStore for FsBlobStore {
+    async fn put(&self, hash: &str, data: Vec<u8>) -> Result<(), BlobError> {
+        let path = self.path(hash)?;
+        if tokio::fs::try_exists(&path).await? {
+            return Ok(());
+        }
+
+        // Written aside and renamed into place, so readers never see a partial blob
+        let temp = self.root.join("tmp").join(Uuid::new_v4().to_string());
+        tokio::fs::write(&temp, data).await?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        if let Err(e) = tokio::fs::rename(&temp, &path).await {
+            let _ = tokio::fs::remove_file(&temp).await;
+            return Err(e.into());
+        }
+
+        Ok(())
+    }
+
+    async fn get(&self, hash: &str) -> Result<Vec<u8>, BlobError> {
+        match tokio::fs::read(self.path(hash)?).await {
+            Ok(data) => Ok(data),
+            Err(e) if e.kind() == ErrorKind::NotFound => Err(BlobError::NotFound(hash.to_string())),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn delete(&self, hash: &str) -> Result<(), BlobError> {
+        match tokio::fs::remove_file(self.path(hash)?).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn exists(&self, hash: &str) -> Result<bool, BlobError> {
+        Ok(tokio::fs::try_exists(self.path(hash)?).await?)
+    }
+}
@@ -0,0 +1,121 @@
+//! Content-addressed binary storage.
+//!
+//! Blobs are stored under the hex SHA-256 of their content, so identical
+//! uploads share one copy. A [`BlobStore`] only moves bytes; reference counts
+//! and per-user quotas are kept in the database by `BlobService`.
+
+mod fs;
+mod s3;
+
+#[cfg(test)]
+mod store_tests;
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+
+use crate::config::Config;
+use crate::error::AppError;
+
+pub use fs::FsBlobStore;
+pub use s3::{S3BlobStore, S3Config};
+
+#[derive(Debug, thiserror::Error)]
+pub enum BlobError {
+    #[error("blob {0} not found")]
+    NotFound(String),
+
+    #[error("invalid blob hash '{0}'")]
+    InvalidHash(String),
+
+    #[error("invalid blob store configuration: {0}")]
+    Config(String),
+
+    #[error("blob I/O failed: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("object store request failed: {0}")]
+    ObjectStore(#[from] object_store::Error),
+}
+
+impl From<BlobError> for AppError {
+    fn from(e: BlobError) -> Self {
+        match e {
+            BlobError::NotFound(_) => AppError::NotFound("Blob not found".to_string()),
+            e => AppError::Internal(e.to_string()),
+        }
+    }
+}
+
+/// Backend holding blob contents, keyed by their SHA-256.
+#[async_trait]
+pub trait BlobStore: Send + Sync {
+    /// Stores `data` under `hash`; as contents never change, overwriting is harmless.
+    async fn put(&self, hash: &str, data: Vec<u8>) -> Result<(), BlobError>;
+
+    async fn get(&self, hash: &str) -> Result<Vec<u8>, BlobError>;
+
+    /// Removes a blob; removing one that does not exist succeeds.
+    async fn delete(&self, hash: &str) -> Result<(), BlobError>;
+
+    async fn exists(&self, hash: &str) -> Result<bool, BlobError>;
+}
+
+/// The configured blob store, with the per-user quota it is shared under.
+#[derive(Clone)]
+pub struct BlobStorage {
+    pub store: Arc<dyn BlobStore>,
+    /// Bytes of distinct blobs each user may reference
+    pub quota_bytes: i64,
+}
+
+impl BlobStorage {
+    pub fn new(store: impl BlobStore + 'static, quota_bytes: i64) -> Self {
+        Self {
+            store: Arc::new(store),
+            quota_bytes,
+        }
+    }
+
+    /// Builds the backend selected by `BLOB_STORE`.
+    pub fn from_config(config: &Config) -> Result<Self, BlobError> {
+        match config.blob_store.as_str() {
+            "fs" => Ok(Self::new(
+                FsBlobStore::new(&config.blob_fs_root)?,
+                config.blob_quota_bytes,
+            )),
+            "s3" => {
+                let bucket = config.s3_bucket.clone().ok_or_else(|| {
+                    BlobError::Config("S3_BUCKET must be set for the s3 blob store".to_string())
+                })?;
+                let store = S3BlobStore::new(S3Config {
+                    bucket,
+                    region: config.s3_region.clone(),
+                    endpoint: config.s3_endpoint.clone(),
+                    access_key_id: config.s3_access_key_id.clone(),
+                    secret_access_key: config.s3_secret_access_key.clone(),
+                    prefix: config.s3_prefix.clone(),
+                })?;
+                Ok(Self::new(store, config.blob_quota_bytes))
+            }
+            other => Err(BlobError::Config(format!(
+                "unknown BLOB_STORE '{other}', expected 'fs' or 's3'"
+            ))),
+        }
+    }
+}
+
+/// Hex-encoded SHA-256 of `data`, the address it is stored under.
+pub fn hash(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// Rejects anything but a lowercase hex SHA-256, so hashes are safe to use in paths.
+fn check_hash(hash: &str) -> Result<(), BlobError> {
+    if hash.len() == 64 && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+        Ok(())
+    } else {
+        Err(BlobError::InvalidHash(hash.to_string()))
+    }
+}
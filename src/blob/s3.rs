@@ -0,0 +1,95 @@
+use async_trait::async_trait;
+use object_store::aws::{AmazonS3, AmazonS3Builder};
+use object_store::path::Path;
+use object_store::{ObjectStore, PutPayload};
+
+use super::{BlobError, BlobStore, check_hash};
+
+#[derive(Debug, Clone)]
+pub struct S3Config {
+    pub bucket: String,
+    pub region: String,
+    /// Endpoint of an S3-compatible service such as MinIO; AWS when absent
+    pub endpoint: Option<String>,
+    /// Taken from the environment or instance metadata when absent
+    pub access_key_id: Option<String>,
+    pub secret_access_key: Option<String>,
+    /// Prepended to every object key, e.g. `blobs/`
+    pub prefix: String,
+}
+
+/// Blobs as objects in an S3 bucket, addressed by path-style requests so that
+/// S3-compatible services work without DNS setup.
+#[derive(Debug)]
+pub struct S3BlobStore {
+    client: AmazonS3,
+    prefix: String,
+}
+
+impl S3BlobStore {
+    pub fn new(config: S3Config) -> Result<Self, BlobError> {
+        let mut builder = AmazonS3Builder::from_env()
+            .with_bucket_name(&config.bucket)
+            .with_region(&config.region)
+            .with_virtual_hosted_style_request(false);
+
+        if let Some(endpoint) = &config.endpoint {
+            builder = builder
+                .with_endpoint(endpoint)
+                .with_allow_http(endpoint.starts_with("http://"));
+        }
+        if let (Some(key_id), Some(secret)) = (&config.access_key_id, &config.secret_access_key) {
+            builder = builder
+                .with_access_key_id(key_id)
+                .with_secret_access_key(secret);
+        }
+
+        Ok(Self {
+            client: builder.build()?,
+            prefix: config.prefix,
+        })
+    }
+
+    fn key(&self, hash: &str) -> Result<Path, BlobError> {
+        check_hash(hash)?;
+        Ok(Path::from(format!(
+            "{}{}/{}",
+            self.prefix,
+            &hash[..2],
+            hash
+        )))
+    }
+}
+
+#[async_trait]
+impl BlobStore for S3BlobStore {
+    async fn put(&self, hash: &str, data: Vec<u8>) -> Result<(), BlobError> {
+        self.client
+            .put(&self.key(hash)?, PutPayload::from(data))
+            .await?;
+        Ok(())
+    }
+
+    async fn get(&self, hash: &str) -> Result<Vec<u8>, BlobError> {
+        match self.client.get(&self.key(hash)?).await {
+            Ok(result) => Ok(result.bytes().await?.to_vec()),
+            Err(object_store::Error::NotFound { .. }) => Err(BlobError::NotFound(hash.to_string())),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn delete(&self, hash: &str) -> Result<(), BlobError> {
+        match self.client.delete(&self.key(hash)?).await {
+            Ok(()) | Err(object_store::Error::NotFound { .. }) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn exists(&self, hash: &str) -> Result<bool, BlobError> {
+        match self.client.head(&self.key(hash)?).await {
+            Ok(_) => Ok(true),
+            Err(object_store::Error::NotFound { .. }) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
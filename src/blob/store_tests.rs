@@ -0,0 +1,137 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    use axum::{
+        Router,
+        body::Bytes,
+        extract::{Path, State},
+        http::{StatusCode, header},
+        response::{IntoResponse, Response},
+        routing::get,
+    };
+
+    use crate::blob::{BlobError, BlobStore, FsBlobStore, S3BlobStore, S3Config, hash};
+
+    async fn round_trip(store: &dyn BlobStore) {
+        let data = b"hello blob".to_vec();
+        let key = hash(&data);
+
+        assert!(!store.exists(&key).await.unwrap());
+        assert!(matches!(store.get(&key).await, Err(BlobError::NotFound(_))));
+
+        store.put(&key, data.clone()).await.unwrap();
+        store.put(&key, data.clone()).await.unwrap();
+        assert!(store.exists(&key).await.unwrap());
+        assert_eq!(store.get(&key).await.unwrap(), data);
+
+        store.delete(&key).await.unwrap();
+        store.delete(&key).await.unwrap();
+        assert!(!store.exists(&key).await.unwrap());
+
+        for invalid in ["../../etc/passwd", "ABC", &key.to_uppercase()] {
+            assert!(matches!(
+                store.get(invalid).await,
+                Err(BlobError::InvalidHash(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_hash_is_hex_sha256() {
+        assert_eq!(
+            hash(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fs_store_round_trip() {
+        let root = std::env::temp_dir().join(format!("xync-blobs-{}", uuid::Uuid::new_v4()));
+        let store = FsBlobStore::new(&root).unwrap();
+
+        round_trip(&store).await;
+
+        let key = hash(b"fanned out");
+        store.put(&key, b"fanned out".to_vec()).await.unwrap();
+        assert!(root.join(&key[..2]).join(&key[2..4]).join(&key).is_file());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    type Objects = Arc<Mutex<HashMap<String, Vec<u8>>>>;
+
+    /// Minimal S3 stand-in: path-style objects kept in memory, signatures ignored.
+    async fn spawn_s3_stub() -> (String, Objects) {
+        async fn get_object(State(objects): State<Objects>, Path(key): Path<String>) -> Response {
+            match objects.lock().unwrap().get(&key) {
+                Some(data) => (
+                    [
+                        (header::ETAG, format!("\"{}\"", hash(data))),
+                        (
+                            header::LAST_MODIFIED,
+                            "Thu, 01 Jan 2026 00:00:00 GMT".to_string(),
+                        ),
+                    ],
+                    data.clone(),
+                )
+                    .into_response(),
+                None => StatusCode::NOT_FOUND.into_response(),
+            }
+        }
+
+        async fn put_object(
+            State(objects): State<Objects>,
+            Path(key): Path<String>,
+            body: Bytes,
+        ) -> Response {
+            let etag = format!("\"{}\"", hash(&body));
+            objects.lock().unwrap().insert(key, body.to_vec());
+            ([(header::ETAG, etag)], "").into_response()
+        }
+
+        async fn delete_object(
+            State(objects): State<Objects>,
+            Path(key): Path<String>,
+        ) -> StatusCode {
+            objects.lock().unwrap().remove(&key);
+            StatusCode::NO_CONTENT
+        }
+
+        let objects = Objects::default();
+        let app = Router::new()
+            .route(
+                "/{*key}",
+                get(get_object).put(put_object).delete(delete_object),
+            )
+            .with_state(objects.clone());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        (format!("http://{addr}"), objects)
+    }
+
+    #[tokio::test]
+    async fn test_s3_store_round_trip() {
+        let (endpoint, objects) = spawn_s3_stub().await;
+        let store = S3BlobStore::new(S3Config {
+            bucket: "xync".to_string(),
+            region: "us-east-1".to_string(),
+            endpoint: Some(endpoint),
+            access_key_id: Some("test".to_string()),
+            secret_access_key: Some("test-secret".to_string()),
+            prefix: "blobs/".to_string(),
+        })
+        .unwrap();
+
+        round_trip(&store).await;
+
+        let key = hash(b"stored remotely");
+        store.put(&key, b"stored remotely".to_vec()).await.unwrap();
+        let stored: Vec<String> = objects.lock().unwrap().keys().cloned().collect();
+        assert_eq!(stored, vec![format!("xync/blobs/{}/{key}", &key[..2])]);
+    }
+}
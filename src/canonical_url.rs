@@ -0,0 +1,99 @@
+//! Canonical form of bookmark URLs, used to detect duplicates.
+//!
+//! Two URLs that lead to the same page should canonicalize to the same string:
+//! the scheme and host are lowercased, default ports dropped, tracking
+//! parameters removed and the remaining query parameters sorted. Fragments are
+//! dropped unless they look like a single-page-app route (`#/...` or `#!...`),
+//! where they address a different page, or [`CanonicalUrlOptions`] keeps them.
+
+use url::Url;
+
+/// Query parameters added by analytics and ad platforms, compared case-insensitively.
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid",
+    "gclid",
+    "gclsrc",
+    "dclid",
+    "msclkid",
+    "yclid",
+    "twclid",
+    "ttclid",
+    "igshid",
+    "mc_cid",
+    "mc_eid",
+    "_ga",
+    "_gl",
+    "_hsenc",
+    "_hsmi",
+    "mkt_tok",
+    "oly_anon_id",
+    "oly_enc_id",
+    "vero_id",
+    "wickedid",
+];
+
+/// Prefixes of tracking parameter families, such as `utm_source`.
+const TRACKING_PREFIXES: &[&str] = &["utm_"];
+
+/// How URLs are canonicalized, from `CANONICAL_URL_KEEP_FRAGMENTS`.
+///
+/// Changing it only affects bookmarks saved afterwards.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CanonicalUrlOptions {
+    /// Keep every fragment, so URLs pointing at different anchors of a page
+    /// are not duplicates
+    pub keep_fragments: bool,
+}
+
+/// Returns the canonical form of `raw` with the default options.
+pub fn canonicalize(raw: &str) -> String {
+    canonicalize_with(raw, CanonicalUrlOptions::default())
+}
+
+/// Returns the canonical form of `raw`, or `raw` trimmed if it is not a valid URL.
+pub fn canonicalize_with(raw: &str, options: CanonicalUrlOptions) -> String {
+    let raw = raw.trim();
+    let Ok(mut url) = Url::parse(raw) else {
+        return raw.to_string();
+    };
+
+    // Parsing already lowercases the scheme and host and drops default ports
+    let mut params: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(key, _)| !is_tracking_param(key))
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    params.sort();
+
+    if params.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(&params);
+    }
+
+    if !options.keep_fragments
+        && !url
+            .fragment()
+            .is_some_and(|fragment| fragment.starts_with('/') || fragment.starts_with('!'))
+    {
+        url.set_fragment(None);
+    }
+
+    if let Some(host) = url.host_str()
+        && host.ends_with('.')
+    {
+        let host = host.trim_end_matches('.').to_string();
+        // Only fails for hosts that cannot be changed, which have no trailing dot
+        let _ = url.set_host(Some(&host));
+    }
+
+    url.into()
+}
+
+fn is_tracking_param(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    TRACKING_PARAMS.contains(&key.as_str())
+        || TRACKING_PREFIXES
+            .iter()
+            .any(|prefix| key.starts_with(prefix))
+}
@@ -0,0 +1,96 @@
+#[cfg(test)]
+mod tests {
+    use crate::canonical_url::{CanonicalUrlOptions, canonicalize, canonicalize_with};
+
+    #[test]
+    fn test_canonicalize_host_and_port() {
+        assert_eq!(
+            canonicalize("HTTPS://Example.COM:443/a"),
+            "https://example.com/a"
+        );
+        assert_eq!(
+            canonicalize("http://example.com:80/a"),
+            "http://example.com/a"
+        );
+        assert_eq!(
+            canonicalize("http://example.com:8080/a"),
+            "http://example.com:8080/a"
+        );
+        assert_eq!(canonicalize("https://example.com."), "https://example.com/");
+    }
+
+    #[test]
+    fn test_canonicalize_strips_tracking_params() {
+        assert_eq!(
+            canonicalize("https://Example.com/a?utm_source=x&UTM_Medium=y&fbclid=z"),
+            "https://example.com/a"
+        );
+        assert_eq!(
+            canonicalize("https://example.com/a?utm_source=x&id=7"),
+            "https://example.com/a?id=7"
+        );
+    }
+
+    #[test]
+    fn test_canonicalize_sorts_query_params() {
+        assert_eq!(
+            canonicalize("https://example.com/search?q=rust&page=2&lang=en"),
+            canonicalize("https://example.com/search?lang=en&q=rust&page=2")
+        );
+        assert_eq!(
+            canonicalize("https://example.com/search?q=rust&page=2&lang=en"),
+            "https://example.com/search?lang=en&page=2&q=rust"
+        );
+        assert_eq!(
+            canonicalize("https://example.com/a?"),
+            "https://example.com/a"
+        );
+    }
+
+    #[test]
+    fn test_canonicalize_fragments() {
+        assert_eq!(
+            canonicalize("https://example.com/docs#section-2"),
+            "https://example.com/docs"
+        );
+        assert_eq!(
+            canonicalize("https://app.example.com/#/inbox"),
+            "https://app.example.com/#/inbox"
+        );
+        assert_eq!(
+            canonicalize("https://example.com/#!/profile"),
+            "https://example.com/#!/profile"
+        );
+    }
+
+    #[test]
+    fn test_canonicalize_keeps_fragments_when_configured() {
+        let keep = CanonicalUrlOptions {
+            keep_fragments: true,
+        };
+        assert_eq!(
+            canonicalize_with("https://Example.com/docs?utm_source=x#section-2", keep),
+            "https://example.com/docs#section-2"
+        );
+        assert_eq!(
+            canonicalize_with("https://app.example.com/#/inbox", keep),
+            "https://app.example.com/#/inbox"
+        );
+        assert_ne!(
+            canonicalize_with("https://example.com/docs#intro", keep),
+            canonicalize_with("https://example.com/docs#usage", keep)
+        );
+        assert_eq!(
+            canonicalize_with(
+                "https://example.com/docs#intro",
+                CanonicalUrlOptions::default()
+            ),
+            "https://example.com/docs"
+        );
+    }
+
+    #[test]
+    fn test_canonicalize_invalid_url_is_trimmed() {
+        assert_eq!(canonicalize("  not a url "), "not a url");
+    }
+}
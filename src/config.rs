@@ -3,10 +3,33 @@ use std::env;
 #[derive(Clone)]
 pub struct Config {
     pub database_url: String,
-    pub jwt_secret: String,
-    pub jwt_expiration_hours: i64,
+    /// HMAC secret, used when no `jwt_keys_dir` is set
+    pub jwt_secret: Option<String>,
+    /// Directory of `<kid>.pem` private keys for EdDSA or RS256 signing
+    pub jwt_keys_dir: Option<String>,
+    pub jwt_signing_kid: Option<String>,
+    pub jwt_expiration_minutes: i64,
+    pub refresh_token_ttl_days: i64,
     pub server_host: String,
     pub server_port: u16,
+    pub tombstone_retention_days: i64,
+    pub idempotency_key_ttl_hours: i64,
+    pub canonical_url_keep_fragments: bool,
+    // Link previews and archiving
+    pub preview_timeout_secs: u64,
+    pub preview_max_bytes: usize,
+    pub preview_allow_private_networks: bool,
+    pub archive_max_bytes: usize,
+    // Blob storage
+    pub blob_store: String,
+    pub blob_fs_root: String,
+    pub blob_quota_bytes: i64,
+    pub s3_bucket: Option<String>,
+    pub s3_region: String,
+    pub s3_endpoint: Option<String>,
+    pub s3_access_key_id: Option<String>,
+    pub s3_secret_access_key: Option<String>,
+    pub s3_prefix: String,
     // Telemetry
     pub otlp_endpoint: Option<String>,
     pub service_name: String,
@@ -14,22 +37,88 @@ pub struct Config {
     pub metrics_port: u16,
 }
 
+/// Replaced by `JWT_EXPIRATION_MINUTES`, still honoured when that is unset
+pub const DEPRECATED_JWT_EXPIRATION_HOURS: &str = "JWT_EXPIRATION_HOURS";
+
+fn jwt_expiration_minutes() -> i64 {
+    if let Ok(minutes) = env::var("JWT_EXPIRATION_MINUTES") {
+        return minutes
+            .parse()
+            .expect("JWT_EXPIRATION_MINUTES must be a valid integer");
+    }
+
+    match env::var(DEPRECATED_JWT_EXPIRATION_HOURS) {
+        Ok(hours) => {
+            hours
+                .parse::<i64>()
+                .expect("JWT_EXPIRATION_HOURS must be a valid integer")
+                * 60
+        }
+        Err(_) => 15,
+    }
+}
+
 impl Config {
     pub fn from_env() -> Self {
         dotenvy::dotenv().ok();
 
         Self {
             database_url: env::var("DATABASE_URL").expect("DATABASE_URL must be set"),
-            jwt_secret: env::var("JWT_SECRET").expect("JWT_SECRET must be set"),
-            jwt_expiration_hours: env::var("JWT_EXPIRATION_HOURS")
-                .unwrap_or_else(|_| "24".to_string())
+            jwt_secret: env::var("JWT_SECRET").ok(),
+            jwt_keys_dir: env::var("JWT_KEYS_DIR").ok(),
+            jwt_signing_kid: env::var("JWT_SIGNING_KID").ok(),
+            jwt_expiration_minutes: jwt_expiration_minutes(),
+            refresh_token_ttl_days: env::var("REFRESH_TOKEN_TTL_DAYS")
+                .unwrap_or_else(|_| "30".to_string())
                 .parse()
-                .expect("JWT_EXPIRATION_HOURS must be a valid integer"),
+                .expect("REFRESH_TOKEN_TTL_DAYS must be a valid integer"),
             server_host: env::var("SERVER_HOST").unwrap_or_else(|_| "127.0.0.1".to_string()),
             server_port: env::var("SERVER_PORT")
                 .unwrap_or_else(|_| "3000".to_string())
                 .parse()
                 .expect("SERVER_PORT must be a valid port number"),
+            tombstone_retention_days: env::var("TOMBSTONE_RETENTION_DAYS")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .expect("TOMBSTONE_RETENTION_DAYS must be a valid integer"),
+            idempotency_key_ttl_hours: env::var("IDEMPOTENCY_KEY_TTL_HOURS")
+                .unwrap_or_else(|_| "24".to_string())
+                .parse()
+                .expect("IDEMPOTENCY_KEY_TTL_HOURS must be a valid integer"),
+            canonical_url_keep_fragments: env::var("CANONICAL_URL_KEEP_FRAGMENTS")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            // Link previews and archiving
+            preview_timeout_secs: env::var("PREVIEW_TIMEOUT_SECS")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .expect("PREVIEW_TIMEOUT_SECS must be a valid integer"),
+            preview_max_bytes: env::var("PREVIEW_MAX_BYTES")
+                .unwrap_or_else(|_| "1048576".to_string())
+                .parse()
+                .expect("PREVIEW_MAX_BYTES must be a valid integer"),
+            preview_allow_private_networks: env::var("PREVIEW_ALLOW_PRIVATE_NETWORKS")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            archive_max_bytes: env::var("ARCHIVE_MAX_BYTES")
+                .unwrap_or_else(|_| "5242880".to_string())
+                .parse()
+                .expect("ARCHIVE_MAX_BYTES must be a valid integer"),
+            // Blob storage
+            blob_store: env::var("BLOB_STORE").unwrap_or_else(|_| "fs".to_string()),
+            blob_fs_root: env::var("BLOB_FS_ROOT").unwrap_or_else(|_| "./data/blobs".to_string()),
+            blob_quota_bytes: env::var("BLOB_QUOTA_BYTES")
+                .unwrap_or_else(|_| "1073741824".to_string())
+                .parse()
+                .expect("BLOB_QUOTA_BYTES must be a valid integer"),
+            s3_bucket: env::var("S3_BUCKET").ok(),
+            s3_region: env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+            s3_endpoint: env::var("S3_ENDPOINT").ok(),
+            s3_access_key_id: env::var("S3_ACCESS_KEY_ID").ok(),
+            s3_secret_access_key: env::var("S3_SECRET_ACCESS_KEY").ok(),
+            s3_prefix: env::var("S3_PREFIX").unwrap_or_default(),
             // Telemetry
             otlp_endpoint: env::var("OTLP_ENDPOINT").ok(),
             service_name: env::var("SERVICE_NAME").unwrap_or_else(|_| "xync-server".to_string()),
@@ -1,11 +1,13 @@
 use axum::{
     Json,
-    http::StatusCode,
+    http::{StatusCode, header},
     response::{IntoResponse, Response},
 };
 use serde::Serialize;
 use thiserror::Error;
 
+use crate::handlers::etag::etag;
+
 pub type Result<T> = std::result::Result<T, AppError>;
 
 #[derive(Error, Debug)]
@@ -28,6 +30,27 @@ pub enum AppError {
     #[error("Resource already exists: {0}")]
     Conflict(String),
 
+    #[error("Resource no longer available: {0}")]
+    Gone(String),
+
+    #[error("Unprocessable request: {0}")]
+    UnprocessableEntity(String),
+
+    #[error("Too many requests: {0}")]
+    TooManyRequests(String),
+
+    #[error("Storage quota exceeded: {0}")]
+    QuotaExceeded(String),
+
+    #[error("Precondition failed: resource has been modified")]
+    PreconditionFailed {
+        current: Box<serde_json::Value>,
+        version: i64,
+    },
+
+    #[error("Operation {index} failed: {source}")]
+    BatchOperation { index: usize, source: Box<AppError> },
+
     #[error("Database error: {0}")]
     Database(#[from] sqlx::Error),
 
@@ -42,27 +65,78 @@ pub enum AppError {
 struct ErrorResponse {
     error: String,
     message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    current: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    operation: Option<usize>,
+}
+
+impl AppError {
+    /// Stale `If-Match`: carries the server's copy so the client can reconcile.
+    pub fn precondition_failed<T: Serialize>(current: &T, version: i64) -> Self {
+        match serde_json::to_value(current) {
+            Ok(current) => AppError::PreconditionFailed {
+                current: Box::new(current),
+                version,
+            },
+            Err(e) => AppError::Internal(e.to_string()),
+        }
+    }
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        let (status, error_type) = match &self {
+        // A failed batch answers like its failing operation, naming its position
+        let (operation, error) = match self {
+            AppError::BatchOperation { index, source } => (Some(index), *source),
+            error => (None, error),
+        };
+
+        let (status, error_type) = match &error {
             AppError::Unauthorized => (StatusCode::UNAUTHORIZED, "unauthorized"),
             AppError::InvalidCredentials => (StatusCode::UNAUTHORIZED, "invalid_credentials"),
             AppError::Forbidden => (StatusCode::FORBIDDEN, "forbidden"),
             AppError::NotFound(_) => (StatusCode::NOT_FOUND, "not_found"),
             AppError::Validation(_) => (StatusCode::BAD_REQUEST, "validation_error"),
             AppError::Conflict(_) => (StatusCode::CONFLICT, "conflict"),
+            AppError::Gone(_) => (StatusCode::GONE, "gone"),
+            AppError::UnprocessableEntity(_) => {
+                (StatusCode::UNPROCESSABLE_ENTITY, "unprocessable_entity")
+            }
+            AppError::TooManyRequests(_) => (StatusCode::TOO_MANY_REQUESTS, "too_many_requests"),
+            AppError::QuotaExceeded(_) => (StatusCode::INSUFFICIENT_STORAGE, "quota_exceeded"),
+            AppError::PreconditionFailed { .. } => {
+                (StatusCode::PRECONDITION_FAILED, "precondition_failed")
+            }
+            AppError::BatchOperation { .. } => {
+                (StatusCode::INTERNAL_SERVER_ERROR, "internal_error")
+            }
             AppError::Database(_) => (StatusCode::INTERNAL_SERVER_ERROR, "database_error"),
             AppError::Jwt(_) => (StatusCode::UNAUTHORIZED, "jwt_error"),
             AppError::Internal(_) => (StatusCode::INTERNAL_SERVER_ERROR, "internal_error"),
         };
 
+        let message = match operation {
+            Some(index) => format!("Operation {index} failed: {error}"),
+            None => error.to_string(),
+        };
+        let (current, etag) = match error {
+            AppError::PreconditionFailed { current, version } => {
+                (Some(*current), Some(etag(version)))
+            }
+            _ => (None, None),
+        };
+
         let body = Json(ErrorResponse {
             error: error_type.to_string(),
-            message: self.to_string(),
+            message,
+            current,
+            operation,
         });
 
-        (status, body).into_response()
+        match etag {
+            Some(etag) => (status, [(header::ETAG, etag)], body).into_response(),
+            None => (status, body).into_response(),
+        }
     }
 }
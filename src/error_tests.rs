@@ -46,6 +46,53 @@ mod tests {
         assert_eq!(response.status(), StatusCode::CONFLICT);
     }
 
+    #[test]
+    fn test_gone_error() {
+        let error = AppError::Gone("Sync cursor expired".to_string());
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::GONE);
+    }
+
+    #[test]
+    fn test_too_many_requests_error() {
+        let error = AppError::TooManyRequests("Locked".to_string());
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[test]
+    fn test_unprocessable_entity_error() {
+        let error = AppError::UnprocessableEntity("Key reused".to_string());
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[test]
+    fn test_quota_exceeded_error() {
+        let error = AppError::QuotaExceeded("1 GiB used".to_string());
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::INSUFFICIENT_STORAGE);
+    }
+
+    #[test]
+    fn test_precondition_failed_error() {
+        let current = serde_json::json!({ "id": 1, "version": 3 });
+        let error = AppError::precondition_failed(&current, 3);
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+        assert_eq!(response.headers()["etag"], "\"3\"");
+    }
+
+    #[test]
+    fn test_batch_operation_error_uses_inner_status() {
+        let error = AppError::BatchOperation {
+            index: 2,
+            source: Box::new(AppError::NotFound("Tag not found".to_string())),
+        };
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
     #[test]
     fn test_internal_error() {
         let error = AppError::Internal("Something went wrong".to_string());
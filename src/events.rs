@@ -0,0 +1,105 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+use sqlx::PgPool;
+use sqlx::postgres::PgListener;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::models::{ChangeEvent, ChangeOperation, EntityType};
+
+/// Postgres channel the `sync_changes` trigger publishes on.
+pub const CHANNEL: &str = "sync_changes";
+
+/// How often streams to clients re-check the credentials they were opened
+/// with, so they end soon after a logout, revocation or expiry.
+pub const REAUTH_INTERVAL: Duration = Duration::from_secs(30);
+
+const CAPACITY: usize = 1024;
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// In-process fan-out of change events received from Postgres.
+///
+/// Every server instance listens on the same channel, so a change committed
+/// through any instance reaches subscribers on all of them.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<ChangeEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CAPACITY);
+        Self { sender }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ChangeEvent> {
+        self.sender.subscribe()
+    }
+
+    pub fn publish(&self, event: ChangeEvent) {
+        // No subscribers is not an error, the event is simply dropped
+        let _ = self.sender.send(event);
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Payload built by the `notify_sync_change` trigger.
+#[derive(Debug, Deserialize)]
+struct Notification {
+    user_id: Uuid,
+    seq: i64,
+    entity_type: EntityType,
+    id: Uuid,
+    operation: ChangeOperation,
+    version: Option<i64>,
+}
+
+impl From<Notification> for ChangeEvent {
+    fn from(notification: Notification) -> Self {
+        Self {
+            user_id: notification.user_id,
+            seq: notification.seq,
+            entity_type: notification.entity_type,
+            entity_id: notification.id,
+            operation: notification.operation,
+            version: notification.version,
+        }
+    }
+}
+
+/// Starts forwarding change notifications from Postgres into `bus`.
+///
+/// Returns once the listener is subscribed, so changes committed afterwards
+/// are guaranteed to be forwarded.
+pub async fn start_listener(pool: &PgPool, bus: EventBus) -> Result<(), sqlx::Error> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen(CHANNEL).await?;
+
+    tokio::spawn(async move {
+        loop {
+            match listener.recv().await {
+                Ok(notification) => {
+                    match serde_json::from_str::<Notification>(notification.payload()) {
+                        Ok(notification) => bus.publish(notification.into()),
+                        Err(e) => {
+                            tracing::warn!(error = %e, "Ignoring malformed change notification")
+                        }
+                    }
+                }
+                Err(e) => {
+                    // The listener reconnects on the next recv
+                    tracing::error!(error = %e, "Change notification listener failed");
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
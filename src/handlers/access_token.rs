@@ -0,0 +1,88 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::auth::AuthUser;
+use crate::error::{AppError, Result};
+use crate::models::{CreatePersonalAccessToken, CreatedPersonalAccessToken, PersonalAccessToken};
+use crate::services::PersonalAccessTokenService;
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/tokens",
+    request_body = CreatePersonalAccessToken,
+    responses(
+        (status = 201, description = "Token created; its secret is shown only in this response", body = CreatedPersonalAccessToken),
+        (status = 400, description = "Validation error"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not available to personal access tokens")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "auth"
+)]
+#[tracing::instrument(skip(pool, auth, input), fields(user_id = %auth.user_id))]
+pub async fn create_access_token(
+    State(pool): State<PgPool>,
+    auth: AuthUser,
+    Json(input): Json<CreatePersonalAccessToken>,
+) -> Result<(StatusCode, Json<CreatedPersonalAccessToken>)> {
+    auth.require_session()?;
+    input
+        .validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let token = PersonalAccessTokenService::create(&pool, auth.user_id, input).await?;
+    Ok((StatusCode::CREATED, Json(token)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/auth/tokens",
+    responses(
+        (status = 200, description = "The user's personal access tokens, newest first", body = Vec<PersonalAccessToken>),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not available to personal access tokens")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "auth"
+)]
+#[tracing::instrument(skip(pool, auth), fields(user_id = %auth.user_id))]
+pub async fn list_access_tokens(
+    State(pool): State<PgPool>,
+    auth: AuthUser,
+) -> Result<Json<Vec<PersonalAccessToken>>> {
+    auth.require_session()?;
+
+    let tokens = PersonalAccessTokenService::list(&pool, auth.user_id).await?;
+    Ok(Json(tokens))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/auth/tokens/{id}",
+    params(("id" = Uuid, Path, description = "Token ID")),
+    responses(
+        (status = 204, description = "Token revoked"),
+        (status = 404, description = "Token not found"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not available to personal access tokens")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "auth"
+)]
+#[tracing::instrument(skip(pool, auth), fields(user_id = %auth.user_id, token_id = %id))]
+pub async fn revoke_access_token(
+    State(pool): State<PgPool>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    auth.require_session()?;
+
+    PersonalAccessTokenService::revoke(&pool, auth.user_id, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,18 +1,59 @@
-use axum::{Json, extract::State, http::StatusCode};
+use axum::{
+    Json,
+    extract::State,
+    http::{StatusCode, header},
+};
 use serde::Serialize;
 use sqlx::PgPool;
 use utoipa::ToSchema;
 use validator::Validate;
 
-use crate::auth::{AuthUser, JwtManager};
+use crate::auth::{AuthUser, JwkSet, JwtManager};
 use crate::error::{AppError, Result};
-use crate::models::{CreateUser, LoginUser, UserResponse};
-use crate::services::UserService;
+use crate::models::{
+    CreateUser, Device, DeviceInfo, LoginUser, RefreshRequest, TwoFactorLogin, User, UserResponse,
+};
+use crate::services::{
+    DeviceService, RefreshTokenService, TokenRevocationService, TwoFactorService, UserService,
+};
 
 #[derive(Serialize, ToSchema)]
 pub struct AuthResponse {
+    /// Short-lived access token, sent as `Authorization: Bearer`
     pub token: String,
+    /// Seconds until `token` expires
+    pub expires_in: i64,
+    /// Single-use token for `POST /api/auth/refresh`
+    pub refresh_token: String,
     pub user: UserResponse,
+    /// Device the token was issued to
+    pub device: Device,
+}
+
+/// A password accepted for an account with two-factor authentication; the
+/// login completes at `POST /api/auth/login/2fa`.
+#[derive(Serialize, ToSchema)]
+pub struct TwoFactorChallenge {
+    /// Token to present together with a code
+    pub challenge_token: String,
+    /// Seconds until `challenge_token` expires
+    pub expires_in: i64,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum LoginResponse {
+    Authenticated(Box<AuthResponse>),
+    TwoFactorRequired(TwoFactorChallenge),
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TokenResponse {
+    pub token: String,
+    /// Seconds until `token` expires
+    pub expires_in: i64,
+    /// Replaces the refresh token that was presented, which can no longer be used
+    pub refresh_token: String,
 }
 
 #[utoipa::path(
@@ -30,22 +71,17 @@ pub struct AuthResponse {
 pub async fn register(
     State(pool): State<PgPool>,
     State(jwt): State<JwtManager>,
-    Json(input): Json<CreateUser>,
+    Json(mut input): Json<CreateUser>,
 ) -> Result<(StatusCode, Json<AuthResponse>)> {
     input
         .validate()
         .map_err(|e| AppError::Validation(e.to_string()))?;
 
+    let device_info = input.device.take();
     let user = UserService::create(&pool, input).await?;
-    let token = jwt.generate_token(user.id, &user.email)?;
+    let response = sign_in(&pool, &jwt, user, device_info).await?;
 
-    Ok((
-        StatusCode::CREATED,
-        Json(AuthResponse {
-            token,
-            user: user.into(),
-        }),
-    ))
+    Ok((StatusCode::CREATED, Json(response)))
 }
 
 #[utoipa::path(
@@ -54,6 +90,7 @@ pub async fn register(
     request_body = LoginUser,
     responses(
         (status = 200, description = "Login successful", body = AuthResponse),
+        (status = 202, description = "Password accepted; a two-factor code is required", body = TwoFactorChallenge),
         (status = 401, description = "Invalid credentials")
     ),
     tag = "auth"
@@ -63,20 +100,135 @@ pub async fn login(
     State(pool): State<PgPool>,
     State(jwt): State<JwtManager>,
     Json(input): Json<LoginUser>,
-) -> Result<Json<AuthResponse>> {
+) -> Result<(StatusCode, Json<LoginResponse>)> {
     input
         .validate()
         .map_err(|e| AppError::Validation(e.to_string()))?;
 
     let user = UserService::authenticate(&pool, &input.email, &input.password).await?;
-    let token = jwt.generate_token(user.id, &user.email)?;
 
-    Ok(Json(AuthResponse {
+    if TwoFactorService::is_enabled(&pool, user.id).await? {
+        let challenge_token = TwoFactorService::start_login(&pool, user.id, input.device).await?;
+        return Ok((
+            StatusCode::ACCEPTED,
+            Json(LoginResponse::TwoFactorRequired(TwoFactorChallenge {
+                challenge_token,
+                expires_in: TwoFactorService::challenge_expires_in(),
+            })),
+        ));
+    }
+
+    let response = sign_in(&pool, &jwt, user, input.device).await?;
+    Ok((
+        StatusCode::OK,
+        Json(LoginResponse::Authenticated(Box::new(response))),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/login/2fa",
+    request_body = TwoFactorLogin,
+    responses(
+        (status = 200, description = "Login successful", body = AuthResponse),
+        (status = 400, description = "Validation error"),
+        (status = 401, description = "Wrong code, or the challenge is invalid, expired or was dropped after too many wrong codes"),
+        (status = 429, description = "Codes are locked for a while after too many wrong ones")
+    ),
+    tag = "auth"
+)]
+#[tracing::instrument(skip(pool, jwt, input))]
+pub async fn login_two_factor(
+    State(pool): State<PgPool>,
+    State(jwt): State<JwtManager>,
+    Json(input): Json<TwoFactorLogin>,
+) -> Result<Json<AuthResponse>> {
+    input
+        .validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let passed =
+        TwoFactorService::complete_login(&pool, &input.challenge_token, &input.code).await?;
+    let user = UserService::get_by_id(&pool, passed.user_id).await?;
+
+    Ok(Json(sign_in(&pool, &jwt, user, passed.device).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/refresh",
+    request_body = RefreshRequest,
+    responses(
+        (status = 200, description = "New access and refresh tokens", body = TokenResponse),
+        (status = 400, description = "Validation error"),
+        (status = 401, description = "Refresh token invalid, expired, revoked or already used")
+    ),
+    tag = "auth"
+)]
+#[tracing::instrument(skip(pool, jwt, input))]
+pub async fn refresh(
+    State(pool): State<PgPool>,
+    State(jwt): State<JwtManager>,
+    Json(input): Json<RefreshRequest>,
+) -> Result<Json<TokenResponse>> {
+    input
+        .validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let rotated =
+        RefreshTokenService::rotate(&pool, &input.refresh_token, jwt.refresh_expiration()).await?;
+    let user = UserService::get_by_id(&pool, rotated.user_id).await?;
+    let token = jwt.generate_token(user.id, &user.email, rotated.device_id)?;
+
+    Ok(Json(TokenResponse {
         token,
-        user: user.into(),
+        expires_in: jwt.expires_in(),
+        refresh_token: rotated.refresh_token,
     }))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/auth/logout",
+    responses(
+        (status = 204, description = "Access token and the device's refresh tokens revoked"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access tokens are revoked through /api/auth/tokens")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "auth"
+)]
+#[tracing::instrument(skip(pool, jwt, auth), fields(user_id = %auth.user_id))]
+pub async fn logout(
+    State(pool): State<PgPool>,
+    State(jwt): State<JwtManager>,
+    auth: AuthUser,
+) -> Result<StatusCode> {
+    TokenRevocationService::logout(&pool, jwt.revocations(), &auth).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/logout-all",
+    responses(
+        (status = 204, description = "Every device signed out and all tokens revoked"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access tokens are revoked through /api/auth/tokens")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "auth"
+)]
+#[tracing::instrument(skip(pool, jwt, auth), fields(user_id = %auth.user_id))]
+pub async fn logout_all(
+    State(pool): State<PgPool>,
+    State(jwt): State<JwtManager>,
+    auth: AuthUser,
+) -> Result<StatusCode> {
+    TokenRevocationService::logout_all(&pool, jwt.revocations(), &auth).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
 #[utoipa::path(
     get,
     path = "/api/auth/me",
@@ -92,3 +244,42 @@ pub async fn me(State(pool): State<PgPool>, auth: AuthUser) -> Result<Json<UserR
     let user = UserService::get_by_id(&pool, auth.user_id).await?;
     Ok(Json(user.into()))
 }
+
+#[utoipa::path(
+    get,
+    path = "/.well-known/jwks.json",
+    responses(
+        (status = 200, description = "Public keys access tokens can be verified with; empty when tokens are signed with a shared secret", body = JwkSet)
+    ),
+    tag = "auth"
+)]
+pub async fn jwks(
+    State(jwt): State<JwtManager>,
+) -> ([(header::HeaderName, &'static str); 1], Json<JwkSet>) {
+    // Verifiers may cache the set briefly; new keys are published before they sign
+    (
+        [(header::CACHE_CONTROL, "public, max-age=300")],
+        Json(jwt.jwks()),
+    )
+}
+
+/// Registers the device being signed in from and issues its tokens.
+async fn sign_in(
+    pool: &PgPool,
+    jwt: &JwtManager,
+    user: User,
+    device: Option<DeviceInfo>,
+) -> Result<AuthResponse> {
+    let device = DeviceService::register(pool, user.id, device).await?;
+    let token = jwt.generate_token(user.id, &user.email, device.id)?;
+    let refresh_token =
+        RefreshTokenService::issue(pool, user.id, device.id, jwt.refresh_expiration()).await?;
+
+    Ok(AuthResponse {
+        token,
+        expires_in: jwt.expires_in(),
+        refresh_token,
+        user: user.into(),
+        device,
+    })
+}
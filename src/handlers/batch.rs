@@ -0,0 +1,53 @@
+use axum::{Json, extract::State};
+use sqlx::PgPool;
+use validator::Validate;
+
+use crate::auth::AuthUser;
+use crate::canonical_url::CanonicalUrlOptions;
+use crate::error::{AppError, Result};
+use crate::models::{BatchEntity, BatchRequest, BatchResponse, Scope};
+use crate::services::BatchService;
+
+#[utoipa::path(
+    post,
+    path = "/api/batch",
+    request_body = BatchRequest,
+    responses(
+        (status = 200, description = "All operations applied", body = BatchResponse),
+        (status = 400, description = "Validation error; nothing was applied"),
+        (status = 404, description = "An operation's target was not found; nothing was applied"),
+        (status = 409, description = "An operation conflicted; nothing was applied"),
+        (status = 412, description = "An operation's version was stale; nothing was applied"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "sync"
+)]
+#[tracing::instrument(skip(pool, auth, input), fields(user_id = %auth.user_id, operations = input.operations.len()))]
+pub async fn run_batch(
+    State(pool): State<PgPool>,
+    State(canonical_urls): State<CanonicalUrlOptions>,
+    auth: AuthUser,
+    Json(input): Json<BatchRequest>,
+) -> Result<Json<BatchResponse>> {
+    input
+        .validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+    for operation in &input.operations {
+        auth.require(write_scope(operation.entity))?;
+    }
+
+    let results =
+        BatchService::execute(&pool, canonical_urls, auth.user_id, input.operations).await?;
+    Ok(Json(BatchResponse { results }))
+}
+
+fn write_scope(entity: BatchEntity) -> Scope {
+    match entity {
+        BatchEntity::Bookmark => Scope::BookmarksWrite,
+        BatchEntity::Note => Scope::NotesWrite,
+        BatchEntity::Tag => Scope::TagsWrite,
+        BatchEntity::Category => Scope::CategoriesWrite,
+    }
+}
@@ -1,33 +1,37 @@
 use axum::{
     Json,
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{Path, Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
 };
 use sqlx::PgPool;
-use utoipa::ToSchema;
 use uuid::Uuid;
 use validator::Validate;
 
 use crate::auth::AuthUser;
+use crate::blob::BlobStorage;
+use crate::canonical_url::CanonicalUrlOptions;
 use crate::error::{AppError, Result};
-use crate::models::{Bookmark, CreateBookmark, UpdateBookmark};
-use crate::services::BookmarkService;
-
-#[derive(serde::Serialize, ToSchema)]
-pub struct BookmarkWithTags {
-    #[serde(flatten)]
-    pub bookmark: Bookmark,
-    pub tags: Vec<crate::models::Tag>,
-}
+use crate::handlers::etag::{ETagHeader, IfMatch, IfNoneMatch, etag_header};
+use crate::models::{
+    BookmarkArchive, BookmarkCreateOutcome, BookmarkListQuery, BookmarkWithTags, CreateBookmark,
+    DuplicateGroup, LinkPreview, MergeBookmarks, Page, PreviewRequest, Scope, UpdateBookmark,
+};
+use crate::preview::PreviewFetcher;
+use crate::services::{ArchiveService, BookmarkService, PreviewService};
 
 #[utoipa::path(
     post,
     path = "/api/bookmarks",
     request_body = CreateBookmark,
     responses(
-        (status = 201, description = "Bookmark created", body = Bookmark),
+        (status = 201, description = "Bookmark created", body = BookmarkWithTags),
+        (status = 200, description = "Merged into an existing bookmark with the same URL", body = BookmarkWithTags),
         (status = 400, description = "Validation error"),
-        (status = 401, description = "Unauthorized")
+        (status = 404, description = "A tag in `tag_ids` was not found"),
+        (status = 409, description = "A bookmark with the same URL exists and `on_duplicate` is `reject`"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
     ),
     security(("bearer_auth" = [])),
     tag = "bookmarks"
@@ -35,34 +39,51 @@ pub struct BookmarkWithTags {
 #[tracing::instrument(skip(pool, auth, input), fields(user_id = %auth.user_id))]
 pub async fn create_bookmark(
     State(pool): State<PgPool>,
+    State(canonical_urls): State<CanonicalUrlOptions>,
     auth: AuthUser,
     Json(input): Json<CreateBookmark>,
-) -> Result<(StatusCode, Json<Bookmark>)> {
+) -> Result<(StatusCode, ETagHeader, Json<BookmarkWithTags>)> {
+    auth.require(Scope::BookmarksWrite)?;
+
     input
         .validate()
         .map_err(|e| AppError::Validation(e.to_string()))?;
 
-    let bookmark = BookmarkService::create(&pool, auth.user_id, input).await?;
+    let (status, bookmark) =
+        match BookmarkService::create(&pool, canonical_urls, auth.user_id, input).await? {
+            BookmarkCreateOutcome::Created(bookmark) => (StatusCode::CREATED, bookmark),
+            BookmarkCreateOutcome::Merged(bookmark) => (StatusCode::OK, bookmark),
+        };
 
-    Ok((StatusCode::CREATED, Json(bookmark)))
+    Ok((
+        status,
+        etag_header(bookmark.bookmark.version),
+        Json(bookmark),
+    ))
 }
 
 #[utoipa::path(
     get,
     path = "/api/bookmarks",
+    params(BookmarkListQuery),
     responses(
-        (status = 200, description = "List of bookmarks", body = Vec<Bookmark>),
-        (status = 401, description = "Unauthorized")
+        (status = 200, description = "Page of bookmarks", body = Page<BookmarkWithTags>),
+        (status = 400, description = "Invalid filter or cursor"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
     ),
     security(("bearer_auth" = [])),
     tag = "bookmarks"
 )]
-#[tracing::instrument(skip(pool, auth), fields(user_id = %auth.user_id))]
+#[tracing::instrument(skip(pool, auth, query), fields(user_id = %auth.user_id))]
 pub async fn list_bookmarks(
     State(pool): State<PgPool>,
     auth: AuthUser,
-) -> Result<Json<Vec<Bookmark>>> {
-    let bookmarks = BookmarkService::list(&pool, auth.user_id).await?;
+    Query(query): Query<BookmarkListQuery>,
+) -> Result<Json<Page<BookmarkWithTags>>> {
+    auth.require(Scope::BookmarksRead)?;
+
+    let bookmarks = BookmarkService::list(&pool, auth.user_id, query).await?;
     Ok(Json(bookmarks))
 }
 
@@ -70,12 +91,15 @@ pub async fn list_bookmarks(
     get,
     path = "/api/bookmarks/{id}",
     params(
-        ("id" = Uuid, Path, description = "Bookmark ID")
+        ("id" = Uuid, Path, description = "Bookmark ID"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag from a previous response")
     ),
     responses(
-        (status = 200, description = "Bookmark found", body = Bookmark),
+        (status = 200, description = "Bookmark found", body = BookmarkWithTags),
+        (status = 304, description = "Bookmark not modified"),
         (status = 404, description = "Bookmark not found"),
-        (status = 401, description = "Unauthorized")
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
     ),
     security(("bearer_auth" = [])),
     tag = "bookmarks"
@@ -85,22 +109,34 @@ pub async fn get_bookmark(
     State(pool): State<PgPool>,
     auth: AuthUser,
     Path(id): Path<Uuid>,
-) -> Result<Json<Bookmark>> {
-    let bookmark = BookmarkService::get_by_id(&pool, auth.user_id, id).await?;
-    Ok(Json(bookmark))
+    if_none_match: IfNoneMatch,
+) -> Result<Response> {
+    auth.require(Scope::BookmarksRead)?;
+
+    let bookmark = BookmarkService::get_with_tags(&pool, auth.user_id, id).await?;
+    let version = bookmark.bookmark.version;
+
+    if if_none_match.matches(version) {
+        return Ok((StatusCode::NOT_MODIFIED, etag_header(version)).into_response());
+    }
+
+    Ok((etag_header(version), Json(bookmark)).into_response())
 }
 
 #[utoipa::path(
     put,
     path = "/api/bookmarks/{id}",
     params(
-        ("id" = Uuid, Path, description = "Bookmark ID")
+        ("id" = Uuid, Path, description = "Bookmark ID"),
+        ("If-Match" = Option<String>, Header, description = "ETag the update is based on")
     ),
     request_body = UpdateBookmark,
     responses(
-        (status = 200, description = "Bookmark updated", body = Bookmark),
-        (status = 404, description = "Bookmark not found"),
-        (status = 401, description = "Unauthorized")
+        (status = 200, description = "Bookmark updated", body = BookmarkWithTags),
+        (status = 412, description = "Bookmark was modified since the given ETag"),
+        (status = 404, description = "Bookmark or a tag in `tag_ids` not found"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
     ),
     security(("bearer_auth" = [])),
     tag = "bookmarks"
@@ -108,24 +144,39 @@ pub async fn get_bookmark(
 #[tracing::instrument(skip(pool, auth, input), fields(user_id = %auth.user_id, bookmark_id = %id))]
 pub async fn update_bookmark(
     State(pool): State<PgPool>,
+    State(canonical_urls): State<CanonicalUrlOptions>,
     auth: AuthUser,
     Path(id): Path<Uuid>,
+    IfMatch(expected_version): IfMatch,
     Json(input): Json<UpdateBookmark>,
-) -> Result<Json<Bookmark>> {
-    let bookmark = BookmarkService::update(&pool, auth.user_id, id, input).await?;
-    Ok(Json(bookmark))
+) -> Result<(ETagHeader, Json<BookmarkWithTags>)> {
+    auth.require(Scope::BookmarksWrite)?;
+
+    let bookmark = BookmarkService::update(
+        &pool,
+        canonical_urls,
+        auth.user_id,
+        id,
+        input,
+        expected_version,
+    )
+    .await?;
+    Ok((etag_header(bookmark.bookmark.version), Json(bookmark)))
 }
 
 #[utoipa::path(
     delete,
     path = "/api/bookmarks/{id}",
     params(
-        ("id" = Uuid, Path, description = "Bookmark ID")
+        ("id" = Uuid, Path, description = "Bookmark ID"),
+        ("If-Match" = Option<String>, Header, description = "ETag the deletion is based on")
     ),
     responses(
         (status = 204, description = "Bookmark deleted"),
+        (status = 412, description = "Bookmark was modified since the given ETag"),
         (status = 404, description = "Bookmark not found"),
-        (status = 401, description = "Unauthorized")
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
     ),
     security(("bearer_auth" = [])),
     tag = "bookmarks"
@@ -135,7 +186,189 @@ pub async fn delete_bookmark(
     State(pool): State<PgPool>,
     auth: AuthUser,
     Path(id): Path<Uuid>,
+    IfMatch(expected_version): IfMatch,
 ) -> Result<StatusCode> {
-    BookmarkService::delete(&pool, auth.user_id, id).await?;
+    auth.require(Scope::BookmarksWrite)?;
+
+    BookmarkService::delete(&pool, auth.user_id, id, expected_version).await?;
     Ok(StatusCode::NO_CONTENT)
 }
+
+#[utoipa::path(
+    get,
+    path = "/api/bookmarks/duplicates",
+    responses(
+        (status = 200, description = "Groups of bookmarks sharing a canonical URL", body = Vec<DuplicateGroup>),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "bookmarks"
+)]
+#[tracing::instrument(skip(pool, auth), fields(user_id = %auth.user_id))]
+pub async fn list_duplicate_bookmarks(
+    State(pool): State<PgPool>,
+    auth: AuthUser,
+) -> Result<Json<Vec<DuplicateGroup>>> {
+    auth.require(Scope::BookmarksRead)?;
+
+    let groups = BookmarkService::duplicates(&pool, auth.user_id).await?;
+    Ok(Json(groups))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/bookmarks/{id}/merge",
+    params(
+        ("id" = Uuid, Path, description = "Bookmark ID to merge into"),
+        ("If-Match" = Option<String>, Header, description = "ETag of the target bookmark")
+    ),
+    request_body = MergeBookmarks,
+    responses(
+        (status = 200, description = "Sources merged into the bookmark and deleted", body = BookmarkWithTags),
+        (status = 400, description = "Validation error"),
+        (status = 412, description = "Bookmark was modified since the given ETag"),
+        (status = 404, description = "Bookmark not found"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "bookmarks"
+)]
+#[tracing::instrument(skip(pool, auth, input), fields(user_id = %auth.user_id, bookmark_id = %id))]
+pub async fn merge_bookmarks(
+    State(pool): State<PgPool>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+    IfMatch(expected_version): IfMatch,
+    Json(input): Json<MergeBookmarks>,
+) -> Result<(ETagHeader, Json<BookmarkWithTags>)> {
+    auth.require(Scope::BookmarksWrite)?;
+
+    input
+        .validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let bookmark = BookmarkService::merge(&pool, auth.user_id, id, input, expected_version).await?;
+    Ok((etag_header(bookmark.bookmark.version), Json(bookmark)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/bookmarks/preview",
+    request_body = PreviewRequest,
+    responses(
+        (status = 200, description = "Metadata read from the page", body = LinkPreview),
+        (status = 400, description = "Invalid URL, or the URL points at a private address"),
+        (status = 422, description = "The page could not be fetched"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "bookmarks"
+)]
+#[tracing::instrument(skip(fetcher, auth, input), fields(user_id = %auth.user_id))]
+pub async fn preview_bookmark(
+    State(fetcher): State<PreviewFetcher>,
+    auth: AuthUser,
+    Json(input): Json<PreviewRequest>,
+) -> Result<Json<LinkPreview>> {
+    auth.require(Scope::BookmarksWrite)?;
+
+    input
+        .validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let preview = fetcher.fetch(&input.url).await?;
+    Ok(Json(preview))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/bookmarks/{id}/preview",
+    params(("id" = Uuid, Path, description = "Bookmark ID")),
+    responses(
+        (status = 200, description = "Bookmark with its preview fetched again", body = BookmarkWithTags),
+        (status = 400, description = "The bookmark's URL points at a private address"),
+        (status = 422, description = "The page could not be fetched"),
+        (status = 404, description = "Bookmark not found"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "bookmarks"
+)]
+#[tracing::instrument(skip(pool, fetcher, blobs, auth), fields(user_id = %auth.user_id, bookmark_id = %id))]
+pub async fn refresh_bookmark_preview(
+    State(pool): State<PgPool>,
+    State(fetcher): State<PreviewFetcher>,
+    State(blobs): State<BlobStorage>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<(ETagHeader, Json<BookmarkWithTags>)> {
+    auth.require(Scope::BookmarksWrite)?;
+
+    let bookmark = PreviewService::refresh(&pool, &fetcher, &blobs, auth.user_id, id).await?;
+    Ok((etag_header(bookmark.bookmark.version), Json(bookmark)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/bookmarks/{id}/archive",
+    params(("id" = Uuid, Path, description = "Bookmark ID")),
+    responses(
+        (status = 200, description = "Readable text of the archived page", body = BookmarkArchive),
+        (status = 404, description = "Bookmark not found or not archived yet"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "bookmarks"
+)]
+#[tracing::instrument(skip(pool, auth), fields(user_id = %auth.user_id, bookmark_id = %id))]
+pub async fn get_bookmark_archive(
+    State(pool): State<PgPool>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<BookmarkArchive>> {
+    auth.require(Scope::BookmarksRead)?;
+
+    let archive = ArchiveService::get(&pool, auth.user_id, id).await?;
+    Ok(Json(archive))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/bookmarks/{id}/archive/raw",
+    params(("id" = Uuid, Path, description = "Bookmark ID")),
+    responses(
+        (status = 200, description = "The archived page as it was fetched", content_type = "text/html"),
+        (status = 404, description = "Bookmark not found or not archived yet"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "bookmarks"
+)]
+#[tracing::instrument(skip(pool, blobs, auth), fields(user_id = %auth.user_id, bookmark_id = %id))]
+pub async fn get_bookmark_archive_raw(
+    State(pool): State<PgPool>,
+    State(blobs): State<BlobStorage>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Response> {
+    auth.require(Scope::BookmarksRead)?;
+
+    let archive = ArchiveService::get_raw(&pool, &blobs, auth.user_id, id).await?;
+
+    // Archived pages are third-party content; keep their scripts off this origin
+    Ok((
+        [
+            (header::CONTENT_TYPE, archive.content_type),
+            (header::CONTENT_SECURITY_POLICY, "sandbox".to_string()),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+        ],
+        archive.raw_content,
+    )
+        .into_response())
+}
@@ -1,7 +1,8 @@
 use axum::{
     Json,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
+    response::{IntoResponse, Response},
 };
 use sqlx::PgPool;
 use uuid::Uuid;
@@ -9,7 +10,8 @@ use validator::Validate;
 
 use crate::auth::AuthUser;
 use crate::error::{AppError, Result};
-use crate::models::{Category, CreateCategory, UpdateCategory};
+use crate::handlers::etag::{ETagHeader, IfMatch, IfNoneMatch, etag_header};
+use crate::models::{Category, CategoryListQuery, CreateCategory, Page, Scope, UpdateCategory};
 use crate::services::CategoryService;
 
 #[utoipa::path(
@@ -20,7 +22,8 @@ use crate::services::CategoryService;
         (status = 201, description = "Category created", body = Category),
         (status = 400, description = "Validation error"),
         (status = 409, description = "Category already exists"),
-        (status = 401, description = "Unauthorized")
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
     ),
     security(("bearer_auth" = [])),
     tag = "categories"
@@ -30,31 +33,43 @@ pub async fn create_category(
     State(pool): State<PgPool>,
     auth: AuthUser,
     Json(input): Json<CreateCategory>,
-) -> Result<(StatusCode, Json<Category>)> {
+) -> Result<(StatusCode, ETagHeader, Json<Category>)> {
+    auth.require(Scope::CategoriesWrite)?;
+
     input
         .validate()
         .map_err(|e| AppError::Validation(e.to_string()))?;
 
     let category = CategoryService::create(&pool, auth.user_id, input).await?;
-    Ok((StatusCode::CREATED, Json(category)))
+    Ok((
+        StatusCode::CREATED,
+        etag_header(category.version),
+        Json(category),
+    ))
 }
 
 #[utoipa::path(
     get,
     path = "/api/categories",
+    params(CategoryListQuery),
     responses(
-        (status = 200, description = "List of categories", body = Vec<Category>),
-        (status = 401, description = "Unauthorized")
+        (status = 200, description = "Page of categories", body = Page<Category>),
+        (status = 400, description = "Invalid filter or cursor"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
     ),
     security(("bearer_auth" = [])),
     tag = "categories"
 )]
-#[tracing::instrument(skip(pool, auth), fields(user_id = %auth.user_id))]
+#[tracing::instrument(skip(pool, auth, query), fields(user_id = %auth.user_id))]
 pub async fn list_categories(
     State(pool): State<PgPool>,
     auth: AuthUser,
-) -> Result<Json<Vec<Category>>> {
-    let categories = CategoryService::list(&pool, auth.user_id).await?;
+    Query(query): Query<CategoryListQuery>,
+) -> Result<Json<Page<Category>>> {
+    auth.require(Scope::CategoriesRead)?;
+
+    let categories = CategoryService::list(&pool, auth.user_id, query).await?;
     Ok(Json(categories))
 }
 
@@ -62,12 +77,15 @@ pub async fn list_categories(
     get,
     path = "/api/categories/{id}",
     params(
-        ("id" = Uuid, Path, description = "Category ID")
+        ("id" = Uuid, Path, description = "Category ID"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag from a previous response")
     ),
     responses(
         (status = 200, description = "Category found", body = Category),
+        (status = 304, description = "Category not modified"),
         (status = 404, description = "Category not found"),
-        (status = 401, description = "Unauthorized")
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
     ),
     security(("bearer_auth" = [])),
     tag = "categories"
@@ -77,22 +95,33 @@ pub async fn get_category(
     State(pool): State<PgPool>,
     auth: AuthUser,
     Path(id): Path<Uuid>,
-) -> Result<Json<Category>> {
+    if_none_match: IfNoneMatch,
+) -> Result<Response> {
+    auth.require(Scope::CategoriesRead)?;
+
     let category = CategoryService::get_by_id(&pool, auth.user_id, id).await?;
-    Ok(Json(category))
+
+    if if_none_match.matches(category.version) {
+        return Ok((StatusCode::NOT_MODIFIED, etag_header(category.version)).into_response());
+    }
+
+    Ok((etag_header(category.version), Json(category)).into_response())
 }
 
 #[utoipa::path(
     put,
     path = "/api/categories/{id}",
     params(
-        ("id" = Uuid, Path, description = "Category ID")
+        ("id" = Uuid, Path, description = "Category ID"),
+        ("If-Match" = Option<String>, Header, description = "ETag the update is based on")
     ),
     request_body = UpdateCategory,
     responses(
         (status = 200, description = "Category updated", body = Category),
+        (status = 412, description = "Category was modified since the given ETag"),
         (status = 404, description = "Category not found"),
-        (status = 401, description = "Unauthorized")
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
     ),
     security(("bearer_auth" = [])),
     tag = "categories"
@@ -102,22 +131,29 @@ pub async fn update_category(
     State(pool): State<PgPool>,
     auth: AuthUser,
     Path(id): Path<Uuid>,
+    IfMatch(expected_version): IfMatch,
     Json(input): Json<UpdateCategory>,
-) -> Result<Json<Category>> {
-    let category = CategoryService::update(&pool, auth.user_id, id, input).await?;
-    Ok(Json(category))
+) -> Result<(ETagHeader, Json<Category>)> {
+    auth.require(Scope::CategoriesWrite)?;
+
+    let category =
+        CategoryService::update(&pool, auth.user_id, id, input, expected_version).await?;
+    Ok((etag_header(category.version), Json(category)))
 }
 
 #[utoipa::path(
     delete,
     path = "/api/categories/{id}",
     params(
-        ("id" = Uuid, Path, description = "Category ID")
+        ("id" = Uuid, Path, description = "Category ID"),
+        ("If-Match" = Option<String>, Header, description = "ETag the deletion is based on")
     ),
     responses(
         (status = 204, description = "Category deleted"),
+        (status = 412, description = "Category was modified since the given ETag"),
         (status = 404, description = "Category not found"),
-        (status = 401, description = "Unauthorized")
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
     ),
     security(("bearer_auth" = [])),
     tag = "categories"
@@ -127,7 +163,10 @@ pub async fn delete_category(
     State(pool): State<PgPool>,
     auth: AuthUser,
     Path(id): Path<Uuid>,
+    IfMatch(expected_version): IfMatch,
 ) -> Result<StatusCode> {
-    CategoryService::delete(&pool, auth.user_id, id).await?;
+    auth.require(Scope::CategoriesWrite)?;
+
+    CategoryService::delete(&pool, auth.user_id, id, expected_version).await?;
     Ok(StatusCode::NO_CONTENT)
 }
@@ -0,0 +1,57 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::auth::{AuthUser, JwtManager};
+use crate::error::Result;
+use crate::models::Device;
+use crate::services::DeviceService;
+
+#[utoipa::path(
+    get,
+    path = "/api/devices",
+    responses(
+        (status = 200, description = "Devices the user has signed in from", body = Vec<Device>),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not available to personal access tokens")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "devices"
+)]
+#[tracing::instrument(skip(pool, auth), fields(user_id = %auth.user_id))]
+pub async fn list_devices(State(pool): State<PgPool>, auth: AuthUser) -> Result<Json<Vec<Device>>> {
+    auth.require_session()?;
+
+    let devices = DeviceService::list(&pool, auth.user_id).await?;
+    Ok(Json(devices))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/devices/{id}",
+    params(("id" = Uuid, Path, description = "Device ID")),
+    responses(
+        (status = 204, description = "Device signed out"),
+        (status = 404, description = "Device not found"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not available to personal access tokens")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "devices"
+)]
+#[tracing::instrument(skip(pool, jwt, auth), fields(user_id = %auth.user_id, device_id = %id))]
+pub async fn revoke_device(
+    State(pool): State<PgPool>,
+    State(jwt): State<JwtManager>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    auth.require_session()?;
+
+    DeviceService::revoke(&pool, jwt.revocations(), auth.user_id, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,94 @@
+use axum::{
+    extract::FromRequestParts,
+    http::{HeaderMap, HeaderName, header, request::Parts},
+};
+
+use crate::error::AppError;
+
+/// `ETag` response header, usable directly as a response part.
+pub type ETagHeader = [(HeaderName, String); 1];
+
+pub fn etag(version: i64) -> String {
+    format!("\"{}\"", version)
+}
+
+pub fn etag_header(version: i64) -> ETagHeader {
+    [(header::ETAG, etag(version))]
+}
+
+/// Parses a single entity tag, accepting weak tags since versions are compared weakly.
+pub fn parse_etag(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let value = value.strip_prefix("W/").unwrap_or(value);
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .and_then(|v| v.parse().ok())
+}
+
+fn header_value<'a>(
+    headers: &'a HeaderMap,
+    name: &header::HeaderName,
+) -> Result<Option<&'a str>, AppError> {
+    headers
+        .get(name)
+        .map(|value| {
+            value
+                .to_str()
+                .map_err(|_| AppError::Validation(format!("Invalid {} header", name)))
+        })
+        .transpose()
+}
+
+/// `If-Match` precondition: the version the client expects to modify, if any.
+///
+/// `None` when the header is absent or `*`, meaning any current version is acceptable.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IfMatch(pub Option<i64>);
+
+impl<S> FromRequestParts<S> for IfMatch
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let Some(value) = header_value(&parts.headers, &header::IF_MATCH)? else {
+            return Ok(IfMatch(None));
+        };
+
+        if value.trim() == "*" {
+            return Ok(IfMatch(None));
+        }
+
+        parse_etag(value)
+            .map(|version| IfMatch(Some(version)))
+            .ok_or_else(|| AppError::Validation("Invalid If-Match header".to_string()))
+    }
+}
+
+/// `If-None-Match` on reads: lets the handler answer `304 Not Modified`.
+#[derive(Debug, Clone, Default)]
+pub struct IfNoneMatch(pub Option<String>);
+
+impl IfNoneMatch {
+    pub fn matches(&self, version: i64) -> bool {
+        self.0.as_deref().is_some_and(|value| {
+            value
+                .split(',')
+                .any(|tag| tag.trim() == "*" || parse_etag(tag) == Some(version))
+        })
+    }
+}
+
+impl<S> FromRequestParts<S> for IfNoneMatch
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let value = header_value(&parts.headers, &header::IF_NONE_MATCH)?;
+        Ok(IfNoneMatch(value.map(str::to_string)))
+    }
+}
@@ -0,0 +1,31 @@
+#[cfg(test)]
+mod tests {
+    use crate::handlers::etag::{IfNoneMatch, etag, parse_etag};
+
+    #[test]
+    fn test_etag_round_trip() {
+        assert_eq!(etag(7), "\"7\"");
+        assert_eq!(parse_etag(&etag(7)), Some(7));
+    }
+
+    #[test]
+    fn test_parse_weak_etag() {
+        assert_eq!(parse_etag("W/\"3\""), Some(3));
+    }
+
+    #[test]
+    fn test_parse_invalid_etag() {
+        assert_eq!(parse_etag("3"), None);
+        assert_eq!(parse_etag("\"abc\""), None);
+    }
+
+    #[test]
+    fn test_if_none_match_list_and_wildcard() {
+        let header = IfNoneMatch(Some("\"1\", \"2\"".to_string()));
+        assert!(header.matches(2));
+        assert!(!header.matches(3));
+
+        assert!(IfNoneMatch(Some("*".to_string())).matches(42));
+        assert!(!IfNoneMatch(None).matches(1));
+    }
+}
@@ -0,0 +1,199 @@
+use std::convert::Infallible;
+use std::time::Duration;
+
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    response::{
+        IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+};
+use sqlx::PgPool;
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    mpsc,
+};
+use tokio::time::{MissedTickBehavior, interval_at};
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::auth::{AuthUser, JwtManager};
+use crate::error::Result;
+use crate::events::{EventBus, REAUTH_INTERVAL};
+use crate::models::{ChangeEvent, EventsQuery, Scope, SyncCursor};
+use crate::services::SyncService;
+
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+const REPLAY_PAGE_SIZE: i64 = 500;
+const STREAM_BUFFER: usize = 64;
+
+#[utoipa::path(
+    get,
+    path = "/api/events",
+    params(
+        EventsQuery,
+        ("Last-Event-ID" = Option<String>, Header, description = "Id of the last event received, to replay missed events")
+    ),
+    responses(
+        (status = 200, description = "Stream of `change` events whose data is a ChangeEvent", body = ChangeEvent, content_type = "text/event-stream"),
+        (status = 400, description = "Invalid cursor"),
+        (status = 410, description = "Cursor expired, full resync required"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "sync"
+)]
+#[tracing::instrument(skip(pool, jwt, events, auth, query, headers), fields(user_id = %auth.user_id))]
+pub async fn stream_events(
+    State(pool): State<PgPool>,
+    State(jwt): State<JwtManager>,
+    State(events): State<EventBus>,
+    auth: AuthUser,
+    Query(query): Query<EventsQuery>,
+    headers: HeaderMap,
+) -> Result<Response> {
+    auth.require(Scope::SyncRead)?;
+
+    let resume_from = headers
+        .get("Last-Event-ID")
+        .and_then(|value| value.to_str().ok())
+        .or(query.since.as_deref())
+        .map(SyncCursor::decode)
+        .transpose()?;
+
+    // Subscribe before reading the log so nothing falls between replay and live events
+    let receiver = events.subscribe();
+
+    let (since, backlog) = match resume_from {
+        Some(since) => {
+            let backlog =
+                SyncService::events_since(&pool, auth.user_id, since, Some(REPLAY_PAGE_SIZE))
+                    .await?;
+            (since, backlog)
+        }
+        None => (
+            SyncService::current_cursor(&pool, auth.user_id).await?,
+            Vec::new(),
+        ),
+    };
+
+    let (sender, stream) = mpsc::channel(STREAM_BUFFER);
+    tokio::spawn(forward_events(
+        pool, jwt, auth, since, backlog, receiver, sender,
+    ));
+
+    Ok(Sse::new(ReceiverStream::new(stream))
+        .keep_alive(
+            KeepAlive::new()
+                .interval(HEARTBEAT_INTERVAL)
+                .text("heartbeat"),
+        )
+        .into_response())
+}
+
+/// Replays the backlog, then relays live events until the client goes away
+/// or its credentials stop being valid.
+async fn forward_events(
+    pool: PgPool,
+    jwt: JwtManager,
+    auth: AuthUser,
+    mut cursor: SyncCursor,
+    mut backlog: Vec<ChangeEvent>,
+    mut receiver: broadcast::Receiver<ChangeEvent>,
+    sender: mpsc::Sender<std::result::Result<Event, Infallible>>,
+) {
+    let user_id = auth.user_id;
+    let mut reauth = interval_at(
+        tokio::time::Instant::now() + REAUTH_INTERVAL,
+        REAUTH_INTERVAL,
+    );
+    reauth.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        // Drain the change log page by page until caught up
+        loop {
+            let full_page = backlog.len() as i64 == REPLAY_PAGE_SIZE;
+
+            for event in backlog.drain(..) {
+                if !send_event(&sender, &mut cursor, event).await {
+                    return;
+                }
+            }
+
+            if !full_page {
+                break;
+            }
+
+            match SyncService::events_since(&pool, user_id, cursor, Some(REPLAY_PAGE_SIZE)).await {
+                Ok(page) => backlog = page,
+                Err(e) => {
+                    tracing::error!(error = %e, %user_id, "Failed to replay change events");
+                    return;
+                }
+            }
+        }
+
+        tokio::select! {
+            _ = sender.closed() => return,
+            _ = reauth.tick() => {
+                if let Err(e) = auth.revalidate(&pool, &jwt).await {
+                    // Logged out, revoked or expired since connecting
+                    tracing::debug!(%user_id, error = %e, "Closing event stream, credentials no longer valid");
+                    return;
+                }
+            }
+            event = receiver.recv() => {
+                match event {
+                    Ok(event) if event.user_id == user_id && event.seq == cursor.0 + 1 => {
+                        if !send_event(&sender, &mut cursor, event).await {
+                            return;
+                        }
+                        continue;
+                    }
+                    Ok(event) if event.user_id == user_id && event.seq > cursor.0 => {
+                        // Sequence numbers are contiguous per user, so a skipped one was
+                        // notified out of order or while the listener was reconnecting
+                        tracing::debug!(%user_id, seq = event.seq, "Gap in event stream, replaying from change log");
+                    }
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!(%user_id, skipped, "Event stream lagged, replaying from change log");
+                    }
+                    Err(RecvError::Closed) => return,
+                }
+
+                // Missed events are still in the change log, so catch up from there
+                match SyncService::events_since(&pool, user_id, cursor, Some(REPLAY_PAGE_SIZE)).await {
+                    Ok(page) => backlog = page,
+                    Err(e) => {
+                        tracing::error!(error = %e, %user_id, "Failed to replay change events");
+                        return;
+                    }
+                }
+            }
+        }
+    }
+}
+
+async fn send_event(
+    sender: &mpsc::Sender<std::result::Result<Event, Infallible>>,
+    cursor: &mut SyncCursor,
+    event: ChangeEvent,
+) -> bool {
+    *cursor = SyncCursor(event.seq);
+
+    let sse_event = match Event::default()
+        .event("change")
+        .id(cursor.encode())
+        .json_data(&event)
+    {
+        Ok(sse_event) => sse_event,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to serialize change event");
+            return true;
+        }
+    };
+
+    sender.send(Ok(sse_event)).await.is_ok()
+}
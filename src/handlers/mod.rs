@@ -1,22 +1,65 @@
+pub mod access_token;
 pub mod auth;
+pub mod batch;
 pub mod bookmark;
 pub mod category;
+pub mod device;
+pub mod etag;
+pub mod events;
 pub mod health;
 pub mod note;
+pub mod saved_search;
+pub mod search;
+pub mod storage;
+pub mod sync;
 pub mod tag;
+pub mod two_factor;
+pub mod ws;
 
+#[cfg(test)]
+mod etag_tests;
+
+pub use auth::__path_jwks;
 pub use auth::__path_login;
+pub use auth::__path_login_two_factor;
+pub use auth::__path_logout;
+pub use auth::__path_logout_all;
 pub use auth::__path_me;
+pub use auth::__path_refresh;
 pub use auth::__path_register;
-pub use auth::{login, me, register};
+pub use auth::{jwks, login, login_two_factor, logout, logout_all, me, refresh, register};
+
+pub use access_token::__path_create_access_token;
+pub use access_token::__path_list_access_tokens;
+pub use access_token::__path_revoke_access_token;
+pub use access_token::{create_access_token, list_access_tokens, revoke_access_token};
+
+pub use two_factor::__path_confirm_two_factor;
+pub use two_factor::__path_disable_two_factor;
+pub use two_factor::__path_get_two_factor_status;
+pub use two_factor::__path_setup_two_factor;
+pub use two_factor::{
+    confirm_two_factor, disable_two_factor, get_two_factor_status, setup_two_factor,
+};
+
+pub use batch::__path_run_batch;
+pub use batch::run_batch;
 
 pub use bookmark::__path_create_bookmark;
 pub use bookmark::__path_delete_bookmark;
 pub use bookmark::__path_get_bookmark;
+pub use bookmark::__path_get_bookmark_archive;
+pub use bookmark::__path_get_bookmark_archive_raw;
 pub use bookmark::__path_list_bookmarks;
+pub use bookmark::__path_list_duplicate_bookmarks;
+pub use bookmark::__path_merge_bookmarks;
+pub use bookmark::__path_preview_bookmark;
+pub use bookmark::__path_refresh_bookmark_preview;
 pub use bookmark::__path_update_bookmark;
 pub use bookmark::{
-    create_bookmark, delete_bookmark, get_bookmark, list_bookmarks, update_bookmark,
+    create_bookmark, delete_bookmark, get_bookmark, get_bookmark_archive, get_bookmark_archive_raw,
+    list_bookmarks, list_duplicate_bookmarks, merge_bookmarks, preview_bookmark,
+    refresh_bookmark_preview, update_bookmark,
 };
 
 pub use category::__path_create_category;
@@ -28,12 +71,21 @@ pub use category::{
     create_category, delete_category, get_category, list_categories, update_category,
 };
 
+pub use device::__path_list_devices;
+pub use device::__path_revoke_device;
+pub use device::{list_devices, revoke_device};
+
 pub use note::__path_create_note;
 pub use note::__path_delete_note;
 pub use note::__path_get_note;
+pub use note::__path_list_note_conflicts;
 pub use note::__path_list_notes;
+pub use note::__path_resolve_note_conflict;
 pub use note::__path_update_note;
-pub use note::{create_note, delete_note, get_note, list_notes, update_note};
+pub use note::{
+    create_note, delete_note, get_note, list_note_conflicts, list_notes, resolve_note_conflict,
+    update_note,
+};
 
 pub use tag::__path_create_tag;
 pub use tag::__path_delete_tag;
@@ -42,6 +94,34 @@ pub use tag::__path_list_tags;
 pub use tag::__path_update_tag;
 pub use tag::{create_tag, delete_tag, get_tag, list_tags, update_tag};
 
+pub use saved_search::__path_create_saved_search;
+pub use saved_search::__path_delete_saved_search;
+pub use saved_search::__path_get_saved_search;
+pub use saved_search::__path_get_saved_search_results;
+pub use saved_search::__path_list_saved_searches;
+pub use saved_search::__path_update_saved_search;
+pub use saved_search::{
+    create_saved_search, delete_saved_search, get_saved_search, get_saved_search_results,
+    list_saved_searches, update_saved_search,
+};
+
+pub use search::__path_search;
+pub use search::__path_suggest;
+pub use search::{search, suggest};
+
+pub use storage::__path_get_storage_usage;
+pub use storage::get_storage_usage;
+
+pub use sync::__path_get_changes;
+pub use sync::__path_list_tombstones;
+pub use sync::{get_changes, list_tombstones};
+
+pub use events::__path_stream_events;
+pub use events::stream_events;
+
+pub use ws::__path_ws_handler;
+pub use ws::ws_handler;
+
 pub use health::__path_liveness;
 pub use health::__path_readiness;
 pub use health::{liveness, readiness};
@@ -1,7 +1,8 @@
 use axum::{
     Json,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
+    response::{IntoResponse, Response},
 };
 use sqlx::PgPool;
 use uuid::Uuid;
@@ -9,7 +10,11 @@ use validator::Validate;
 
 use crate::auth::AuthUser;
 use crate::error::{AppError, Result};
-use crate::models::{CreateNote, Note, UpdateNote};
+use crate::handlers::etag::{ETagHeader, IfMatch, IfNoneMatch, etag_header};
+use crate::models::{
+    CreateNote, Note, NoteConflict, NoteListQuery, NoteUpdateOutcome, Page, ResolveNoteConflict,
+    Scope, UpdateNote,
+};
 use crate::services::NoteService;
 
 #[utoipa::path(
@@ -19,7 +24,8 @@ use crate::services::NoteService;
     responses(
         (status = 201, description = "Note created", body = Note),
         (status = 400, description = "Validation error"),
-        (status = 401, description = "Unauthorized")
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
     ),
     security(("bearer_auth" = [])),
     tag = "notes"
@@ -29,28 +35,39 @@ pub async fn create_note(
     State(pool): State<PgPool>,
     auth: AuthUser,
     Json(input): Json<CreateNote>,
-) -> Result<(StatusCode, Json<Note>)> {
+) -> Result<(StatusCode, ETagHeader, Json<Note>)> {
+    auth.require(Scope::NotesWrite)?;
+
     input
         .validate()
         .map_err(|e| AppError::Validation(e.to_string()))?;
 
     let note = NoteService::create(&pool, auth.user_id, input).await?;
-    Ok((StatusCode::CREATED, Json(note)))
+    Ok((StatusCode::CREATED, etag_header(note.version), Json(note)))
 }
 
 #[utoipa::path(
     get,
     path = "/api/notes",
+    params(NoteListQuery),
     responses(
-        (status = 200, description = "List of notes", body = Vec<Note>),
-        (status = 401, description = "Unauthorized")
+        (status = 200, description = "Page of notes", body = Page<Note>),
+        (status = 400, description = "Invalid filter or cursor"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
     ),
     security(("bearer_auth" = [])),
     tag = "notes"
 )]
-#[tracing::instrument(skip(pool, auth), fields(user_id = %auth.user_id))]
-pub async fn list_notes(State(pool): State<PgPool>, auth: AuthUser) -> Result<Json<Vec<Note>>> {
-    let notes = NoteService::list(&pool, auth.user_id).await?;
+#[tracing::instrument(skip(pool, auth, query), fields(user_id = %auth.user_id))]
+pub async fn list_notes(
+    State(pool): State<PgPool>,
+    auth: AuthUser,
+    Query(query): Query<NoteListQuery>,
+) -> Result<Json<Page<Note>>> {
+    auth.require(Scope::NotesRead)?;
+
+    let notes = NoteService::list(&pool, auth.user_id, query).await?;
     Ok(Json(notes))
 }
 
@@ -58,12 +75,15 @@ pub async fn list_notes(State(pool): State<PgPool>, auth: AuthUser) -> Result<Js
     get,
     path = "/api/notes/{id}",
     params(
-        ("id" = Uuid, Path, description = "Note ID")
+        ("id" = Uuid, Path, description = "Note ID"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag from a previous response")
     ),
     responses(
         (status = 200, description = "Note found", body = Note),
+        (status = 304, description = "Note not modified"),
         (status = 404, description = "Note not found"),
-        (status = 401, description = "Unauthorized")
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
     ),
     security(("bearer_auth" = [])),
     tag = "notes"
@@ -73,22 +93,34 @@ pub async fn get_note(
     State(pool): State<PgPool>,
     auth: AuthUser,
     Path(id): Path<Uuid>,
-) -> Result<Json<Note>> {
+    if_none_match: IfNoneMatch,
+) -> Result<Response> {
+    auth.require(Scope::NotesRead)?;
+
     let note = NoteService::get_by_id(&pool, auth.user_id, id).await?;
-    Ok(Json(note))
+
+    if if_none_match.matches(note.version) {
+        return Ok((StatusCode::NOT_MODIFIED, etag_header(note.version)).into_response());
+    }
+
+    Ok((etag_header(note.version), Json(note)).into_response())
 }
 
 #[utoipa::path(
     put,
     path = "/api/notes/{id}",
     params(
-        ("id" = Uuid, Path, description = "Note ID")
+        ("id" = Uuid, Path, description = "Note ID"),
+        ("If-Match" = Option<String>, Header, description = "ETag the update is based on")
     ),
     request_body = UpdateNote,
     responses(
-        (status = 200, description = "Note updated", body = Note),
+        (status = 200, description = "Note updated, merged with concurrent edits if needed", body = Note),
+        (status = 409, description = "Edit conflicts with concurrent changes", body = NoteConflict),
+        (status = 412, description = "Note was modified since the given ETag"),
         (status = 404, description = "Note not found"),
-        (status = 401, description = "Unauthorized")
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
     ),
     security(("bearer_auth" = [])),
     tag = "notes"
@@ -98,22 +130,34 @@ pub async fn update_note(
     State(pool): State<PgPool>,
     auth: AuthUser,
     Path(id): Path<Uuid>,
+    IfMatch(expected_version): IfMatch,
     Json(input): Json<UpdateNote>,
-) -> Result<Json<Note>> {
-    let note = NoteService::update(&pool, auth.user_id, id, input).await?;
-    Ok(Json(note))
+) -> Result<Response> {
+    auth.require(Scope::NotesWrite)?;
+
+    match NoteService::update(&pool, auth.user_id, id, input, expected_version).await? {
+        NoteUpdateOutcome::Applied(note) => {
+            Ok((etag_header(note.version), Json(note)).into_response())
+        }
+        NoteUpdateOutcome::Conflicted(conflict) => {
+            Ok((StatusCode::CONFLICT, Json(conflict)).into_response())
+        }
+    }
 }
 
 #[utoipa::path(
     delete,
     path = "/api/notes/{id}",
     params(
-        ("id" = Uuid, Path, description = "Note ID")
+        ("id" = Uuid, Path, description = "Note ID"),
+        ("If-Match" = Option<String>, Header, description = "ETag the deletion is based on")
     ),
     responses(
         (status = 204, description = "Note deleted"),
+        (status = 412, description = "Note was modified since the given ETag"),
         (status = 404, description = "Note not found"),
-        (status = 401, description = "Unauthorized")
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
     ),
     security(("bearer_auth" = [])),
     tag = "notes"
@@ -123,7 +167,80 @@ pub async fn delete_note(
     State(pool): State<PgPool>,
     auth: AuthUser,
     Path(id): Path<Uuid>,
+    IfMatch(expected_version): IfMatch,
 ) -> Result<StatusCode> {
-    NoteService::delete(&pool, auth.user_id, id).await?;
+    auth.require(Scope::NotesWrite)?;
+
+    NoteService::delete(&pool, auth.user_id, id, expected_version).await?;
     Ok(StatusCode::NO_CONTENT)
 }
+
+#[utoipa::path(
+    get,
+    path = "/api/notes/{id}/conflicts",
+    params(("id" = Uuid, Path, description = "Note ID")),
+    responses(
+        (status = 200, description = "Unresolved conflicts", body = Vec<NoteConflict>),
+        (status = 404, description = "Note not found"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "notes"
+)]
+#[tracing::instrument(skip(pool, auth), fields(user_id = %auth.user_id, note_id = %id))]
+pub async fn list_note_conflicts(
+    State(pool): State<PgPool>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<NoteConflict>>> {
+    auth.require(Scope::NotesRead)?;
+
+    let conflicts = NoteService::list_conflicts(&pool, auth.user_id, id).await?;
+    Ok(Json(conflicts))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/notes/{id}/conflicts/{conflict_id}/resolve",
+    params(
+        ("id" = Uuid, Path, description = "Note ID"),
+        ("conflict_id" = Uuid, Path, description = "Conflict ID"),
+        ("If-Match" = Option<String>, Header, description = "ETag the resolution is based on")
+    ),
+    request_body = ResolveNoteConflict,
+    responses(
+        (status = 200, description = "Conflict resolved", body = Note),
+        (status = 400, description = "Validation error"),
+        (status = 412, description = "Note was modified since the given ETag"),
+        (status = 404, description = "Conflict not found"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "notes"
+)]
+#[tracing::instrument(
+    skip(pool, auth, input),
+    fields(user_id = %auth.user_id, note_id = %id, conflict_id = %conflict_id)
+)]
+pub async fn resolve_note_conflict(
+    State(pool): State<PgPool>,
+    auth: AuthUser,
+    Path((id, conflict_id)): Path<(Uuid, Uuid)>,
+    IfMatch(expected_version): IfMatch,
+    Json(input): Json<ResolveNoteConflict>,
+) -> Result<(ETagHeader, Json<Note>)> {
+    auth.require(Scope::NotesWrite)?;
+
+    let note = NoteService::resolve_conflict(
+        &pool,
+        auth.user_id,
+        id,
+        conflict_id,
+        input,
+        expected_version,
+    )
+    .await?;
+    Ok((etag_header(note.version), Json(note)))
+}
@@ -0,0 +1,213 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::auth::AuthUser;
+use crate::error::{AppError, Result};
+use crate::handlers::etag::{ETagHeader, IfMatch, IfNoneMatch, etag_header};
+use crate::models::{
+    CreateSavedSearch, Page, SavedSearch, SavedSearchListQuery, SavedSearchResultsQuery, Scope,
+    SearchResult, UpdateSavedSearch,
+};
+use crate::services::SavedSearchService;
+
+#[utoipa::path(
+    post,
+    path = "/api/saved-searches",
+    request_body = CreateSavedSearch,
+    responses(
+        (status = 201, description = "Saved search created", body = SavedSearch),
+        (status = 400, description = "Validation error or malformed query"),
+        (status = 409, description = "Saved search name already exists"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "saved-searches"
+)]
+#[tracing::instrument(skip(pool, auth, input), fields(user_id = %auth.user_id))]
+pub async fn create_saved_search(
+    State(pool): State<PgPool>,
+    auth: AuthUser,
+    Json(input): Json<CreateSavedSearch>,
+) -> Result<(StatusCode, ETagHeader, Json<SavedSearch>)> {
+    auth.require(Scope::SearchesWrite)?;
+
+    input
+        .validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let saved_search = SavedSearchService::create(&pool, auth.user_id, input).await?;
+    Ok((
+        StatusCode::CREATED,
+        etag_header(saved_search.version),
+        Json(saved_search),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/saved-searches",
+    params(SavedSearchListQuery),
+    responses(
+        (status = 200, description = "Page of saved searches", body = Page<SavedSearch>),
+        (status = 400, description = "Invalid cursor"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "saved-searches"
+)]
+#[tracing::instrument(skip(pool, auth, query), fields(user_id = %auth.user_id))]
+pub async fn list_saved_searches(
+    State(pool): State<PgPool>,
+    auth: AuthUser,
+    Query(query): Query<SavedSearchListQuery>,
+) -> Result<Json<Page<SavedSearch>>> {
+    auth.require(Scope::SearchesRead)?;
+
+    let saved_searches = SavedSearchService::list(&pool, auth.user_id, query).await?;
+    Ok(Json(saved_searches))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/saved-searches/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Saved search ID"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag from a previous response")
+    ),
+    responses(
+        (status = 200, description = "Saved search found", body = SavedSearch),
+        (status = 304, description = "Saved search not modified"),
+        (status = 404, description = "Saved search not found"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "saved-searches"
+)]
+#[tracing::instrument(skip(pool, auth), fields(user_id = %auth.user_id, saved_search_id = %id))]
+pub async fn get_saved_search(
+    State(pool): State<PgPool>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+    if_none_match: IfNoneMatch,
+) -> Result<Response> {
+    auth.require(Scope::SearchesRead)?;
+
+    let saved_search = SavedSearchService::get_by_id(&pool, auth.user_id, id).await?;
+
+    if if_none_match.matches(saved_search.version) {
+        return Ok((StatusCode::NOT_MODIFIED, etag_header(saved_search.version)).into_response());
+    }
+
+    Ok((etag_header(saved_search.version), Json(saved_search)).into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/saved-searches/{id}/results",
+    params(
+        ("id" = Uuid, Path, description = "Saved search ID"),
+        SavedSearchResultsQuery
+    ),
+    responses(
+        (status = 200, description = "Page of matching bookmarks and notes, in the saved order", body = Page<SearchResult>),
+        (status = 400, description = "Invalid cursor"),
+        (status = 404, description = "Saved search not found"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "saved-searches"
+)]
+#[tracing::instrument(skip(pool, auth, query), fields(user_id = %auth.user_id, saved_search_id = %id))]
+pub async fn get_saved_search_results(
+    State(pool): State<PgPool>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+    Query(query): Query<SavedSearchResultsQuery>,
+) -> Result<Json<Page<SearchResult>>> {
+    auth.require(Scope::SearchesRead)?;
+    auth.require(Scope::BookmarksRead)?;
+    auth.require(Scope::NotesRead)?;
+
+    let results = SavedSearchService::results(&pool, auth.user_id, id, query).await?;
+    Ok(Json(results))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/saved-searches/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Saved search ID"),
+        ("If-Match" = Option<String>, Header, description = "ETag the update is based on")
+    ),
+    request_body = UpdateSavedSearch,
+    responses(
+        (status = 200, description = "Saved search updated", body = SavedSearch),
+        (status = 400, description = "Validation error or malformed query"),
+        (status = 409, description = "Saved search name already exists"),
+        (status = 412, description = "Saved search was modified since the given ETag"),
+        (status = 404, description = "Saved search not found"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "saved-searches"
+)]
+#[tracing::instrument(skip(pool, auth, input), fields(user_id = %auth.user_id, saved_search_id = %id))]
+pub async fn update_saved_search(
+    State(pool): State<PgPool>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+    IfMatch(expected_version): IfMatch,
+    Json(input): Json<UpdateSavedSearch>,
+) -> Result<(ETagHeader, Json<SavedSearch>)> {
+    auth.require(Scope::SearchesWrite)?;
+
+    input
+        .validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let saved_search =
+        SavedSearchService::update(&pool, auth.user_id, id, input, expected_version).await?;
+    Ok((etag_header(saved_search.version), Json(saved_search)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/saved-searches/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Saved search ID"),
+        ("If-Match" = Option<String>, Header, description = "ETag the deletion is based on")
+    ),
+    responses(
+        (status = 204, description = "Saved search deleted"),
+        (status = 412, description = "Saved search was modified since the given ETag"),
+        (status = 404, description = "Saved search not found"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "saved-searches"
+)]
+#[tracing::instrument(skip(pool, auth), fields(user_id = %auth.user_id, saved_search_id = %id))]
+pub async fn delete_saved_search(
+    State(pool): State<PgPool>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+    IfMatch(expected_version): IfMatch,
+) -> Result<StatusCode> {
+    auth.require(Scope::SearchesWrite)?;
+
+    SavedSearchService::delete(&pool, auth.user_id, id, expected_version).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,68 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+use sqlx::PgPool;
+
+use crate::auth::AuthUser;
+use crate::error::Result;
+use crate::models::{Scope, SearchQuery, SearchResult, SearchSuggestion, SearchType, SuggestQuery};
+use crate::services::SearchService;
+
+#[utoipa::path(
+    get,
+    path = "/api/search",
+    params(SearchQuery),
+    responses(
+        (status = 200, description = "Matching bookmarks and notes, best first", body = Vec<SearchResult>),
+        (status = 400, description = "Missing or malformed search query"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "search"
+)]
+#[tracing::instrument(skip(pool, auth, query), fields(user_id = %auth.user_id))]
+pub async fn search(
+    State(pool): State<PgPool>,
+    auth: AuthUser,
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<Vec<SearchResult>>> {
+    match query.search_type {
+        Some(SearchType::Bookmark) => auth.require(Scope::BookmarksRead)?,
+        Some(SearchType::Note) => auth.require(Scope::NotesRead)?,
+        None => {
+            auth.require(Scope::BookmarksRead)?;
+            auth.require(Scope::NotesRead)?;
+        }
+    }
+
+    let results = SearchService::search(&pool, auth.user_id, &query).await?;
+    Ok(Json(results))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/search/suggest",
+    params(SuggestQuery),
+    responses(
+        (status = 200, description = "Bookmarks and notes whose titles contain the prefix", body = Vec<SearchSuggestion>),
+        (status = 400, description = "Missing or overlong prefix"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "search"
+)]
+#[tracing::instrument(skip(pool, auth, query), fields(user_id = %auth.user_id))]
+pub async fn suggest(
+    State(pool): State<PgPool>,
+    auth: AuthUser,
+    Query(query): Query<SuggestQuery>,
+) -> Result<Json<Vec<SearchSuggestion>>> {
+    auth.require(Scope::BookmarksRead)?;
+    auth.require(Scope::NotesRead)?;
+
+    let suggestions = SearchService::suggest(&pool, auth.user_id, &query).await?;
+    Ok(Json(suggestions))
+}
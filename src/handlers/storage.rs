@@ -0,0 +1,31 @@
+use axum::{Json, extract::State};
+use sqlx::PgPool;
+
+use crate::auth::AuthUser;
+use crate::blob::BlobStorage;
+use crate::error::Result;
+use crate::models::{Scope, StorageUsage};
+use crate::services::BlobService;
+
+#[utoipa::path(
+    get,
+    path = "/api/storage/usage",
+    responses(
+        (status = 200, description = "Storage used by the current user", body = StorageUsage),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "storage"
+)]
+#[tracing::instrument(skip(pool, blobs, auth), fields(user_id = %auth.user_id))]
+pub async fn get_storage_usage(
+    State(pool): State<PgPool>,
+    State(blobs): State<BlobStorage>,
+    auth: AuthUser,
+) -> Result<Json<StorageUsage>> {
+    auth.require(Scope::StorageRead)?;
+
+    let usage = BlobService::usage(&pool, &blobs, auth.user_id).await?;
+    Ok(Json(usage))
+}
@@ -0,0 +1,71 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+use sqlx::PgPool;
+
+use crate::auth::AuthUser;
+use crate::error::Result;
+use crate::models::{Scope, SyncChanges, SyncCursor, SyncQuery, Tombstone, TombstoneQuery};
+use crate::services::{DeviceService, SyncService};
+
+#[utoipa::path(
+    get,
+    path = "/api/sync/changes",
+    params(SyncQuery),
+    responses(
+        (status = 200, description = "Changes since the given cursor", body = SyncChanges),
+        (status = 400, description = "Invalid cursor"),
+        (status = 410, description = "Cursor expired, full resync required"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "sync"
+)]
+#[tracing::instrument(skip(pool, auth, query), fields(user_id = %auth.user_id))]
+pub async fn get_changes(
+    State(pool): State<PgPool>,
+    auth: AuthUser,
+    Query(query): Query<SyncQuery>,
+) -> Result<Json<SyncChanges>> {
+    auth.require(Scope::SyncRead)?;
+
+    let since = match query.since.as_deref() {
+        Some(cursor) => SyncCursor::decode(cursor)?,
+        None => SyncCursor::default(),
+    };
+
+    let changes = SyncService::changes_since(&pool, auth.user_id, since, query.limit).await?;
+
+    // Syncing from a cursor confirms everything before it was applied
+    if let (Some(device_id), true) = (auth.device_id, query.since.is_some()) {
+        DeviceService::acknowledge(&pool, auth.user_id, device_id, since).await?;
+    }
+
+    Ok(Json(changes))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/sync/tombstones",
+    params(TombstoneQuery),
+    responses(
+        (status = 200, description = "Deleted entities still within the retention window", body = Vec<Tombstone>),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "sync"
+)]
+#[tracing::instrument(skip(pool, auth, query), fields(user_id = %auth.user_id))]
+pub async fn list_tombstones(
+    State(pool): State<PgPool>,
+    auth: AuthUser,
+    Query(query): Query<TombstoneQuery>,
+) -> Result<Json<Vec<Tombstone>>> {
+    auth.require(Scope::SyncRead)?;
+
+    let tombstones = SyncService::list_tombstones(&pool, auth.user_id, query.since).await?;
+    Ok(Json(tombstones))
+}
@@ -1,7 +1,8 @@
 use axum::{
     Json,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
+    response::{IntoResponse, Response},
 };
 use sqlx::PgPool;
 use uuid::Uuid;
@@ -9,7 +10,8 @@ use validator::Validate;
 
 use crate::auth::AuthUser;
 use crate::error::{AppError, Result};
-use crate::models::{CreateTag, Tag, UpdateTag};
+use crate::handlers::etag::{ETagHeader, IfMatch, IfNoneMatch, etag_header};
+use crate::models::{CreateTag, Page, Scope, Tag, TagListQuery, UpdateTag};
 use crate::services::TagService;
 
 #[utoipa::path(
@@ -20,7 +22,8 @@ use crate::services::TagService;
         (status = 201, description = "Tag created", body = Tag),
         (status = 400, description = "Validation error"),
         (status = 409, description = "Tag already exists"),
-        (status = 401, description = "Unauthorized")
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
     ),
     security(("bearer_auth" = [])),
     tag = "tags"
@@ -30,28 +33,39 @@ pub async fn create_tag(
     State(pool): State<PgPool>,
     auth: AuthUser,
     Json(input): Json<CreateTag>,
-) -> Result<(StatusCode, Json<Tag>)> {
+) -> Result<(StatusCode, ETagHeader, Json<Tag>)> {
+    auth.require(Scope::TagsWrite)?;
+
     input
         .validate()
         .map_err(|e| AppError::Validation(e.to_string()))?;
 
     let tag = TagService::create(&pool, auth.user_id, input).await?;
-    Ok((StatusCode::CREATED, Json(tag)))
+    Ok((StatusCode::CREATED, etag_header(tag.version), Json(tag)))
 }
 
 #[utoipa::path(
     get,
     path = "/api/tags",
+    params(TagListQuery),
     responses(
-        (status = 200, description = "List of tags", body = Vec<Tag>),
-        (status = 401, description = "Unauthorized")
+        (status = 200, description = "Page of tags", body = Page<Tag>),
+        (status = 400, description = "Invalid filter or cursor"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
     ),
     security(("bearer_auth" = [])),
     tag = "tags"
 )]
-#[tracing::instrument(skip(pool, auth), fields(user_id = %auth.user_id))]
-pub async fn list_tags(State(pool): State<PgPool>, auth: AuthUser) -> Result<Json<Vec<Tag>>> {
-    let tags = TagService::list(&pool, auth.user_id).await?;
+#[tracing::instrument(skip(pool, auth, query), fields(user_id = %auth.user_id))]
+pub async fn list_tags(
+    State(pool): State<PgPool>,
+    auth: AuthUser,
+    Query(query): Query<TagListQuery>,
+) -> Result<Json<Page<Tag>>> {
+    auth.require(Scope::TagsRead)?;
+
+    let tags = TagService::list(&pool, auth.user_id, query).await?;
     Ok(Json(tags))
 }
 
@@ -59,12 +73,15 @@ pub async fn list_tags(State(pool): State<PgPool>, auth: AuthUser) -> Result<Jso
     get,
     path = "/api/tags/{id}",
     params(
-        ("id" = Uuid, Path, description = "Tag ID")
+        ("id" = Uuid, Path, description = "Tag ID"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag from a previous response")
     ),
     responses(
         (status = 200, description = "Tag found", body = Tag),
+        (status = 304, description = "Tag not modified"),
         (status = 404, description = "Tag not found"),
-        (status = 401, description = "Unauthorized")
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
     ),
     security(("bearer_auth" = [])),
     tag = "tags"
@@ -74,22 +91,33 @@ pub async fn get_tag(
     State(pool): State<PgPool>,
     auth: AuthUser,
     Path(id): Path<Uuid>,
-) -> Result<Json<Tag>> {
+    if_none_match: IfNoneMatch,
+) -> Result<Response> {
+    auth.require(Scope::TagsRead)?;
+
     let tag = TagService::get_by_id(&pool, auth.user_id, id).await?;
-    Ok(Json(tag))
+
+    if if_none_match.matches(tag.version) {
+        return Ok((StatusCode::NOT_MODIFIED, etag_header(tag.version)).into_response());
+    }
+
+    Ok((etag_header(tag.version), Json(tag)).into_response())
 }
 
 #[utoipa::path(
     put,
     path = "/api/tags/{id}",
     params(
-        ("id" = Uuid, Path, description = "Tag ID")
+        ("id" = Uuid, Path, description = "Tag ID"),
+        ("If-Match" = Option<String>, Header, description = "ETag the update is based on")
     ),
     request_body = UpdateTag,
     responses(
         (status = 200, description = "Tag updated", body = Tag),
+        (status = 412, description = "Tag was modified since the given ETag"),
         (status = 404, description = "Tag not found"),
-        (status = 401, description = "Unauthorized")
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
     ),
     security(("bearer_auth" = [])),
     tag = "tags"
@@ -99,22 +127,28 @@ pub async fn update_tag(
     State(pool): State<PgPool>,
     auth: AuthUser,
     Path(id): Path<Uuid>,
+    IfMatch(expected_version): IfMatch,
     Json(input): Json<UpdateTag>,
-) -> Result<Json<Tag>> {
-    let tag = TagService::update(&pool, auth.user_id, id, input).await?;
-    Ok(Json(tag))
+) -> Result<(ETagHeader, Json<Tag>)> {
+    auth.require(Scope::TagsWrite)?;
+
+    let tag = TagService::update(&pool, auth.user_id, id, input, expected_version).await?;
+    Ok((etag_header(tag.version), Json(tag)))
 }
 
 #[utoipa::path(
     delete,
     path = "/api/tags/{id}",
     params(
-        ("id" = Uuid, Path, description = "Tag ID")
+        ("id" = Uuid, Path, description = "Tag ID"),
+        ("If-Match" = Option<String>, Header, description = "ETag the deletion is based on")
     ),
     responses(
         (status = 204, description = "Tag deleted"),
+        (status = 412, description = "Tag was modified since the given ETag"),
         (status = 404, description = "Tag not found"),
-        (status = 401, description = "Unauthorized")
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
     ),
     security(("bearer_auth" = [])),
     tag = "tags"
@@ -124,7 +158,10 @@ pub async fn delete_tag(
     State(pool): State<PgPool>,
     auth: AuthUser,
     Path(id): Path<Uuid>,
+    IfMatch(expected_version): IfMatch,
 ) -> Result<StatusCode> {
-    TagService::delete(&pool, auth.user_id, id).await?;
+    auth.require(Scope::TagsWrite)?;
+
+    TagService::delete(&pool, auth.user_id, id, expected_version).await?;
     Ok(StatusCode::NO_CONTENT)
 }
@@ -0,0 +1,117 @@
+use axum::{Json, extract::State, http::StatusCode};
+use sqlx::PgPool;
+use validator::Validate;
+
+use crate::auth::AuthUser;
+use crate::error::{AppError, Result};
+use crate::models::{
+    DisableTwoFactor, RecoveryCodes, TwoFactorCode, TwoFactorEnrollment, TwoFactorStatus,
+};
+use crate::services::{TwoFactorService, UserService};
+
+#[utoipa::path(
+    get,
+    path = "/api/auth/2fa",
+    responses(
+        (status = 200, description = "Whether two-factor authentication is enabled", body = TwoFactorStatus),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not available to personal access tokens")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "auth"
+)]
+#[tracing::instrument(skip(pool, auth), fields(user_id = %auth.user_id))]
+pub async fn get_two_factor_status(
+    State(pool): State<PgPool>,
+    auth: AuthUser,
+) -> Result<Json<TwoFactorStatus>> {
+    auth.require_session()?;
+
+    let status = TwoFactorService::status(&pool, auth.user_id).await?;
+    Ok(Json(status))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/2fa/setup",
+    responses(
+        (status = 200, description = "Secret to add to an authenticator app; replaces any unconfirmed setup", body = TwoFactorEnrollment),
+        (status = 409, description = "Two-factor authentication is already enabled"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not available to personal access tokens")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "auth"
+)]
+#[tracing::instrument(skip(pool, auth), fields(user_id = %auth.user_id))]
+pub async fn setup_two_factor(
+    State(pool): State<PgPool>,
+    auth: AuthUser,
+) -> Result<Json<TwoFactorEnrollment>> {
+    auth.require_session()?;
+
+    let user = UserService::get_by_id(&pool, auth.user_id).await?;
+    let enrollment = TwoFactorService::begin_enrollment(&pool, &user).await?;
+    Ok(Json(enrollment))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/2fa/confirm",
+    request_body = TwoFactorCode,
+    responses(
+        (status = 200, description = "Two-factor authentication enabled; the recovery codes are shown only once", body = RecoveryCodes),
+        (status = 400, description = "Invalid code"),
+        (status = 409, description = "Two-factor authentication is already enabled"),
+        (status = 422, description = "Setup has not been started"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not available to personal access tokens")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "auth"
+)]
+#[tracing::instrument(skip(pool, auth, input), fields(user_id = %auth.user_id))]
+pub async fn confirm_two_factor(
+    State(pool): State<PgPool>,
+    auth: AuthUser,
+    Json(input): Json<TwoFactorCode>,
+) -> Result<Json<RecoveryCodes>> {
+    auth.require_session()?;
+    input
+        .validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let recovery_codes =
+        TwoFactorService::confirm_enrollment(&pool, auth.user_id, &input.code).await?;
+    Ok(Json(recovery_codes))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/2fa/disable",
+    request_body = DisableTwoFactor,
+    responses(
+        (status = 204, description = "Two-factor authentication disabled and recovery codes discarded"),
+        (status = 400, description = "Validation error"),
+        (status = 401, description = "Wrong password or code"),
+        (status = 403, description = "Not available to personal access tokens"),
+        (status = 422, description = "Two-factor authentication is not enabled"),
+        (status = 429, description = "Codes are locked for a while after too many wrong ones")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "auth"
+)]
+#[tracing::instrument(skip(pool, auth, input), fields(user_id = %auth.user_id))]
+pub async fn disable_two_factor(
+    State(pool): State<PgPool>,
+    auth: AuthUser,
+    Json(input): Json<DisableTwoFactor>,
+) -> Result<StatusCode> {
+    auth.require_session()?;
+    input
+        .validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+
+    TwoFactorService::disable(&pool, auth.user_id, &input.password, &input.code).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,122 @@
+use axum::{
+    extract::{
+        Query, State,
+        ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade, close_code},
+    },
+    http::{HeaderMap, header},
+    response::Response,
+};
+use sqlx::PgPool;
+use tokio::sync::broadcast::{Receiver, error::RecvError};
+use tokio::time::{MissedTickBehavior, interval_at};
+
+use crate::auth::{AuthUser, JwtManager};
+use crate::error::{AppError, Result};
+use crate::events::{EventBus, REAUTH_INTERVAL};
+use crate::models::{ChangeEvent, Scope, WsQuery};
+
+#[utoipa::path(
+    get,
+    path = "/api/ws",
+    params(WsQuery),
+    responses(
+        (status = 101, description = "Switching to a WebSocket pushing ChangeEvent messages", body = ChangeEvent),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Personal access token lacks the required scope")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "sync"
+)]
+#[tracing::instrument(skip_all)]
+pub async fn ws_handler(
+    State(pool): State<PgPool>,
+    State(jwt): State<JwtManager>,
+    State(events): State<EventBus>,
+    Query(query): Query<WsQuery>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> Result<Response> {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or(query.token.as_deref())
+        .ok_or(AppError::Unauthorized)?;
+
+    let auth = AuthUser::from_token(&pool, &jwt, token).await?;
+    auth.require(Scope::SyncRead)?;
+
+    // Subscribe before upgrading so nothing committed after the handshake is missed
+    let receiver = events.subscribe();
+
+    Ok(ws.on_upgrade(move |socket| push_changes(socket, pool, jwt, auth, receiver)))
+}
+
+async fn push_changes(
+    mut socket: WebSocket,
+    pool: PgPool,
+    jwt: JwtManager,
+    auth: AuthUser,
+    mut receiver: Receiver<ChangeEvent>,
+) {
+    let user_id = auth.user_id;
+    tracing::debug!(%user_id, "WebSocket connected");
+
+    let mut reauth = interval_at(
+        tokio::time::Instant::now() + REAUTH_INTERVAL,
+        REAUTH_INTERVAL,
+    );
+    reauth.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            _ = reauth.tick() => {
+                if let Err(e) = auth.revalidate(&pool, &jwt).await {
+                    // Logged out, revoked or expired since connecting
+                    tracing::debug!(%user_id, error = %e, "Closing WebSocket, credentials no longer valid");
+                    let _ = socket
+                        .send(Message::Close(Some(CloseFrame {
+                            code: close_code::POLICY,
+                            reason: "unauthorized".into(),
+                        })))
+                        .await;
+                    break;
+                }
+            }
+            event = receiver.recv() => match event {
+                Ok(event) if event.user_id == user_id => {
+                    let payload = match serde_json::to_string(&event) {
+                        Ok(payload) => payload,
+                        Err(e) => {
+                            tracing::error!(error = %e, "Failed to serialize change event");
+                            continue;
+                        }
+                    };
+
+                    if socket.send(Message::Text(payload.into())).await.is_err() {
+                        break;
+                    }
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => {
+                    // Events were dropped; the client has to catch up through delta sync
+                    tracing::warn!(%user_id, skipped, "WebSocket client lagged behind");
+                    let _ = socket
+                        .send(Message::Close(Some(CloseFrame {
+                            code: close_code::AGAIN,
+                            reason: "lagged".into(),
+                        })))
+                        .await;
+                    break;
+                }
+                Err(RecvError::Closed) => break,
+            },
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+
+    tracing::debug!(%user_id, "WebSocket disconnected");
+}
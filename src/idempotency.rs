@@ -0,0 +1,216 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::{OriginalUri, Request, State},
+    http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::auth::{AuthUser, JwtManager};
+use crate::error::{AppError, Result};
+use crate::models::IdempotencyRecord;
+use crate::services::{IdempotencyClaim, IdempotencyService};
+
+pub const IDEMPOTENCY_KEY: &str = "idempotency-key";
+pub const IDEMPOTENT_REPLAYED: &str = "idempotent-replayed";
+
+/// Routes whose responses carry credentials, which must never be written to
+/// the replay cache. They are always run, even when retried with a key.
+const NEVER_STORED: &[&str] = &[
+    "/api/auth/register",
+    "/api/auth/login",
+    "/api/auth/login/2fa",
+    "/api/auth/refresh",
+    "/api/auth/tokens",
+    "/api/auth/2fa/setup",
+    "/api/auth/2fa/confirm",
+];
+
+const MAX_KEY_LENGTH: usize = 255;
+const MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+/// State for the [`idempotency`] middleware.
+#[derive(Clone)]
+pub struct Idempotency {
+    pool: PgPool,
+    jwt: JwtManager,
+    ttl: chrono::Duration,
+}
+
+impl Idempotency {
+    pub fn new(pool: PgPool, jwt: JwtManager, ttl_hours: i64) -> Self {
+        Self {
+            pool,
+            jwt,
+            ttl: chrono::Duration::hours(ttl_hours),
+        }
+    }
+}
+
+/// Replays the stored response for POST requests retried with the same `Idempotency-Key`.
+///
+/// Keys are scoped to the authenticated user, so unauthenticated requests
+/// pass through untouched, as do routes issuing credentials, whose responses
+/// are never stored. Server errors release the key so the request can be retried.
+pub async fn idempotency(
+    State(state): State<Idempotency>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if request.method() != Method::POST
+        || !request.headers().contains_key(IDEMPOTENCY_KEY)
+        || issues_credentials(&request)
+    {
+        return next.run(request).await;
+    }
+
+    match handle(&state, request, next).await {
+        Ok(response) => response,
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn handle(state: &Idempotency, request: Request, next: Next) -> Result<Response> {
+    let key = request
+        .headers()
+        .get(IDEMPOTENCY_KEY)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned)
+        .filter(|key| !key.is_empty() && key.len() <= MAX_KEY_LENGTH)
+        .ok_or_else(|| {
+            AppError::Validation(format!(
+                "Idempotency-Key must be 1 to {MAX_KEY_LENGTH} visible ASCII characters"
+            ))
+        })?;
+
+    let Some(user_id) = authenticate(state, request.headers()).await else {
+        return Ok(next.run(request).await);
+    };
+
+    let (parts, body) = request.into_parts();
+    let body = axum::body::to_bytes(body, MAX_BODY_BYTES)
+        .await
+        .map_err(|_| AppError::Validation("Request body too large".to_string()))?;
+    let request_hash = request_hash(&parts.method, &parts.uri.to_string(), &body);
+
+    let expired_before = Utc::now() - state.ttl;
+    match IdempotencyService::claim(&state.pool, user_id, &key, &request_hash, expired_before)
+        .await?
+    {
+        IdempotencyClaim::Existing(record) => replay(record, &request_hash),
+        IdempotencyClaim::Claimed => {
+            let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+            store(state, user_id, &key, response).await
+        }
+    }
+}
+
+fn issues_credentials(request: &Request) -> bool {
+    // Nested routers see the path without their prefix
+    let path = match request.extensions().get::<OriginalUri>() {
+        Some(OriginalUri(uri)) => uri.path(),
+        None => request.uri().path(),
+    };
+
+    NEVER_STORED.contains(&path)
+}
+
+async fn authenticate(state: &Idempotency, headers: &HeaderMap) -> Option<Uuid> {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))?;
+
+    AuthUser::from_token(&state.pool, &state.jwt, token)
+        .await
+        .ok()
+        .map(|auth| auth.user_id)
+}
+
+fn request_hash(method: &Method, uri: &str, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(method.as_str());
+    hasher.update(b" ");
+    hasher.update(uri);
+    hasher.update(b"\n");
+    hasher.update(body);
+    format!("{:x}", hasher.finalize())
+}
+
+fn replay(record: IdempotencyRecord, request_hash: &str) -> Result<Response> {
+    if record.request_hash != request_hash {
+        return Err(AppError::UnprocessableEntity(
+            "Idempotency-Key was already used for a different request".to_string(),
+        ));
+    }
+
+    let Some(status) = record.status else {
+        return Err(AppError::Conflict(
+            "A request with this Idempotency-Key is still being processed".to_string(),
+        ));
+    };
+
+    let status = u16::try_from(status)
+        .ok()
+        .and_then(|status| StatusCode::from_u16(status).ok())
+        .ok_or_else(|| AppError::Internal("Invalid stored response status".to_string()))?;
+
+    let mut response = Response::new(Body::from(record.body.unwrap_or_default()));
+    *response.status_mut() = status;
+
+    let headers = response.headers_mut();
+    for (name, value) in record.headers.map(|h| h.0).unwrap_or_default() {
+        if let (Ok(name), Ok(value)) = (HeaderName::try_from(name), HeaderValue::try_from(value)) {
+            headers.append(name, value);
+        }
+    }
+    headers.insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
+
+    Ok(response)
+}
+
+async fn store(
+    state: &Idempotency,
+    user_id: Uuid,
+    key: &str,
+    response: Response,
+) -> Result<Response> {
+    if response.status().is_server_error() {
+        IdempotencyService::release(&state.pool, user_id, key).await?;
+        return Ok(response);
+    }
+
+    let (parts, body) = response.into_parts();
+    let body: Bytes = match axum::body::to_bytes(body, MAX_BODY_BYTES).await {
+        Ok(body) => body,
+        Err(e) => {
+            IdempotencyService::release(&state.pool, user_id, key).await?;
+            return Err(AppError::Internal(format!(
+                "Failed to buffer response: {e}"
+            )));
+        }
+    };
+
+    let headers = parts
+        .headers
+        .iter()
+        .filter(|(name, _)| *name != header::CONTENT_LENGTH && *name != header::TRANSFER_ENCODING)
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect();
+
+    IdempotencyService::complete(
+        &state.pool,
+        user_id,
+        key,
+        parts.status.as_u16(),
+        headers,
+        &body,
+    )
+    .await?;
+
+    Ok(Response::from_parts(parts, Body::from(body)))
+}
@@ -0,0 +1,212 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use sqlx::PgPool;
+
+use crate::blob::BlobStorage;
+use crate::canonical_url::CanonicalUrlOptions;
+use crate::preview::PreviewFetcher;
+use crate::services::{
+    ArchiveService, BlobService, BookmarkService, IdempotencyService, PreviewService,
+    RefreshTokenService, SyncService, TokenRevocationService, TwoFactorService,
+};
+
+const TOMBSTONE_GC_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const IDEMPOTENCY_KEY_GC_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const REFRESH_TOKEN_GC_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const REVOKED_TOKEN_GC_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const LOGIN_CHALLENGE_GC_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const CANONICAL_URL_BACKFILL_BATCH: i64 = 500;
+const PREVIEW_POLL_INTERVAL: Duration = Duration::from_secs(5);
+const PREVIEW_BATCH: i64 = 10;
+const BLOB_GC_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const BLOB_GC_BATCH: i64 = 500;
+/// How long a blob stays unreferenced before it is deleted, so uploads
+/// awaiting their first reference are never collected.
+const BLOB_GRACE_HOURS: i64 = 24;
+const ARCHIVE_MIGRATION_BATCH: i64 = 50;
+
+/// Periodically removes tombstones older than the retention window.
+pub fn spawn_tombstone_gc(pool: PgPool, retention_days: i64) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(TOMBSTONE_GC_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let cutoff = Utc::now() - chrono::Duration::days(retention_days);
+            match SyncService::purge_tombstones(&pool, cutoff).await {
+                Ok(purged) => tracing::info!(purged, "Tombstone garbage collection finished"),
+                Err(e) => tracing::error!(error = %e, "Tombstone garbage collection failed"),
+            }
+        }
+    });
+}
+
+/// Periodically removes stored idempotent responses older than their replay window.
+pub fn spawn_idempotency_key_gc(pool: PgPool, ttl_hours: i64) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(IDEMPOTENCY_KEY_GC_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let cutoff = Utc::now() - chrono::Duration::hours(ttl_hours);
+            match IdempotencyService::purge_expired(&pool, cutoff).await {
+                Ok(purged) => tracing::info!(purged, "Idempotency key garbage collection finished"),
+                Err(e) => tracing::error!(error = %e, "Idempotency key garbage collection failed"),
+            }
+        }
+    });
+}
+
+/// Periodically removes expired refresh tokens.
+pub fn spawn_refresh_token_gc(pool: PgPool) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(REFRESH_TOKEN_GC_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            match RefreshTokenService::purge_expired(&pool, Utc::now()).await {
+                Ok(purged) => tracing::info!(purged, "Refresh token garbage collection finished"),
+                Err(e) => tracing::error!(error = %e, "Refresh token garbage collection failed"),
+            }
+        }
+    });
+}
+
+/// Periodically removes denylist entries of access tokens that have expired anyway.
+pub fn spawn_revoked_token_gc(pool: PgPool) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(REVOKED_TOKEN_GC_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            match TokenRevocationService::purge_expired(&pool, Utc::now()).await {
+                Ok(purged) => tracing::info!(purged, "Revoked token garbage collection finished"),
+                Err(e) => tracing::error!(error = %e, "Revoked token garbage collection failed"),
+            }
+        }
+    });
+}
+
+/// Periodically removes login challenges that were never answered.
+pub fn spawn_login_challenge_gc(pool: PgPool) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(LOGIN_CHALLENGE_GC_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            match TwoFactorService::purge_expired_challenges(&pool, Utc::now()).await {
+                Ok(purged) => tracing::info!(purged, "Login challenge garbage collection finished"),
+                Err(e) => tracing::error!(error = %e, "Login challenge garbage collection failed"),
+            }
+        }
+    });
+}
+
+/// Fills in canonical URLs for bookmarks stored before they were computed.
+pub fn spawn_canonical_url_backfill(pool: PgPool, canonical_urls: CanonicalUrlOptions) {
+    tokio::spawn(async move {
+        let mut total = 0;
+
+        loop {
+            match BookmarkService::backfill_canonical_urls(
+                &pool,
+                canonical_urls,
+                CANONICAL_URL_BACKFILL_BATCH,
+            )
+            .await
+            {
+                Ok(0) => break,
+                Ok(updated) => total += updated,
+                Err(e) => {
+                    tracing::error!(error = %e, "Canonical URL backfill failed");
+                    return;
+                }
+            }
+        }
+
+        if total > 0 {
+            tracing::info!(updated = total, "Canonical URL backfill finished");
+        }
+    });
+}
+
+/// Fetches previews for newly saved bookmarks, and retries failed fetches.
+pub fn spawn_preview_worker(pool: PgPool, fetcher: PreviewFetcher, blobs: BlobStorage) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PREVIEW_POLL_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            // Keep going while there is a backlog
+            loop {
+                match PreviewService::process_due(&pool, &fetcher, &blobs, PREVIEW_BATCH).await {
+                    Ok(claimed) if (claimed as i64) < PREVIEW_BATCH => break,
+                    Ok(_) => {}
+                    Err(e) => {
+                        tracing::error!(error = %e, "Preview fetching failed");
+                        break;
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Periodically deletes blobs that are no longer referenced.
+pub fn spawn_blob_gc(pool: PgPool, blobs: BlobStorage) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(BLOB_GC_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let cutoff = Utc::now() - chrono::Duration::hours(BLOB_GRACE_HOURS);
+            let mut total = 0;
+            loop {
+                match BlobService::collect_garbage(&pool, &blobs, cutoff, BLOB_GC_BATCH).await {
+                    Ok(deleted) => {
+                        total += deleted;
+                        if (deleted as i64) < BLOB_GC_BATCH {
+                            tracing::info!(deleted = total, "Blob garbage collection finished");
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        tracing::error!(error = %e, "Blob garbage collection failed");
+                        break;
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Moves pages archived before blob storage out of the database.
+pub fn spawn_archive_blob_migration(pool: PgPool, blobs: BlobStorage) {
+    tokio::spawn(async move {
+        let mut total = 0;
+
+        loop {
+            match ArchiveService::move_inline_to_blobs(&pool, &blobs, ARCHIVE_MIGRATION_BATCH).await
+            {
+                Ok(0) => break,
+                Ok(moved) => total += moved,
+                Err(e) => {
+                    tracing::error!(error = %e, "Archive migration to blob storage failed");
+                    return;
+                }
+            }
+        }
+
+        if total > 0 {
+            tracing::info!(moved = total, "Archive migration to blob storage finished");
+        }
+    });
+}
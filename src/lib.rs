@@ -1,13 +1,23 @@
+pub mod archive;
 pub mod auth;
+pub mod blob;
+pub mod canonical_url;
 pub mod config;
 pub mod db;
 pub mod error;
+pub mod events;
 pub mod handlers;
+pub mod idempotency;
+pub mod jobs;
 pub mod metrics;
 pub mod models;
+pub mod preview;
+pub mod search;
 pub mod services;
 pub mod telemetry;
 
+#[cfg(test)]
+mod canonical_url_tests;
 #[cfg(test)]
 mod error_tests;
 
@@ -22,6 +32,10 @@ pub use error::{AppError, Result};
 pub struct AppState {
     pub pool: PgPool,
     pub jwt: auth::JwtManager,
+    pub events: events::EventBus,
+    pub previews: preview::PreviewFetcher,
+    pub blobs: blob::BlobStorage,
+    pub canonical_urls: canonical_url::CanonicalUrlOptions,
 }
 
 impl FromRef<AppState> for PgPool {
@@ -35,3 +49,27 @@ impl FromRef<AppState> for auth::JwtManager {
         state.jwt.clone()
     }
 }
+
+impl FromRef<AppState> for events::EventBus {
+    fn from_ref(state: &AppState) -> Self {
+        state.events.clone()
+    }
+}
+
+impl FromRef<AppState> for preview::PreviewFetcher {
+    fn from_ref(state: &AppState) -> Self {
+        state.previews.clone()
+    }
+}
+
+impl FromRef<AppState> for blob::BlobStorage {
+    fn from_ref(state: &AppState) -> Self {
+        state.blobs.clone()
+    }
+}
+
+impl FromRef<AppState> for canonical_url::CanonicalUrlOptions {
+    fn from_ref(state: &AppState) -> Self {
+        state.canonical_urls
+    }
+}
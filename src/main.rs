@@ -1,12 +1,19 @@
-use axum::{Extension, Router, routing::get, routing::post};
+use std::time::Duration;
+
+use axum::{Extension, Router, middleware, routing::delete, routing::get, routing::post};
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::trace::TraceLayer;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
-use xync_server::auth::JwtManager;
+use xync_server::auth::{JwtKeys, JwtManager};
+use xync_server::blob::BlobStorage;
+use xync_server::canonical_url::CanonicalUrlOptions;
+use xync_server::events::EventBus;
 use xync_server::handlers;
+use xync_server::idempotency::{Idempotency, idempotency};
 use xync_server::models::*;
+use xync_server::preview::{PreviewConfig, PreviewFetcher};
 use xync_server::telemetry;
 use xync_server::{AppState, Config, Database};
 
@@ -15,17 +22,36 @@ use xync_server::{AppState, Config, Database};
     paths(
         handlers::register,
         handlers::login,
+        handlers::login_two_factor,
+        handlers::refresh,
+        handlers::logout,
+        handlers::logout_all,
         handlers::me,
+        handlers::create_access_token,
+        handlers::list_access_tokens,
+        handlers::revoke_access_token,
+        handlers::get_two_factor_status,
+        handlers::setup_two_factor,
+        handlers::confirm_two_factor,
+        handlers::disable_two_factor,
         handlers::create_bookmark,
         handlers::list_bookmarks,
         handlers::get_bookmark,
         handlers::update_bookmark,
         handlers::delete_bookmark,
+        handlers::list_duplicate_bookmarks,
+        handlers::merge_bookmarks,
+        handlers::preview_bookmark,
+        handlers::refresh_bookmark_preview,
+        handlers::get_bookmark_archive,
+        handlers::get_bookmark_archive_raw,
         handlers::create_note,
         handlers::list_notes,
         handlers::get_note,
         handlers::update_note,
         handlers::delete_note,
+        handlers::list_note_conflicts,
+        handlers::resolve_note_conflict,
         handlers::create_tag,
         handlers::list_tags,
         handlers::get_tag,
@@ -36,17 +62,51 @@ use xync_server::{AppState, Config, Database};
         handlers::get_category,
         handlers::update_category,
         handlers::delete_category,
+        handlers::list_devices,
+        handlers::revoke_device,
+        handlers::search,
+        handlers::suggest,
+        handlers::create_saved_search,
+        handlers::list_saved_searches,
+        handlers::get_saved_search,
+        handlers::get_saved_search_results,
+        handlers::update_saved_search,
+        handlers::delete_saved_search,
+        handlers::get_storage_usage,
+        handlers::get_changes,
+        handlers::run_batch,
+        handlers::list_tombstones,
+        handlers::ws_handler,
+        handlers::stream_events,
+        handlers::jwks,
         handlers::liveness,
         handlers::readiness,
     ),
     components(
         schemas(
-            CreateUser, LoginUser, UserResponse,
-            Bookmark, CreateBookmark, UpdateBookmark,
-            Note, CreateNote, UpdateNote,
+            CreateUser, LoginUser, RefreshRequest, UserResponse,
+            PersonalAccessToken, CreatePersonalAccessToken, CreatedPersonalAccessToken, Scope,
+            TwoFactorStatus, TwoFactorEnrollment, TwoFactorCode, TwoFactorLogin, DisableTwoFactor,
+            RecoveryCodes,
+            Device, DeviceInfo,
+            Bookmark, BookmarkWithTags, CreateBookmark, UpdateBookmark,
+            DuplicatePolicy, DuplicateGroup, MergeBookmarks,
+            PreviewStatus, PreviewRequest, LinkPreview, BookmarkArchive,
+            Note, CreateNote, UpdateNote, NoteConflict, ResolveNoteConflict, ConflictResolution,
             Tag, CreateTag, UpdateTag,
             Category, CreateCategory, UpdateCategory,
+            SyncChanges, BookmarkTagLinks, Tombstone, EntityType, ChangeEvent,
+            SearchResult, SearchType, SearchResultSort, SearchSuggestion,
+            StorageUsage,
+            SavedSearch, CreateSavedSearch, UpdateSavedSearch, SavedSearchSort,
+            SortOrder, TagMatch, BookmarkSort, NoteSort, TagSort, CategorySort,
+            BatchRequest, BatchOperation, BatchOp, BatchEntity, BatchResponse, BatchResult,
             handlers::auth::AuthResponse,
+            handlers::auth::TokenResponse,
+            handlers::auth::TwoFactorChallenge,
+            handlers::auth::LoginResponse,
+            xync_server::auth::JwkSet,
+            xync_server::auth::Jwk,
             handlers::health::HealthResponse,
             handlers::health::ReadinessResponse,
         )
@@ -58,6 +118,11 @@ use xync_server::{AppState, Config, Database};
         (name = "notes", description = "Note management"),
         (name = "tags", description = "Tag management"),
         (name = "categories", description = "Category management"),
+        (name = "devices", description = "Device management"),
+        (name = "search", description = "Full-text search"),
+        (name = "saved-searches", description = "Saved searches"),
+        (name = "storage", description = "Blob storage usage"),
+        (name = "sync", description = "Incremental sync"),
         (name = "health", description = "Health check endpoints"),
     )
 )]
@@ -88,6 +153,14 @@ async fn main() {
     // Initialize telemetry (tracing + optional OpenTelemetry)
     telemetry::init_telemetry(&config);
 
+    // Logged here because telemetry isn't set up while the config is read
+    if std::env::var(xync_server::config::DEPRECATED_JWT_EXPIRATION_HOURS).is_ok() {
+        tracing::warn!(
+            jwt_expiration_minutes = config.jwt_expiration_minutes,
+            "JWT_EXPIRATION_HOURS is deprecated, set JWT_EXPIRATION_MINUTES instead"
+        );
+    }
+
     tracing::info!(
         service_name = %config.service_name,
         "Starting xync-server"
@@ -103,13 +176,65 @@ async fn main() {
 
     tracing::info!("Database connected and migrations applied");
 
-    let jwt = JwtManager::new(&config.jwt_secret, config.jwt_expiration_hours);
+    let jwt = match &config.jwt_keys_dir {
+        Some(dir) => {
+            let keys = JwtKeys::load_dir(dir, config.jwt_signing_kid.as_deref())
+                .expect("Failed to load JWT keys");
+            JwtManager::from_keys(
+                keys,
+                config.jwt_expiration_minutes,
+                config.refresh_token_ttl_days,
+            )
+        }
+        None => JwtManager::new(
+            config
+                .jwt_secret
+                .as_deref()
+                .expect("JWT_SECRET or JWT_KEYS_DIR must be set"),
+            config.jwt_expiration_minutes,
+            config.refresh_token_ttl_days,
+        ),
+    };
+    xync_server::auth::start_revocation_listener(&db.pool, jwt.revocations().clone())
+        .await
+        .expect("Failed to listen for token revocations");
+
+    let events = EventBus::new();
+    xync_server::events::start_listener(&db.pool, events.clone())
+        .await
+        .expect("Failed to listen for change notifications");
+
+    let previews = PreviewFetcher::new(PreviewConfig {
+        timeout: Duration::from_secs(config.preview_timeout_secs),
+        max_bytes: config.preview_max_bytes,
+        archive_max_bytes: config.archive_max_bytes,
+        allow_private_networks: config.preview_allow_private_networks,
+    });
+
+    let blobs = BlobStorage::from_config(&config).expect("Failed to set up blob storage");
+    let canonical_urls = CanonicalUrlOptions {
+        keep_fragments: config.canonical_url_keep_fragments,
+    };
 
     let state = AppState {
         pool: db.pool.clone(),
         jwt: jwt.clone(),
+        events,
+        previews: previews.clone(),
+        blobs: blobs.clone(),
+        canonical_urls,
     };
 
+    xync_server::jobs::spawn_tombstone_gc(db.pool.clone(), config.tombstone_retention_days);
+    xync_server::jobs::spawn_idempotency_key_gc(db.pool.clone(), config.idempotency_key_ttl_hours);
+    xync_server::jobs::spawn_refresh_token_gc(db.pool.clone());
+    xync_server::jobs::spawn_revoked_token_gc(db.pool.clone());
+    xync_server::jobs::spawn_login_challenge_gc(db.pool.clone());
+    xync_server::jobs::spawn_canonical_url_backfill(db.pool.clone(), canonical_urls);
+    xync_server::jobs::spawn_preview_worker(db.pool.clone(), previews, blobs.clone());
+    xync_server::jobs::spawn_archive_blob_migration(db.pool.clone(), blobs.clone());
+    xync_server::jobs::spawn_blob_gc(db.pool.clone(), blobs);
+
     // Initialize Prometheus metrics
     let metrics_handle = xync_server::metrics::init_metrics();
 
@@ -121,7 +246,20 @@ async fn main() {
     let api_routes = Router::new()
         .route("/auth/register", post(handlers::register))
         .route("/auth/login", post(handlers::login))
+        .route("/auth/login/2fa", post(handlers::login_two_factor))
+        .route("/auth/refresh", post(handlers::refresh))
+        .route("/auth/logout", post(handlers::logout))
+        .route("/auth/logout-all", post(handlers::logout_all))
         .route("/auth/me", get(handlers::me))
+        .route(
+            "/auth/tokens",
+            post(handlers::create_access_token).get(handlers::list_access_tokens),
+        )
+        .route("/auth/tokens/{id}", delete(handlers::revoke_access_token))
+        .route("/auth/2fa", get(handlers::get_two_factor_status))
+        .route("/auth/2fa/setup", post(handlers::setup_two_factor))
+        .route("/auth/2fa/confirm", post(handlers::confirm_two_factor))
+        .route("/auth/2fa/disable", post(handlers::disable_two_factor))
         .route(
             "/bookmarks",
             post(handlers::create_bookmark).get(handlers::list_bookmarks),
@@ -132,6 +270,24 @@ async fn main() {
                 .put(handlers::update_bookmark)
                 .delete(handlers::delete_bookmark),
         )
+        .route(
+            "/bookmarks/duplicates",
+            get(handlers::list_duplicate_bookmarks),
+        )
+        .route("/bookmarks/{id}/merge", post(handlers::merge_bookmarks))
+        .route("/bookmarks/preview", post(handlers::preview_bookmark))
+        .route(
+            "/bookmarks/{id}/preview",
+            post(handlers::refresh_bookmark_preview),
+        )
+        .route(
+            "/bookmarks/{id}/archive",
+            get(handlers::get_bookmark_archive),
+        )
+        .route(
+            "/bookmarks/{id}/archive/raw",
+            get(handlers::get_bookmark_archive_raw),
+        )
         .route(
             "/notes",
             post(handlers::create_note).get(handlers::list_notes),
@@ -142,6 +298,11 @@ async fn main() {
                 .put(handlers::update_note)
                 .delete(handlers::delete_note),
         )
+        .route("/notes/{id}/conflicts", get(handlers::list_note_conflicts))
+        .route(
+            "/notes/{id}/conflicts/{conflict_id}/resolve",
+            post(handlers::resolve_note_conflict),
+        )
         .route("/tags", post(handlers::create_tag).get(handlers::list_tags))
         .route(
             "/tags/{id}",
@@ -158,10 +319,44 @@ async fn main() {
             get(handlers::get_category)
                 .put(handlers::update_category)
                 .delete(handlers::delete_category),
-        );
+        )
+        .route("/devices", get(handlers::list_devices))
+        .route("/devices/{id}", delete(handlers::revoke_device))
+        .route("/search", get(handlers::search))
+        .route("/search/suggest", get(handlers::suggest))
+        .route(
+            "/saved-searches",
+            post(handlers::create_saved_search).get(handlers::list_saved_searches),
+        )
+        .route(
+            "/saved-searches/{id}",
+            get(handlers::get_saved_search)
+                .put(handlers::update_saved_search)
+                .delete(handlers::delete_saved_search),
+        )
+        .route(
+            "/saved-searches/{id}/results",
+            get(handlers::get_saved_search_results),
+        )
+        .route("/storage/usage", get(handlers::get_storage_usage))
+        .route("/sync/changes", get(handlers::get_changes))
+        .route("/sync/tombstones", get(handlers::list_tombstones))
+        .route("/batch", post(handlers::run_batch))
+        .route("/ws", get(handlers::ws_handler))
+        .route("/events", get(handlers::stream_events))
+        .layer(middleware::from_fn_with_state(
+            Idempotency::new(
+                db.pool.clone(),
+                jwt.clone(),
+                config.idempotency_key_ttl_hours,
+            ),
+            idempotency,
+        ));
 
     let app = Router::new()
         .nest("/api", api_routes)
+        // Public keys for verifying access tokens
+        .route("/.well-known/jwks.json", get(handlers::jwks))
         // Health check endpoints
         .route("/health/live", get(handlers::liveness))
         .route("/health/ready", get(handlers::readiness))
@@ -174,7 +369,7 @@ async fn main() {
             }),
         )
         .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
-        .layer(TraceLayer::new_for_http())
+        .layer(TraceLayer::new_for_http().make_span_with(telemetry::request_span))
         .layer(cors)
         .layer(Extension(jwt))
         .with_state(state);
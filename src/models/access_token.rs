@@ -0,0 +1,115 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Permissions a personal access token can be limited to.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    ToSchema,
+    sqlx::Type,
+)]
+#[sqlx(type_name = "VARCHAR")]
+pub enum Scope {
+    #[serde(rename = "bookmarks:read")]
+    #[sqlx(rename = "bookmarks:read")]
+    BookmarksRead,
+    #[serde(rename = "bookmarks:write")]
+    #[sqlx(rename = "bookmarks:write")]
+    BookmarksWrite,
+    #[serde(rename = "notes:read")]
+    #[sqlx(rename = "notes:read")]
+    NotesRead,
+    #[serde(rename = "notes:write")]
+    #[sqlx(rename = "notes:write")]
+    NotesWrite,
+    #[serde(rename = "tags:read")]
+    #[sqlx(rename = "tags:read")]
+    TagsRead,
+    #[serde(rename = "tags:write")]
+    #[sqlx(rename = "tags:write")]
+    TagsWrite,
+    #[serde(rename = "categories:read")]
+    #[sqlx(rename = "categories:read")]
+    CategoriesRead,
+    #[serde(rename = "categories:write")]
+    #[sqlx(rename = "categories:write")]
+    CategoriesWrite,
+    #[serde(rename = "searches:read")]
+    #[sqlx(rename = "searches:read")]
+    SearchesRead,
+    #[serde(rename = "searches:write")]
+    #[sqlx(rename = "searches:write")]
+    SearchesWrite,
+    #[serde(rename = "sync:read")]
+    #[sqlx(rename = "sync:read")]
+    SyncRead,
+    #[serde(rename = "storage:read")]
+    #[sqlx(rename = "storage:read")]
+    StorageRead,
+}
+
+impl Scope {
+    /// Whether holding this scope allows what `required` protects; writing implies reading.
+    pub fn grants(self, required: Scope) -> bool {
+        self == required || self.read_scope() == Some(required)
+    }
+
+    fn read_scope(self) -> Option<Scope> {
+        match self {
+            Scope::BookmarksWrite => Some(Scope::BookmarksRead),
+            Scope::NotesWrite => Some(Scope::NotesRead),
+            Scope::TagsWrite => Some(Scope::TagsRead),
+            Scope::CategoriesWrite => Some(Scope::CategoriesRead),
+            Scope::SearchesWrite => Some(Scope::SearchesRead),
+            _ => None,
+        }
+    }
+}
+
+/// A personal access token, as listed to its owner; the secret itself is never shown again.
+#[derive(Debug, Clone, FromRow, Serialize, ToSchema)]
+pub struct PersonalAccessToken {
+    pub id: Uuid,
+    #[serde(skip_serializing)]
+    pub user_id: Uuid,
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    /// Absent for tokens that never expire
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Roughly when the token last authenticated a request
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct CreatePersonalAccessToken {
+    /// What the token is for, e.g. the script using it
+    #[validate(length(min = 1, max = 100, message = "Name must be 1 to 100 characters"))]
+    pub name: String,
+    #[validate(length(min = 1, message = "At least one scope is required"))]
+    pub scopes: Vec<Scope>,
+    /// Days until the token expires; it never expires when omitted
+    #[validate(range(min = 1, max = 3650, message = "Expiry must be 1 to 3650 days"))]
+    pub expires_in_days: Option<i64>,
+}
+
+/// A newly created token together with its secret, which is shown only this once.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreatedPersonalAccessToken {
+    #[serde(flatten)]
+    pub token: PersonalAccessToken,
+    /// Value to send as `Authorization: Bearer <secret>`
+    pub secret: String,
+}
@@ -0,0 +1,32 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Offline copy of a bookmarked page.
+#[derive(Debug, Clone, FromRow, Serialize, ToSchema)]
+pub struct BookmarkArchive {
+    pub bookmark_id: Uuid,
+    /// URL the page was read from, after redirects
+    pub url: String,
+    pub content_type: String,
+    /// Hex-encoded SHA-256 of the raw page
+    pub content_hash: String,
+    /// Size of the raw page in bytes
+    pub size: i64,
+    /// Whether the page was larger than the archive limit and was cut off
+    pub truncated: bool,
+    pub title: Option<String>,
+    /// Main text of the page; paragraphs are separated by blank lines
+    pub text: String,
+    pub word_count: i32,
+    pub archived_at: DateTime<Utc>,
+}
+
+/// The page exactly as it was fetched.
+#[derive(Debug, FromRow)]
+pub struct RawArchive {
+    pub content_type: String,
+    pub raw_content: Vec<u8>,
+}
@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchOp {
+    Create,
+    Update,
+    Delete,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchEntity {
+    Bookmark,
+    Note,
+    Tag,
+    Category,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BatchOperation {
+    pub op: BatchOp,
+    pub entity: BatchEntity,
+    /// Temporary id that later operations in the batch may use in place of the real id
+    pub client_id: Option<String>,
+    /// Target of an update or delete (real id or earlier `client_id`), or the id to create with
+    pub id: Option<String>,
+    /// Version the operation is based on, as with `If-Match`
+    pub version: Option<i64>,
+    /// Create or update payload, in the same shape as the matching endpoint
+    #[schema(value_type = Option<Object>)]
+    pub data: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct BatchRequest {
+    #[validate(length(
+        min = 1,
+        max = 500,
+        message = "A batch must contain 1 to 500 operations"
+    ))]
+    pub operations: Vec<BatchOperation>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BatchResult {
+    pub index: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    pub id: Uuid,
+    /// HTTP status the matching single-item endpoint would have returned
+    pub status: u16,
+    /// Resulting entity, or the conflict record for a conflicting note update
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub data: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BatchResponse {
+    pub results: Vec<BatchResult>,
+}
@@ -1,24 +1,50 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 use validator::Validate;
 
+use super::{PreviewStatus, SortOrder, Tag, TagMatch, validate_client_id};
+
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize, ToSchema)]
 pub struct Bookmark {
     pub id: Uuid,
     pub user_id: Uuid,
     pub url: String,
+    /// Normalized `url` used to detect duplicates
+    pub canonical_url: Option<String>,
     pub title: String,
     pub description: Option<String>,
     pub category_id: Option<Uuid>,
+    pub is_favorite: bool,
+    /// Page title found when the URL was fetched
+    pub preview_title: Option<String>,
+    pub preview_description: Option<String>,
+    pub preview_image: Option<String>,
+    pub favicon: Option<String>,
+    pub preview_status: PreviewStatus,
+    pub preview_fetched_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub version: i64,
+}
+
+/// A bookmark together with the tags linked to it.
+#[derive(Debug, Clone, FromRow, Serialize, ToSchema)]
+pub struct BookmarkWithTags {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub bookmark: Bookmark,
+    #[sqlx(json)]
+    pub tags: Vec<Tag>,
 }
 
 #[derive(Debug, Deserialize, Validate, ToSchema)]
 pub struct CreateBookmark {
+    /// Client-generated id (UUID v4 or v7); assigned by the server when omitted
+    #[validate(custom(function = "validate_client_id"))]
+    pub id: Option<Uuid>,
     #[validate(url(message = "Invalid URL format"))]
     pub url: String,
     #[validate(length(min = 1, message = "Title is required"))]
@@ -26,6 +52,10 @@ pub struct CreateBookmark {
     pub description: Option<String>,
     pub category_id: Option<Uuid>,
     pub tag_ids: Option<Vec<Uuid>>,
+    /// Marks the bookmark as a favorite (default `false`)
+    pub is_favorite: Option<bool>,
+    /// What to do if a bookmark with the same canonical URL exists (default `allow`)
+    pub on_duplicate: Option<DuplicatePolicy>,
 }
 
 #[derive(Debug, Deserialize, Validate, ToSchema)]
@@ -36,4 +66,84 @@ pub struct UpdateBookmark {
     pub description: Option<String>,
     pub category_id: Option<Uuid>,
     pub tag_ids: Option<Vec<Uuid>>,
+    pub is_favorite: Option<bool>,
+    /// What to do if the new URL matches another bookmark (default `allow`; `merge` is not supported)
+    pub on_duplicate: Option<DuplicatePolicy>,
+}
+
+/// How to handle a bookmark whose canonical URL is already bookmarked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicatePolicy {
+    /// Store it as a separate bookmark
+    #[default]
+    Allow,
+    /// Fail with `409 Conflict`
+    Reject,
+    /// Fold it into the oldest existing bookmark for the URL
+    Merge,
+}
+
+/// Result of creating a bookmark under a [`DuplicatePolicy`].
+#[derive(Debug)]
+pub enum BookmarkCreateOutcome {
+    Created(BookmarkWithTags),
+    /// Merged into an existing bookmark for the same canonical URL
+    Merged(BookmarkWithTags),
+}
+
+/// Bookmarks sharing a canonical URL.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DuplicateGroup {
+    pub canonical_url: String,
+    /// Oldest first
+    pub bookmarks: Vec<BookmarkWithTags>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct MergeBookmarks {
+    /// Bookmarks to fold into the target and then delete
+    #[validate(length(
+        min = 1,
+        max = 100,
+        message = "Between 1 and 100 source ids are required"
+    ))]
+    pub source_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BookmarkSort {
+    #[default]
+    CreatedAt,
+    UpdatedAt,
+    Title,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BookmarkListQuery {
+    /// Maximum number of items to return (default 50, max 200)
+    pub limit: Option<i64>,
+    /// `next_cursor` from the previous page
+    pub cursor: Option<String>,
+    /// Field to sort by (default `created_at`)
+    pub sort: Option<BookmarkSort>,
+    /// Sort direction (default `desc` for dates, `asc` for title)
+    pub order: Option<SortOrder>,
+    /// Only bookmarks in this category
+    pub category_id: Option<Uuid>,
+    /// Comma-separated tag ids to filter by
+    pub tag_id: Option<String>,
+    /// Whether bookmarks need `any` (default) or `all` of the tags
+    pub tag_match: Option<TagMatch>,
+    /// Only items created after this time
+    pub created_after: Option<DateTime<Utc>>,
+    /// Only items updated after this time
+    pub updated_after: Option<DateTime<Utc>>,
+    /// Only bookmarks on this domain or its subdomains
+    pub url_domain: Option<String>,
+    /// Also return the number of items matching the filters
+    #[serde(default)]
+    pub include_total: bool,
 }
@@ -1,10 +1,12 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 use validator::Validate;
 
+use super::{SortOrder, validate_client_id};
+
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize, ToSchema)]
 pub struct Category {
     pub id: Uuid,
@@ -13,10 +15,14 @@ pub struct Category {
     pub description: Option<String>,
     pub parent_id: Option<Uuid>,
     pub created_at: DateTime<Utc>,
+    pub version: i64,
 }
 
 #[derive(Debug, Deserialize, Validate, ToSchema)]
 pub struct CreateCategory {
+    /// Client-generated id (UUID v4 or v7); assigned by the server when omitted
+    #[validate(custom(function = "validate_client_id"))]
+    pub id: Option<Uuid>,
     #[validate(length(min = 1, message = "Category name is required"))]
     pub name: String,
     pub description: Option<String>,
@@ -29,3 +35,31 @@ pub struct UpdateCategory {
     pub description: Option<String>,
     pub parent_id: Option<Uuid>,
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CategorySort {
+    #[default]
+    Name,
+    CreatedAt,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CategoryListQuery {
+    /// Maximum number of items to return (default 50, max 200)
+    pub limit: Option<i64>,
+    /// `next_cursor` from the previous page
+    pub cursor: Option<String>,
+    /// Field to sort by (default `name`)
+    pub sort: Option<CategorySort>,
+    /// Sort direction (default `desc` for dates, `asc` for name)
+    pub order: Option<SortOrder>,
+    /// Only direct children of this category
+    pub parent_id: Option<Uuid>,
+    /// Only items created after this time
+    pub created_after: Option<DateTime<Utc>>,
+    /// Also return the number of items matching the filters
+    #[serde(default)]
+    pub include_total: bool,
+}
@@ -0,0 +1,52 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::SyncCursor;
+
+#[derive(Debug, Clone, FromRow, Serialize, ToSchema)]
+pub struct Device {
+    pub id: Uuid,
+    #[serde(skip_serializing)]
+    pub user_id: Uuid,
+    pub name: String,
+    pub platform: Option<String>,
+    pub client_version: Option<String>,
+    /// Last sync cursor the device acknowledged by syncing from it
+    #[serde(
+        rename = "last_sync_cursor",
+        serialize_with = "serialize_optional_cursor"
+    )]
+    #[schema(value_type = Option<String>)]
+    pub last_sync_seq: Option<i64>,
+    pub last_seen_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    /// Set once the device has been signed out remotely
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+fn serialize_optional_cursor<S: serde::Serializer>(
+    seq: &Option<i64>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    match seq {
+        Some(seq) => serializer.serialize_str(&SyncCursor(*seq).encode()),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Describes the device a client is signing in from.
+#[derive(Debug, Clone, Default, Deserialize, Validate, ToSchema)]
+pub struct DeviceInfo {
+    /// Id of a device returned by an earlier sign-in, to sign in on it again
+    pub id: Option<Uuid>,
+    #[validate(length(min = 1, max = 100, message = "Device name must be 1-100 characters"))]
+    pub name: String,
+    #[validate(length(max = 50, message = "Platform must be at most 50 characters"))]
+    pub platform: Option<String>,
+    #[validate(length(max = 50, message = "Client version must be at most 50 characters"))]
+    pub client_version: Option<String>,
+}
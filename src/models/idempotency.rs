@@ -0,0 +1,15 @@
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+use sqlx::types::Json;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct IdempotencyRecord {
+    pub user_id: Uuid,
+    pub key: String,
+    pub request_hash: String,
+    pub status: Option<i32>,
+    pub headers: Option<Json<Vec<(String, String)>>>,
+    pub body: Option<Vec<u8>>,
+    pub created_at: DateTime<Utc>,
+}
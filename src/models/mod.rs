@@ -1,14 +1,60 @@
+mod access_token;
+mod archive;
+mod batch;
 mod bookmark;
 mod category;
+mod device;
+mod idempotency;
 mod note;
+mod pagination;
+mod preview;
+mod saved_search;
+mod search;
+mod storage;
+mod sync;
 mod tag;
+mod two_factor;
 mod user;
+mod validation;
 
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests;
 
-pub use bookmark::{Bookmark, CreateBookmark, UpdateBookmark};
-pub use category::{Category, CreateCategory, UpdateCategory};
-pub use note::{CreateNote, Note, UpdateNote};
-pub use tag::{CreateTag, Tag, UpdateTag};
-pub use user::{CreateUser, LoginUser, User, UserResponse};
+pub use access_token::{
+    CreatePersonalAccessToken, CreatedPersonalAccessToken, PersonalAccessToken, Scope,
+};
+pub use archive::{BookmarkArchive, RawArchive};
+pub use batch::{BatchEntity, BatchOp, BatchOperation, BatchRequest, BatchResponse, BatchResult};
+pub use bookmark::{
+    Bookmark, BookmarkCreateOutcome, BookmarkListQuery, BookmarkSort, BookmarkWithTags,
+    CreateBookmark, DuplicateGroup, DuplicatePolicy, MergeBookmarks, UpdateBookmark,
+};
+pub use category::{Category, CategoryListQuery, CategorySort, CreateCategory, UpdateCategory};
+pub use device::{Device, DeviceInfo};
+pub use idempotency::IdempotencyRecord;
+pub use note::{
+    ConflictResolution, CreateNote, Note, NoteConflict, NoteListQuery, NoteRevision, NoteSort,
+    NoteUpdateOutcome, ResolveNoteConflict, UpdateNote,
+};
+pub use pagination::{Page, PageCursor, SortOrder, TagMatch};
+pub use preview::{LinkPreview, PreviewRequest, PreviewStatus};
+pub use saved_search::{
+    CreateSavedSearch, SavedSearch, SavedSearchListQuery, SavedSearchResultsQuery, SavedSearchSort,
+    UpdateSavedSearch,
+};
+pub use search::{
+    SearchQuery, SearchResult, SearchResultSort, SearchSuggestion, SearchType, SuggestQuery,
+};
+pub use storage::StorageUsage;
+pub use sync::{
+    BookmarkTagLinks, ChangeEvent, ChangeOperation, EntityType, EventsQuery, SyncChange,
+    SyncChanges, SyncCursor, SyncQuery, Tombstone, TombstoneQuery, WsQuery,
+};
+pub use tag::{CreateTag, Tag, TagListQuery, TagSort, UpdateTag};
+pub use two_factor::{
+    DisableTwoFactor, RecoveryCodes, TwoFactorCode, TwoFactorEnrollment, TwoFactorLogin,
+    TwoFactorStatus,
+};
+pub use user::{CreateUser, LoginUser, RefreshRequest, User, UserResponse};
+pub use validation::validate_client_id;
@@ -1,10 +1,12 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 use validator::Validate;
 
+use super::{SortOrder, validate_client_id};
+
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize, ToSchema)]
 pub struct Note {
     pub id: Uuid,
@@ -13,10 +15,14 @@ pub struct Note {
     pub content: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub version: i64,
 }
 
 #[derive(Debug, Deserialize, Validate, ToSchema)]
 pub struct CreateNote {
+    /// Client-generated id (UUID v4 or v7); assigned by the server when omitted
+    #[validate(custom(function = "validate_client_id"))]
+    pub id: Option<Uuid>,
     #[validate(length(min = 1, message = "Title is required"))]
     pub title: String,
     pub content: String,
@@ -27,3 +33,86 @@ pub struct UpdateNote {
     pub title: Option<String>,
     pub content: Option<String>,
 }
+
+#[derive(Debug, Clone, FromRow)]
+pub struct NoteRevision {
+    pub note_id: Uuid,
+    pub version: i64,
+    pub title: String,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An edit based on an older version that could not be merged into the current note.
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, ToSchema)]
+pub struct NoteConflict {
+    pub id: Uuid,
+    pub note_id: Uuid,
+    pub user_id: Uuid,
+    pub base_version: i64,
+    pub server_version: i64,
+    pub server_title: String,
+    pub server_content: String,
+    pub client_title: String,
+    pub client_content: String,
+    pub created_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+/// Result of applying an update to a note.
+#[derive(Debug)]
+pub enum NoteUpdateOutcome {
+    /// Applied directly, or merged cleanly with concurrent changes
+    Applied(Note),
+    /// Concurrent changes overlap; the note is left unchanged
+    Conflicted(NoteConflict),
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictResolution {
+    /// Keep the note as it is on the server
+    Server,
+    /// Replace the note with the client's edit
+    Client,
+    /// Replace the note with the supplied title and content
+    Manual,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ResolveNoteConflict {
+    pub resolution: ConflictResolution,
+    /// Required for `manual` resolutions
+    pub title: Option<String>,
+    /// Required for `manual` resolutions
+    pub content: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NoteSort {
+    #[default]
+    UpdatedAt,
+    CreatedAt,
+    Title,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct NoteListQuery {
+    /// Maximum number of items to return (default 50, max 200)
+    pub limit: Option<i64>,
+    /// `next_cursor` from the previous page
+    pub cursor: Option<String>,
+    /// Field to sort by (default `updated_at`)
+    pub sort: Option<NoteSort>,
+    /// Sort direction (default `desc` for dates, `asc` for title)
+    pub order: Option<SortOrder>,
+    /// Only items created after this time
+    pub created_after: Option<DateTime<Utc>>,
+    /// Only items updated after this time
+    pub updated_after: Option<DateTime<Utc>>,
+    /// Also return the number of items matching the filters
+    #[serde(default)]
+    pub include_total: bool,
+}
@@ -0,0 +1,67 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "VARCHAR", rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+/// How multiple `tag_id` filters combine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TagMatch {
+    /// Items carrying at least one of the tags
+    #[default]
+    Any,
+    /// Items carrying every one of the tags
+    All,
+}
+
+/// One page of a list, in the requested sort order.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Cursor to pass as `cursor` for the next page; absent on the last page
+    pub next_cursor: Option<String>,
+    /// Number of items matching the filters, when `include_total` was requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<i64>,
+}
+
+/// Opaque position after the last item of a page.
+///
+/// Carries the sort it was issued for, so it cannot be replayed against a
+/// different ordering.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PageCursor {
+    pub sort: String,
+    pub order: SortOrder,
+    /// Sort column value of the last item
+    pub key: String,
+    /// Id of the last item, breaking ties between equal keys
+    pub id: Uuid,
+}
+
+impl PageCursor {
+    const PREFIX: &'static str = "p1.";
+
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).unwrap_or_default();
+        format!("{}{}", Self::PREFIX, URL_SAFE_NO_PAD.encode(json))
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self> {
+        cursor
+            .strip_prefix(Self::PREFIX)
+            .and_then(|encoded| URL_SAFE_NO_PAD.decode(encoded).ok())
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .ok_or_else(|| AppError::Validation("Invalid page cursor".to_string()))
+    }
+}
@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+/// Progress of the background preview fetch for a bookmark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+pub enum PreviewStatus {
+    /// Not fetched yet, or waiting to be retried
+    Pending,
+    Ready,
+    /// Gave up after repeated failures
+    Failed,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct PreviewRequest {
+    #[validate(url(message = "Invalid URL format"))]
+    pub url: String,
+}
+
+/// Metadata extracted from a web page.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, ToSchema)]
+pub struct LinkPreview {
+    /// URL the metadata was read from, after redirects
+    pub url: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// Absolute URL of the page's preview image
+    pub image: Option<String>,
+    /// Absolute URL of the page's icon
+    pub favicon: Option<String>,
+}
@@ -0,0 +1,82 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+use validator::Validate;
+
+use super::{SearchResultSort, SortOrder, validate_client_id};
+
+/// A named search query whose results are evaluated on demand.
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, ToSchema)]
+pub struct SavedSearch {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    /// Query in the `/api/search` syntax, e.g. `is:bookmark tag:rust category:Reading`
+    pub query: String,
+    /// Ordering of the results
+    pub sort: SearchResultSort,
+    /// Direction of the results; the sort's default when absent
+    #[sqlx(rename = "sort_order")]
+    pub order: Option<SortOrder>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub version: i64,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct CreateSavedSearch {
+    /// Client-generated id (UUID v4 or v7); assigned by the server when omitted
+    #[validate(custom(function = "validate_client_id"))]
+    pub id: Option<Uuid>,
+    #[validate(length(min = 1, max = 255, message = "Name must be 1 to 255 characters"))]
+    pub name: String,
+    pub query: String,
+    /// Ordering of the results (default `updated_at`)
+    pub sort: Option<SearchResultSort>,
+    /// Direction of the results (default `desc` for dates, `asc` for title)
+    pub order: Option<SortOrder>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct UpdateSavedSearch {
+    #[validate(length(min = 1, max = 255, message = "Name must be 1 to 255 characters"))]
+    pub name: Option<String>,
+    pub query: Option<String>,
+    pub sort: Option<SearchResultSort>,
+    pub order: Option<SortOrder>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SavedSearchSort {
+    #[default]
+    Name,
+    CreatedAt,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SavedSearchListQuery {
+    /// Maximum number of items to return (default 50, max 200)
+    pub limit: Option<i64>,
+    /// `next_cursor` from the previous page
+    pub cursor: Option<String>,
+    /// Field to sort by (default `name`)
+    pub sort: Option<SavedSearchSort>,
+    /// Sort direction (default `desc` for dates, `asc` for name)
+    pub order: Option<SortOrder>,
+    /// Also return the number of items matching the filters
+    #[serde(default)]
+    pub include_total: bool,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SavedSearchResultsQuery {
+    /// Maximum number of results to return (default 50, max 200)
+    pub limit: Option<i64>,
+    /// `next_cursor` from the previous page
+    pub cursor: Option<String>,
+}
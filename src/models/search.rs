@@ -0,0 +1,76 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use super::EntityType;
+
+/// Kinds of entities covered by search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchType {
+    Bookmark,
+    Note,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchQuery {
+    /// Search terms and qualifiers, e.g. `rust tag:web -is:untagged "exact phrase"`
+    pub q: String,
+    /// Only return results of this type
+    #[serde(rename = "type")]
+    pub search_type: Option<SearchType>,
+    /// Maximum number of results (default 20, max 100)
+    pub limit: Option<i64>,
+    /// Also match titles and URLs that are spelled similarly to the search terms
+    #[serde(default)]
+    pub fuzzy: bool,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SuggestQuery {
+    /// Text typed so far
+    pub prefix: String,
+    /// Maximum number of suggestions (default 10, max 20)
+    pub limit: Option<i64>,
+}
+
+/// Autocomplete candidate for the search box.
+#[derive(Debug, Clone, FromRow, Serialize, ToSchema)]
+pub struct SearchSuggestion {
+    pub entity_type: EntityType,
+    pub id: Uuid,
+    pub title: String,
+    /// Bookmark URL; absent for notes
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, ToSchema)]
+pub struct SearchResult {
+    pub entity_type: EntityType,
+    pub id: Uuid,
+    pub title: String,
+    /// Bookmark URL; absent for notes
+    pub url: Option<String>,
+    /// HTML-escaped excerpt with matches wrapped in `<mark>` tags
+    pub snippet: String,
+    pub rank: f32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Orderings available to saved search results.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema, sqlx::Type,
+)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+pub enum SearchResultSort {
+    #[default]
+    UpdatedAt,
+    CreatedAt,
+    Title,
+}
@@ -0,0 +1,11 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// Blob storage used by the current user.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct StorageUsage {
+    /// Bytes of distinct blobs the user references; shared content counts once
+    pub used_bytes: i64,
+    pub quota_bytes: i64,
+    pub blob_count: i64,
+}
@@ -0,0 +1,141 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use super::{Bookmark, Category, Note, SavedSearch, Tag};
+use crate::error::{AppError, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+pub enum EntityType {
+    Bookmark,
+    Note,
+    Tag,
+    Category,
+    BookmarkTags,
+    SavedSearch,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+pub enum ChangeOperation {
+    Upsert,
+    Delete,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct SyncChange {
+    pub user_id: Uuid,
+    pub seq: i64,
+    pub entity_type: EntityType,
+    pub entity_id: Uuid,
+    pub operation: ChangeOperation,
+    pub changed_at: DateTime<Utc>,
+}
+
+/// Opaque position in a user's change log handed out to syncing clients.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SyncCursor(pub i64);
+
+impl SyncCursor {
+    const PREFIX: &'static str = "v1.";
+
+    pub fn encode(&self) -> String {
+        format!("{}{:016x}", Self::PREFIX, self.0)
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self> {
+        cursor
+            .strip_prefix(Self::PREFIX)
+            .and_then(|hex| i64::from_str_radix(hex, 16).ok())
+            .filter(|seq| *seq >= 0)
+            .map(SyncCursor)
+            .ok_or_else(|| AppError::Validation("Invalid sync cursor".to_string()))
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct SyncQuery {
+    /// Cursor returned by a previous sync; omit to fetch everything
+    pub since: Option<String>,
+    /// Maximum number of change log entries to consume (default 500, max 1000)
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BookmarkTagLinks {
+    pub bookmark_id: Uuid,
+    pub tag_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, ToSchema)]
+pub struct Tombstone {
+    #[serde(skip_serializing)]
+    pub user_id: Uuid,
+    pub entity_type: EntityType,
+    #[serde(rename = "id")]
+    pub entity_id: Uuid,
+    pub deleted_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct TombstoneQuery {
+    /// Only return tombstones for deletions after this time
+    pub since: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SyncChanges {
+    pub bookmarks: Vec<Bookmark>,
+    pub notes: Vec<Note>,
+    pub tags: Vec<Tag>,
+    pub categories: Vec<Category>,
+    pub saved_searches: Vec<SavedSearch>,
+    /// Full tag set of every bookmark whose links changed
+    pub bookmark_tags: Vec<BookmarkTagLinks>,
+    pub deleted: Vec<Tombstone>,
+    /// Cursor to pass as `since` on the next sync
+    pub cursor: String,
+    /// Whether more changes are available past `cursor`
+    pub has_more: bool,
+}
+
+/// Pushed to connected clients whenever an entry is added to their change log.
+#[derive(Debug, Clone, FromRow, Serialize, ToSchema)]
+pub struct ChangeEvent {
+    #[serde(skip_serializing)]
+    pub user_id: Uuid,
+    /// Sync cursor just past this change
+    #[serde(rename = "cursor", serialize_with = "serialize_cursor")]
+    #[schema(value_type = String)]
+    pub seq: i64,
+    pub entity_type: EntityType,
+    #[serde(rename = "id")]
+    pub entity_id: Uuid,
+    pub operation: ChangeOperation,
+    /// Entity version after the change; absent for deletions
+    pub version: Option<i64>,
+}
+
+fn serialize_cursor<S: serde::Serializer>(
+    seq: &i64,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_str(&SyncCursor(*seq).encode())
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct WsQuery {
+    /// Access token, for clients that cannot set an `Authorization` header
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct EventsQuery {
+    /// Cursor to replay missed events from; the `Last-Event-ID` header takes precedence
+    pub since: Option<String>,
+}
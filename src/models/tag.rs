@@ -1,10 +1,12 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 use validator::Validate;
 
+use super::{SortOrder, validate_client_id};
+
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize, ToSchema)]
 pub struct Tag {
     pub id: Uuid,
@@ -12,10 +14,14 @@ pub struct Tag {
     pub name: String,
     pub color: Option<String>,
     pub created_at: DateTime<Utc>,
+    pub version: i64,
 }
 
 #[derive(Debug, Deserialize, Validate, ToSchema)]
 pub struct CreateTag {
+    /// Client-generated id (UUID v4 or v7); assigned by the server when omitted
+    #[validate(custom(function = "validate_client_id"))]
+    pub id: Option<Uuid>,
     #[validate(length(min = 1, message = "Tag name is required"))]
     pub name: String,
     pub color: Option<String>,
@@ -26,3 +32,29 @@ pub struct UpdateTag {
     pub name: Option<String>,
     pub color: Option<String>,
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TagSort {
+    #[default]
+    Name,
+    CreatedAt,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TagListQuery {
+    /// Maximum number of items to return (default 50, max 200)
+    pub limit: Option<i64>,
+    /// `next_cursor` from the previous page
+    pub cursor: Option<String>,
+    /// Field to sort by (default `name`)
+    pub sort: Option<TagSort>,
+    /// Sort direction (default `desc` for dates, `asc` for name)
+    pub order: Option<SortOrder>,
+    /// Only items created after this time
+    pub created_after: Option<DateTime<Utc>>,
+    /// Also return the number of items matching the filters
+    #[serde(default)]
+    pub include_total: bool,
+}
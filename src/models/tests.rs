@@ -1,7 +1,8 @@
 #[cfg(test)]
 mod tests {
     use crate::models::{
-        CreateBookmark, CreateCategory, CreateNote, CreateTag, CreateUser, LoginUser,
+        CreateBookmark, CreateCategory, CreateNote, CreatePersonalAccessToken, CreateTag,
+        CreateUser, DeviceInfo, LoginUser, PageCursor, Scope, SortOrder, SyncCursor,
         UpdateBookmark, UpdateCategory, UpdateNote, UpdateTag,
     };
     use uuid::Uuid;
@@ -13,6 +14,7 @@ mod tests {
             email: "test@example.com".to_string(),
             password: "password123".to_string(),
             name: "Test User".to_string(),
+            device: None,
         };
         assert!(user.validate().is_ok());
     }
@@ -23,6 +25,7 @@ mod tests {
             email: "invalid-email".to_string(),
             password: "password123".to_string(),
             name: "Test User".to_string(),
+            device: None,
         };
         assert!(user.validate().is_err());
     }
@@ -33,6 +36,7 @@ mod tests {
             email: "test@example.com".to_string(),
             password: "short".to_string(),
             name: "Test User".to_string(),
+            device: None,
         };
         assert!(user.validate().is_err());
     }
@@ -43,6 +47,7 @@ mod tests {
             email: "test@example.com".to_string(),
             password: "password123".to_string(),
             name: "".to_string(),
+            device: None,
         };
         assert!(user.validate().is_err());
     }
@@ -52,6 +57,7 @@ mod tests {
         let login = LoginUser {
             email: "test@example.com".to_string(),
             password: "anypassword".to_string(),
+            device: None,
         };
         assert!(login.validate().is_ok());
     }
@@ -61,6 +67,22 @@ mod tests {
         let login = LoginUser {
             email: "not-an-email".to_string(),
             password: "password".to_string(),
+            device: None,
+        };
+        assert!(login.validate().is_err());
+    }
+
+    #[test]
+    fn test_login_user_validation_empty_device_name() {
+        let login = LoginUser {
+            email: "test@example.com".to_string(),
+            password: "anypassword".to_string(),
+            device: Some(DeviceInfo {
+                id: None,
+                name: "".to_string(),
+                platform: Some("ios".to_string()),
+                client_version: None,
+            }),
         };
         assert!(login.validate().is_err());
     }
@@ -68,11 +90,14 @@ mod tests {
     #[test]
     fn test_create_bookmark_validation_valid() {
         let bookmark = CreateBookmark {
+            id: None,
             url: "https://example.com".to_string(),
             title: "Example Site".to_string(),
             description: Some("A description".to_string()),
             category_id: None,
             tag_ids: None,
+            is_favorite: None,
+            on_duplicate: None,
         };
         assert!(bookmark.validate().is_ok());
     }
@@ -80,11 +105,14 @@ mod tests {
     #[test]
     fn test_create_bookmark_validation_invalid_url() {
         let bookmark = CreateBookmark {
+            id: None,
             url: "not-a-valid-url".to_string(),
             title: "Example".to_string(),
             description: None,
             category_id: None,
             tag_ids: None,
+            is_favorite: None,
+            on_duplicate: None,
         };
         assert!(bookmark.validate().is_err());
     }
@@ -92,18 +120,37 @@ mod tests {
     #[test]
     fn test_create_bookmark_validation_empty_title() {
         let bookmark = CreateBookmark {
+            id: None,
             url: "https://example.com".to_string(),
             title: "".to_string(),
             description: None,
             category_id: None,
             tag_ids: None,
+            is_favorite: None,
+            on_duplicate: None,
         };
         assert!(bookmark.validate().is_err());
     }
 
+    #[test]
+    fn test_create_bookmark_validation_client_id() {
+        let bookmark = CreateBookmark {
+            id: Some(Uuid::new_v4()),
+            url: "https://example.com".to_string(),
+            title: "Example".to_string(),
+            description: None,
+            category_id: None,
+            tag_ids: None,
+            is_favorite: None,
+            on_duplicate: None,
+        };
+        assert!(bookmark.validate().is_ok());
+    }
+
     #[test]
     fn test_create_note_validation_valid() {
         let note = CreateNote {
+            id: None,
             title: "My Note".to_string(),
             content: "Some content".to_string(),
         };
@@ -113,15 +160,37 @@ mod tests {
     #[test]
     fn test_create_note_validation_empty_title() {
         let note = CreateNote {
+            id: None,
             title: "".to_string(),
             content: "Content".to_string(),
         };
         assert!(note.validate().is_err());
     }
 
+    #[test]
+    fn test_create_note_validation_v7_client_id() {
+        let note = CreateNote {
+            id: Some(Uuid::parse_str("01920a6e-5c6a-7b1e-9c3d-2f4a5b6c7d8e").unwrap()),
+            title: "My Note".to_string(),
+            content: "Some content".to_string(),
+        };
+        assert!(note.validate().is_ok());
+    }
+
+    #[test]
+    fn test_create_note_validation_rejects_v1_client_id() {
+        let note = CreateNote {
+            id: Some(Uuid::parse_str("c232ab00-9414-11ec-b3c8-9e6bdeced846").unwrap()),
+            title: "My Note".to_string(),
+            content: "Some content".to_string(),
+        };
+        assert!(note.validate().is_err());
+    }
+
     #[test]
     fn test_create_tag_validation_valid() {
         let tag = CreateTag {
+            id: None,
             name: "important".to_string(),
             color: Some("#ff0000".to_string()),
         };
@@ -131,6 +200,7 @@ mod tests {
     #[test]
     fn test_create_tag_validation_empty_name() {
         let tag = CreateTag {
+            id: None,
             name: "".to_string(),
             color: None,
         };
@@ -140,6 +210,7 @@ mod tests {
     #[test]
     fn test_create_category_validation_valid() {
         let category = CreateCategory {
+            id: None,
             name: "Work".to_string(),
             description: Some("Work related items".to_string()),
             parent_id: None,
@@ -150,6 +221,7 @@ mod tests {
     #[test]
     fn test_create_category_validation_empty_name() {
         let category = CreateCategory {
+            id: None,
             name: "".to_string(),
             description: None,
             parent_id: None,
@@ -165,6 +237,8 @@ mod tests {
             description: None,
             category_id: None,
             tag_ids: None,
+            is_favorite: None,
+            on_duplicate: None,
         };
         // All fields are optional, so this should be valid
         assert!(update.url.is_none());
@@ -200,4 +274,81 @@ mod tests {
         };
         assert_eq!(update.parent_id, Some(parent_id));
     }
+
+    #[test]
+    fn test_sync_cursor_round_trip() {
+        let cursor = SyncCursor(42);
+        let encoded = cursor.encode();
+        assert_eq!(SyncCursor::decode(&encoded).unwrap(), cursor);
+    }
+
+    #[test]
+    fn test_sync_cursor_rejects_garbage() {
+        assert!(SyncCursor::decode("not-a-cursor").is_err());
+        assert!(SyncCursor::decode("v1.zzzz").is_err());
+        assert!(SyncCursor::decode("42").is_err());
+    }
+
+    #[test]
+    fn test_page_cursor_round_trip() {
+        let cursor = PageCursor {
+            sort: "title".to_string(),
+            order: SortOrder::Asc,
+            key: "Rust, \"async\" & more".to_string(),
+            id: Uuid::new_v4(),
+        };
+        let encoded = cursor.encode();
+        assert!(encoded.starts_with("p1."));
+        assert_eq!(PageCursor::decode(&encoded).unwrap(), cursor);
+    }
+
+    #[test]
+    fn test_page_cursor_rejects_garbage() {
+        assert!(PageCursor::decode("p1.not-base64!").is_err());
+        assert!(PageCursor::decode("p1.e30").is_err());
+        assert!(PageCursor::decode(&SyncCursor(42).encode()).is_err());
+    }
+
+    #[test]
+    fn test_scope_names() {
+        assert_eq!(
+            serde_json::to_value(Scope::BookmarksRead).unwrap(),
+            "bookmarks:read"
+        );
+        assert_eq!(
+            serde_json::from_value::<Scope>(serde_json::json!("notes:write")).unwrap(),
+            Scope::NotesWrite
+        );
+        assert!(serde_json::from_value::<Scope>(serde_json::json!("bookmarks:admin")).is_err());
+    }
+
+    #[test]
+    fn test_write_scope_grants_read() {
+        assert!(Scope::BookmarksWrite.grants(Scope::BookmarksWrite));
+        assert!(Scope::BookmarksWrite.grants(Scope::BookmarksRead));
+        assert!(!Scope::BookmarksRead.grants(Scope::BookmarksWrite));
+        assert!(!Scope::BookmarksWrite.grants(Scope::NotesRead));
+        assert!(!Scope::SyncRead.grants(Scope::StorageRead));
+    }
+
+    #[test]
+    fn test_create_personal_access_token_validation() {
+        let token = |scopes: Vec<Scope>, expires_in_days: Option<i64>| CreatePersonalAccessToken {
+            name: "backup script".to_string(),
+            scopes,
+            expires_in_days,
+        };
+        assert!(token(vec![Scope::BookmarksRead], None).validate().is_ok());
+        assert!(
+            token(vec![Scope::BookmarksRead], Some(90))
+                .validate()
+                .is_ok()
+        );
+        assert!(token(vec![], None).validate().is_err());
+        assert!(
+            token(vec![Scope::BookmarksRead], Some(0))
+                .validate()
+                .is_err()
+        );
+    }
 }
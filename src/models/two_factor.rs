@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TwoFactorStatus {
+    pub enabled: bool,
+    /// Unused recovery codes left
+    pub recovery_codes_remaining: i64,
+}
+
+/// A started enrollment; it takes effect once confirmed with a code.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TwoFactorEnrollment {
+    /// Base32 secret, for typing into an authenticator app
+    pub secret: String,
+    /// `otpauth://` URI, for showing as a QR code
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RecoveryCodes {
+    /// Single-use codes for signing in without the authenticator; shown only once
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct TwoFactorCode {
+    /// Current code from the authenticator app
+    #[validate(length(min = 1, max = 32, message = "Code is required"))]
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct TwoFactorLogin {
+    /// Challenge token returned by `/api/auth/login`
+    #[validate(length(min = 1, max = 512, message = "Challenge token is required"))]
+    pub challenge_token: String,
+    /// Current code from the authenticator app, or an unused recovery code
+    #[validate(length(min = 1, max = 32, message = "Code is required"))]
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct DisableTwoFactor {
+    pub password: String,
+    /// Current code from the authenticator app, or an unused recovery code
+    #[validate(length(min = 1, max = 32, message = "Code is required"))]
+    pub code: String,
+}
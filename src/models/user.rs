@@ -5,6 +5,8 @@ use utoipa::ToSchema;
 use uuid::Uuid;
 use validator::Validate;
 
+use super::DeviceInfo;
+
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize, ToSchema)]
 pub struct User {
     pub id: Uuid,
@@ -24,6 +26,9 @@ pub struct CreateUser {
     pub password: String,
     #[validate(length(min = 1, message = "Name is required"))]
     pub name: String,
+    /// Device the account is created from
+    #[validate(nested)]
+    pub device: Option<DeviceInfo>,
 }
 
 #[derive(Debug, Deserialize, Validate, ToSchema)]
@@ -31,6 +36,16 @@ pub struct LoginUser {
     #[validate(email(message = "Invalid email format"))]
     pub email: String,
     pub password: String,
+    /// Device being signed in
+    #[validate(nested)]
+    pub device: Option<DeviceInfo>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct RefreshRequest {
+    /// Refresh token from the last sign-in or refresh; it can be used only once
+    #[validate(length(min = 1, max = 512, message = "Refresh token is required"))]
+    pub refresh_token: String,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
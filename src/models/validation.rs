@@ -0,0 +1,11 @@
+use uuid::Uuid;
+use validator::ValidationError;
+
+/// Accepts only randomly generated (v4) or time-ordered (v7) UUIDs as client-provided ids.
+pub fn validate_client_id(id: &Uuid) -> Result<(), ValidationError> {
+    match id.get_version_num() {
+        4 | 7 => Ok(()),
+        _ => Err(ValidationError::new("client_id")
+            .with_message("Id must be a version 4 or version 7 UUID".into())),
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use scraper::{Html, Selector};
+use url::Url;
+
+use crate::models::LinkPreview;
+
+const MAX_TITLE_CHARS: usize = 500;
+const MAX_DESCRIPTION_CHARS: usize = 2000;
+
+static TITLE: LazyLock<Selector> = LazyLock::new(|| selector("title"));
+static META: LazyLock<Selector> = LazyLock::new(|| selector("meta[content]"));
+static BASE: LazyLock<Selector> = LazyLock::new(|| selector("base[href]"));
+static LINK: LazyLock<Selector> = LazyLock::new(|| selector("link[rel][href]"));
+
+fn selector(css: &str) -> Selector {
+    Selector::parse(css).expect("valid selector")
+}
+
+/// Reads preview metadata from an HTML document fetched from `url`.
+///
+/// Open Graph and Twitter card tags take precedence over `<title>` and the
+/// plain `description` meta tag. Image and icon URLs are resolved against
+/// `<base href>` or `url`; pages that declare no icon get `/favicon.ico`.
+pub fn extract(html: &str, url: &Url) -> LinkPreview {
+    let document = Html::parse_document(html);
+
+    let base = document
+        .select(&BASE)
+        .next()
+        .and_then(|base| url.join(base.attr("href")?).ok())
+        .unwrap_or_else(|| url.clone());
+
+    // First value for each property or name, keyed in lowercase
+    let mut meta: HashMap<String, String> = HashMap::new();
+    for element in document.select(&META) {
+        let element = element.value();
+        let (Some(key), Some(content)) = (
+            element.attr("property").or_else(|| element.attr("name")),
+            element.attr("content"),
+        ) else {
+            continue;
+        };
+        meta.entry(key.trim().to_ascii_lowercase())
+            .or_insert_with(|| content.to_string());
+    }
+    let first_meta = |keys: &[&str]| keys.iter().find_map(|key| meta.get(*key));
+
+    let title = first_meta(&["og:title", "twitter:title"])
+        .and_then(|title| clean(title, MAX_TITLE_CHARS))
+        .or_else(|| {
+            let title: String = document.select(&TITLE).next()?.text().collect();
+            clean(&title, MAX_TITLE_CHARS)
+        });
+
+    let description = first_meta(&["og:description", "twitter:description", "description"])
+        .and_then(|description| clean(description, MAX_DESCRIPTION_CHARS));
+
+    let image = first_meta(&[
+        "og:image",
+        "og:image:url",
+        "og:image:secure_url",
+        "twitter:image",
+        "twitter:image:src",
+    ])
+    .and_then(|image| resolve(&base, image));
+
+    let favicon = icon_href(&document)
+        .and_then(|href| resolve(&base, href))
+        .or_else(|| resolve(url, "/favicon.ico"));
+
+    LinkPreview {
+        url: url.to_string(),
+        title,
+        description,
+        image,
+        favicon,
+    }
+}
+
+/// `href` of the page's icon, preferring `icon` links over `apple-touch-icon`.
+fn icon_href(document: &Html) -> Option<&str> {
+    let links: Vec<_> = document
+        .select(&LINK)
+        .filter_map(|link| {
+            let rel = link.attr("rel")?.to_ascii_lowercase();
+            Some((rel, link.attr("href")?))
+        })
+        .collect();
+
+    let has_rel = |rel: &str, wanted: &str| rel.split_ascii_whitespace().any(|r| r == wanted);
+
+    links
+        .iter()
+        .find(|(rel, _)| has_rel(rel, "icon"))
+        .or_else(|| {
+            links
+                .iter()
+                .find(|(rel, _)| has_rel(rel, "apple-touch-icon"))
+        })
+        .map(|(_, href)| *href)
+}
+
+/// Absolute http(s) URL for `reference`, or `None` for other schemes such as `data:`.
+fn resolve(base: &Url, reference: &str) -> Option<String> {
+    let resolved = base.join(reference.trim()).ok()?;
+    matches!(resolved.scheme(), "http" | "https").then(|| resolved.to_string())
+}
+
+/// Collapses whitespace and truncates to `max_chars`; `None` if nothing is left.
+fn clean(text: &str, max_chars: usize) -> Option<String> {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let text: String = text.chars().take(max_chars).collect();
+    (!text.is_empty()).then_some(text)
+}
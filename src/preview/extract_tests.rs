@@ -0,0 +1,99 @@
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    use crate::preview::extract;
+
+    fn page_url() -> Url {
+        Url::parse("https://example.com/articles/rust?ref=feed").unwrap()
+    }
+
+    #[test]
+    fn test_prefers_open_graph_tags() {
+        let html = r#"<html><head>
+            <title>Fallback Title | Example</title>
+            <meta name="description" content="Plain description">
+            <meta property="og:title" content="  Learning   Rust ">
+            <meta property="og:description" content="OG description">
+            <meta property="og:image" content="/images/cover.png">
+            <link rel="icon" href="icons/favicon.svg">
+        </head><body></body></html>"#;
+
+        let preview = extract(html, &page_url());
+
+        assert_eq!(preview.url, "https://example.com/articles/rust?ref=feed");
+        assert_eq!(preview.title.as_deref(), Some("Learning Rust"));
+        assert_eq!(preview.description.as_deref(), Some("OG description"));
+        assert_eq!(
+            preview.image.as_deref(),
+            Some("https://example.com/images/cover.png")
+        );
+        assert_eq!(
+            preview.favicon.as_deref(),
+            Some("https://example.com/articles/icons/favicon.svg")
+        );
+    }
+
+    #[test]
+    fn test_falls_back_to_title_and_twitter_tags() {
+        let html = r#"<head>
+            <title>
+                Page Title
+            </title>
+            <meta name="twitter:description" content="Twitter description">
+            <meta name="description" content="Plain description">
+            <meta name="twitter:image" content="https://cdn.example.net/card.jpg">
+        </head>"#;
+
+        let preview = extract(html, &page_url());
+
+        assert_eq!(preview.title.as_deref(), Some("Page Title"));
+        assert_eq!(preview.description.as_deref(), Some("Twitter description"));
+        assert_eq!(
+            preview.image.as_deref(),
+            Some("https://cdn.example.net/card.jpg")
+        );
+    }
+
+    #[test]
+    fn test_defaults_favicon_and_skips_empty_values() {
+        let html = r#"<head><title>   </title><meta name="description" content=""></head>"#;
+
+        let preview = extract(html, &page_url());
+
+        assert_eq!(preview.title, None);
+        assert_eq!(preview.description, None);
+        assert_eq!(preview.image, None);
+        assert_eq!(
+            preview.favicon.as_deref(),
+            Some("https://example.com/favicon.ico")
+        );
+    }
+
+    #[test]
+    fn test_resolves_against_base_and_ignores_non_http_urls() {
+        let html = r#"<head>
+            <base href="https://static.example.com/assets/">
+            <meta property="og:image" content="data:image/png;base64,AAAA">
+            <link rel="apple-touch-icon" href="touch.png">
+            <link rel="shortcut icon" href="favicon.ico">
+        </head>"#;
+
+        let preview = extract(html, &page_url());
+
+        assert_eq!(preview.image, None);
+        assert_eq!(
+            preview.favicon.as_deref(),
+            Some("https://static.example.com/assets/favicon.ico")
+        );
+    }
+
+    #[test]
+    fn test_truncates_long_titles() {
+        let html = format!("<title>{}</title>", "a".repeat(600));
+
+        let preview = extract(&html, &page_url());
+
+        assert_eq!(preview.title.map(|t| t.chars().count()), Some(500));
+    }
+}
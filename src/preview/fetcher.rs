@@ -0,0 +1,270 @@
+use std::borrow::Cow;
+use std::error::Error as _;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::{Client, Response, StatusCode, header, redirect};
+use url::{Host, Url};
+
+use super::extract::extract;
+use super::guard::{BlockedHost, GuardedResolver, is_blocked_ip};
+use crate::error::AppError;
+use crate::models::LinkPreview;
+
+const MAX_REDIRECTS: usize = 5;
+const USER_AGENT: &str = concat!("xync-server/", env!("CARGO_PKG_VERSION"), " (link preview)");
+const HTML: &str = "text/html";
+const XHTML: &str = "application/xhtml+xml";
+const ACCEPT: &str = "text/html,application/xhtml+xml;q=0.9,*/*;q=0.1";
+
+#[derive(Debug, thiserror::Error)]
+pub enum PreviewError {
+    #[error("invalid URL: {0}")]
+    InvalidUrl(String),
+
+    #[error("only http and https URLs can be previewed")]
+    UnsupportedScheme,
+
+    #[error("{0} is not a public address")]
+    BlockedAddress(String),
+
+    #[error("too many redirects")]
+    TooManyRedirects,
+
+    #[error("server responded with {0}")]
+    Status(StatusCode),
+
+    #[error("unsupported content type '{0}'")]
+    UnsupportedContent(String),
+
+    #[error("timed out")]
+    Timeout,
+
+    #[error("request failed: {0}")]
+    Request(reqwest::Error),
+}
+
+impl From<PreviewError> for AppError {
+    fn from(e: PreviewError) -> Self {
+        match e {
+            PreviewError::InvalidUrl(_)
+            | PreviewError::UnsupportedScheme
+            | PreviewError::BlockedAddress(_) => {
+                AppError::Validation(format!("Cannot preview URL: {e}"))
+            }
+            _ => AppError::UnprocessableEntity(format!("Could not fetch preview: {e}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PreviewConfig {
+    /// Deadline for the whole fetch, redirects included
+    pub timeout: Duration,
+    /// Bytes of the response body read for a preview; anything after is ignored
+    pub max_bytes: usize,
+    /// Bytes of the response body kept by [`PreviewFetcher::fetch_page`]
+    pub archive_max_bytes: usize,
+    /// Allow fetching from loopback and private networks, e.g. for tests
+    pub allow_private_networks: bool,
+}
+
+/// HTTP client for link previews.
+///
+/// Only connects to public addresses: hosts are checked after DNS resolution and
+/// again on every redirect, and proxies from the environment are ignored.
+#[derive(Debug, Clone)]
+pub struct PreviewFetcher {
+    client: Client,
+    config: PreviewConfig,
+}
+
+impl PreviewFetcher {
+    pub fn new(config: PreviewConfig) -> Self {
+        let client = Client::builder()
+            .user_agent(USER_AGENT)
+            .connect_timeout(config.timeout)
+            .redirect(redirect::Policy::none())
+            .no_proxy()
+            .dns_resolver(Arc::new(GuardedResolver {
+                allow_private: config.allow_private_networks,
+            }))
+            .build()
+            .expect("Failed to build preview HTTP client");
+
+        Self { client, config }
+    }
+
+    /// Fetches `url`, following redirects, and extracts its metadata.
+    pub async fn fetch(&self, url: &str) -> Result<LinkPreview, PreviewError> {
+        let page = self.fetch_limited(url, self.config.max_bytes).await?;
+        Ok(page.preview())
+    }
+
+    /// Fetches `url` for archiving, keeping up to `archive_max_bytes` of the body.
+    pub async fn fetch_page(&self, url: &str) -> Result<FetchedPage, PreviewError> {
+        self.fetch_limited(url, self.config.archive_max_bytes).await
+    }
+
+    async fn fetch_limited(
+        &self,
+        url: &str,
+        max_bytes: usize,
+    ) -> Result<FetchedPage, PreviewError> {
+        let url = Url::parse(url).map_err(|e| PreviewError::InvalidUrl(e.to_string()))?;
+
+        tokio::time::timeout(self.config.timeout, self.follow(url, max_bytes))
+            .await
+            .map_err(|_| PreviewError::Timeout)?
+    }
+
+    async fn follow(&self, mut url: Url, max_bytes: usize) -> Result<FetchedPage, PreviewError> {
+        for _ in 0..=MAX_REDIRECTS {
+            self.check(&url)?;
+
+            let response = self
+                .client
+                .get(url.clone())
+                .header(header::ACCEPT, ACCEPT)
+                .send()
+                .await
+                .map_err(request_error)?;
+
+            let status = response.status();
+            if status.is_redirection() {
+                let location = response
+                    .headers()
+                    .get(header::LOCATION)
+                    .and_then(|location| location.to_str().ok())
+                    .ok_or(PreviewError::Status(status))?;
+                url = url
+                    .join(location)
+                    .map_err(|e| PreviewError::InvalidUrl(e.to_string()))?;
+                continue;
+            }
+            if !status.is_success() {
+                return Err(PreviewError::Status(status));
+            }
+
+            return read(url, response, max_bytes).await;
+        }
+
+        Err(PreviewError::TooManyRedirects)
+    }
+
+    /// Rejects non-HTTP schemes and literal IPs the resolver would never see.
+    fn check(&self, url: &Url) -> Result<(), PreviewError> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(PreviewError::UnsupportedScheme);
+        }
+
+        let ip = match url.host() {
+            Some(Host::Domain(_)) => return Ok(()),
+            Some(Host::Ipv4(ip)) => IpAddr::V4(ip),
+            Some(Host::Ipv6(ip)) => IpAddr::V6(ip),
+            None => return Err(PreviewError::InvalidUrl("missing host".to_string())),
+        };
+
+        if !self.config.allow_private_networks && is_blocked_ip(ip) {
+            return Err(PreviewError::BlockedAddress(ip.to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads up to `max_bytes` of an HTML page or image.
+async fn read(
+    url: Url,
+    mut response: Response,
+    max_bytes: usize,
+) -> Result<FetchedPage, PreviewError> {
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    if !content_type.starts_with("image/") && !matches!(content_type.as_str(), "" | HTML | XHTML) {
+        return Err(PreviewError::UnsupportedContent(content_type));
+    }
+
+    let mut body = Vec::new();
+    let mut truncated = false;
+    while let Some(chunk) = response.chunk().await.map_err(request_error)? {
+        let remaining = max_bytes - body.len();
+        if chunk.len() > remaining {
+            body.extend_from_slice(&chunk[..remaining]);
+            truncated = true;
+            break;
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(FetchedPage {
+        url,
+        content_type: if content_type.is_empty() {
+            HTML.to_string()
+        } else {
+            content_type
+        },
+        body,
+        truncated,
+    })
+}
+
+/// Response to a successful fetch.
+#[derive(Debug, Clone)]
+pub struct FetchedPage {
+    /// URL the page was read from, after redirects
+    pub url: Url,
+    /// Media type without parameters, e.g. `text/html`
+    pub content_type: String,
+    pub body: Vec<u8>,
+    /// Whether the body was cut off at the size limit
+    pub truncated: bool,
+}
+
+impl FetchedPage {
+    pub fn is_html(&self) -> bool {
+        matches!(self.content_type.as_str(), HTML | XHTML)
+    }
+
+    /// Body decoded as UTF-8, with invalid sequences replaced.
+    pub fn text(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.body)
+    }
+
+    pub fn preview(&self) -> LinkPreview {
+        if self.is_html() {
+            return extract(&self.text(), &self.url);
+        }
+
+        // An image is its own preview
+        let mut preview = extract("", &self.url);
+        preview.image = Some(self.url.to_string());
+        preview
+    }
+}
+
+fn request_error(e: reqwest::Error) -> PreviewError {
+    if e.is_timeout() {
+        return PreviewError::Timeout;
+    }
+
+    let mut source = e.source();
+    while let Some(cause) = source {
+        if let Some(BlockedHost(host)) = cause.downcast_ref::<BlockedHost>() {
+            return PreviewError::BlockedAddress(host.clone());
+        }
+        source = cause.source();
+    }
+
+    PreviewError::Request(e)
+}
@@ -0,0 +1,72 @@
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use crate::preview::is_blocked_ip;
+
+    fn blocked(ip: &str) -> bool {
+        is_blocked_ip(ip.parse::<IpAddr>().unwrap())
+    }
+
+    #[test]
+    fn test_blocks_private_and_reserved_ipv4() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "172.31.255.255",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "224.0.0.1",
+            "198.18.0.1",
+            "192.0.2.10",
+            "240.0.0.1",
+        ] {
+            assert!(blocked(ip), "{ip} should be blocked");
+        }
+    }
+
+    #[test]
+    fn test_allows_public_ipv4() {
+        for ip in ["93.184.216.34", "8.8.8.8", "172.32.0.1", "100.128.0.1"] {
+            assert!(!blocked(ip), "{ip} should be allowed");
+        }
+    }
+
+    #[test]
+    fn test_blocks_private_and_embedded_ipv6() {
+        for ip in [
+            "::1",
+            "::",
+            "fc00::1",
+            "fd12:3456::1",
+            "fe80::1",
+            "ff02::1",
+            "2001:db8::1",
+            "::ffff:127.0.0.1",
+            "::ffff:10.0.0.1",
+            "64:ff9b::a9fe:a9fe",
+            "::127.0.0.1",
+            "::10.0.0.1",
+            "2002:7f00:1::",
+            "2002:a9fe:a9fe::1",
+        ] {
+            assert!(blocked(ip), "{ip} should be blocked");
+        }
+    }
+
+    #[test]
+    fn test_allows_public_ipv6() {
+        for ip in [
+            "2606:4700:4700::1111",
+            "::ffff:8.8.8.8",
+            "64:ff9b::808:808",
+            "2002:808:808::1",
+        ] {
+            assert!(!blocked(ip), "{ip} should be allowed");
+        }
+    }
+}
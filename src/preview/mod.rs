@@ -0,0 +1,19 @@
+//! Link previews.
+//!
+//! Pages are fetched with a client that refuses to connect to private,
+//! loopback and link-local addresses, reads at most a fixed number of bytes
+//! and gives up after a deadline. The title, description, image and icon are
+//! then read from the page's `<head>`.
+
+mod extract;
+mod fetcher;
+mod guard;
+
+#[cfg(test)]
+mod extract_tests;
+#[cfg(test)]
+mod guard_tests;
+
+pub use extract::extract;
+pub use fetcher::{FetchedPage, PreviewConfig, PreviewError, PreviewFetcher};
+pub use guard::{BlockedHost, GuardedResolver, is_blocked_ip};
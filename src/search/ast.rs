@@ -0,0 +1,69 @@
+use chrono::NaiveDate;
+
+/// A parsed search query; all terms must match.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SearchExpr {
+    pub terms: Vec<Term>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Term {
+    pub negated: bool,
+    pub kind: TermKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TermKind {
+    /// Bare word matched against the full-text index
+    Word(String),
+    /// `"quoted words"` matched in order
+    Phrase(String),
+    /// `tag:<name>`
+    Tag(String),
+    /// `category:<name>` or `category:<parent>/<child>`, including subcategories
+    Category(String),
+    /// `site:<domain>`, including subdomains
+    Site(String),
+    /// `is:<flag>`
+    Is(Flag),
+    /// `before:<date>`, created before the start of the day (UTC)
+    Before(NaiveDate),
+    /// `after:<date>`, created after the end of the day (UTC)
+    After(NaiveDate),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flag {
+    Bookmark,
+    Note,
+    Tagged,
+    Untagged,
+    Categorized,
+    Uncategorized,
+    Favorite,
+}
+
+impl Flag {
+    pub const NAMES: &'static [&'static str] = &[
+        "bookmark",
+        "note",
+        "tagged",
+        "untagged",
+        "categorized",
+        "uncategorized",
+        "favorite",
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "bookmark" => Some(Flag::Bookmark),
+            "note" => Some(Flag::Note),
+            "tagged" => Some(Flag::Tagged),
+            "untagged" => Some(Flag::Untagged),
+            "categorized" => Some(Flag::Categorized),
+            "uncategorized" => Some(Flag::Uncategorized),
+            "favorite" => Some(Flag::Favorite),
+            _ => None,
+        }
+    }
+}
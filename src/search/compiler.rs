@@ -0,0 +1,216 @@
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use sqlx::{Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use super::ast::{Flag, SearchExpr, Term, TermKind};
+
+/// Maximum category nesting followed when matching `category:` paths.
+const MAX_CATEGORY_DEPTH: i32 = 32;
+
+/// Table a compiled query is evaluated against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchTarget {
+    /// `bookmarks b`
+    Bookmark,
+    /// `notes n`
+    Note,
+}
+
+impl SearchTarget {
+    fn alias(self) -> &'static str {
+        match self {
+            SearchTarget::Bookmark => "b",
+            SearchTarget::Note => "n",
+        }
+    }
+}
+
+impl SearchExpr {
+    /// Free-text terms in `websearch_to_tsquery` syntax, or `None` if there are none.
+    pub fn text_query(&self) -> Option<String> {
+        let parts: Vec<String> = self
+            .text_terms()
+            .map(|(negated, text)| {
+                let sign = if negated { "-" } else { "" };
+                format!("{sign}{text}")
+            })
+            .collect();
+
+        (!parts.is_empty()).then(|| parts.join(" "))
+    }
+
+    /// Negated free-text terms as a `websearch_to_tsquery` alternation, or `None`.
+    ///
+    /// Fuzzy matches and matches in archived pages bypass the full-text query,
+    /// so exclusions are applied separately.
+    pub fn excluded_text_query(&self) -> Option<String> {
+        let parts: Vec<String> = self
+            .text_terms()
+            .filter(|(negated, _)| *negated)
+            .map(|(_, text)| text)
+            .collect();
+
+        (!parts.is_empty()).then(|| parts.join(" OR "))
+    }
+
+    /// Plain text of the positive free-text terms, for trigram similarity, or `None`.
+    pub fn fuzzy_text(&self) -> Option<String> {
+        let parts: Vec<String> = self
+            .text_terms()
+            .filter(|(negated, text)| !negated && !text.eq_ignore_ascii_case("or"))
+            .map(|(_, text)| text.trim_matches('"').to_string())
+            .collect();
+
+        (!parts.is_empty()).then(|| parts.join(" "))
+    }
+
+    /// Words and phrases with their negation, phrases quoted.
+    fn text_terms(&self) -> impl Iterator<Item = (bool, String)> + '_ {
+        self.terms.iter().filter_map(|term| {
+            let (text, quoted) = match &term.kind {
+                TermKind::Word(word) => (word, false),
+                TermKind::Phrase(phrase) => (phrase, true),
+                _ => return None,
+            };
+
+            // Quotes are the only websearch syntax that could escape a term
+            let text = text.replace('"', " ");
+            let text = text.trim();
+            if text.is_empty() {
+                return None;
+            }
+
+            let text = if quoted {
+                format!("\"{text}\"")
+            } else {
+                text.to_string()
+            };
+            Some((term.negated, text))
+        })
+    }
+
+    /// Appends an `AND` condition for every qualifier, as it applies to `target`.
+    ///
+    /// Qualifiers that only make sense for bookmarks (tags, categories, sites)
+    /// never match notes, so negating them always does.
+    pub fn push_filters(
+        &self,
+        builder: &mut QueryBuilder<'_, Postgres>,
+        target: SearchTarget,
+        user_id: Uuid,
+    ) {
+        for term in &self.terms {
+            if matches!(term.kind, TermKind::Word(_) | TermKind::Phrase(_)) {
+                continue;
+            }
+
+            builder.push(if term.negated { " AND NOT (" } else { " AND (" });
+            push_condition(builder, term, target, user_id);
+            builder.push(")");
+        }
+    }
+}
+
+fn push_condition(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    term: &Term,
+    target: SearchTarget,
+    user_id: Uuid,
+) {
+    let alias = target.alias();
+    let is_bookmark = target == SearchTarget::Bookmark;
+
+    match &term.kind {
+        TermKind::Word(_) | TermKind::Phrase(_) => {
+            builder.push("TRUE");
+        }
+        TermKind::Before(date) => {
+            builder
+                .push(format!("{alias}.created_at < "))
+                .push_bind(start_of_day(*date));
+        }
+        TermKind::After(date) => {
+            let next_day = date.checked_add_days(Days::new(1)).unwrap_or(*date);
+            builder
+                .push(format!("{alias}.created_at >= "))
+                .push_bind(start_of_day(next_day));
+        }
+        TermKind::Is(Flag::Bookmark) => {
+            builder.push(if is_bookmark { "TRUE" } else { "FALSE" });
+        }
+        TermKind::Is(Flag::Note) => {
+            builder.push(if is_bookmark { "FALSE" } else { "TRUE" });
+        }
+        TermKind::Is(flag) if !is_bookmark => {
+            // Notes are never tagged, categorized or favorites
+            let matches = matches!(flag, Flag::Untagged | Flag::Uncategorized);
+            builder.push(if matches { "TRUE" } else { "FALSE" });
+        }
+        TermKind::Is(Flag::Tagged | Flag::Untagged) => {
+            if term.kind == TermKind::Is(Flag::Untagged) {
+                builder.push("NOT ");
+            }
+            builder.push("EXISTS (SELECT 1 FROM bookmark_tags bt WHERE bt.bookmark_id = b.id)");
+        }
+        TermKind::Is(Flag::Categorized) => {
+            builder.push("b.category_id IS NOT NULL");
+        }
+        TermKind::Is(Flag::Uncategorized) => {
+            builder.push("b.category_id IS NULL");
+        }
+        TermKind::Is(Flag::Favorite) => {
+            builder.push("b.is_favorite");
+        }
+        TermKind::Tag(_) | TermKind::Category(_) | TermKind::Site(_) if !is_bookmark => {
+            builder.push("FALSE");
+        }
+        TermKind::Tag(name) => {
+            builder
+                .push(
+                    "EXISTS (SELECT 1 FROM bookmark_tags bt \
+                     JOIN tags t ON t.id = bt.tag_id AND t.user_id = b.user_id \
+                     WHERE bt.bookmark_id = b.id AND lower(t.name) = lower(",
+                )
+                .push_bind(name.clone())
+                .push("))");
+        }
+        TermKind::Category(path) => {
+            // Matches the named category and everything nested below it, with
+            // the path read from a top-level category
+            builder
+                .push(
+                    "EXISTS (WITH RECURSIVE paths (id, path, depth) AS ( \
+                     SELECT id, lower(name)::TEXT, 1 FROM categories \
+                     WHERE parent_id IS NULL AND user_id = ",
+                )
+                .push_bind(user_id)
+                .push(
+                    " UNION ALL \
+                     SELECT c.id, p.path || '/' || lower(c.name), p.depth + 1 \
+                     FROM categories c JOIN paths p ON c.parent_id = p.id \
+                     WHERE p.depth < ",
+                )
+                .push_bind(MAX_CATEGORY_DEPTH)
+                .push(
+                    ") SELECT 1 FROM paths WHERE paths.id = b.category_id \
+                     AND starts_with(paths.path || '/', ",
+                )
+                .push_bind(path.to_lowercase())
+                .push(" || '/'))");
+        }
+        TermKind::Site(domain) => {
+            builder
+                .push("COALESCE(url_host(b.url) = ")
+                .push_bind(domain.clone())
+                .push(" OR right(url_host(b.url), ")
+                .push_bind(domain.chars().count() as i32 + 1)
+                .push(") = ")
+                .push_bind(format!(".{domain}"))
+                .push(", FALSE)");
+        }
+    }
+}
+
+fn start_of_day(date: NaiveDate) -> DateTime<Utc> {
+    date.and_time(chrono::NaiveTime::MIN).and_utc()
+}
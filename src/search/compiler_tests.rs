@@ -0,0 +1,24 @@
+#[cfg(test)]
+mod tests {
+    use sqlx::{Postgres, QueryBuilder};
+    use uuid::Uuid;
+
+    use crate::search::{SearchTarget, parse};
+
+    fn filters(query: &str) -> String {
+        let mut builder = QueryBuilder::<Postgres>::new("SELECT b.id FROM bookmarks b WHERE TRUE");
+        parse(query)
+            .unwrap()
+            .push_filters(&mut builder, SearchTarget::Bookmark, Uuid::new_v4());
+        builder.sql().to_string()
+    }
+
+    #[test]
+    fn test_category_paths_match_from_the_top_level() {
+        let sql = filters("category:Work");
+
+        assert!(sql.contains("WHERE parent_id IS NULL"));
+        assert!(sql.contains("starts_with(paths.path || '/', $3 || '/')"));
+        assert!(!sql.contains("strpos"));
+    }
+}
@@ -0,0 +1,19 @@
+//! Query language for `/api/search`.
+//!
+//! Free text is combined with qualifiers such as `tag:rust`, `category:"Work/Infra"`,
+//! `site:github.com`, `is:untagged` and `before:2026-01-01`. Any term can be
+//! negated with a leading `-`. Queries are parsed into a [`SearchExpr`] and then
+//! compiled into parameterised SQL.
+
+mod ast;
+mod compiler;
+mod parser;
+
+#[cfg(test)]
+mod compiler_tests;
+#[cfg(test)]
+mod parser_tests;
+
+pub use ast::{Flag, SearchExpr, Term, TermKind};
+pub use compiler::SearchTarget;
+pub use parser::{ParseError, parse};
@@ -0,0 +1,204 @@
+use chrono::NaiveDate;
+
+use super::ast::{Flag, SearchExpr, Term, TermKind};
+use crate::error::AppError;
+
+const MAX_QUERY_LENGTH: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ParseError {
+    #[error("search query is required")]
+    Empty,
+
+    #[error("search query must be at most {MAX_QUERY_LENGTH} characters")]
+    TooLong,
+
+    #[error("unterminated quote starting at character {0}")]
+    UnterminatedQuote(usize),
+
+    #[error("empty quotes at character {0}")]
+    EmptyQuote(usize),
+
+    #[error("missing value after '{0}:'")]
+    MissingValue(String),
+
+    #[error("unknown value '{0}' for 'is:', expected one of: {names}", names = Flag::NAMES.join(", "))]
+    UnknownFlag(String),
+
+    #[error("invalid date '{value}' for '{qualifier}:', expected YYYY-MM-DD")]
+    InvalidDate { qualifier: String, value: String },
+
+    #[error("invalid domain '{0}' for 'site:'")]
+    InvalidSite(String),
+}
+
+impl From<ParseError> for AppError {
+    fn from(e: ParseError) -> Self {
+        AppError::Validation(format!("Invalid search query: {e}"))
+    }
+}
+
+/// Parses a search query into its terms.
+///
+/// Words of the form `key:value` are qualifiers only when `key` is a known
+/// qualifier name; anything else, such as a pasted URL, is treated as text.
+pub fn parse(input: &str) -> Result<SearchExpr, ParseError> {
+    if input.chars().count() > MAX_QUERY_LENGTH {
+        return Err(ParseError::TooLong);
+    }
+
+    let mut lexer = Lexer { input, pos: 0 };
+    let mut terms = Vec::new();
+
+    loop {
+        lexer.skip_whitespace();
+        let Some(c) = lexer.peek() else {
+            break;
+        };
+
+        let negated = c == '-' && lexer.peek_second().is_some_and(|c| !c.is_whitespace());
+        if negated {
+            lexer.bump();
+        }
+
+        let kind = if lexer.peek() == Some('"') {
+            TermKind::Phrase(lexer.quoted()?.to_string())
+        } else {
+            lexer.word_or_qualifier()?
+        };
+
+        terms.push(Term { negated, kind });
+    }
+
+    if terms.is_empty() {
+        return Err(ParseError::Empty);
+    }
+
+    Ok(SearchExpr { terms })
+}
+
+struct Lexer<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn peek_second(&self) -> Option<char> {
+        self.input[self.pos..].chars().nth(1)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.take_while(char::is_whitespace);
+    }
+
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> &'a str {
+        let start = self.pos;
+        while self.peek().is_some_and(&predicate) {
+            self.bump();
+        }
+        &self.input[start..self.pos]
+    }
+
+    /// 1-based character position of a byte offset, for error messages.
+    fn position(&self, offset: usize) -> usize {
+        self.input[..offset].chars().count() + 1
+    }
+
+    /// Reads a `"quoted"` string, returning its contents.
+    fn quoted(&mut self) -> Result<&'a str, ParseError> {
+        let start = self.pos;
+        self.bump();
+        let contents = self.take_while(|c| c != '"');
+
+        if self.bump() != Some('"') {
+            return Err(ParseError::UnterminatedQuote(self.position(start)));
+        }
+        if contents.trim().is_empty() {
+            return Err(ParseError::EmptyQuote(self.position(start)));
+        }
+
+        Ok(contents.trim())
+    }
+
+    fn word_or_qualifier(&mut self) -> Result<TermKind, ParseError> {
+        let start = self.pos;
+        let key = self.take_while(|c| c != ':' && c != '"' && !c.is_whitespace());
+
+        if self.peek() == Some(':') && is_qualifier(key) {
+            self.bump();
+            let value = if self.peek() == Some('"') {
+                self.quoted()?
+            } else {
+                self.take_while(|c| !c.is_whitespace())
+            };
+            return qualifier(&key.to_lowercase(), value);
+        }
+
+        self.take_while(|c| !c.is_whitespace());
+        Ok(TermKind::Word(self.input[start..self.pos].to_string()))
+    }
+}
+
+const QUALIFIERS: &[&str] = &["tag", "category", "site", "is", "before", "after"];
+
+fn is_qualifier(key: &str) -> bool {
+    QUALIFIERS.iter().any(|q| q.eq_ignore_ascii_case(key))
+}
+
+fn qualifier(key: &str, value: &str) -> Result<TermKind, ParseError> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(ParseError::MissingValue(key.to_string()));
+    }
+
+    match key {
+        "tag" => Ok(TermKind::Tag(value.to_string())),
+        "category" => {
+            let path = value.trim_matches('/');
+            if path.is_empty() {
+                return Err(ParseError::MissingValue(key.to_string()));
+            }
+            Ok(TermKind::Category(path.to_string()))
+        }
+        "site" => site(value).map(TermKind::Site),
+        "is" => Flag::from_name(&value.to_lowercase())
+            .map(TermKind::Is)
+            .ok_or_else(|| ParseError::UnknownFlag(value.to_string())),
+        "before" => date(key, value).map(TermKind::Before),
+        "after" => date(key, value).map(TermKind::After),
+        _ => unreachable!("checked by is_qualifier"),
+    }
+}
+
+fn site(value: &str) -> Result<String, ParseError> {
+    let domain = value.to_lowercase();
+    let domain = domain
+        .strip_prefix("https://")
+        .or_else(|| domain.strip_prefix("http://"))
+        .unwrap_or(&domain)
+        .trim_end_matches('/')
+        .trim_end_matches('.');
+
+    if domain.is_empty() || domain.contains(['/', ':', '@', '"']) {
+        return Err(ParseError::InvalidSite(value.to_string()));
+    }
+
+    Ok(domain.to_string())
+}
+
+fn date(key: &str, value: &str) -> Result<NaiveDate, ParseError> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| ParseError::InvalidDate {
+        qualifier: key.to_string(),
+        value: value.to_string(),
+    })
+}
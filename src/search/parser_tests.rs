@@ -0,0 +1,142 @@
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::error::AppError;
+    use crate::search::{Flag, ParseError, Term, TermKind, parse};
+
+    fn term(kind: TermKind) -> Term {
+        Term {
+            negated: false,
+            kind,
+        }
+    }
+
+    fn negated(kind: TermKind) -> Term {
+        Term {
+            negated: true,
+            kind,
+        }
+    }
+
+    #[test]
+    fn test_parse_qualifiers_and_text() {
+        let expr = parse(
+            r#"tag:rust category:"Work/Infra" is:untagged site:https://GitHub.com/ before:2026-01-01 -tag:archived "exact phrase" async"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            expr.terms,
+            vec![
+                term(TermKind::Tag("rust".to_string())),
+                term(TermKind::Category("Work/Infra".to_string())),
+                term(TermKind::Is(Flag::Untagged)),
+                term(TermKind::Site("github.com".to_string())),
+                term(TermKind::Before(
+                    NaiveDate::from_ymd_opt(2026, 1, 1).unwrap()
+                )),
+                negated(TermKind::Tag("archived".to_string())),
+                term(TermKind::Phrase("exact phrase".to_string())),
+                term(TermKind::Word("async".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_qualifier_keys_are_case_insensitive() {
+        let expr = parse("TAG:Rust Is:Note is:FAVORITE").unwrap();
+
+        assert_eq!(
+            expr.terms,
+            vec![
+                term(TermKind::Tag("Rust".to_string())),
+                term(TermKind::Is(Flag::Note)),
+                term(TermKind::Is(Flag::Favorite)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_unknown_qualifier_is_text() {
+        let expr = parse("https://example.com/path todo:later").unwrap();
+
+        assert_eq!(
+            expr.terms,
+            vec![
+                term(TermKind::Word("https://example.com/path".to_string())),
+                term(TermKind::Word("todo:later".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_lone_dash_is_text() {
+        let expr = parse("a - b").unwrap();
+
+        assert_eq!(expr.terms.len(), 3);
+        assert_eq!(expr.terms[1], term(TermKind::Word("-".to_string())));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse("   "), Err(ParseError::Empty));
+        assert_eq!(
+            parse(r#"tag:rust "open"#),
+            Err(ParseError::UnterminatedQuote(10))
+        );
+        assert_eq!(parse(r#""  ""#), Err(ParseError::EmptyQuote(1)));
+        assert_eq!(
+            parse("tag: rust"),
+            Err(ParseError::MissingValue("tag".to_string()))
+        );
+        assert_eq!(
+            parse("is:starred"),
+            Err(ParseError::UnknownFlag("starred".to_string()))
+        );
+        assert_eq!(
+            parse("before:2026-13-01"),
+            Err(ParseError::InvalidDate {
+                qualifier: "before".to_string(),
+                value: "2026-13-01".to_string(),
+            })
+        );
+        assert_eq!(
+            parse("site:example.com/path"),
+            Err(ParseError::InvalidSite("example.com/path".to_string()))
+        );
+        assert_eq!(parse(&"a".repeat(1001)), Err(ParseError::TooLong));
+    }
+
+    #[test]
+    fn test_parse_error_is_validation_error() {
+        let error: AppError = parse("after:yesterday").unwrap_err().into();
+
+        assert!(matches!(error, AppError::Validation(message) if message.contains("after:")));
+    }
+
+    #[test]
+    fn test_text_query() {
+        let expr = parse(r#"rust -java "exact phrase" -"bad phrase" tag:web"#).unwrap();
+        assert_eq!(
+            expr.text_query().as_deref(),
+            Some(r#"rust -java "exact phrase" -"bad phrase""#)
+        );
+
+        let expr = parse("tag:web is:bookmark").unwrap();
+        assert_eq!(expr.text_query(), None);
+    }
+
+    #[test]
+    fn test_fuzzy_and_excluded_text() {
+        let expr = parse(r#"progamming OR "rust book" -java -"bad phrase" tag:web"#).unwrap();
+        assert_eq!(expr.fuzzy_text().as_deref(), Some("progamming rust book"));
+        assert_eq!(
+            expr.excluded_text_query().as_deref(),
+            Some(r#"java OR "bad phrase""#)
+        );
+
+        let expr = parse("rust").unwrap();
+        assert_eq!(expr.excluded_text_query(), None);
+    }
+}
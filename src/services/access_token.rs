@@ -0,0 +1,161 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::auth::{AuthUser, Credential, generate_token, hash_token};
+use crate::error::{AppError, Result};
+use crate::models::{
+    CreatePersonalAccessToken, CreatedPersonalAccessToken, PersonalAccessToken, Scope,
+};
+
+/// Marks personal access tokens apart from signed access tokens, and makes
+/// leaked ones easy to spot with secret scanners.
+const TOKEN_PREFIX: &str = "xync_pat_";
+
+#[derive(Debug, FromRow)]
+struct StoredToken {
+    id: Uuid,
+    user_id: Uuid,
+    email: String,
+    scopes: Vec<Scope>,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+pub struct PersonalAccessTokenService;
+
+impl PersonalAccessTokenService {
+    /// Creates a token; its secret is returned only here.
+    pub async fn create(
+        pool: &PgPool,
+        user_id: Uuid,
+        input: CreatePersonalAccessToken,
+    ) -> Result<CreatedPersonalAccessToken> {
+        let secret = format!("{TOKEN_PREFIX}{}", generate_token());
+        let expires_at = input
+            .expires_in_days
+            .map(|days| Utc::now() + Duration::days(days));
+
+        let mut scopes = input.scopes;
+        scopes.sort();
+        scopes.dedup();
+
+        let token = sqlx::query_as::<_, PersonalAccessToken>(
+            r#"
+            INSERT INTO personal_access_tokens (id, user_id, name, token_hash, scopes, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id, user_id, name, scopes, expires_at, last_used_at, created_at
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .bind(&input.name)
+        .bind(hash_token(&secret))
+        .bind(&scopes)
+        .bind(expires_at)
+        .fetch_one(pool)
+        .await?;
+
+        tracing::info!(%user_id, token_id = %token.id, "Personal access token created");
+        Ok(CreatedPersonalAccessToken { token, secret })
+    }
+
+    pub async fn list(pool: &PgPool, user_id: Uuid) -> Result<Vec<PersonalAccessToken>> {
+        let tokens = sqlx::query_as::<_, PersonalAccessToken>(
+            r#"
+            SELECT id, user_id, name, scopes, expires_at, last_used_at, created_at
+            FROM personal_access_tokens
+            WHERE user_id = $1
+            ORDER BY created_at DESC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(tokens)
+    }
+
+    /// Deletes a token; requests presenting it are rejected from then on.
+    pub async fn revoke(pool: &PgPool, user_id: Uuid, id: Uuid) -> Result<()> {
+        let result =
+            sqlx::query("DELETE FROM personal_access_tokens WHERE id = $1 AND user_id = $2")
+                .bind(id)
+                .bind(user_id)
+                .execute(pool)
+                .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound(
+                "Personal access token not found".to_string(),
+            ));
+        }
+
+        tracing::info!(%user_id, token_id = %id, "Personal access token revoked");
+        Ok(())
+    }
+
+    /// Whether a token still exists and has not expired.
+    pub async fn is_active(pool: &PgPool, id: Uuid) -> Result<bool> {
+        let active = sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM personal_access_tokens
+                WHERE id = $1 AND (expires_at IS NULL OR expires_at > NOW())
+            )
+            "#,
+        )
+        .bind(id)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(active)
+    }
+
+    pub fn is_personal_access_token(token: &str) -> bool {
+        token.starts_with(TOKEN_PREFIX)
+    }
+
+    /// Authenticates a request presenting a personal access token and notes its use.
+    pub async fn authenticate(pool: &PgPool, token: &str) -> Result<AuthUser> {
+        let stored = sqlx::query_as::<_, StoredToken>(
+            r#"
+            SELECT t.id, t.user_id, u.email, t.scopes, t.expires_at
+            FROM personal_access_tokens t
+            JOIN users u ON u.id = t.user_id
+            WHERE t.token_hash = $1
+            "#,
+        )
+        .bind(hash_token(token))
+        .fetch_optional(pool)
+        .await?
+        .ok_or(AppError::Unauthorized)?;
+
+        if stored
+            .expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now())
+        {
+            return Err(AppError::Unauthorized);
+        }
+
+        // Recorded at most once a minute so busy scripts don't write on every request
+        sqlx::query(
+            r#"
+            UPDATE personal_access_tokens SET last_used_at = NOW()
+            WHERE id = $1 AND (last_used_at IS NULL OR last_used_at < NOW() - INTERVAL '1 minute')
+            "#,
+        )
+        .bind(stored.id)
+        .execute(pool)
+        .await?;
+
+        Ok(AuthUser {
+            user_id: stored.user_id,
+            email: stored.email,
+            device_id: None,
+            credential: Credential::PersonalAccessToken {
+                id: stored.id,
+                scopes: stored.scopes,
+            },
+        })
+    }
+}
@@ -0,0 +1,223 @@
+use sqlx::{FromRow, PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::archive;
+use crate::blob::BlobStorage;
+use crate::error::{AppError, Result};
+use crate::models::{BookmarkArchive, RawArchive};
+use crate::preview::FetchedPage;
+use crate::services::BlobService;
+
+/// Archive row as stored; pages archived before blob storage keep their bytes inline.
+#[derive(Debug, FromRow)]
+struct StoredArchive {
+    user_id: Uuid,
+    content_type: String,
+    content_hash: String,
+    raw_content: Option<Vec<u8>>,
+}
+
+pub struct ArchiveService;
+
+impl ArchiveService {
+    /// Uploads an HTML page to blob storage ahead of [`Self::store_in_tx`], returning its hash.
+    ///
+    /// Returns `None` for pages other than HTML, which are not archived.
+    pub async fn upload(
+        pool: &PgPool,
+        blobs: &BlobStorage,
+        page: &FetchedPage,
+    ) -> Result<Option<String>> {
+        if !page.is_html() {
+            return Ok(None);
+        }
+
+        BlobService::put(pool, blobs, page.body.clone())
+            .await
+            .map(Some)
+    }
+
+    /// Replaces the bookmark's archive with `page`, already uploaded under `content_hash`.
+    ///
+    /// Fails with `QuotaExceeded` if the page would take the user over their storage quota.
+    pub async fn store_in_tx(
+        conn: &mut PgConnection,
+        blobs: &BlobStorage,
+        user_id: Uuid,
+        bookmark_id: Uuid,
+        page: &FetchedPage,
+        content_hash: &str,
+    ) -> Result<()> {
+        BlobService::check_quota(&mut *conn, user_id, content_hash, blobs.quota_bytes).await?;
+
+        let readable = archive::readable(&page.text());
+        let word_count = readable.text.split_whitespace().count() as i32;
+
+        let previous = Self::lock(&mut *conn, bookmark_id).await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO bookmark_archives
+                (bookmark_id, user_id, url, content_type, raw_content, content_hash, truncated,
+                 title, text, word_count)
+            VALUES ($1, $2, $3, $4, NULL, $5, $6, $7, $8, $9)
+            ON CONFLICT (bookmark_id) DO UPDATE
+            SET url = EXCLUDED.url,
+                content_type = EXCLUDED.content_type,
+                raw_content = NULL,
+                content_hash = EXCLUDED.content_hash,
+                truncated = EXCLUDED.truncated,
+                title = EXCLUDED.title,
+                text = EXCLUDED.text,
+                word_count = EXCLUDED.word_count,
+                archived_at = NOW()
+            "#,
+        )
+        .bind(bookmark_id)
+        .bind(user_id)
+        .bind(page.url.as_str())
+        .bind(&page.content_type)
+        .bind(content_hash)
+        .bind(page.truncated)
+        .bind(&readable.title)
+        .bind(&readable.text)
+        .bind(word_count)
+        .execute(&mut *conn)
+        .await?;
+
+        BlobService::add_reference(&mut *conn, user_id, content_hash).await?;
+        if let Some(previous) = previous {
+            Self::release(conn, &previous).await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn get(pool: &PgPool, user_id: Uuid, bookmark_id: Uuid) -> Result<BookmarkArchive> {
+        sqlx::query_as::<_, BookmarkArchive>(
+            r#"
+            SELECT a.bookmark_id, a.url, a.content_type, a.content_hash,
+                   COALESCE(octet_length(a.raw_content)::BIGINT, bl.size, 0) AS size,
+                   a.truncated, a.title, a.text, a.word_count, a.archived_at
+            FROM bookmark_archives a
+            LEFT JOIN blobs bl ON bl.hash = a.content_hash
+            WHERE a.bookmark_id = $1 AND a.user_id = $2
+            "#,
+        )
+        .bind(bookmark_id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(not_archived)
+    }
+
+    pub async fn get_raw(
+        pool: &PgPool,
+        blobs: &BlobStorage,
+        user_id: Uuid,
+        bookmark_id: Uuid,
+    ) -> Result<RawArchive> {
+        let archive = sqlx::query_as::<_, StoredArchive>(
+            "SELECT user_id, content_type, content_hash, raw_content FROM bookmark_archives \
+             WHERE bookmark_id = $1 AND user_id = $2",
+        )
+        .bind(bookmark_id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(not_archived)?;
+
+        let raw_content = match archive.raw_content {
+            Some(raw_content) => raw_content,
+            None => BlobService::get(blobs, &archive.content_hash).await?,
+        };
+
+        Ok(RawArchive {
+            content_type: archive.content_type,
+            raw_content,
+        })
+    }
+
+    /// Drops a bookmark's archive, e.g. because its URL changed.
+    pub async fn delete_in_tx(conn: &mut PgConnection, bookmark_id: Uuid) -> Result<()> {
+        let deleted = sqlx::query_as::<_, StoredArchive>(
+            "DELETE FROM bookmark_archives WHERE bookmark_id = $1 \
+             RETURNING user_id, content_type, content_hash, \
+                 CASE WHEN raw_content IS NOT NULL THEN ''::BYTEA END AS raw_content",
+        )
+        .bind(bookmark_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        if let Some(deleted) = deleted {
+            Self::release(conn, &deleted).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Moves up to `limit` pages archived inline into blob storage.
+    ///
+    /// Returns the number moved. These pages predate quotas, so they are not
+    /// checked against them.
+    pub async fn move_inline_to_blobs(
+        pool: &PgPool,
+        blobs: &BlobStorage,
+        limit: i64,
+    ) -> Result<usize> {
+        let archives = sqlx::query_as::<_, (Uuid, Vec<u8>)>(
+            "SELECT bookmark_id, raw_content FROM bookmark_archives \
+             WHERE raw_content IS NOT NULL LIMIT $1",
+        )
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+
+        let found = archives.len();
+        for (bookmark_id, raw_content) in archives {
+            let hash = BlobService::put(pool, blobs, raw_content).await?;
+            let mut tx = pool.begin().await?;
+
+            let user_id = sqlx::query_scalar::<_, Uuid>(
+                "UPDATE bookmark_archives SET raw_content = NULL, content_hash = $2 \
+                 WHERE bookmark_id = $1 AND raw_content IS NOT NULL RETURNING user_id",
+            )
+            .bind(bookmark_id)
+            .bind(&hash)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+            if let Some(user_id) = user_id {
+                BlobService::add_reference(&mut tx, user_id, &hash).await?;
+            }
+            tx.commit().await?;
+        }
+
+        Ok(found)
+    }
+
+    /// Locks the bookmark's current archive, if any, before it is replaced.
+    async fn lock(conn: &mut PgConnection, bookmark_id: Uuid) -> Result<Option<StoredArchive>> {
+        let archive = sqlx::query_as::<_, StoredArchive>(
+            "SELECT user_id, content_type, content_hash, NULL::BYTEA AS raw_content \
+             FROM bookmark_archives WHERE bookmark_id = $1 AND raw_content IS NULL FOR UPDATE",
+        )
+        .bind(bookmark_id)
+        .fetch_optional(conn)
+        .await?;
+
+        Ok(archive)
+    }
+
+    /// Drops the blob reference held by an archive; inline archives hold none.
+    async fn release(conn: &mut PgConnection, archive: &StoredArchive) -> Result<()> {
+        if archive.raw_content.is_some() {
+            return Ok(());
+        }
+        BlobService::remove_reference(conn, archive.user_id, &archive.content_hash).await
+    }
+}
+
+fn not_archived() -> AppError {
+    AppError::NotFound("Bookmark archive not found".to_string())
+}
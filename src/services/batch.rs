@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+
+use axum::http::StatusCode;
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::canonical_url::CanonicalUrlOptions;
+use crate::error::{AppError, Result};
+use crate::models::{
+    BatchEntity, BatchOp, BatchOperation, BatchResult, BookmarkCreateOutcome, CreateBookmark,
+    CreateCategory, CreateNote, CreateTag, NoteUpdateOutcome, UpdateBookmark, UpdateCategory,
+    UpdateNote, UpdateTag, validate_client_id,
+};
+use crate::services::{BookmarkService, CategoryService, NoteService, TagService};
+
+pub struct BatchService;
+
+impl BatchService {
+    /// Runs the operations in order inside a single transaction.
+    ///
+    /// The first failing operation rolls back the whole batch. Later operations
+    /// may refer to entities created earlier by their `client_id`.
+    pub async fn execute(
+        pool: &PgPool,
+        canonical_urls: CanonicalUrlOptions,
+        user_id: Uuid,
+        operations: Vec<BatchOperation>,
+    ) -> Result<Vec<BatchResult>> {
+        let mut tx = pool.begin().await?;
+        let mut client_ids = HashMap::new();
+        let mut results = Vec::with_capacity(operations.len());
+
+        for (index, operation) in operations.into_iter().enumerate() {
+            let result = Self::run(
+                &mut tx,
+                canonical_urls,
+                user_id,
+                &mut client_ids,
+                index,
+                operation,
+            )
+            .await
+            .map_err(|e| AppError::BatchOperation {
+                index,
+                source: Box::new(e),
+            })?;
+            results.push(result);
+        }
+
+        tx.commit().await?;
+
+        Ok(results)
+    }
+
+    async fn run(
+        conn: &mut PgConnection,
+        canonical_urls: CanonicalUrlOptions,
+        user_id: Uuid,
+        client_ids: &mut HashMap<String, Uuid>,
+        index: usize,
+        operation: BatchOperation,
+    ) -> Result<BatchResult> {
+        let BatchOperation {
+            op,
+            entity,
+            client_id,
+            id,
+            version,
+            mut data,
+        } = operation;
+
+        let mut id = match (op, id) {
+            (BatchOp::Create, id) => {
+                // The id may also be given inside the payload, as for single creates
+                let id = id.or_else(|| {
+                    data.as_ref()
+                        .and_then(|data| data.get("id"))
+                        .and_then(Value::as_str)
+                        .map(str::to_owned)
+                });
+                match id {
+                    Some(id) => {
+                        let id = Uuid::parse_str(&id).map_err(|_| {
+                            AppError::Validation(
+                                "Id of a created entity must be a UUID".to_string(),
+                            )
+                        })?;
+                        validate_client_id(&id).map_err(|e| AppError::Validation(e.to_string()))?;
+                        id
+                    }
+                    None => Uuid::new_v4(),
+                }
+            }
+            (_, Some(reference)) => resolve(client_ids, &reference)?,
+            (_, None) => {
+                return Err(AppError::Validation(
+                    "Update and delete operations require an id".to_string(),
+                ));
+            }
+        };
+
+        if let (BatchOp::Create, Some(client_id)) = (op, &client_id)
+            && client_ids.insert(client_id.clone(), id).is_some()
+        {
+            return Err(AppError::Validation(format!(
+                "Duplicate client_id '{client_id}'"
+            )));
+        }
+
+        if let Some(data) = data.as_mut() {
+            resolve_references(client_ids, entity, data)?;
+        }
+
+        let (status, data) = match (op, entity) {
+            (BatchOp::Create, BatchEntity::Bookmark) => {
+                let input: CreateBookmark = parse_valid(data)?;
+                match BookmarkService::create_in_tx(conn, canonical_urls, user_id, id, input)
+                    .await?
+                {
+                    BookmarkCreateOutcome::Created(bookmark) => {
+                        (StatusCode::CREATED, Some(to_value(&bookmark)?))
+                    }
+                    BookmarkCreateOutcome::Merged(bookmark) => {
+                        // Later references to the client_id mean the bookmark merged into
+                        id = bookmark.bookmark.id;
+                        if let Some(client_id) = &client_id {
+                            client_ids.insert(client_id.clone(), id);
+                        }
+                        (StatusCode::OK, Some(to_value(&bookmark)?))
+                    }
+                }
+            }
+            (BatchOp::Create, BatchEntity::Note) => {
+                let input: CreateNote = parse_valid(data)?;
+                let note = NoteService::create_in_tx(conn, user_id, id, input).await?;
+                (StatusCode::CREATED, Some(to_value(&note)?))
+            }
+            (BatchOp::Create, BatchEntity::Tag) => {
+                let input: CreateTag = parse_valid(data)?;
+                let tag = TagService::create_in_tx(conn, user_id, id, input).await?;
+                (StatusCode::CREATED, Some(to_value(&tag)?))
+            }
+            (BatchOp::Create, BatchEntity::Category) => {
+                let input: CreateCategory = parse_valid(data)?;
+                let category = CategoryService::create_in_tx(conn, user_id, id, input).await?;
+                (StatusCode::CREATED, Some(to_value(&category)?))
+            }
+            (BatchOp::Update, BatchEntity::Bookmark) => {
+                let input: UpdateBookmark = parse(data)?;
+                let bookmark = BookmarkService::update_in_tx(
+                    conn,
+                    canonical_urls,
+                    user_id,
+                    id,
+                    input,
+                    version,
+                )
+                .await?;
+                (StatusCode::OK, Some(to_value(&bookmark)?))
+            }
+            (BatchOp::Update, BatchEntity::Note) => {
+                let input: UpdateNote = parse(data)?;
+                match NoteService::update_in_tx(conn, user_id, id, input, version).await? {
+                    NoteUpdateOutcome::Applied(note) => (StatusCode::OK, Some(to_value(&note)?)),
+                    NoteUpdateOutcome::Conflicted(conflict) => {
+                        (StatusCode::CONFLICT, Some(to_value(&conflict)?))
+                    }
+                }
+            }
+            (BatchOp::Update, BatchEntity::Tag) => {
+                let input: UpdateTag = parse(data)?;
+                let tag = TagService::update_in_tx(conn, user_id, id, input, version).await?;
+                (StatusCode::OK, Some(to_value(&tag)?))
+            }
+            (BatchOp::Update, BatchEntity::Category) => {
+                let input: UpdateCategory = parse(data)?;
+                let category =
+                    CategoryService::update_in_tx(conn, user_id, id, input, version).await?;
+                (StatusCode::OK, Some(to_value(&category)?))
+            }
+            (BatchOp::Delete, BatchEntity::Bookmark) => {
+                BookmarkService::delete_in_tx(conn, user_id, id, version).await?;
+                (StatusCode::NO_CONTENT, None)
+            }
+            (BatchOp::Delete, BatchEntity::Note) => {
+                NoteService::delete_in_tx(conn, user_id, id, version).await?;
+                (StatusCode::NO_CONTENT, None)
+            }
+            (BatchOp::Delete, BatchEntity::Tag) => {
+                TagService::delete_in_tx(conn, user_id, id, version).await?;
+                (StatusCode::NO_CONTENT, None)
+            }
+            (BatchOp::Delete, BatchEntity::Category) => {
+                CategoryService::delete_in_tx(conn, user_id, id, version).await?;
+                (StatusCode::NO_CONTENT, None)
+            }
+        };
+
+        Ok(BatchResult {
+            index,
+            client_id,
+            id,
+            status: status.as_u16(),
+            data,
+        })
+    }
+}
+
+/// Looks up a temporary `client_id`, falling back to a literal UUID.
+fn resolve(client_ids: &HashMap<String, Uuid>, reference: &str) -> Result<Uuid> {
+    client_ids
+        .get(reference)
+        .copied()
+        .or_else(|| Uuid::parse_str(reference).ok())
+        .ok_or_else(|| AppError::Validation(format!("Unknown id or client_id '{reference}'")))
+}
+
+/// Rewrites `client_id` references in id-valued payload fields to real ids.
+fn resolve_references(
+    client_ids: &HashMap<String, Uuid>,
+    entity: BatchEntity,
+    data: &mut serde_json::Value,
+) -> Result<()> {
+    let fields: &[&str] = match entity {
+        BatchEntity::Bookmark => &["category_id", "tag_ids"],
+        BatchEntity::Category => &["parent_id"],
+        BatchEntity::Note | BatchEntity::Tag => &[],
+    };
+
+    for field in fields {
+        match data.get_mut(*field) {
+            Some(serde_json::Value::String(reference)) => {
+                *reference = resolve(client_ids, reference)?.to_string();
+            }
+            Some(serde_json::Value::Array(references)) => {
+                for reference in references {
+                    if let serde_json::Value::String(reference) = reference {
+                        *reference = resolve(client_ids, reference)?.to_string();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn parse<T: DeserializeOwned>(data: Option<serde_json::Value>) -> Result<T> {
+    let data = data.ok_or_else(|| AppError::Validation("Operation requires data".to_string()))?;
+
+    serde_json::from_value(data).map_err(|e| AppError::Validation(e.to_string()))
+}
+
+/// Parses a create payload, validating it like the create endpoints do.
+fn parse_valid<T: DeserializeOwned + Validate>(data: Option<serde_json::Value>) -> Result<T> {
+    let input: T = parse(data)?;
+    input
+        .validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+
+    Ok(input)
+}
+
+fn to_value<T: Serialize>(value: &T) -> Result<serde_json::Value> {
+    serde_json::to_value(value).map_err(|e| AppError::Internal(e.to_string()))
+}
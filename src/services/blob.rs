@@ -0,0 +1,185 @@
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::blob::{self, BlobStorage};
+use crate::error::{AppError, Result};
+use crate::models::StorageUsage;
+
+pub struct BlobService;
+
+impl BlobService {
+    /// Uploads `data` unless an identical blob is already referenced, returning its hash.
+    ///
+    /// The blob stays unreferenced, and is eventually collected, until
+    /// [`Self::add_reference`] is called for it.
+    pub async fn put(pool: &PgPool, storage: &BlobStorage, data: Vec<u8>) -> Result<String> {
+        let hash = blob::hash(&data);
+
+        // Registering first means an upload whose reference is never taken is
+        // still collected; refreshing released_at keeps collection away meanwhile
+        let referenced = sqlx::query_scalar::<_, bool>(
+            r#"
+            INSERT INTO blobs (hash, size) VALUES ($1, $2)
+            ON CONFLICT (hash) DO UPDATE
+            SET released_at = CASE WHEN blobs.ref_count = 0 THEN NOW() END
+            RETURNING ref_count > 0
+            "#,
+        )
+        .bind(&hash)
+        .bind(data.len() as i64)
+        .fetch_one(pool)
+        .await?;
+
+        if !referenced {
+            storage.store.put(&hash, data).await?;
+        }
+
+        Ok(hash)
+    }
+
+    /// Fails with `QuotaExceeded` if referencing the blob would take the user over `quota_bytes`.
+    ///
+    /// Blobs the user already references don't count again. Holds a lock on
+    /// the user's quota until the transaction ends.
+    pub async fn check_quota(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        hash: &str,
+        quota_bytes: i64,
+    ) -> Result<()> {
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtextextended('blob quota ' || $1::TEXT, 0))")
+            .bind(user_id)
+            .execute(&mut *conn)
+            .await?;
+
+        let (used, added) = sqlx::query_as::<_, (i64, i64)>(
+            r#"
+            SELECT
+                (SELECT COALESCE(SUM(b.size), 0)::BIGINT FROM user_blobs ub
+                 JOIN blobs b ON b.hash = ub.hash WHERE ub.user_id = $1),
+                (SELECT COALESCE(SUM(b.size), 0)::BIGINT FROM blobs b WHERE b.hash = $2
+                 AND NOT EXISTS (SELECT 1 FROM user_blobs ub
+                                 WHERE ub.user_id = $1 AND ub.hash = b.hash))
+            "#,
+        )
+        .bind(user_id)
+        .bind(hash)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        if used + added > quota_bytes {
+            return Err(AppError::QuotaExceeded(format!(
+                "{added} more bytes would exceed the {quota_bytes} byte quota ({used} used)"
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Records one more use of a blob by the user.
+    pub async fn add_reference(conn: &mut PgConnection, user_id: Uuid, hash: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO user_blobs (user_id, hash, ref_count) VALUES ($1, $2, 1)
+            ON CONFLICT (user_id, hash) DO UPDATE SET ref_count = user_blobs.ref_count + 1
+            "#,
+        )
+        .bind(user_id)
+        .bind(hash)
+        .execute(conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Drops one use of a blob by the user; unreferenced blobs are collected later.
+    pub async fn remove_reference(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        hash: &str,
+    ) -> Result<()> {
+        let deleted = sqlx::query(
+            "DELETE FROM user_blobs WHERE user_id = $1 AND hash = $2 AND ref_count = 1",
+        )
+        .bind(user_id)
+        .bind(hash)
+        .execute(&mut *conn)
+        .await?;
+
+        if deleted.rows_affected() == 0 {
+            sqlx::query(
+                "UPDATE user_blobs SET ref_count = ref_count - 1 WHERE user_id = $1 AND hash = $2",
+            )
+            .bind(user_id)
+            .bind(hash)
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn get(storage: &BlobStorage, hash: &str) -> Result<Vec<u8>> {
+        Ok(storage.store.get(hash).await?)
+    }
+
+    pub async fn usage(
+        pool: &PgPool,
+        storage: &BlobStorage,
+        user_id: Uuid,
+    ) -> Result<StorageUsage> {
+        let (used_bytes, blob_count) = sqlx::query_as::<_, (i64, i64)>(
+            r#"
+            SELECT COALESCE(SUM(b.size), 0)::BIGINT, COUNT(*)
+            FROM user_blobs ub JOIN blobs b ON b.hash = ub.hash
+            WHERE ub.user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(StorageUsage {
+            used_bytes,
+            quota_bytes: storage.quota_bytes,
+            blob_count,
+        })
+    }
+
+    /// Deletes up to `limit` blobs that have been unreferenced since before `released_before`.
+    ///
+    /// Rows stay locked until their objects are gone, so a concurrent upload
+    /// of the same content waits and then uploads it again.
+    pub async fn collect_garbage(
+        pool: &PgPool,
+        storage: &BlobStorage,
+        released_before: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<u64> {
+        let mut tx = pool.begin().await?;
+
+        let hashes = sqlx::query_scalar::<_, String>(
+            r#"
+            DELETE FROM blobs WHERE hash IN (
+                SELECT hash FROM blobs
+                WHERE ref_count = 0 AND released_at < $1
+                LIMIT $2
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING hash
+            "#,
+        )
+        .bind(released_before)
+        .bind(limit)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        for hash in &hashes {
+            storage.store.delete(hash).await?;
+        }
+
+        tx.commit().await?;
+        Ok(hashes.len() as u64)
+    }
+}
@@ -1,27 +1,115 @@
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgExecutor, PgPool, Postgres, QueryBuilder};
 use uuid::Uuid;
 
+use crate::canonical_url::{self, CanonicalUrlOptions};
 use crate::error::{AppError, Result};
-use crate::models::{Bookmark, CreateBookmark, UpdateBookmark};
+use crate::models::{
+    Bookmark, BookmarkCreateOutcome, BookmarkListQuery, BookmarkSort, BookmarkWithTags,
+    ChangeOperation, CreateBookmark, DuplicateGroup, DuplicatePolicy, EntityType, MergeBookmarks,
+    Page, TagMatch, UpdateBookmark,
+};
+use crate::services::pagination::{Keyset, timestamp_key};
+use crate::services::{ArchiveService, PreviewService, SyncService};
+
+/// Bookmark columns plus a `tags` JSON array aggregated in the same query.
+const SELECT_WITH_TAGS: &str = r#"
+    SELECT b.*,
+           COALESCE(
+               (SELECT json_agg(t ORDER BY t.name)
+                FROM bookmark_tags bt
+                JOIN tags t ON t.id = bt.tag_id AND t.user_id = b.user_id
+                WHERE bt.bookmark_id = b.id),
+               '[]'::json
+           ) AS tags
+    FROM bookmarks b
+"#;
 
 pub struct BookmarkService;
 
 impl BookmarkService {
-    pub async fn create(pool: &PgPool, user_id: Uuid, input: CreateBookmark) -> Result<Bookmark> {
+    /// Creates a bookmark, applying the input's duplicate policy.
+    pub async fn create(
+        pool: &PgPool,
+        canonical_urls: CanonicalUrlOptions,
+        user_id: Uuid,
+        input: CreateBookmark,
+    ) -> Result<BookmarkCreateOutcome> {
+        let id = input.id.unwrap_or_else(Uuid::new_v4);
+        let mut tx = pool.begin().await?;
+        let outcome = Self::create_in_tx(&mut tx, canonical_urls, user_id, id, input).await?;
+        tx.commit().await?;
+
+        Ok(outcome)
+    }
+
+    /// Same as [`Self::create`], within the caller's transaction, using `id` for the new row.
+    pub async fn create_in_tx(
+        conn: &mut PgConnection,
+        canonical_urls: CanonicalUrlOptions,
+        user_id: Uuid,
+        id: Uuid,
+        input: CreateBookmark,
+    ) -> Result<BookmarkCreateOutcome> {
+        if let Some(tag_ids) = &input.tag_ids {
+            Self::ensure_tags_owned(&mut *conn, user_id, tag_ids).await?;
+        }
+
+        let canonical_url = canonical_url::canonicalize_with(&input.url, canonical_urls);
+        let policy = input.on_duplicate.unwrap_or_default();
+
+        if policy != DuplicatePolicy::Allow
+            && let Some(existing) =
+                Self::find_duplicate(&mut *conn, user_id, &canonical_url, None).await?
+        {
+            if policy == DuplicatePolicy::Reject {
+                return Err(duplicate_error(&existing));
+            }
+
+            Self::merge_fields(
+                &mut *conn,
+                user_id,
+                existing.id,
+                input.description.as_deref(),
+                input.category_id,
+                input.is_favorite.unwrap_or(false),
+                None,
+            )
+            .await?;
+            if let Some(tag_ids) = input.tag_ids {
+                Self::add_tags(&mut *conn, user_id, existing.id, &tag_ids).await?;
+            }
+
+            let merged = Self::get_with_tags(&mut *conn, user_id, existing.id).await?;
+            return Ok(BookmarkCreateOutcome::Merged(merged));
+        }
+
         let bookmark = sqlx::query_as::<_, Bookmark>(
             r#"
-            INSERT INTO bookmarks (id, user_id, url, title, description, category_id, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, $5, $6, NOW(), NOW())
+            INSERT INTO bookmarks (id, user_id, url, canonical_url, title, description, category_id, is_favorite, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, NOW(), NOW())
+            ON CONFLICT (id) DO NOTHING
             RETURNING *
             "#,
         )
-        .bind(Uuid::new_v4())
+        .bind(id)
         .bind(user_id)
         .bind(&input.url)
+        .bind(&canonical_url)
         .bind(&input.title)
         .bind(&input.description)
         .bind(input.category_id)
-        .fetch_one(pool)
+        .bind(input.is_favorite.unwrap_or(false))
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| AppError::Conflict(format!("Bookmark id {id} is already in use")))?;
+
+        SyncService::record(
+            &mut *conn,
+            user_id,
+            EntityType::Bookmark,
+            bookmark.id,
+            ChangeOperation::Upsert,
+        )
         .await?;
 
         if let Some(tag_ids) = input.tag_ids {
@@ -31,92 +119,648 @@ impl BookmarkService {
                 )
                 .bind(bookmark.id)
                 .bind(tag_id)
-                .execute(pool)
+                .execute(&mut *conn)
                 .await?;
             }
+
+            SyncService::record(
+                &mut *conn,
+                user_id,
+                EntityType::BookmarkTags,
+                bookmark.id,
+                ChangeOperation::Upsert,
+            )
+            .await?;
         }
 
-        Ok(bookmark)
+        let bookmark = Self::get_with_tags(&mut *conn, user_id, bookmark.id).await?;
+        Ok(BookmarkCreateOutcome::Created(bookmark))
     }
 
-    pub async fn get_by_id(pool: &PgPool, user_id: Uuid, bookmark_id: Uuid) -> Result<Bookmark> {
+    pub async fn get_by_id(
+        executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        bookmark_id: Uuid,
+    ) -> Result<Bookmark> {
         sqlx::query_as::<_, Bookmark>("SELECT * FROM bookmarks WHERE id = $1 AND user_id = $2")
             .bind(bookmark_id)
             .bind(user_id)
-            .fetch_optional(pool)
+            .fetch_optional(executor)
             .await?
             .ok_or_else(|| AppError::NotFound("Bookmark not found".to_string()))
     }
 
-    pub async fn list(pool: &PgPool, user_id: Uuid) -> Result<Vec<Bookmark>> {
-        let bookmarks = sqlx::query_as::<_, Bookmark>(
-            "SELECT * FROM bookmarks WHERE user_id = $1 ORDER BY created_at DESC",
-        )
+    /// Same as [`Self::get_by_id`], with the bookmark's tags embedded.
+    pub async fn get_with_tags(
+        executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        bookmark_id: Uuid,
+    ) -> Result<BookmarkWithTags> {
+        sqlx::query_as::<_, BookmarkWithTags>(&format!(
+            "{SELECT_WITH_TAGS} WHERE b.id = $1 AND b.user_id = $2"
+        ))
+        .bind(bookmark_id)
         .bind(user_id)
-        .fetch_all(pool)
-        .await?;
+        .fetch_optional(executor)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Bookmark not found".to_string()))
+    }
+
+    pub async fn list(
+        pool: &PgPool,
+        user_id: Uuid,
+        query: BookmarkListQuery,
+    ) -> Result<Page<BookmarkWithTags>> {
+        let keyset = Keyset::new(
+            query.sort.unwrap_or_default(),
+            query.order,
+            query.limit,
+            query.cursor.as_deref(),
+        )?;
+        let tag_ids = query.tag_id.as_deref().map(parse_tag_ids).transpose()?;
+        let url_domain = query
+            .url_domain
+            .as_deref()
+            .map(normalize_domain)
+            .transpose()?;
+
+        let push_filters = |builder: &mut QueryBuilder<'_, Postgres>| {
+            builder.push(" WHERE b.user_id = ").push_bind(user_id);
+
+            if let Some(category_id) = query.category_id {
+                builder.push(" AND b.category_id = ").push_bind(category_id);
+            }
+            if let Some(tag_ids) = &tag_ids {
+                match query.tag_match.unwrap_or_default() {
+                    TagMatch::Any => builder
+                        .push(
+                            " AND EXISTS (SELECT 1 FROM bookmark_tags bt \
+                             WHERE bt.bookmark_id = b.id AND bt.tag_id = ANY(",
+                        )
+                        .push_bind(tag_ids.clone())
+                        .push("))"),
+                    TagMatch::All => builder
+                        .push(
+                            " AND (SELECT COUNT(*) FROM bookmark_tags bt \
+                             WHERE bt.bookmark_id = b.id AND bt.tag_id = ANY(",
+                        )
+                        .push_bind(tag_ids.clone())
+                        .push(")) = ")
+                        .push_bind(tag_ids.len() as i64),
+                };
+            }
+            if let Some(created_after) = query.created_after {
+                builder
+                    .push(" AND b.created_at > ")
+                    .push_bind(created_after);
+            }
+            if let Some(updated_after) = query.updated_after {
+                builder
+                    .push(" AND b.updated_at > ")
+                    .push_bind(updated_after);
+            }
+            if let Some(domain) = &url_domain {
+                builder
+                    .push(" AND (url_host(b.url) = ")
+                    .push_bind(domain.clone())
+                    .push(" OR right(url_host(b.url), ")
+                    .push_bind(domain.chars().count() as i32 + 1)
+                    .push(") = ")
+                    .push_bind(format!(".{domain}"))
+                    .push(")");
+            }
+        };
+
+        let mut select = QueryBuilder::new(SELECT_WITH_TAGS);
+        push_filters(&mut select);
+        keyset.push_after(&mut select, "b.id");
+        keyset.push_order_and_limit(&mut select, "b.id");
+        let bookmarks = select
+            .build_query_as::<BookmarkWithTags>()
+            .fetch_all(pool)
+            .await?;
 
-        Ok(bookmarks)
+        let total = if query.include_total {
+            let mut count = QueryBuilder::new("SELECT COUNT(*) FROM bookmarks b");
+            push_filters(&mut count);
+            Some(count.build_query_scalar::<i64>().fetch_one(pool).await?)
+        } else {
+            None
+        };
+
+        let sort = keyset.sort();
+        Ok(keyset.page(bookmarks, total, |item| {
+            let bookmark = &item.bookmark;
+            let key = match sort {
+                BookmarkSort::CreatedAt => timestamp_key(&bookmark.created_at),
+                BookmarkSort::UpdatedAt => timestamp_key(&bookmark.updated_at),
+                BookmarkSort::Title => bookmark.title.clone(),
+            };
+            (key, bookmark.id)
+        }))
     }
 
     pub async fn update(
         pool: &PgPool,
+        canonical_urls: CanonicalUrlOptions,
         user_id: Uuid,
         bookmark_id: Uuid,
         input: UpdateBookmark,
-    ) -> Result<Bookmark> {
-        Self::get_by_id(pool, user_id, bookmark_id).await?;
+        expected_version: Option<i64>,
+    ) -> Result<BookmarkWithTags> {
+        let mut tx = pool.begin().await?;
+        let bookmark = Self::update_in_tx(
+            &mut tx,
+            canonical_urls,
+            user_id,
+            bookmark_id,
+            input,
+            expected_version,
+        )
+        .await?;
+        tx.commit().await?;
 
-        let bookmark = sqlx::query_as::<_, Bookmark>(
+        Ok(bookmark)
+    }
+
+    /// Same as [`Self::update`], within the caller's transaction.
+    pub async fn update_in_tx(
+        conn: &mut PgConnection,
+        canonical_urls: CanonicalUrlOptions,
+        user_id: Uuid,
+        bookmark_id: Uuid,
+        input: UpdateBookmark,
+        expected_version: Option<i64>,
+    ) -> Result<BookmarkWithTags> {
+        let existing = Self::get_by_id(&mut *conn, user_id, bookmark_id).await?;
+        if let Some(tag_ids) = &input.tag_ids {
+            Self::ensure_tags_owned(&mut *conn, user_id, tag_ids).await?;
+        }
+
+        let canonical_url = input
+            .url
+            .as_deref()
+            .map(|url| canonical_url::canonicalize_with(url, canonical_urls));
+        match input.on_duplicate.unwrap_or_default() {
+            DuplicatePolicy::Allow => {}
+            DuplicatePolicy::Reject => {
+                if let Some(canonical_url) = &canonical_url
+                    && let Some(existing) =
+                        Self::find_duplicate(&mut *conn, user_id, canonical_url, Some(bookmark_id))
+                            .await?
+                {
+                    return Err(duplicate_error(&existing));
+                }
+            }
+            DuplicatePolicy::Merge => {
+                return Err(AppError::Validation(
+                    "on_duplicate=merge is only supported when creating bookmarks; \
+                     use the merge endpoint instead"
+                        .to_string(),
+                ));
+            }
+        }
+
+        let updated = sqlx::query_as::<_, Bookmark>(
             r#"
             UPDATE bookmarks
             SET url = COALESCE($3, url),
-                title = COALESCE($4, title),
-                description = COALESCE($5, description),
-                category_id = COALESCE($6, category_id),
-                updated_at = NOW()
-            WHERE id = $1 AND user_id = $2
+                canonical_url = COALESCE($4, canonical_url),
+                title = COALESCE($5, title),
+                description = COALESCE($6, description),
+                category_id = COALESCE($7, category_id),
+                is_favorite = COALESCE($8, is_favorite),
+                updated_at = NOW(),
+                version = version + 1
+            WHERE id = $1 AND user_id = $2 AND ($9::BIGINT IS NULL OR version = $9)
             RETURNING *
             "#,
         )
         .bind(bookmark_id)
         .bind(user_id)
         .bind(&input.url)
+        .bind(&canonical_url)
         .bind(&input.title)
         .bind(&input.description)
         .bind(input.category_id)
-        .fetch_one(pool)
+        .bind(input.is_favorite)
+        .bind(expected_version)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        if updated.is_none() {
+            let current = Self::get_with_tags(&mut *conn, user_id, bookmark_id).await?;
+            let version = current.bookmark.version;
+            return Err(AppError::precondition_failed(&current, version));
+        }
+
+        if input.url.as_ref().is_some_and(|url| *url != existing.url) {
+            PreviewService::reset(&mut *conn, bookmark_id).await?;
+        }
+
+        SyncService::record(
+            &mut *conn,
+            user_id,
+            EntityType::Bookmark,
+            bookmark_id,
+            ChangeOperation::Upsert,
+        )
         .await?;
 
         if let Some(tag_ids) = input.tag_ids {
             sqlx::query("DELETE FROM bookmark_tags WHERE bookmark_id = $1")
                 .bind(bookmark_id)
-                .execute(pool)
+                .execute(&mut *conn)
                 .await?;
 
             for tag_id in tag_ids {
                 sqlx::query("INSERT INTO bookmark_tags (bookmark_id, tag_id) VALUES ($1, $2)")
                     .bind(bookmark_id)
                     .bind(tag_id)
-                    .execute(pool)
+                    .execute(&mut *conn)
                     .await?;
             }
+
+            SyncService::record(
+                &mut *conn,
+                user_id,
+                EntityType::BookmarkTags,
+                bookmark_id,
+                ChangeOperation::Upsert,
+            )
+            .await?;
+        }
+
+        Self::get_with_tags(&mut *conn, user_id, bookmark_id).await
+    }
+
+    pub async fn delete(
+        pool: &PgPool,
+        user_id: Uuid,
+        bookmark_id: Uuid,
+        expected_version: Option<i64>,
+    ) -> Result<()> {
+        let mut tx = pool.begin().await?;
+        Self::delete_in_tx(&mut tx, user_id, bookmark_id, expected_version).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Same as [`Self::delete`], within the caller's transaction.
+    pub async fn delete_in_tx(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        bookmark_id: Uuid,
+        expected_version: Option<i64>,
+    ) -> Result<()> {
+        // Releases the archive's blob; a failed delete rolls this back with it
+        ArchiveService::delete_in_tx(&mut *conn, bookmark_id).await?;
+
+        let result = sqlx::query(
+            "DELETE FROM bookmarks WHERE id = $1 AND user_id = $2 AND ($3::BIGINT IS NULL OR version = $3)",
+        )
+        .bind(bookmark_id)
+        .bind(user_id)
+        .bind(expected_version)
+        .execute(&mut *conn)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            let current = Self::get_with_tags(&mut *conn, user_id, bookmark_id).await?;
+            let version = current.bookmark.version;
+            return Err(AppError::precondition_failed(&current, version));
+        }
+
+        SyncService::record(
+            &mut *conn,
+            user_id,
+            EntityType::Bookmark,
+            bookmark_id,
+            ChangeOperation::Delete,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Groups of the user's bookmarks that share a canonical URL.
+    pub async fn duplicates(pool: &PgPool, user_id: Uuid) -> Result<Vec<DuplicateGroup>> {
+        let bookmarks = sqlx::query_as::<_, BookmarkWithTags>(&format!(
+            r#"{SELECT_WITH_TAGS}
+            WHERE b.user_id = $1 AND b.canonical_url IN (
+                SELECT canonical_url FROM bookmarks
+                WHERE user_id = $1 AND canonical_url IS NOT NULL
+                GROUP BY canonical_url
+                HAVING COUNT(*) > 1
+            )
+            ORDER BY b.canonical_url, b.created_at, b.id"#
+        ))
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+
+        let mut groups: Vec<DuplicateGroup> = Vec::new();
+        for bookmark in bookmarks {
+            let canonical_url = bookmark.bookmark.canonical_url.clone().unwrap_or_default();
+            match groups.last_mut() {
+                Some(group) if group.canonical_url == canonical_url => {
+                    group.bookmarks.push(bookmark)
+                }
+                _ => groups.push(DuplicateGroup {
+                    canonical_url,
+                    bookmarks: vec![bookmark],
+                }),
+            }
         }
 
+        Ok(groups)
+    }
+
+    /// Folds the source bookmarks into the target and deletes them.
+    ///
+    /// The target keeps its own URL and title; it gains the sources' tags, and
+    /// their description and category where it has none.
+    pub async fn merge(
+        pool: &PgPool,
+        user_id: Uuid,
+        target_id: Uuid,
+        input: MergeBookmarks,
+        expected_version: Option<i64>,
+    ) -> Result<BookmarkWithTags> {
+        let mut source_ids = input.source_ids;
+        source_ids.sort();
+        source_ids.dedup();
+        if source_ids.contains(&target_id) {
+            return Err(AppError::Validation(
+                "A bookmark cannot be merged into itself".to_string(),
+            ));
+        }
+
+        let mut tx = pool.begin().await?;
+        Self::get_by_id(&mut *tx, user_id, target_id).await?;
+
+        let sources = sqlx::query_as::<_, Bookmark>(
+            "SELECT * FROM bookmarks WHERE user_id = $1 AND id = ANY($2) ORDER BY created_at, id",
+        )
+        .bind(user_id)
+        .bind(&source_ids)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        if sources.len() != source_ids.len() {
+            return Err(AppError::NotFound("Bookmark not found".to_string()));
+        }
+
+        let description = sources
+            .iter()
+            .filter_map(|source| source.description.as_deref())
+            .find(|description| !description.is_empty());
+        let category_id = sources.iter().find_map(|source| source.category_id);
+        let is_favorite = sources.iter().any(|source| source.is_favorite);
+
+        Self::merge_fields(
+            &mut tx,
+            user_id,
+            target_id,
+            description,
+            category_id,
+            is_favorite,
+            expected_version,
+        )
+        .await?;
+
+        let tag_ids = sqlx::query_scalar::<_, Uuid>(
+            "SELECT DISTINCT tag_id FROM bookmark_tags WHERE bookmark_id = ANY($1)",
+        )
+        .bind(&source_ids)
+        .fetch_all(&mut *tx)
+        .await?;
+        Self::add_tags(&mut tx, user_id, target_id, &tag_ids).await?;
+
+        for source in &sources {
+            Self::delete_in_tx(&mut tx, user_id, source.id, None).await?;
+        }
+
+        let bookmark = Self::get_with_tags(&mut *tx, user_id, target_id).await?;
+        tx.commit().await?;
+
         Ok(bookmark)
     }
 
-    pub async fn delete(pool: &PgPool, user_id: Uuid, bookmark_id: Uuid) -> Result<()> {
-        let result = sqlx::query("DELETE FROM bookmarks WHERE id = $1 AND user_id = $2")
-            .bind(bookmark_id)
+    /// Fills in computed canonical URLs for up to `batch_size` bookmarks that lack one.
+    ///
+    /// Returns the number of bookmarks updated.
+    pub async fn backfill_canonical_urls(
+        pool: &PgPool,
+        canonical_urls: CanonicalUrlOptions,
+        batch_size: i64,
+    ) -> Result<u64> {
+        let rows = sqlx::query_as::<_, (Uuid, String)>(
+            "SELECT id, url FROM bookmarks WHERE canonical_url IS NULL LIMIT $1",
+        )
+        .bind(batch_size)
+        .fetch_all(pool)
+        .await?;
+
+        let (ids, canonical_urls): (Vec<Uuid>, Vec<String>) = rows
+            .into_iter()
+            .map(|(id, url)| (id, canonical_url::canonicalize_with(&url, canonical_urls)))
+            .unzip();
+
+        let result = sqlx::query(
+            r#"
+            UPDATE bookmarks b
+            SET canonical_url = c.canonical_url
+            FROM UNNEST($1::UUID[], $2::TEXT[]) AS c (id, canonical_url)
+            WHERE b.id = c.id
+            "#,
+        )
+        .bind(&ids)
+        .bind(&canonical_urls)
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Oldest other bookmark of the user's with this canonical URL.
+    ///
+    /// Takes a transaction-scoped lock on the URL first, so concurrent requests
+    /// checking the same URL see each other's inserts.
+    async fn find_duplicate(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        canonical_url: &str,
+        except_id: Option<Uuid>,
+    ) -> Result<Option<Bookmark>> {
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtextextended($1::TEXT || ' ' || $2, 0))")
             .bind(user_id)
-            .execute(pool)
+            .bind(canonical_url)
+            .execute(&mut *conn)
             .await?;
 
-        if result.rows_affected() == 0 {
-            return Err(AppError::NotFound("Bookmark not found".to_string()));
+        let existing = sqlx::query_as::<_, Bookmark>(
+            r#"
+            SELECT * FROM bookmarks
+            WHERE user_id = $1 AND canonical_url = $2 AND ($3::UUID IS NULL OR id <> $3)
+            ORDER BY created_at, id
+            LIMIT 1
+            "#,
+        )
+        .bind(user_id)
+        .bind(canonical_url)
+        .bind(except_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(existing)
+    }
+
+    /// Sets the description and category where the bookmark has none, and
+    /// marks it a favorite if `is_favorite`, bumping its version.
+    async fn merge_fields(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        bookmark_id: Uuid,
+        description: Option<&str>,
+        category_id: Option<Uuid>,
+        is_favorite: bool,
+        expected_version: Option<i64>,
+    ) -> Result<()> {
+        let updated = sqlx::query_as::<_, Bookmark>(
+            r#"
+            UPDATE bookmarks
+            SET description = COALESCE(NULLIF(description, ''), $3),
+                category_id = COALESCE(category_id, $4),
+                is_favorite = is_favorite OR $5,
+                updated_at = NOW(),
+                version = version + 1
+            WHERE id = $1 AND user_id = $2 AND ($6::BIGINT IS NULL OR version = $6)
+            RETURNING *
+            "#,
+        )
+        .bind(bookmark_id)
+        .bind(user_id)
+        .bind(description)
+        .bind(category_id)
+        .bind(is_favorite)
+        .bind(expected_version)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        if updated.is_none() {
+            let current = Self::get_with_tags(&mut *conn, user_id, bookmark_id).await?;
+            let version = current.bookmark.version;
+            return Err(AppError::precondition_failed(&current, version));
+        }
+
+        SyncService::record(
+            &mut *conn,
+            user_id,
+            EntityType::Bookmark,
+            bookmark_id,
+            ChangeOperation::Upsert,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Fails with `NotFound` unless every tag belongs to the user.
+    async fn ensure_tags_owned(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        tag_ids: &[Uuid],
+    ) -> Result<()> {
+        let missing = sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM UNNEST($1::UUID[]) AS requested(id)
+                WHERE NOT EXISTS (
+                    SELECT 1 FROM tags t WHERE t.id = requested.id AND t.user_id = $2
+                )
+            )
+            "#,
+        )
+        .bind(tag_ids)
+        .bind(user_id)
+        .fetch_one(conn)
+        .await?;
+
+        if missing {
+            return Err(AppError::NotFound("Tag not found".to_string()));
         }
 
         Ok(())
     }
+
+    /// Links the user's tags among `tag_ids` to a bookmark, keeping its existing ones.
+    async fn add_tags(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        bookmark_id: Uuid,
+        tag_ids: &[Uuid],
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO bookmark_tags (bookmark_id, tag_id)
+            SELECT $1, t.id FROM tags t WHERE t.id = ANY($2) AND t.user_id = $3
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(bookmark_id)
+        .bind(tag_ids)
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+
+        SyncService::record(
+            &mut *conn,
+            user_id,
+            EntityType::BookmarkTags,
+            bookmark_id,
+            ChangeOperation::Upsert,
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+fn duplicate_error(existing: &Bookmark) -> AppError {
+    AppError::Conflict(format!("Bookmark {} already has this URL", existing.id))
+}
+
+/// Parses the comma-separated `tag_id` filter, ignoring duplicates.
+fn parse_tag_ids(raw: &str) -> Result<Vec<Uuid>> {
+    let mut tag_ids = Vec::new();
+
+    for part in raw
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+    {
+        let tag_id = Uuid::parse_str(part)
+            .map_err(|_| AppError::Validation(format!("Invalid tag_id '{part}'")))?;
+        if !tag_ids.contains(&tag_id) {
+            tag_ids.push(tag_id);
+        }
+    }
+
+    if tag_ids.is_empty() {
+        return Err(AppError::Validation("tag_id must not be empty".to_string()));
+    }
+
+    Ok(tag_ids)
+}
+
+fn normalize_domain(raw: &str) -> Result<String> {
+    let domain = raw.trim().trim_end_matches('.').to_lowercase();
+
+    if domain.is_empty() || domain.contains(['/', ':', '@', ' ']) {
+        return Err(AppError::Validation(format!("Invalid url_domain '{raw}'")));
+    }
+
+    Ok(domain)
 }
@@ -1,19 +1,39 @@
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgExecutor, PgPool, Postgres, QueryBuilder};
 use uuid::Uuid;
 
 use crate::error::{AppError, Result};
-use crate::models::{Category, CreateCategory, UpdateCategory};
+use crate::models::{
+    Category, CategoryListQuery, CategorySort, ChangeOperation, CreateCategory, EntityType, Page,
+    UpdateCategory,
+};
+use crate::services::SyncService;
+use crate::services::pagination::{Keyset, timestamp_key};
 
 pub struct CategoryService;
 
 impl CategoryService {
     pub async fn create(pool: &PgPool, user_id: Uuid, input: CreateCategory) -> Result<Category> {
+        let id = input.id.unwrap_or_else(Uuid::new_v4);
+        let mut tx = pool.begin().await?;
+        let category = Self::create_in_tx(&mut tx, user_id, id, input).await?;
+        tx.commit().await?;
+
+        Ok(category)
+    }
+
+    /// Same as [`Self::create`], within the caller's transaction, using `id` for the new row.
+    pub async fn create_in_tx(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        id: Uuid,
+        input: CreateCategory,
+    ) -> Result<Category> {
         let existing = sqlx::query_scalar::<_, i64>(
             "SELECT COUNT(*) FROM categories WHERE user_id = $1 AND name = $2",
         )
         .bind(user_id)
         .bind(&input.name)
-        .fetch_one(pool)
+        .fetch_one(&mut *conn)
         .await?;
 
         if existing > 0 {
@@ -21,45 +41,98 @@ impl CategoryService {
         }
 
         if let Some(parent_id) = input.parent_id {
-            Self::get_by_id(pool, user_id, parent_id).await?;
+            Self::get_by_id(&mut *conn, user_id, parent_id).await?;
         }
 
         let category = sqlx::query_as::<_, Category>(
             r#"
             INSERT INTO categories (id, user_id, name, description, parent_id, created_at)
             VALUES ($1, $2, $3, $4, $5, NOW())
+            ON CONFLICT (id) DO NOTHING
             RETURNING *
             "#,
         )
-        .bind(Uuid::new_v4())
+        .bind(id)
         .bind(user_id)
         .bind(&input.name)
         .bind(&input.description)
         .bind(input.parent_id)
-        .fetch_one(pool)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| AppError::Conflict(format!("Category id {id} is already in use")))?;
+
+        SyncService::record(
+            &mut *conn,
+            user_id,
+            EntityType::Category,
+            category.id,
+            ChangeOperation::Upsert,
+        )
         .await?;
 
         Ok(category)
     }
 
-    pub async fn get_by_id(pool: &PgPool, user_id: Uuid, category_id: Uuid) -> Result<Category> {
+    pub async fn get_by_id(
+        executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        category_id: Uuid,
+    ) -> Result<Category> {
         sqlx::query_as::<_, Category>("SELECT * FROM categories WHERE id = $1 AND user_id = $2")
             .bind(category_id)
             .bind(user_id)
-            .fetch_optional(pool)
+            .fetch_optional(executor)
             .await?
             .ok_or_else(|| AppError::NotFound("Category not found".to_string()))
     }
 
-    pub async fn list(pool: &PgPool, user_id: Uuid) -> Result<Vec<Category>> {
-        let categories = sqlx::query_as::<_, Category>(
-            "SELECT * FROM categories WHERE user_id = $1 ORDER BY name ASC",
-        )
-        .bind(user_id)
-        .fetch_all(pool)
-        .await?;
+    pub async fn list(
+        pool: &PgPool,
+        user_id: Uuid,
+        query: CategoryListQuery,
+    ) -> Result<Page<Category>> {
+        let keyset = Keyset::new(
+            query.sort.unwrap_or_default(),
+            query.order,
+            query.limit,
+            query.cursor.as_deref(),
+        )?;
+
+        let push_filters = |builder: &mut QueryBuilder<'_, Postgres>| {
+            builder.push(" WHERE c.user_id = ").push_bind(user_id);
 
-        Ok(categories)
+            if let Some(parent_id) = query.parent_id {
+                builder.push(" AND c.parent_id = ").push_bind(parent_id);
+            }
+            if let Some(created_after) = query.created_after {
+                builder
+                    .push(" AND c.created_at > ")
+                    .push_bind(created_after);
+            }
+        };
+
+        let mut select = QueryBuilder::new("SELECT c.* FROM categories c");
+        push_filters(&mut select);
+        keyset.push_after(&mut select, "c.id");
+        keyset.push_order_and_limit(&mut select, "c.id");
+        let categories = select.build_query_as::<Category>().fetch_all(pool).await?;
+
+        let total = if query.include_total {
+            let mut count = QueryBuilder::new("SELECT COUNT(*) FROM categories c");
+            push_filters(&mut count);
+            Some(count.build_query_scalar::<i64>().fetch_one(pool).await?)
+        } else {
+            None
+        };
+
+        let sort = keyset.sort();
+        Ok(keyset.page(categories, total, |category| {
+            let key = match sort {
+                CategorySort::Name => category.name.clone(),
+                CategorySort::CreatedAt => timestamp_key(&category.created_at),
+            };
+            (key, category.id)
+        }))
     }
 
     pub async fn update(
@@ -67,8 +140,25 @@ impl CategoryService {
         user_id: Uuid,
         category_id: Uuid,
         input: UpdateCategory,
+        expected_version: Option<i64>,
+    ) -> Result<Category> {
+        let mut tx = pool.begin().await?;
+        let category =
+            Self::update_in_tx(&mut tx, user_id, category_id, input, expected_version).await?;
+        tx.commit().await?;
+
+        Ok(category)
+    }
+
+    /// Same as [`Self::update`], within the caller's transaction.
+    pub async fn update_in_tx(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        category_id: Uuid,
+        input: UpdateCategory,
+        expected_version: Option<i64>,
     ) -> Result<Category> {
-        Self::get_by_id(pool, user_id, category_id).await?;
+        Self::get_by_id(&mut *conn, user_id, category_id).await?;
 
         if let Some(parent_id) = input.parent_id {
             if parent_id == category_id {
@@ -76,7 +166,7 @@ impl CategoryService {
                     "Category cannot be its own parent".to_string(),
                 ));
             }
-            Self::get_by_id(pool, user_id, parent_id).await?;
+            Self::get_by_id(&mut *conn, user_id, parent_id).await?;
         }
 
         let category = sqlx::query_as::<_, Category>(
@@ -84,8 +174,9 @@ impl CategoryService {
             UPDATE categories
             SET name = COALESCE($3, name),
                 description = COALESCE($4, description),
-                parent_id = COALESCE($5, parent_id)
-            WHERE id = $1 AND user_id = $2
+                parent_id = COALESCE($5, parent_id),
+                version = version + 1
+            WHERE id = $1 AND user_id = $2 AND ($6::BIGINT IS NULL OR version = $6)
             RETURNING *
             "#,
         )
@@ -94,21 +185,118 @@ impl CategoryService {
         .bind(&input.name)
         .bind(&input.description)
         .bind(input.parent_id)
-        .fetch_one(pool)
+        .bind(expected_version)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        let Some(category) = category else {
+            let current = Self::get_by_id(&mut *conn, user_id, category_id).await?;
+            return Err(AppError::precondition_failed(&current, current.version));
+        };
+
+        SyncService::record(
+            &mut *conn,
+            user_id,
+            EntityType::Category,
+            category_id,
+            ChangeOperation::Upsert,
+        )
         .await?;
 
         Ok(category)
     }
 
-    pub async fn delete(pool: &PgPool, user_id: Uuid, category_id: Uuid) -> Result<()> {
-        let result = sqlx::query("DELETE FROM categories WHERE id = $1 AND user_id = $2")
-            .bind(category_id)
-            .bind(user_id)
-            .execute(pool)
-            .await?;
+    pub async fn delete(
+        pool: &PgPool,
+        user_id: Uuid,
+        category_id: Uuid,
+        expected_version: Option<i64>,
+    ) -> Result<()> {
+        let mut tx = pool.begin().await?;
+        Self::delete_in_tx(&mut tx, user_id, category_id, expected_version).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Same as [`Self::delete`], within the caller's transaction.
+    pub async fn delete_in_tx(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        category_id: Uuid,
+        expected_version: Option<i64>,
+    ) -> Result<()> {
+        // Detach bookmarks and child categories here rather than leaving it to
+        // ON DELETE SET NULL, so their versions move along with their contents
+        let bookmark_ids = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            UPDATE bookmarks
+            SET category_id = NULL, version = version + 1
+            WHERE category_id = $1 AND user_id = $2
+            RETURNING id
+            "#,
+        )
+        .bind(category_id)
+        .bind(user_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let child_ids = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            UPDATE categories
+            SET parent_id = NULL, version = version + 1
+            WHERE parent_id = $1 AND user_id = $2
+            RETURNING id
+            "#,
+        )
+        .bind(category_id)
+        .bind(user_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let result = sqlx::query(
+            "DELETE FROM categories WHERE id = $1 AND user_id = $2 AND ($3::BIGINT IS NULL OR version = $3)",
+        )
+        .bind(category_id)
+        .bind(user_id)
+        .bind(expected_version)
+        .execute(&mut *conn)
+        .await?;
 
         if result.rows_affected() == 0 {
-            return Err(AppError::NotFound("Category not found".to_string()));
+            let current = Self::get_by_id(&mut *conn, user_id, category_id).await?;
+            return Err(AppError::precondition_failed(&current, current.version));
+        }
+
+        SyncService::record(
+            &mut *conn,
+            user_id,
+            EntityType::Category,
+            category_id,
+            ChangeOperation::Delete,
+        )
+        .await?;
+
+        for bookmark_id in bookmark_ids {
+            SyncService::record(
+                &mut *conn,
+                user_id,
+                EntityType::Bookmark,
+                bookmark_id,
+                ChangeOperation::Upsert,
+            )
+            .await?;
+        }
+
+        for child_id in child_ids {
+            SyncService::record(
+                &mut *conn,
+                user_id,
+                EntityType::Category,
+                child_id,
+                ChangeOperation::Upsert,
+            )
+            .await?;
         }
 
         Ok(())
@@ -0,0 +1,227 @@
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::auth::{NEVER_EXPIRES, REVOCATION_CHANNEL, RevocationCache};
+use crate::error::{AppError, Result};
+use crate::models::{Device, DeviceInfo, SyncCursor};
+
+const DEFAULT_DEVICE_NAME: &str = "Unknown device";
+
+pub struct DeviceService;
+
+impl DeviceService {
+    /// Registers the device a client signs in from.
+    ///
+    /// A device the user already signed in from, named by its id or by the
+    /// same name and platform, is reused rather than listed again.
+    pub async fn register(
+        pool: &PgPool,
+        user_id: Uuid,
+        info: Option<DeviceInfo>,
+    ) -> Result<Device> {
+        // Anonymous sign-ins can't be told apart, so each gets its own device
+        if let Some(info) = &info
+            && let Some(device) = Self::reuse(pool, user_id, info).await?
+        {
+            return Ok(device);
+        }
+
+        let info = info.unwrap_or_else(|| DeviceInfo {
+            name: DEFAULT_DEVICE_NAME.to_string(),
+            ..Default::default()
+        });
+
+        let device = sqlx::query_as::<_, Device>(
+            r#"
+            INSERT INTO devices (id, user_id, name, platform, client_version, last_seen_at, created_at)
+            VALUES ($1, $2, $3, $4, $5, NOW(), NOW())
+            RETURNING *
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .bind(&info.name)
+        .bind(&info.platform)
+        .bind(&info.client_version)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(device)
+    }
+
+    /// Updates and returns a matching device that is still signed in, preferring one named by id.
+    async fn reuse(pool: &PgPool, user_id: Uuid, info: &DeviceInfo) -> Result<Option<Device>> {
+        let device = sqlx::query_as::<_, Device>(
+            r#"
+            UPDATE devices
+            SET name = $3, platform = $4, client_version = $5, last_seen_at = NOW()
+            WHERE id = (
+                SELECT id FROM devices
+                WHERE user_id = $1 AND revoked_at IS NULL
+                  AND (id = $2 OR (name = $3 AND platform IS NOT DISTINCT FROM $4))
+                ORDER BY (id = $2) IS TRUE DESC, last_seen_at DESC
+                LIMIT 1
+            )
+            RETURNING *
+            "#,
+        )
+        .bind(user_id)
+        .bind(info.id)
+        .bind(&info.name)
+        .bind(&info.platform)
+        .bind(&info.client_version)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(device)
+    }
+
+    pub async fn list(pool: &PgPool, user_id: Uuid) -> Result<Vec<Device>> {
+        let devices = sqlx::query_as::<_, Device>(
+            r#"
+            SELECT * FROM devices
+            WHERE user_id = $1
+            ORDER BY revoked_at IS NOT NULL, last_seen_at DESC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(devices)
+    }
+
+    /// Signs a device out; its tokens are rejected from then on.
+    ///
+    /// The device row is kept so its last acknowledged sync cursor stays on record.
+    pub async fn revoke(
+        pool: &PgPool,
+        cache: &RevocationCache,
+        user_id: Uuid,
+        device_id: Uuid,
+    ) -> Result<Device> {
+        let mut tx = pool.begin().await?;
+
+        let device = sqlx::query_as::<_, Device>(
+            r#"
+            UPDATE devices
+            SET revoked_at = COALESCE(revoked_at, NOW())
+            WHERE id = $1 AND user_id = $2
+            RETURNING *
+            "#,
+        )
+        .bind(device_id)
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Device not found".to_string()))?;
+
+        Self::notify_revoked(&mut tx, device_id).await?;
+        tx.commit().await?;
+        cache.revoke(device_id, NEVER_EXPIRES);
+
+        tracing::info!(%user_id, %device_id, "Device revoked");
+
+        Ok(device)
+    }
+
+    /// Signs out every device of the user, e.g. on logout everywhere.
+    ///
+    /// Returns the devices signed out, for the caller to mark in its cache once committed.
+    pub async fn revoke_all_in_tx(conn: &mut PgConnection, user_id: Uuid) -> Result<Vec<Uuid>> {
+        let device_ids = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            UPDATE devices SET revoked_at = NOW()
+            WHERE user_id = $1 AND revoked_at IS NULL
+            RETURNING id
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        for &device_id in &device_ids {
+            Self::notify_revoked(&mut *conn, device_id).await?;
+        }
+
+        Ok(device_ids)
+    }
+
+    /// Marks a signed-out device in the cache of every instance once committed.
+    async fn notify_revoked(conn: &mut PgConnection, device_id: Uuid) -> Result<()> {
+        sqlx::query("SELECT pg_notify($1, $2)")
+            .bind(REVOCATION_CHANNEL)
+            .bind(format!("{} {}", device_id, NEVER_EXPIRES))
+            .execute(conn)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Rejects revoked devices, consulting `cache` first, and refreshes
+    /// `last_seen_at` at most once a minute when asking the database.
+    pub async fn authorize(
+        pool: &PgPool,
+        cache: &RevocationCache,
+        user_id: Uuid,
+        device_id: Uuid,
+    ) -> Result<()> {
+        if let Some(revoked) = cache.get(device_id) {
+            return if revoked {
+                Err(AppError::Unauthorized)
+            } else {
+                Ok(())
+            };
+        }
+
+        let active = sqlx::query_scalar::<_, bool>(
+            r#"
+            WITH device AS (
+                SELECT id, last_seen_at FROM devices
+                WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL
+            ),
+            touched AS (
+                UPDATE devices d
+                SET last_seen_at = NOW()
+                FROM device
+                WHERE d.id = device.id AND device.last_seen_at < NOW() - INTERVAL '1 minute'
+            )
+            SELECT EXISTS (SELECT 1 FROM device)
+            "#,
+        )
+        .bind(device_id)
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+
+        cache.insert(device_id, !active, NEVER_EXPIRES);
+        if !active {
+            return Err(AppError::Unauthorized);
+        }
+
+        Ok(())
+    }
+
+    /// Records that the device has applied every change up to `cursor`.
+    pub async fn acknowledge(
+        pool: &PgPool,
+        user_id: Uuid,
+        device_id: Uuid,
+        cursor: SyncCursor,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE devices
+            SET last_sync_seq = GREATEST(COALESCE(last_sync_seq, 0), $3)
+            WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL
+            "#,
+        )
+        .bind(device_id)
+        .bind(user_id)
+        .bind(cursor.0)
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use sqlx::types::Json;
+use uuid::Uuid;
+
+use crate::error::Result;
+use crate::models::IdempotencyRecord;
+
+/// In-flight requests older than this are assumed abandoned and may be retried.
+const ABANDONED_AFTER_MINUTES: i64 = 5;
+
+pub enum IdempotencyClaim {
+    /// The caller owns the key and must complete or release it.
+    Claimed,
+    /// The key was used before within its window.
+    Existing(IdempotencyRecord),
+}
+
+pub struct IdempotencyService;
+
+impl IdempotencyService {
+    /// Reserves `key` for a new request unless an unexpired record already holds it.
+    pub async fn claim(
+        pool: &PgPool,
+        user_id: Uuid,
+        key: &str,
+        request_hash: &str,
+        expired_before: DateTime<Utc>,
+    ) -> Result<IdempotencyClaim> {
+        let abandoned_before = Utc::now() - chrono::Duration::minutes(ABANDONED_AFTER_MINUTES);
+
+        let claimed = sqlx::query_scalar::<_, String>(
+            r#"
+            INSERT INTO idempotency_keys (user_id, key, request_hash, created_at)
+            VALUES ($1, $2, $3, NOW())
+            ON CONFLICT (user_id, key) DO UPDATE
+            SET request_hash = EXCLUDED.request_hash,
+                status = NULL,
+                headers = NULL,
+                body = NULL,
+                created_at = NOW()
+            WHERE idempotency_keys.created_at < $4
+               OR (idempotency_keys.status IS NULL AND idempotency_keys.created_at < $5)
+            RETURNING key
+            "#,
+        )
+        .bind(user_id)
+        .bind(key)
+        .bind(request_hash)
+        .bind(expired_before)
+        .bind(abandoned_before)
+        .fetch_optional(pool)
+        .await?;
+
+        if claimed.is_some() {
+            return Ok(IdempotencyClaim::Claimed);
+        }
+
+        let record = sqlx::query_as::<_, IdempotencyRecord>(
+            "SELECT * FROM idempotency_keys WHERE user_id = $1 AND key = $2",
+        )
+        .bind(user_id)
+        .bind(key)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(IdempotencyClaim::Existing(record))
+    }
+
+    pub async fn complete(
+        pool: &PgPool,
+        user_id: Uuid,
+        key: &str,
+        status: u16,
+        headers: Vec<(String, String)>,
+        body: &[u8],
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE idempotency_keys
+            SET status = $3, headers = $4, body = $5
+            WHERE user_id = $1 AND key = $2
+            "#,
+        )
+        .bind(user_id)
+        .bind(key)
+        .bind(i32::from(status))
+        .bind(Json(headers))
+        .bind(body)
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Gives up a claimed key so the request can be retried.
+    pub async fn release(pool: &PgPool, user_id: Uuid, key: &str) -> Result<()> {
+        sqlx::query("DELETE FROM idempotency_keys WHERE user_id = $1 AND key = $2")
+            .bind(user_id)
+            .bind(key)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn purge_expired(pool: &PgPool, expired_before: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM idempotency_keys WHERE created_at < $1")
+            .bind(expired_before)
+            .execute(pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -1,11 +1,38 @@
+mod access_token;
+mod archive;
+mod batch;
+mod blob;
 mod bookmark;
 mod category;
+mod device;
+mod idempotency;
 mod note;
+mod pagination;
+mod preview;
+mod refresh_token;
+mod saved_search;
+mod search;
+mod sync;
 mod tag;
+mod token_revocation;
+mod two_factor;
 mod user;
 
+pub use access_token::PersonalAccessTokenService;
+pub use archive::ArchiveService;
+pub use batch::BatchService;
+pub use blob::BlobService;
 pub use bookmark::BookmarkService;
 pub use category::CategoryService;
+pub use device::DeviceService;
+pub use idempotency::{IdempotencyClaim, IdempotencyService};
 pub use note::NoteService;
+pub use preview::PreviewService;
+pub use refresh_token::{RefreshTokenService, RotatedToken};
+pub use saved_search::SavedSearchService;
+pub use search::SearchService;
+pub use sync::SyncService;
 pub use tag::TagService;
+pub use token_revocation::TokenRevocationService;
+pub use two_factor::{PassedChallenge, TwoFactorService};
 pub use user::UserService;
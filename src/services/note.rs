@@ -1,89 +1,455 @@
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgExecutor, PgPool, Postgres, QueryBuilder};
 use uuid::Uuid;
 
 use crate::error::{AppError, Result};
-use crate::models::{CreateNote, Note, UpdateNote};
+use crate::models::{
+    ChangeOperation, ConflictResolution, CreateNote, EntityType, Note, NoteConflict, NoteListQuery,
+    NoteRevision, NoteSort, NoteUpdateOutcome, Page, ResolveNoteConflict, UpdateNote,
+};
+use crate::services::SyncService;
+use crate::services::pagination::{Keyset, timestamp_key};
 
 pub struct NoteService;
 
 impl NoteService {
+    /// How many of a note's latest revisions are kept as merge bases; an
+    /// `If-Match` naming an older version fails as stale.
+    pub const REVISION_LIMIT: i64 = 50;
+
     pub async fn create(pool: &PgPool, user_id: Uuid, input: CreateNote) -> Result<Note> {
+        let id = input.id.unwrap_or_else(Uuid::new_v4);
+        let mut tx = pool.begin().await?;
+        let note = Self::create_in_tx(&mut tx, user_id, id, input).await?;
+        tx.commit().await?;
+
+        Ok(note)
+    }
+
+    /// Same as [`Self::create`], within the caller's transaction, using `id` for the new row.
+    pub async fn create_in_tx(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        id: Uuid,
+        input: CreateNote,
+    ) -> Result<Note> {
         let note = sqlx::query_as::<_, Note>(
             r#"
             INSERT INTO notes (id, user_id, title, content, created_at, updated_at)
             VALUES ($1, $2, $3, $4, NOW(), NOW())
+            ON CONFLICT (id) DO NOTHING
             RETURNING *
             "#,
         )
-        .bind(Uuid::new_v4())
+        .bind(id)
         .bind(user_id)
         .bind(&input.title)
         .bind(&input.content)
-        .fetch_one(pool)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| AppError::Conflict(format!("Note id {id} is already in use")))?;
+
+        Self::record_revision(&mut *conn, &note).await?;
+
+        SyncService::record(
+            &mut *conn,
+            user_id,
+            EntityType::Note,
+            note.id,
+            ChangeOperation::Upsert,
+        )
         .await?;
 
         Ok(note)
     }
 
-    pub async fn get_by_id(pool: &PgPool, user_id: Uuid, note_id: Uuid) -> Result<Note> {
+    pub async fn get_by_id(
+        executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        note_id: Uuid,
+    ) -> Result<Note> {
         sqlx::query_as::<_, Note>("SELECT * FROM notes WHERE id = $1 AND user_id = $2")
             .bind(note_id)
             .bind(user_id)
-            .fetch_optional(pool)
+            .fetch_optional(executor)
             .await?
             .ok_or_else(|| AppError::NotFound("Note not found".to_string()))
     }
 
-    pub async fn list(pool: &PgPool, user_id: Uuid) -> Result<Vec<Note>> {
-        let notes = sqlx::query_as::<_, Note>(
-            "SELECT * FROM notes WHERE user_id = $1 ORDER BY updated_at DESC",
+    pub async fn list(pool: &PgPool, user_id: Uuid, query: NoteListQuery) -> Result<Page<Note>> {
+        let keyset = Keyset::new(
+            query.sort.unwrap_or_default(),
+            query.order,
+            query.limit,
+            query.cursor.as_deref(),
+        )?;
+
+        let push_filters = |builder: &mut QueryBuilder<'_, Postgres>| {
+            builder.push(" WHERE n.user_id = ").push_bind(user_id);
+
+            if let Some(created_after) = query.created_after {
+                builder
+                    .push(" AND n.created_at > ")
+                    .push_bind(created_after);
+            }
+            if let Some(updated_after) = query.updated_after {
+                builder
+                    .push(" AND n.updated_at > ")
+                    .push_bind(updated_after);
+            }
+        };
+
+        let mut select = QueryBuilder::new("SELECT n.* FROM notes n");
+        push_filters(&mut select);
+        keyset.push_after(&mut select, "n.id");
+        keyset.push_order_and_limit(&mut select, "n.id");
+        let notes = select.build_query_as::<Note>().fetch_all(pool).await?;
+
+        let total = if query.include_total {
+            let mut count = QueryBuilder::new("SELECT COUNT(*) FROM notes n");
+            push_filters(&mut count);
+            Some(count.build_query_scalar::<i64>().fetch_one(pool).await?)
+        } else {
+            None
+        };
+
+        let sort = keyset.sort();
+        Ok(keyset.page(notes, total, |note| {
+            let key = match sort {
+                NoteSort::UpdatedAt => timestamp_key(&note.updated_at),
+                NoteSort::CreatedAt => timestamp_key(&note.created_at),
+                NoteSort::Title => note.title.clone(),
+            };
+            (key, note.id)
+        }))
+    }
+
+    /// Updates a note, merging with concurrent edits when `expected_version` is stale.
+    ///
+    /// An edit based on an older version is three-way merged line by line against
+    /// the current note. When both sides changed the same lines, the note is left
+    /// untouched and the client's edit is kept as a conflict record instead.
+    pub async fn update(
+        pool: &PgPool,
+        user_id: Uuid,
+        note_id: Uuid,
+        input: UpdateNote,
+        expected_version: Option<i64>,
+    ) -> Result<NoteUpdateOutcome> {
+        let mut tx = pool.begin().await?;
+        let outcome =
+            Self::update_in_tx(&mut tx, user_id, note_id, input, expected_version).await?;
+        tx.commit().await?;
+
+        Ok(outcome)
+    }
+
+    /// Same as [`Self::update`], within the caller's transaction.
+    pub async fn update_in_tx(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        note_id: Uuid,
+        input: UpdateNote,
+        expected_version: Option<i64>,
+    ) -> Result<NoteUpdateOutcome> {
+        let current = Self::get_by_id(&mut *conn, user_id, note_id).await?;
+
+        let base_version = match expected_version {
+            Some(version) if version != current.version => version,
+            _ => {
+                let note = Self::apply(
+                    conn,
+                    user_id,
+                    note_id,
+                    input.title.as_deref(),
+                    input.content.as_deref(),
+                    expected_version,
+                )
+                .await?;
+
+                return Self::applied_or_stale(conn, user_id, note_id, note).await;
+            }
+        };
+
+        let Some(base) = Self::get_revision(&mut *conn, note_id, base_version).await? else {
+            return Err(AppError::precondition_failed(&current, current.version));
+        };
+
+        let client_title = input.title.unwrap_or_else(|| base.title.clone());
+        let client_content = input.content.unwrap_or_else(|| base.content.clone());
+
+        let merged_title = diffy::merge(&base.title, &current.title, &client_title);
+        let merged_content = diffy::merge(&base.content, &current.content, &client_content);
+
+        if let (Ok(title), Ok(content)) = (merged_title, merged_content) {
+            let note = Self::apply(
+                conn,
+                user_id,
+                note_id,
+                Some(&title),
+                Some(&content),
+                Some(current.version),
+            )
+            .await?;
+
+            return Self::applied_or_stale(conn, user_id, note_id, note).await;
+        }
+
+        let conflict = sqlx::query_as::<_, NoteConflict>(
+            r#"
+            INSERT INTO note_conflicts (
+                id, note_id, user_id, base_version, server_version,
+                server_title, server_content, client_title, client_content, created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, NOW())
+            RETURNING *
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(note_id)
+        .bind(user_id)
+        .bind(base_version)
+        .bind(current.version)
+        .bind(&current.title)
+        .bind(&current.content)
+        .bind(&client_title)
+        .bind(&client_content)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(NoteUpdateOutcome::Conflicted(conflict))
+    }
+
+    pub async fn list_conflicts(
+        pool: &PgPool,
+        user_id: Uuid,
+        note_id: Uuid,
+    ) -> Result<Vec<NoteConflict>> {
+        Self::get_by_id(pool, user_id, note_id).await?;
+
+        let conflicts = sqlx::query_as::<_, NoteConflict>(
+            r#"
+            SELECT * FROM note_conflicts
+            WHERE note_id = $1 AND user_id = $2 AND resolved_at IS NULL
+            ORDER BY created_at ASC
+            "#,
         )
+        .bind(note_id)
         .bind(user_id)
         .fetch_all(pool)
         .await?;
 
-        Ok(notes)
+        Ok(conflicts)
     }
 
-    pub async fn update(
+    pub async fn resolve_conflict(
         pool: &PgPool,
         user_id: Uuid,
         note_id: Uuid,
-        input: UpdateNote,
+        conflict_id: Uuid,
+        input: ResolveNoteConflict,
+        expected_version: Option<i64>,
     ) -> Result<Note> {
-        Self::get_by_id(pool, user_id, note_id).await?;
+        let mut tx = pool.begin().await?;
 
+        let conflict = sqlx::query_as::<_, NoteConflict>(
+            r#"
+            UPDATE note_conflicts
+            SET resolved_at = NOW()
+            WHERE id = $1 AND note_id = $2 AND user_id = $3 AND resolved_at IS NULL
+            RETURNING *
+            "#,
+        )
+        .bind(conflict_id)
+        .bind(note_id)
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Conflict not found".to_string()))?;
+
+        let (title, content) = match input.resolution {
+            ConflictResolution::Server => {
+                let note = Self::get_by_id(&mut *tx, user_id, note_id).await?;
+                tx.commit().await?;
+                return Ok(note);
+            }
+            ConflictResolution::Client => {
+                (Some(conflict.client_title), Some(conflict.client_content))
+            }
+            ConflictResolution::Manual => {
+                if input.title.is_none() && input.content.is_none() {
+                    return Err(AppError::Validation(
+                        "Manual resolution requires a title or content".to_string(),
+                    ));
+                }
+                (input.title, input.content)
+            }
+        };
+
+        let note = Self::apply(
+            &mut tx,
+            user_id,
+            note_id,
+            title.as_deref(),
+            content.as_deref(),
+            expected_version,
+        )
+        .await?;
+
+        let NoteUpdateOutcome::Applied(note) =
+            Self::applied_or_stale(&mut tx, user_id, note_id, note).await?
+        else {
+            unreachable!("apply never produces conflicts");
+        };
+        tx.commit().await?;
+
+        Ok(note)
+    }
+
+    async fn get_revision(
+        conn: &mut PgConnection,
+        note_id: Uuid,
+        version: i64,
+    ) -> Result<Option<NoteRevision>> {
+        let revision = sqlx::query_as::<_, NoteRevision>(
+            "SELECT * FROM note_revisions WHERE note_id = $1 AND version = $2",
+        )
+        .bind(note_id)
+        .bind(version)
+        .fetch_optional(conn)
+        .await?;
+
+        Ok(revision)
+    }
+
+    /// Writes new note contents along with their revision and change log entry.
+    ///
+    /// Returns `None` when `expected_version` no longer matches.
+    async fn apply(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        note_id: Uuid,
+        title: Option<&str>,
+        content: Option<&str>,
+        expected_version: Option<i64>,
+    ) -> Result<Option<Note>> {
         let note = sqlx::query_as::<_, Note>(
             r#"
             UPDATE notes
             SET title = COALESCE($3, title),
                 content = COALESCE($4, content),
-                updated_at = NOW()
-            WHERE id = $1 AND user_id = $2
+                updated_at = NOW(),
+                version = version + 1
+            WHERE id = $1 AND user_id = $2 AND ($5::BIGINT IS NULL OR version = $5)
             RETURNING *
             "#,
         )
         .bind(note_id)
         .bind(user_id)
-        .bind(&input.title)
-        .bind(&input.content)
-        .fetch_one(pool)
+        .bind(title)
+        .bind(content)
+        .bind(expected_version)
+        .fetch_optional(&mut *conn)
         .await?;
 
-        Ok(note)
+        let Some(note) = note else {
+            return Ok(None);
+        };
+
+        Self::record_revision(conn, &note).await?;
+
+        SyncService::record(
+            conn,
+            user_id,
+            EntityType::Note,
+            note_id,
+            ChangeOperation::Upsert,
+        )
+        .await?;
+
+        Ok(Some(note))
     }
 
-    pub async fn delete(pool: &PgPool, user_id: Uuid, note_id: Uuid) -> Result<()> {
-        let result = sqlx::query("DELETE FROM notes WHERE id = $1 AND user_id = $2")
-            .bind(note_id)
-            .bind(user_id)
-            .execute(pool)
+    async fn applied_or_stale(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        note_id: Uuid,
+        note: Option<Note>,
+    ) -> Result<NoteUpdateOutcome> {
+        match note {
+            Some(note) => Ok(NoteUpdateOutcome::Applied(note)),
+            None => {
+                let current = Self::get_by_id(conn, user_id, note_id).await?;
+                Err(AppError::precondition_failed(&current, current.version))
+            }
+        }
+    }
+
+    async fn record_revision(conn: &mut PgConnection, note: &Note) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO note_revisions (note_id, version, title, content, created_at)
+            VALUES ($1, $2, $3, $4, NOW())
+            "#,
+        )
+        .bind(note.id)
+        .bind(note.version)
+        .bind(&note.title)
+        .bind(&note.content)
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query("DELETE FROM note_revisions WHERE note_id = $1 AND version <= $2")
+            .bind(note.id)
+            .bind(note.version - Self::REVISION_LIMIT)
+            .execute(&mut *conn)
             .await?;
 
+        Ok(())
+    }
+
+    pub async fn delete(
+        pool: &PgPool,
+        user_id: Uuid,
+        note_id: Uuid,
+        expected_version: Option<i64>,
+    ) -> Result<()> {
+        let mut tx = pool.begin().await?;
+        Self::delete_in_tx(&mut tx, user_id, note_id, expected_version).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Same as [`Self::delete`], within the caller's transaction.
+    pub async fn delete_in_tx(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        note_id: Uuid,
+        expected_version: Option<i64>,
+    ) -> Result<()> {
+        let result = sqlx::query(
+            "DELETE FROM notes WHERE id = $1 AND user_id = $2 AND ($3::BIGINT IS NULL OR version = $3)",
+        )
+        .bind(note_id)
+        .bind(user_id)
+        .bind(expected_version)
+        .execute(&mut *conn)
+        .await?;
+
         if result.rows_affected() == 0 {
-            return Err(AppError::NotFound("Note not found".to_string()));
+            let current = Self::get_by_id(&mut *conn, user_id, note_id).await?;
+            return Err(AppError::precondition_failed(&current, current.version));
         }
 
+        SyncService::record(
+            &mut *conn,
+            user_id,
+            EntityType::Note,
+            note_id,
+            ChangeOperation::Delete,
+        )
+        .await?;
+
         Ok(())
     }
 }
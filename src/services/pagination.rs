@@ -0,0 +1,283 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use sqlx::{Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+use crate::models::{
+    BookmarkSort, CategorySort, NoteSort, Page, PageCursor, SavedSearchSort, SearchResultSort,
+    SortOrder, TagSort,
+};
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 200;
+
+/// A column lists can be ordered by.
+pub(crate) trait SortKey: Copy {
+    /// Name used in the query string and in cursors.
+    fn name(self) -> &'static str;
+    /// Qualified column; interpolated into SQL, so it must be a constant.
+    fn column(self) -> &'static str;
+    fn is_timestamp(self) -> bool;
+
+    fn default_order(self) -> SortOrder {
+        if self.is_timestamp() {
+            SortOrder::Desc
+        } else {
+            SortOrder::Asc
+        }
+    }
+}
+
+/// Keyset pagination state for one list request.
+pub(crate) struct Keyset<S> {
+    sort: S,
+    order: SortOrder,
+    limit: i64,
+    after: Option<(CursorKey, Uuid)>,
+}
+
+/// A cursor's sort key, parsed for the column it is compared with.
+enum CursorKey {
+    Timestamp(DateTime<Utc>),
+    Text(String),
+}
+
+impl<S: SortKey> Keyset<S> {
+    pub fn new(
+        sort: S,
+        order: Option<SortOrder>,
+        limit: Option<i64>,
+        cursor: Option<&str>,
+    ) -> Result<Self> {
+        let order = order.unwrap_or_else(|| sort.default_order());
+        let after = cursor.map(PageCursor::decode).transpose()?;
+
+        if let Some(after) = &after
+            && (after.sort != sort.name() || after.order != order)
+        {
+            return Err(AppError::Validation(
+                "Cursor was issued for a different sort order".to_string(),
+            ));
+        }
+
+        let after = after
+            .map(|after| {
+                let key = if sort.is_timestamp() {
+                    DateTime::parse_from_rfc3339(&after.key)
+                        .map(|key| CursorKey::Timestamp(key.with_timezone(&Utc)))
+                        .map_err(|_| AppError::Validation("Invalid page cursor".to_string()))?
+                } else {
+                    CursorKey::Text(after.key)
+                };
+                Ok::<_, AppError>((key, after.id))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            sort,
+            order,
+            limit: limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE),
+            after,
+        })
+    }
+
+    pub fn sort(&self) -> S {
+        self.sort
+    }
+
+    /// Restricts the query to rows after the cursor, if any.
+    pub fn push_after(&self, query: &mut QueryBuilder<'_, Postgres>, id_column: &str) {
+        let Some((key, id)) = &self.after else {
+            return;
+        };
+
+        let comparison = match self.order {
+            SortOrder::Asc => ">",
+            SortOrder::Desc => "<",
+        };
+
+        query.push(format!(
+            " AND ({}, {id_column}) {comparison} (",
+            self.sort.column()
+        ));
+        match key {
+            CursorKey::Timestamp(key) => query.push_bind(*key),
+            CursorKey::Text(key) => query.push_bind(key.clone()).push("::text"),
+        };
+        query.push(", ").push_bind(*id).push(")");
+    }
+
+    /// Appends `ORDER BY` and fetches one row past the page to detect the end.
+    pub fn push_order_and_limit(&self, query: &mut QueryBuilder<'_, Postgres>, id_column: &str) {
+        let direction = match self.order {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        };
+
+        query
+            .push(format!(
+                " ORDER BY {} {direction}, {id_column} {direction} LIMIT ",
+                self.sort.column()
+            ))
+            .push_bind(self.limit + 1);
+    }
+
+    /// Trims the extra row and issues the cursor for the next page.
+    pub fn page<T>(
+        &self,
+        mut items: Vec<T>,
+        total: Option<i64>,
+        key: impl Fn(&T) -> (String, Uuid),
+    ) -> Page<T> {
+        let has_more = items.len() as i64 > self.limit;
+        items.truncate(self.limit as usize);
+
+        let next_cursor = items.last().filter(|_| has_more).map(|last| {
+            let (key, id) = key(last);
+            PageCursor {
+                sort: self.sort.name().to_string(),
+                order: self.order,
+                key,
+                id,
+            }
+            .encode()
+        });
+
+        Page {
+            items,
+            next_cursor,
+            total,
+        }
+    }
+}
+
+impl SortKey for BookmarkSort {
+    fn name(self) -> &'static str {
+        match self {
+            BookmarkSort::CreatedAt => "created_at",
+            BookmarkSort::UpdatedAt => "updated_at",
+            BookmarkSort::Title => "title",
+        }
+    }
+
+    fn column(self) -> &'static str {
+        match self {
+            BookmarkSort::CreatedAt => "b.created_at",
+            BookmarkSort::UpdatedAt => "b.updated_at",
+            BookmarkSort::Title => "b.title",
+        }
+    }
+
+    fn is_timestamp(self) -> bool {
+        self != BookmarkSort::Title
+    }
+}
+
+impl SortKey for NoteSort {
+    fn name(self) -> &'static str {
+        match self {
+            NoteSort::UpdatedAt => "updated_at",
+            NoteSort::CreatedAt => "created_at",
+            NoteSort::Title => "title",
+        }
+    }
+
+    fn column(self) -> &'static str {
+        match self {
+            NoteSort::UpdatedAt => "n.updated_at",
+            NoteSort::CreatedAt => "n.created_at",
+            NoteSort::Title => "n.title",
+        }
+    }
+
+    fn is_timestamp(self) -> bool {
+        self != NoteSort::Title
+    }
+}
+
+impl SortKey for TagSort {
+    fn name(self) -> &'static str {
+        match self {
+            TagSort::Name => "name",
+            TagSort::CreatedAt => "created_at",
+        }
+    }
+
+    fn column(self) -> &'static str {
+        match self {
+            TagSort::Name => "t.name",
+            TagSort::CreatedAt => "t.created_at",
+        }
+    }
+
+    fn is_timestamp(self) -> bool {
+        self == TagSort::CreatedAt
+    }
+}
+
+impl SortKey for CategorySort {
+    fn name(self) -> &'static str {
+        match self {
+            CategorySort::Name => "name",
+            CategorySort::CreatedAt => "created_at",
+        }
+    }
+
+    fn column(self) -> &'static str {
+        match self {
+            CategorySort::Name => "c.name",
+            CategorySort::CreatedAt => "c.created_at",
+        }
+    }
+
+    fn is_timestamp(self) -> bool {
+        self == CategorySort::CreatedAt
+    }
+}
+
+impl SortKey for SavedSearchSort {
+    fn name(self) -> &'static str {
+        match self {
+            SavedSearchSort::Name => "name",
+            SavedSearchSort::CreatedAt => "created_at",
+        }
+    }
+
+    fn column(self) -> &'static str {
+        match self {
+            SavedSearchSort::Name => "s.name",
+            SavedSearchSort::CreatedAt => "s.created_at",
+        }
+    }
+
+    fn is_timestamp(self) -> bool {
+        self == SavedSearchSort::CreatedAt
+    }
+}
+
+impl SortKey for SearchResultSort {
+    fn name(self) -> &'static str {
+        match self {
+            SearchResultSort::UpdatedAt => "updated_at",
+            SearchResultSort::CreatedAt => "created_at",
+            SearchResultSort::Title => "title",
+        }
+    }
+
+    fn column(self) -> &'static str {
+        match self {
+            SearchResultSort::UpdatedAt => "results.updated_at",
+            SearchResultSort::CreatedAt => "results.created_at",
+            SearchResultSort::Title => "results.title",
+        }
+    }
+
+    fn is_timestamp(self) -> bool {
+        self != SearchResultSort::Title
+    }
+}
+
+/// Cursor key for timestamp columns, at the database's microsecond precision.
+pub(crate) fn timestamp_key(timestamp: &DateTime<Utc>) -> String {
+    timestamp.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
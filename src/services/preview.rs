@@ -0,0 +1,237 @@
+use sqlx::{FromRow, PgConnection, PgPool};
+use tokio::task::JoinSet;
+use uuid::Uuid;
+
+use crate::blob::BlobStorage;
+use crate::error::{AppError, Result};
+use crate::models::{BookmarkWithTags, ChangeOperation, EntityType};
+use crate::preview::{FetchedPage, PreviewFetcher};
+use crate::services::{ArchiveService, BookmarkService, SyncService};
+
+/// Fetch attempts before a bookmark's preview is marked `failed`.
+const MAX_ATTEMPTS: i32 = 3;
+/// Delay before retrying a failed fetch, doubled after every attempt.
+const RETRY_DELAY_SECS: f64 = 300.0;
+
+/// A bookmark claimed for a preview fetch.
+#[derive(Debug, FromRow)]
+struct PreviewJob {
+    id: Uuid,
+    user_id: Uuid,
+    url: String,
+    #[sqlx(rename = "preview_attempts")]
+    attempts: i32,
+}
+
+pub struct PreviewService;
+
+impl PreviewService {
+    /// Fetches previews for up to `limit` bookmarks that are due, concurrently,
+    /// and archives their pages.
+    ///
+    /// Returns the number of bookmarks claimed. A claim pushes the bookmark's
+    /// next attempt back, so a fetch cut short by a crash is retried later.
+    pub async fn process_due(
+        pool: &PgPool,
+        fetcher: &PreviewFetcher,
+        blobs: &BlobStorage,
+        limit: i64,
+    ) -> Result<usize> {
+        let jobs = sqlx::query_as::<_, PreviewJob>(
+            r#"
+            UPDATE bookmarks
+            SET preview_attempts = preview_attempts + 1,
+                preview_next_attempt_at =
+                    NOW() + make_interval(secs => $2 * power(2, preview_attempts))
+            WHERE id IN (
+                SELECT id FROM bookmarks
+                WHERE preview_status = 'pending' AND preview_next_attempt_at <= NOW()
+                ORDER BY preview_next_attempt_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, user_id, url, preview_attempts
+            "#,
+        )
+        .bind(limit)
+        .bind(RETRY_DELAY_SECS)
+        .fetch_all(pool)
+        .await?;
+
+        let claimed = jobs.len();
+        let mut fetches = JoinSet::new();
+        for job in jobs {
+            let fetcher = fetcher.clone();
+            fetches.spawn(async move {
+                let page = fetcher.fetch_page(&job.url).await;
+                (job, page)
+            });
+        }
+
+        while let Some(fetched) = fetches.join_next().await {
+            let Ok((job, page)) = fetched else {
+                continue;
+            };
+
+            match page {
+                Ok(page) => Self::store(pool, blobs, &job, &page).await?,
+                Err(e) => {
+                    tracing::debug!(bookmark_id = %job.id, error = %e, "Preview fetch failed");
+                    if job.attempts >= MAX_ATTEMPTS {
+                        Self::mark_failed(pool, &job).await?;
+                    }
+                }
+            }
+        }
+
+        Ok(claimed)
+    }
+
+    /// Fetches a bookmark's preview and archive right away, replacing the stored ones.
+    ///
+    /// Fetch errors are returned and leave the stored preview and archive untouched.
+    pub async fn refresh(
+        pool: &PgPool,
+        fetcher: &PreviewFetcher,
+        blobs: &BlobStorage,
+        user_id: Uuid,
+        bookmark_id: Uuid,
+    ) -> Result<BookmarkWithTags> {
+        let bookmark = BookmarkService::get_by_id(pool, user_id, bookmark_id).await?;
+        let page = fetcher.fetch_page(&bookmark.url).await?;
+
+        let job = PreviewJob {
+            id: bookmark.id,
+            user_id,
+            url: bookmark.url,
+            attempts: 0,
+        };
+        Self::store(pool, blobs, &job, &page).await?;
+
+        BookmarkService::get_with_tags(pool, user_id, bookmark_id).await
+    }
+
+    /// Clears a bookmark's preview and archive and queues it to be fetched again, e.g. after its URL changed.
+    pub async fn reset(conn: &mut PgConnection, bookmark_id: Uuid) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE bookmarks
+            SET preview_title = NULL,
+                preview_description = NULL,
+                preview_image = NULL,
+                favicon = NULL,
+                preview_status = 'pending',
+                preview_fetched_at = NULL,
+                preview_attempts = 0,
+                preview_next_attempt_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(bookmark_id)
+        .execute(&mut *conn)
+        .await?;
+
+        ArchiveService::delete_in_tx(conn, bookmark_id).await
+    }
+
+    /// Saves a fetched page's preview and archive, unless the bookmark's URL
+    /// changed in the meantime.
+    ///
+    /// A page that would take the user over their storage quota is not
+    /// archived, but its preview is still saved.
+    async fn store(
+        pool: &PgPool,
+        blobs: &BlobStorage,
+        job: &PreviewJob,
+        page: &FetchedPage,
+    ) -> Result<()> {
+        let preview = page.preview();
+        let content_hash = ArchiveService::upload(pool, blobs, page).await?;
+        let mut tx = pool.begin().await?;
+
+        let result = sqlx::query(
+            r#"
+            UPDATE bookmarks
+            SET preview_title = $3,
+                preview_description = $4,
+                preview_image = $5,
+                favicon = $6,
+                preview_status = 'ready',
+                preview_fetched_at = NOW(),
+                version = version + 1
+            WHERE id = $1 AND url = $2
+            "#,
+        )
+        .bind(job.id)
+        .bind(&job.url)
+        .bind(&preview.title)
+        .bind(&preview.description)
+        .bind(&preview.image)
+        .bind(&preview.favicon)
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() > 0 {
+            if let Some(content_hash) = content_hash {
+                let archived = ArchiveService::store_in_tx(
+                    &mut tx,
+                    blobs,
+                    job.user_id,
+                    job.id,
+                    page,
+                    &content_hash,
+                )
+                .await;
+
+                match archived {
+                    Err(AppError::QuotaExceeded(e)) => {
+                        tracing::info!(bookmark_id = %job.id, error = %e, "Page not archived");
+                    }
+                    archived => archived?,
+                }
+            }
+            Self::record(&mut tx, job).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn mark_failed(pool: &PgPool, job: &PreviewJob) -> Result<()> {
+        let mut tx = pool.begin().await?;
+
+        let result = sqlx::query(
+            r#"
+            UPDATE bookmarks
+            SET preview_status = 'failed', version = version + 1
+            WHERE id = $1 AND url = $2 AND preview_status = 'pending'
+                AND preview_attempts >= $3
+            "#,
+        )
+        .bind(job.id)
+        .bind(&job.url)
+        .bind(MAX_ATTEMPTS)
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() > 0 {
+            Self::record(&mut tx, job).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn record(conn: &mut PgConnection, job: &PreviewJob) -> Result<()> {
+        SyncService::record(
+            conn,
+            job.user_id,
+            EntityType::Bookmark,
+            job.id,
+            ChangeOperation::Upsert,
+        )
+        .await?;
+
+        Ok(())
+    }
+}
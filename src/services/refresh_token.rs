@@ -0,0 +1,184 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{FromRow, PgConnection, PgExecutor, PgPool};
+use uuid::Uuid;
+
+use crate::auth::{generate_token, hash_token};
+use crate::error::{AppError, Result};
+
+/// A refresh token exchanged for new tokens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RotatedToken {
+    pub user_id: Uuid,
+    pub device_id: Uuid,
+    /// Successor to present on the next refresh
+    pub refresh_token: String,
+}
+
+#[derive(Debug, FromRow)]
+struct StoredToken {
+    user_id: Uuid,
+    device_id: Uuid,
+    family_id: Uuid,
+    expires_at: DateTime<Utc>,
+    used_at: Option<DateTime<Utc>>,
+    revoked_at: Option<DateTime<Utc>>,
+    device_revoked: bool,
+}
+
+pub struct RefreshTokenService;
+
+impl RefreshTokenService {
+    /// Issues the first refresh token of a new family, on sign-in.
+    pub async fn issue(
+        pool: &PgPool,
+        user_id: Uuid,
+        device_id: Uuid,
+        ttl: Duration,
+    ) -> Result<String> {
+        let token = generate_token();
+
+        Self::insert(pool, user_id, device_id, Uuid::new_v4(), &token, ttl).await?;
+
+        Ok(token)
+    }
+
+    /// Uses up a refresh token and issues its successor in the same family.
+    ///
+    /// Presenting a token that was already used means it leaked, or its
+    /// successor did: the whole family is revoked so neither the client nor
+    /// whoever copied the token can refresh again.
+    pub async fn rotate(pool: &PgPool, token: &str, ttl: Duration) -> Result<RotatedToken> {
+        let mut tx = pool.begin().await?;
+
+        let stored = sqlx::query_as::<_, StoredToken>(
+            r#"
+            SELECT t.user_id, t.device_id, t.family_id, t.expires_at, t.used_at, t.revoked_at,
+                   d.revoked_at IS NOT NULL AS device_revoked
+            FROM refresh_tokens t
+            JOIN devices d ON d.id = t.device_id
+            WHERE t.token_hash = $1
+            FOR UPDATE OF t
+            "#,
+        )
+        .bind(hash_token(token))
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(AppError::Unauthorized)?;
+
+        if stored.used_at.is_some() && stored.revoked_at.is_none() {
+            Self::revoke_family(&mut tx, stored.family_id).await?;
+            tx.commit().await?;
+
+            tracing::warn!(
+                user_id = %stored.user_id,
+                device_id = %stored.device_id,
+                family_id = %stored.family_id,
+                "Refresh token reused; token family revoked"
+            );
+            return Err(AppError::Unauthorized);
+        }
+
+        if stored.used_at.is_some()
+            || stored.revoked_at.is_some()
+            || stored.device_revoked
+            || stored.expires_at <= Utc::now()
+        {
+            return Err(AppError::Unauthorized);
+        }
+
+        sqlx::query("UPDATE refresh_tokens SET used_at = NOW() WHERE token_hash = $1")
+            .bind(hash_token(token))
+            .execute(&mut *tx)
+            .await?;
+
+        let refresh_token = generate_token();
+        Self::insert(
+            &mut *tx,
+            stored.user_id,
+            stored.device_id,
+            stored.family_id,
+            &refresh_token,
+            ttl,
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(RotatedToken {
+            user_id: stored.user_id,
+            device_id: stored.device_id,
+            refresh_token,
+        })
+    }
+
+    /// Revokes the refresh tokens issued to a device, on logout.
+    pub async fn revoke_device_in_tx(conn: &mut PgConnection, device_id: Uuid) -> Result<()> {
+        sqlx::query(
+            "UPDATE refresh_tokens SET revoked_at = NOW() WHERE device_id = $1 AND revoked_at IS NULL",
+        )
+        .bind(device_id)
+        .execute(conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Revokes every refresh token of the user.
+    pub async fn revoke_user_in_tx(conn: &mut PgConnection, user_id: Uuid) -> Result<()> {
+        sqlx::query(
+            "UPDATE refresh_tokens SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL",
+        )
+        .bind(user_id)
+        .execute(conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Removes tokens that expired before `expired_before`.
+    pub async fn purge_expired(pool: &PgPool, expired_before: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM refresh_tokens WHERE expires_at < $1")
+            .bind(expired_before)
+            .execute(pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn insert(
+        executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        device_id: Uuid,
+        family_id: Uuid,
+        token: &str,
+        ttl: Duration,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO refresh_tokens (id, user_id, device_id, family_id, token_hash, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .bind(device_id)
+        .bind(family_id)
+        .bind(hash_token(token))
+        .bind(Utc::now() + ttl)
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn revoke_family(conn: &mut PgConnection, family_id: Uuid) -> Result<()> {
+        sqlx::query(
+            "UPDATE refresh_tokens SET revoked_at = NOW() WHERE family_id = $1 AND revoked_at IS NULL",
+        )
+        .bind(family_id)
+        .execute(conn)
+        .await?;
+
+        Ok(())
+    }
+}
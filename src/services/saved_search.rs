@@ -0,0 +1,263 @@
+use sqlx::{PgConnection, PgExecutor, PgPool, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+use crate::models::{
+    ChangeOperation, CreateSavedSearch, EntityType, Page, SavedSearch, SavedSearchListQuery,
+    SavedSearchResultsQuery, SavedSearchSort, SearchResult, UpdateSavedSearch,
+};
+use crate::search;
+use crate::services::pagination::{Keyset, timestamp_key};
+use crate::services::{SearchService, SyncService};
+
+pub struct SavedSearchService;
+
+impl SavedSearchService {
+    pub async fn create(
+        pool: &PgPool,
+        user_id: Uuid,
+        input: CreateSavedSearch,
+    ) -> Result<SavedSearch> {
+        search::parse(&input.query)?;
+
+        let id = input.id.unwrap_or_else(Uuid::new_v4);
+        let mut tx = pool.begin().await?;
+        Self::ensure_name_available(&mut tx, user_id, &input.name, None).await?;
+
+        let saved_search = sqlx::query_as::<_, SavedSearch>(
+            r#"
+            INSERT INTO saved_searches (id, user_id, name, query, sort, sort_order, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, NOW(), NOW())
+            ON CONFLICT (id) DO NOTHING
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(&input.name)
+        .bind(&input.query)
+        .bind(input.sort.unwrap_or_default())
+        .bind(input.order)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::Conflict(format!("Saved search id {id} is already in use")))?;
+
+        SyncService::record(
+            &mut tx,
+            user_id,
+            EntityType::SavedSearch,
+            saved_search.id,
+            ChangeOperation::Upsert,
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(saved_search)
+    }
+
+    pub async fn get_by_id(
+        executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        saved_search_id: Uuid,
+    ) -> Result<SavedSearch> {
+        sqlx::query_as::<_, SavedSearch>(
+            "SELECT * FROM saved_searches WHERE id = $1 AND user_id = $2",
+        )
+        .bind(saved_search_id)
+        .bind(user_id)
+        .fetch_optional(executor)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Saved search not found".to_string()))
+    }
+
+    pub async fn list(
+        pool: &PgPool,
+        user_id: Uuid,
+        query: SavedSearchListQuery,
+    ) -> Result<Page<SavedSearch>> {
+        let keyset = Keyset::new(
+            query.sort.unwrap_or_default(),
+            query.order,
+            query.limit,
+            query.cursor.as_deref(),
+        )?;
+
+        let mut select =
+            QueryBuilder::<Postgres>::new("SELECT s.* FROM saved_searches s WHERE s.user_id = ");
+        select.push_bind(user_id);
+        keyset.push_after(&mut select, "s.id");
+        keyset.push_order_and_limit(&mut select, "s.id");
+        let saved_searches = select
+            .build_query_as::<SavedSearch>()
+            .fetch_all(pool)
+            .await?;
+
+        let total = if query.include_total {
+            Some(
+                sqlx::query_scalar::<_, i64>(
+                    "SELECT COUNT(*) FROM saved_searches WHERE user_id = $1",
+                )
+                .bind(user_id)
+                .fetch_one(pool)
+                .await?,
+            )
+        } else {
+            None
+        };
+
+        let sort = keyset.sort();
+        Ok(keyset.page(saved_searches, total, |saved_search| {
+            let key = match sort {
+                SavedSearchSort::Name => saved_search.name.clone(),
+                SavedSearchSort::CreatedAt => timestamp_key(&saved_search.created_at),
+            };
+            (key, saved_search.id)
+        }))
+    }
+
+    /// Evaluates the saved query against the user's current bookmarks and notes.
+    pub async fn results(
+        pool: &PgPool,
+        user_id: Uuid,
+        saved_search_id: Uuid,
+        query: SavedSearchResultsQuery,
+    ) -> Result<Page<SearchResult>> {
+        let saved_search = Self::get_by_id(pool, user_id, saved_search_id).await?;
+        let expr = search::parse(&saved_search.query)?;
+
+        let keyset = Keyset::new(
+            saved_search.sort,
+            saved_search.order,
+            query.limit,
+            query.cursor.as_deref(),
+        )?;
+
+        SearchService::page(pool, user_id, &expr, &keyset).await
+    }
+
+    pub async fn update(
+        pool: &PgPool,
+        user_id: Uuid,
+        saved_search_id: Uuid,
+        input: UpdateSavedSearch,
+        expected_version: Option<i64>,
+    ) -> Result<SavedSearch> {
+        if let Some(query) = &input.query {
+            search::parse(query)?;
+        }
+
+        let mut tx = pool.begin().await?;
+        Self::get_by_id(&mut *tx, user_id, saved_search_id).await?;
+
+        if let Some(name) = &input.name {
+            Self::ensure_name_available(&mut tx, user_id, name, Some(saved_search_id)).await?;
+        }
+
+        let saved_search = sqlx::query_as::<_, SavedSearch>(
+            r#"
+            UPDATE saved_searches
+            SET name = COALESCE($3, name),
+                query = COALESCE($4, query),
+                sort = COALESCE($5, sort),
+                sort_order = COALESCE($6, sort_order),
+                updated_at = NOW(),
+                version = version + 1
+            WHERE id = $1 AND user_id = $2 AND ($7::BIGINT IS NULL OR version = $7)
+            RETURNING *
+            "#,
+        )
+        .bind(saved_search_id)
+        .bind(user_id)
+        .bind(&input.name)
+        .bind(&input.query)
+        .bind(input.sort)
+        .bind(input.order)
+        .bind(expected_version)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(saved_search) = saved_search else {
+            let current = Self::get_by_id(&mut *tx, user_id, saved_search_id).await?;
+            return Err(AppError::precondition_failed(&current, current.version));
+        };
+
+        SyncService::record(
+            &mut tx,
+            user_id,
+            EntityType::SavedSearch,
+            saved_search_id,
+            ChangeOperation::Upsert,
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(saved_search)
+    }
+
+    pub async fn delete(
+        pool: &PgPool,
+        user_id: Uuid,
+        saved_search_id: Uuid,
+        expected_version: Option<i64>,
+    ) -> Result<()> {
+        let mut tx = pool.begin().await?;
+
+        let result = sqlx::query(
+            "DELETE FROM saved_searches WHERE id = $1 AND user_id = $2 AND ($3::BIGINT IS NULL OR version = $3)",
+        )
+        .bind(saved_search_id)
+        .bind(user_id)
+        .bind(expected_version)
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            let current = Self::get_by_id(&mut *tx, user_id, saved_search_id).await?;
+            return Err(AppError::precondition_failed(&current, current.version));
+        }
+
+        SyncService::record(
+            &mut tx,
+            user_id,
+            EntityType::SavedSearch,
+            saved_search_id,
+            ChangeOperation::Delete,
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn ensure_name_available(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        name: &str,
+        except_id: Option<Uuid>,
+    ) -> Result<()> {
+        let taken = sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM saved_searches
+                WHERE user_id = $1 AND name = $2 AND ($3::UUID IS NULL OR id <> $3)
+            )
+            "#,
+        )
+        .bind(user_id)
+        .bind(name)
+        .bind(except_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        if taken {
+            return Err(AppError::Conflict(
+                "A saved search with this name already exists".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
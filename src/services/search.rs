@@ -0,0 +1,232 @@
+use sqlx::{PgPool, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+use crate::models::{
+    Page, SearchQuery, SearchResult, SearchResultSort, SearchSuggestion, SearchType, SuggestQuery,
+};
+use crate::search::{self, SearchExpr, SearchTarget};
+use crate::services::pagination::{Keyset, timestamp_key};
+
+const DEFAULT_LIMIT: i64 = 20;
+const MAX_LIMIT: i64 = 100;
+
+const DEFAULT_SUGGESTIONS: i64 = 10;
+const MAX_SUGGESTIONS: i64 = 20;
+const MAX_PREFIX_LENGTH: usize = 100;
+
+/// Minimum `word_similarity` for a fuzzy match; lower than the `pg_trgm`
+/// default of 0.6 so that a transposed or missing letter still matches.
+const FUZZY_THRESHOLD: &str = "0.4";
+
+const HEADLINE_OPTIONS: &str = "'StartSel=<mark>, StopSel=</mark>, MaxWords=35, MinWords=15'";
+
+pub struct SearchService;
+
+impl SearchService {
+    /// Ranks the user's bookmarks and notes against a query in the search language.
+    ///
+    /// Free text is matched against the full-text index, archived pages
+    /// included, and in fuzzy mode also by trigram similarity to titles and
+    /// URLs; qualifiers such as `tag:` or `before:` narrow the results.
+    /// Without free text, results are ordered by most recent update.
+    pub async fn search(
+        pool: &PgPool,
+        user_id: Uuid,
+        query: &SearchQuery,
+    ) -> Result<Vec<SearchResult>> {
+        let expr = search::parse(&query.q)?;
+        let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+        let mut builder = matching(&expr, user_id, query.search_type, query.fuzzy);
+        builder
+            .push(" ORDER BY rank DESC, updated_at DESC, id LIMIT ")
+            .push_bind(limit);
+
+        // The similarity operators read their threshold from the session
+        let mut tx = pool.begin().await?;
+        if query.fuzzy {
+            sqlx::query("SELECT set_config('pg_trgm.word_similarity_threshold', $1, true)")
+                .bind(FUZZY_THRESHOLD)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        let results = builder
+            .build_query_as::<SearchResult>()
+            .fetch_all(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(results)
+    }
+
+    /// One page of everything matching `expr`, in a fixed order rather than by rank.
+    pub(crate) async fn page(
+        pool: &PgPool,
+        user_id: Uuid,
+        expr: &SearchExpr,
+        keyset: &Keyset<SearchResultSort>,
+    ) -> Result<Page<SearchResult>> {
+        let mut builder = matching(expr, user_id, None, false);
+        builder.push(" WHERE TRUE");
+        keyset.push_after(&mut builder, "results.id");
+        keyset.push_order_and_limit(&mut builder, "results.id");
+
+        let results = builder
+            .build_query_as::<SearchResult>()
+            .fetch_all(pool)
+            .await?;
+
+        let sort = keyset.sort();
+        Ok(keyset.page(results, None, |result| {
+            let key = match sort {
+                SearchResultSort::UpdatedAt => timestamp_key(&result.updated_at),
+                SearchResultSort::CreatedAt => timestamp_key(&result.created_at),
+                SearchResultSort::Title => result.title.clone(),
+            };
+            (key, result.id)
+        }))
+    }
+
+    /// Titles containing `prefix`, those starting with it first, for autocomplete.
+    ///
+    /// Bookmarks also match on their URL.
+    pub async fn suggest(
+        pool: &PgPool,
+        user_id: Uuid,
+        query: &SuggestQuery,
+    ) -> Result<Vec<SearchSuggestion>> {
+        let prefix = query.prefix.trim();
+        if prefix.is_empty() || prefix.chars().count() > MAX_PREFIX_LENGTH {
+            return Err(AppError::Validation(format!(
+                "Prefix must be 1 to {MAX_PREFIX_LENGTH} characters"
+            )));
+        }
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_SUGGESTIONS)
+            .clamp(1, MAX_SUGGESTIONS);
+        let escaped = escape_like(prefix);
+
+        let suggestions = sqlx::query_as::<_, SearchSuggestion>(
+            r#"
+            SELECT entity_type, id, title, url FROM (
+                SELECT 'bookmark'::VARCHAR AS entity_type, b.id, b.title, b.url, b.updated_at,
+                       b.title ILIKE $3 AS starts_with,
+                       GREATEST(word_similarity($2, b.title), word_similarity($2, b.url)) AS score
+                FROM bookmarks b
+                WHERE b.user_id = $1 AND (b.title ILIKE $4 OR b.url ILIKE $4)
+                UNION ALL
+                SELECT 'note'::VARCHAR, n.id, n.title, NULL, n.updated_at,
+                       n.title ILIKE $3, word_similarity($2, n.title)
+                FROM notes n
+                WHERE n.user_id = $1 AND n.title ILIKE $4
+            ) suggestions
+            ORDER BY starts_with DESC, score DESC, updated_at DESC, id
+            LIMIT $5
+            "#,
+        )
+        .bind(user_id)
+        .bind(prefix)
+        .bind(format!("{escaped}%"))
+        .bind(format!("%{escaped}%"))
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(suggestions)
+    }
+}
+
+/// Selects the user's bookmarks and notes matching `expr` as a `results` subquery.
+fn matching<'a>(
+    expr: &SearchExpr,
+    user_id: Uuid,
+    search_type: Option<SearchType>,
+    fuzzy: bool,
+) -> QueryBuilder<'a, Postgres> {
+    let include = |t| search_type.is_none_or(|search_type| search_type == t);
+    let excluded = expr.excluded_text_query();
+    let fuzzy_text = if fuzzy { expr.fuzzy_text() } else { None };
+
+    let mut builder =
+        QueryBuilder::<Postgres>::new("WITH query AS (SELECT websearch_to_tsquery('english', ");
+    builder
+        .push_bind(expr.text_query())
+        .push(") AS tsq, websearch_to_tsquery('english', ")
+        .push_bind(excluded)
+        .push(") AS excluded, ")
+        .push_bind(fuzzy_text)
+        .push("::TEXT AS fuzzy) SELECT * FROM (");
+
+    builder.push(format!(
+        "SELECT 'bookmark'::VARCHAR AS entity_type, b.id, b.title, b.url, \
+         {} AS snippet, {} + COALESCE(ts_rank_cd(a.search_vector, q.tsq), 0) AS rank, \
+         b.created_at, b.updated_at \
+         FROM bookmarks b LEFT JOIN bookmark_archives a ON a.bookmark_id = b.id, query q \
+         WHERE b.user_id = ",
+        // Quote the archived page when that is the only place the terms occur
+        snippet(
+            "CASE WHEN a.search_vector @@ q.tsq AND NOT b.search_vector @@ q.tsq \
+             THEN left(a.text, 20000) ELSE COALESCE(NULLIF(b.description, ''), b.title) END"
+        ),
+        rank(
+            "b",
+            "GREATEST(word_similarity(q.fuzzy, b.title), word_similarity(q.fuzzy, b.url))"
+        ),
+    ));
+    builder
+        .push_bind(user_id)
+        .push(
+            " AND (q.tsq IS NULL OR b.search_vector @@ q.tsq OR a.search_vector @@ q.tsq \
+             OR q.fuzzy <% b.title OR q.fuzzy <% b.url) \
+             AND (q.excluded IS NULL OR NOT (b.search_vector @@ q.excluded \
+             OR COALESCE(a.search_vector @@ q.excluded, FALSE))) AND ",
+        )
+        .push_bind(include(SearchType::Bookmark));
+    expr.push_filters(&mut builder, SearchTarget::Bookmark, user_id);
+
+    builder.push(format!(
+        " UNION ALL SELECT 'note'::VARCHAR, n.id, n.title, NULL, {}, {}, n.created_at, n.updated_at \
+         FROM notes n, query q WHERE n.user_id = ",
+        snippet("COALESCE(NULLIF(n.content, ''), n.title)"),
+        rank("n", "word_similarity(q.fuzzy, n.title)"),
+    ));
+    builder
+        .push_bind(user_id)
+        .push(
+            " AND (q.tsq IS NULL OR n.search_vector @@ q.tsq OR q.fuzzy <% n.title) \
+             AND (q.excluded IS NULL OR NOT n.search_vector @@ q.excluded) AND ",
+        )
+        .push_bind(include(SearchType::Note));
+    expr.push_filters(&mut builder, SearchTarget::Note, user_id);
+
+    builder.push(") results");
+    builder
+}
+
+/// Escaped excerpt of `text` with matches highlighted, or its start without free text.
+fn snippet(text: &str) -> String {
+    format!(
+        "COALESCE(ts_headline('english', search_escape_html({text}), q.tsq, {HEADLINE_OPTIONS}), \
+         search_escape_html(left({text}, 200)))"
+    )
+}
+
+/// Full-text rank plus, in fuzzy mode, the best trigram similarity.
+fn rank(alias: &str, similarity: &str) -> String {
+    format!("COALESCE(ts_rank_cd({alias}.search_vector, q.tsq), 0) + COALESCE({similarity}, 0)")
+}
+
+/// Escapes `LIKE` wildcards so user input matches literally.
+fn escape_like(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
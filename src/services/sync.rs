@@ -0,0 +1,339 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+use crate::models::{
+    Bookmark, BookmarkTagLinks, Category, ChangeEvent, ChangeOperation, EntityType, Note,
+    SavedSearch, SyncChange, SyncChanges, SyncCursor, Tag, Tombstone,
+};
+
+const DEFAULT_LIMIT: i64 = 500;
+const MAX_LIMIT: i64 = 1000;
+
+pub struct SyncService;
+
+impl SyncService {
+    /// Appends an entry to the user's change log, leaving a tombstone for
+    /// deletes and clearing it when the entity is recreated.
+    ///
+    /// Must run inside the transaction that performs the change: the per-user
+    /// sequence row stays locked until commit, so sequence numbers become
+    /// visible to readers in order.
+    pub async fn record(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        entity_type: EntityType,
+        entity_id: Uuid,
+        operation: ChangeOperation,
+    ) -> Result<i64> {
+        let seq = sqlx::query_scalar::<_, i64>(
+            r#"
+            WITH next AS (
+                INSERT INTO sync_sequences (user_id, last_seq)
+                VALUES ($1, 1)
+                ON CONFLICT (user_id) DO UPDATE SET last_seq = sync_sequences.last_seq + 1
+                RETURNING last_seq
+            )
+            INSERT INTO sync_changes (user_id, seq, entity_type, entity_id, operation, changed_at)
+            SELECT $1, last_seq, $2, $3, $4, NOW() FROM next
+            RETURNING seq
+            "#,
+        )
+        .bind(user_id)
+        .bind(entity_type)
+        .bind(entity_id)
+        .bind(operation)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        let tombstone = match operation {
+            ChangeOperation::Delete => {
+                r#"
+                INSERT INTO tombstones (user_id, entity_type, entity_id, deleted_at)
+                VALUES ($1, $2, $3, NOW())
+                ON CONFLICT (user_id, entity_type, entity_id) DO UPDATE SET deleted_at = NOW()
+                "#
+            }
+            // A client-chosen id can bring a deleted entity back
+            ChangeOperation::Upsert => {
+                "DELETE FROM tombstones WHERE user_id = $1 AND entity_type = $2 AND entity_id = $3"
+            }
+        };
+        sqlx::query(tombstone)
+            .bind(user_id)
+            .bind(entity_type)
+            .bind(entity_id)
+            .execute(&mut *conn)
+            .await?;
+
+        Ok(seq)
+    }
+
+    pub async fn changes_since(
+        pool: &PgPool,
+        user_id: Uuid,
+        since: SyncCursor,
+        limit: Option<i64>,
+    ) -> Result<SyncChanges> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+        Self::ensure_cursor_retained(pool, user_id, since).await?;
+
+        let changes = sqlx::query_as::<_, SyncChange>(
+            r#"
+            SELECT * FROM sync_changes
+            WHERE user_id = $1 AND seq > $2
+            ORDER BY seq ASC
+            LIMIT $3
+            "#,
+        )
+        .bind(user_id)
+        .bind(since.0)
+        .bind(limit + 1)
+        .fetch_all(pool)
+        .await?;
+
+        let has_more = changes.len() as i64 > limit;
+        let changes = &changes[..changes.len().min(limit as usize)];
+        let cursor = changes.last().map_or(since, |c| SyncCursor(c.seq));
+
+        // Only the latest operation per entity matters to the client
+        let mut latest: HashMap<(EntityType, Uuid), ChangeOperation> = HashMap::new();
+        for change in changes {
+            latest.insert((change.entity_type, change.entity_id), change.operation);
+        }
+
+        let mut upserted: HashMap<EntityType, Vec<Uuid>> = HashMap::new();
+        let mut deleted_ids = Vec::new();
+        for ((entity_type, id), operation) in &latest {
+            match operation {
+                ChangeOperation::Upsert => upserted.entry(*entity_type).or_default().push(*id),
+                ChangeOperation::Delete => deleted_ids.push(*id),
+            }
+        }
+
+        let ids = |entity_type| upserted.get(&entity_type).cloned().unwrap_or_default();
+
+        let bookmarks = sqlx::query_as::<_, Bookmark>(
+            "SELECT * FROM bookmarks WHERE user_id = $1 AND id = ANY($2) ORDER BY updated_at ASC",
+        )
+        .bind(user_id)
+        .bind(ids(EntityType::Bookmark))
+        .fetch_all(pool)
+        .await?;
+
+        let notes = sqlx::query_as::<_, Note>(
+            "SELECT * FROM notes WHERE user_id = $1 AND id = ANY($2) ORDER BY updated_at ASC",
+        )
+        .bind(user_id)
+        .bind(ids(EntityType::Note))
+        .fetch_all(pool)
+        .await?;
+
+        let tags = sqlx::query_as::<_, Tag>(
+            "SELECT * FROM tags WHERE user_id = $1 AND id = ANY($2) ORDER BY created_at ASC",
+        )
+        .bind(user_id)
+        .bind(ids(EntityType::Tag))
+        .fetch_all(pool)
+        .await?;
+
+        let categories = sqlx::query_as::<_, Category>(
+            "SELECT * FROM categories WHERE user_id = $1 AND id = ANY($2) ORDER BY created_at ASC",
+        )
+        .bind(user_id)
+        .bind(ids(EntityType::Category))
+        .fetch_all(pool)
+        .await?;
+
+        let saved_searches = sqlx::query_as::<_, SavedSearch>(
+            "SELECT * FROM saved_searches WHERE user_id = $1 AND id = ANY($2) ORDER BY created_at ASC",
+        )
+        .bind(user_id)
+        .bind(ids(EntityType::SavedSearch))
+        .fetch_all(pool)
+        .await?;
+
+        let bookmark_tags = sqlx::query_as::<_, (Uuid, Vec<Uuid>)>(
+            r#"
+            SELECT b.id,
+                   COALESCE(ARRAY_AGG(bt.tag_id) FILTER (WHERE bt.tag_id IS NOT NULL), '{}')
+            FROM bookmarks b
+            LEFT JOIN bookmark_tags bt ON bt.bookmark_id = b.id
+            WHERE b.user_id = $1 AND b.id = ANY($2)
+            GROUP BY b.id
+            "#,
+        )
+        .bind(user_id)
+        .bind(ids(EntityType::BookmarkTags))
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|(bookmark_id, tag_ids)| BookmarkTagLinks {
+            bookmark_id,
+            tag_ids,
+        })
+        .collect();
+
+        let deleted = sqlx::query_as::<_, Tombstone>(
+            "SELECT * FROM tombstones WHERE user_id = $1 AND entity_id = ANY($2) ORDER BY deleted_at ASC",
+        )
+        .bind(user_id)
+        .bind(&deleted_ids)
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .filter(|t| latest.get(&(t.entity_type, t.entity_id)) == Some(&ChangeOperation::Delete))
+        .collect();
+
+        Ok(SyncChanges {
+            bookmarks,
+            notes,
+            tags,
+            categories,
+            saved_searches,
+            bookmark_tags,
+            deleted,
+            cursor: cursor.encode(),
+            has_more,
+        })
+    }
+
+    /// Cursor at the head of the user's change log.
+    pub async fn current_cursor(pool: &PgPool, user_id: Uuid) -> Result<SyncCursor> {
+        let last_seq =
+            sqlx::query_scalar::<_, i64>("SELECT last_seq FROM sync_sequences WHERE user_id = $1")
+                .bind(user_id)
+                .fetch_optional(pool)
+                .await?
+                .unwrap_or(0);
+
+        Ok(SyncCursor(last_seq))
+    }
+
+    /// Change log entries after `since` in the shape pushed to live subscribers.
+    pub async fn events_since(
+        pool: &PgPool,
+        user_id: Uuid,
+        since: SyncCursor,
+        limit: Option<i64>,
+    ) -> Result<Vec<ChangeEvent>> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+        Self::ensure_cursor_retained(pool, user_id, since).await?;
+
+        let events = sqlx::query_as::<_, ChangeEvent>(
+            r#"
+            SELECT user_id, seq, entity_type, entity_id, operation,
+                   CASE WHEN operation = 'upsert'
+                       THEN sync_entity_version(entity_type, entity_id)
+                   END AS version
+            FROM sync_changes
+            WHERE user_id = $1 AND seq > $2
+            ORDER BY seq ASC
+            LIMIT $3
+            "#,
+        )
+        .bind(user_id)
+        .bind(since.0)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(events)
+    }
+
+    /// Deletions before the purge point are gone, so an older cursor would miss them.
+    async fn ensure_cursor_retained(pool: &PgPool, user_id: Uuid, since: SyncCursor) -> Result<()> {
+        let purged_seq = sqlx::query_scalar::<_, i64>(
+            "SELECT purged_seq FROM sync_sequences WHERE user_id = $1",
+        )
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?
+        .unwrap_or(0);
+
+        if since.0 > 0 && since.0 < purged_seq {
+            return Err(AppError::Gone(
+                "Sync cursor expired, full resync required".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub async fn list_tombstones(
+        pool: &PgPool,
+        user_id: Uuid,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<Vec<Tombstone>> {
+        let tombstones = sqlx::query_as::<_, Tombstone>(
+            r#"
+            SELECT * FROM tombstones
+            WHERE user_id = $1 AND ($2::timestamptz IS NULL OR deleted_at > $2)
+            ORDER BY deleted_at ASC
+            "#,
+        )
+        .bind(user_id)
+        .bind(since)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(tombstones)
+    }
+
+    /// Drops tombstones deleted before `cutoff` along with their change log entries.
+    ///
+    /// Cursors that predate the removed entries are rejected afterwards, forcing
+    /// those clients into a full resync instead of silently missing deletions.
+    pub async fn purge_tombstones(pool: &PgPool, cutoff: DateTime<Utc>) -> Result<u64> {
+        let mut tx = pool.begin().await?;
+
+        // Entries superseded by a later change to the same entity carry no information
+        sqlx::query(
+            r#"
+            DELETE FROM sync_changes c
+            WHERE c.changed_at < $1
+              AND EXISTS (
+                SELECT 1 FROM sync_changes n
+                WHERE n.user_id = c.user_id
+                  AND n.entity_type = c.entity_type
+                  AND n.entity_id = c.entity_id
+                  AND n.seq > c.seq
+              )
+            "#,
+        )
+        .bind(cutoff)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            WITH purged AS (
+                DELETE FROM sync_changes
+                WHERE operation = 'delete' AND changed_at < $1
+                RETURNING user_id, seq
+            )
+            UPDATE sync_sequences s
+            SET purged_seq = GREATEST(s.purged_seq, p.max_seq)
+            FROM (SELECT user_id, MAX(seq) AS max_seq FROM purged GROUP BY user_id) p
+            WHERE s.user_id = p.user_id
+            "#,
+        )
+        .bind(cutoff)
+        .execute(&mut *tx)
+        .await?;
+
+        let result = sqlx::query("DELETE FROM tombstones WHERE deleted_at < $1")
+            .bind(cutoff)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -1,19 +1,38 @@
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgExecutor, PgPool, Postgres, QueryBuilder};
 use uuid::Uuid;
 
 use crate::error::{AppError, Result};
-use crate::models::{CreateTag, Tag, UpdateTag};
+use crate::models::{
+    ChangeOperation, CreateTag, EntityType, Page, Tag, TagListQuery, TagSort, UpdateTag,
+};
+use crate::services::SyncService;
+use crate::services::pagination::{Keyset, timestamp_key};
 
 pub struct TagService;
 
 impl TagService {
     pub async fn create(pool: &PgPool, user_id: Uuid, input: CreateTag) -> Result<Tag> {
+        let id = input.id.unwrap_or_else(Uuid::new_v4);
+        let mut tx = pool.begin().await?;
+        let tag = Self::create_in_tx(&mut tx, user_id, id, input).await?;
+        tx.commit().await?;
+
+        Ok(tag)
+    }
+
+    /// Same as [`Self::create`], within the caller's transaction, using `id` for the new row.
+    pub async fn create_in_tx(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        id: Uuid,
+        input: CreateTag,
+    ) -> Result<Tag> {
         let existing = sqlx::query_scalar::<_, i64>(
             "SELECT COUNT(*) FROM tags WHERE user_id = $1 AND name = $2",
         )
         .bind(user_id)
         .bind(&input.name)
-        .fetch_one(pool)
+        .fetch_one(&mut *conn)
         .await?;
 
         if existing > 0 {
@@ -24,36 +43,83 @@ impl TagService {
             r#"
             INSERT INTO tags (id, user_id, name, color, created_at)
             VALUES ($1, $2, $3, $4, NOW())
+            ON CONFLICT (id) DO NOTHING
             RETURNING *
             "#,
         )
-        .bind(Uuid::new_v4())
+        .bind(id)
         .bind(user_id)
         .bind(&input.name)
         .bind(&input.color)
-        .fetch_one(pool)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| AppError::Conflict(format!("Tag id {id} is already in use")))?;
+
+        SyncService::record(
+            &mut *conn,
+            user_id,
+            EntityType::Tag,
+            tag.id,
+            ChangeOperation::Upsert,
+        )
         .await?;
 
         Ok(tag)
     }
 
-    pub async fn get_by_id(pool: &PgPool, user_id: Uuid, tag_id: Uuid) -> Result<Tag> {
+    pub async fn get_by_id(
+        executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        tag_id: Uuid,
+    ) -> Result<Tag> {
         sqlx::query_as::<_, Tag>("SELECT * FROM tags WHERE id = $1 AND user_id = $2")
             .bind(tag_id)
             .bind(user_id)
-            .fetch_optional(pool)
+            .fetch_optional(executor)
             .await?
             .ok_or_else(|| AppError::NotFound("Tag not found".to_string()))
     }
 
-    pub async fn list(pool: &PgPool, user_id: Uuid) -> Result<Vec<Tag>> {
-        let tags =
-            sqlx::query_as::<_, Tag>("SELECT * FROM tags WHERE user_id = $1 ORDER BY name ASC")
-                .bind(user_id)
-                .fetch_all(pool)
-                .await?;
+    pub async fn list(pool: &PgPool, user_id: Uuid, query: TagListQuery) -> Result<Page<Tag>> {
+        let keyset = Keyset::new(
+            query.sort.unwrap_or_default(),
+            query.order,
+            query.limit,
+            query.cursor.as_deref(),
+        )?;
+
+        let push_filters = |builder: &mut QueryBuilder<'_, Postgres>| {
+            builder.push(" WHERE t.user_id = ").push_bind(user_id);
 
-        Ok(tags)
+            if let Some(created_after) = query.created_after {
+                builder
+                    .push(" AND t.created_at > ")
+                    .push_bind(created_after);
+            }
+        };
+
+        let mut select = QueryBuilder::new("SELECT t.* FROM tags t");
+        push_filters(&mut select);
+        keyset.push_after(&mut select, "t.id");
+        keyset.push_order_and_limit(&mut select, "t.id");
+        let tags = select.build_query_as::<Tag>().fetch_all(pool).await?;
+
+        let total = if query.include_total {
+            let mut count = QueryBuilder::new("SELECT COUNT(*) FROM tags t");
+            push_filters(&mut count);
+            Some(count.build_query_scalar::<i64>().fetch_one(pool).await?)
+        } else {
+            None
+        };
+
+        let sort = keyset.sort();
+        Ok(keyset.page(tags, total, |tag| {
+            let key = match sort {
+                TagSort::Name => tag.name.clone(),
+                TagSort::CreatedAt => timestamp_key(&tag.created_at),
+            };
+            (key, tag.id)
+        }))
     }
 
     pub async fn update(
@@ -61,15 +127,32 @@ impl TagService {
         user_id: Uuid,
         tag_id: Uuid,
         input: UpdateTag,
+        expected_version: Option<i64>,
+    ) -> Result<Tag> {
+        let mut tx = pool.begin().await?;
+        let tag = Self::update_in_tx(&mut tx, user_id, tag_id, input, expected_version).await?;
+        tx.commit().await?;
+
+        Ok(tag)
+    }
+
+    /// Same as [`Self::update`], within the caller's transaction.
+    pub async fn update_in_tx(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        tag_id: Uuid,
+        input: UpdateTag,
+        expected_version: Option<i64>,
     ) -> Result<Tag> {
-        Self::get_by_id(pool, user_id, tag_id).await?;
+        Self::get_by_id(&mut *conn, user_id, tag_id).await?;
 
         let tag = sqlx::query_as::<_, Tag>(
             r#"
             UPDATE tags
             SET name = COALESCE($3, name),
-                color = COALESCE($4, color)
-            WHERE id = $1 AND user_id = $2
+                color = COALESCE($4, color),
+                version = version + 1
+            WHERE id = $1 AND user_id = $2 AND ($5::BIGINT IS NULL OR version = $5)
             RETURNING *
             "#,
         )
@@ -77,23 +160,119 @@ impl TagService {
         .bind(user_id)
         .bind(&input.name)
         .bind(&input.color)
-        .fetch_one(pool)
+        .bind(expected_version)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        let Some(tag) = tag else {
+            let current = Self::get_by_id(&mut *conn, user_id, tag_id).await?;
+            return Err(AppError::precondition_failed(&current, current.version));
+        };
+
+        SyncService::record(
+            &mut *conn,
+            user_id,
+            EntityType::Tag,
+            tag_id,
+            ChangeOperation::Upsert,
+        )
         .await?;
 
+        // Bookmarks embed their tags, so their ETags and synced copies change too
+        for bookmark_id in Self::bump_bookmarks(&mut *conn, user_id, tag_id).await? {
+            SyncService::record(
+                &mut *conn,
+                user_id,
+                EntityType::Bookmark,
+                bookmark_id,
+                ChangeOperation::Upsert,
+            )
+            .await?;
+        }
+
         Ok(tag)
     }
 
-    pub async fn delete(pool: &PgPool, user_id: Uuid, tag_id: Uuid) -> Result<()> {
-        let result = sqlx::query("DELETE FROM tags WHERE id = $1 AND user_id = $2")
-            .bind(tag_id)
-            .bind(user_id)
-            .execute(pool)
-            .await?;
+    pub async fn delete(
+        pool: &PgPool,
+        user_id: Uuid,
+        tag_id: Uuid,
+        expected_version: Option<i64>,
+    ) -> Result<()> {
+        let mut tx = pool.begin().await?;
+        Self::delete_in_tx(&mut tx, user_id, tag_id, expected_version).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Same as [`Self::delete`], within the caller's transaction.
+    pub async fn delete_in_tx(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        tag_id: Uuid,
+        expected_version: Option<i64>,
+    ) -> Result<()> {
+        // Links are removed by the cascade, so capture the affected bookmarks first
+        let bookmark_ids = Self::bump_bookmarks(&mut *conn, user_id, tag_id).await?;
+
+        let result = sqlx::query(
+            "DELETE FROM tags WHERE id = $1 AND user_id = $2 AND ($3::BIGINT IS NULL OR version = $3)",
+        )
+        .bind(tag_id)
+        .bind(user_id)
+        .bind(expected_version)
+        .execute(&mut *conn)
+        .await?;
 
         if result.rows_affected() == 0 {
-            return Err(AppError::NotFound("Tag not found".to_string()));
+            let current = Self::get_by_id(&mut *conn, user_id, tag_id).await?;
+            return Err(AppError::precondition_failed(&current, current.version));
+        }
+
+        SyncService::record(
+            &mut *conn,
+            user_id,
+            EntityType::Tag,
+            tag_id,
+            ChangeOperation::Delete,
+        )
+        .await?;
+
+        for bookmark_id in bookmark_ids {
+            SyncService::record(
+                &mut *conn,
+                user_id,
+                EntityType::BookmarkTags,
+                bookmark_id,
+                ChangeOperation::Upsert,
+            )
+            .await?;
         }
 
         Ok(())
     }
+
+    /// Bumps the version of the bookmarks the tag is linked to, returning their ids.
+    async fn bump_bookmarks(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        tag_id: Uuid,
+    ) -> Result<Vec<Uuid>> {
+        let bookmark_ids = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            UPDATE bookmarks
+            SET version = version + 1
+            WHERE id IN (SELECT bookmark_id FROM bookmark_tags WHERE tag_id = $1)
+              AND user_id = $2
+            RETURNING id
+            "#,
+        )
+        .bind(tag_id)
+        .bind(user_id)
+        .fetch_all(conn)
+        .await?;
+
+        Ok(bookmark_ids)
+    }
 }
@@ -0,0 +1,118 @@
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::auth::{AuthUser, Credential, NEVER_EXPIRES, REVOCATION_CHANNEL, RevocationCache};
+use crate::error::{AppError, Result};
+use crate::services::{DeviceService, RefreshTokenService};
+
+pub struct TokenRevocationService;
+
+impl TokenRevocationService {
+    /// Whether an access token has been revoked, consulting `cache` first.
+    pub async fn is_revoked(
+        pool: &PgPool,
+        cache: &RevocationCache,
+        jti: Uuid,
+        expires_at: i64,
+    ) -> Result<bool> {
+        if let Some(revoked) = cache.get(jti) {
+            return Ok(revoked);
+        }
+
+        let revoked = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS (SELECT 1 FROM revoked_tokens WHERE jti = $1)",
+        )
+        .bind(jti)
+        .fetch_one(pool)
+        .await?;
+
+        cache.insert(jti, revoked, expires_at);
+        Ok(revoked)
+    }
+
+    /// Ends the session `auth` belongs to: its access token and its device's refresh tokens.
+    pub async fn logout(pool: &PgPool, cache: &RevocationCache, auth: &AuthUser) -> Result<()> {
+        let (jti, exp) = Self::session(auth)?;
+        let mut tx = pool.begin().await?;
+
+        Self::revoke_in_tx(&mut tx, auth.user_id, jti, exp).await?;
+        if let Some(device_id) = auth.device_id {
+            RefreshTokenService::revoke_device_in_tx(&mut tx, device_id).await?;
+        }
+
+        tx.commit().await?;
+        cache.revoke(jti, exp);
+
+        tracing::info!(user_id = %auth.user_id, %jti, "Signed out");
+        Ok(())
+    }
+
+    /// Ends every session of the user: all devices are signed out, which
+    /// invalidates their access and refresh tokens.
+    pub async fn logout_all(pool: &PgPool, cache: &RevocationCache, auth: &AuthUser) -> Result<()> {
+        let (jti, exp) = Self::session(auth)?;
+        let mut tx = pool.begin().await?;
+
+        Self::revoke_in_tx(&mut tx, auth.user_id, jti, exp).await?;
+        let device_ids = DeviceService::revoke_all_in_tx(&mut tx, auth.user_id).await?;
+        RefreshTokenService::revoke_user_in_tx(&mut tx, auth.user_id).await?;
+
+        tx.commit().await?;
+        cache.revoke(jti, exp);
+        for device_id in device_ids {
+            cache.revoke(device_id, NEVER_EXPIRES);
+        }
+
+        tracing::info!(user_id = %auth.user_id, "Signed out everywhere");
+        Ok(())
+    }
+
+    /// Removes entries for tokens that expired before `expired_before`.
+    pub async fn purge_expired(pool: &PgPool, expired_before: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM revoked_tokens WHERE expires_at < $1")
+            .bind(expired_before)
+            .execute(pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// The presented access token's id and expiry; personal access tokens
+    /// are revoked through their own endpoint instead.
+    fn session(auth: &AuthUser) -> Result<(Uuid, i64)> {
+        match auth.credential {
+            Credential::Session { jti, exp } => Ok((jti, exp)),
+            Credential::PersonalAccessToken { .. } => Err(AppError::Forbidden),
+        }
+    }
+
+    /// Adds the token to the denylist and tells every instance once committed.
+    async fn revoke_in_tx(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        jti: Uuid,
+        exp: i64,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO revoked_tokens (jti, user_id, expires_at)
+            VALUES ($1, $2, to_timestamp($3))
+            ON CONFLICT (jti) DO NOTHING
+            "#,
+        )
+        .bind(jti)
+        .bind(user_id)
+        .bind(exp as f64)
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query("SELECT pg_notify($1, $2)")
+            .bind(REVOCATION_CHANNEL)
+            .bind(format!("{} {}", jti, exp))
+            .execute(conn)
+            .await?;
+
+        Ok(())
+    }
+}
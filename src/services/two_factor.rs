@@ -0,0 +1,435 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{FromRow, PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::auth::{generate_token, hash_token, totp};
+use crate::error::{AppError, Result};
+use crate::models::{DeviceInfo, RecoveryCodes, TwoFactorEnrollment, TwoFactorStatus, User};
+use crate::services::UserService;
+
+/// How long a login challenge can be answered.
+const CHALLENGE_TTL_MINUTES: i64 = 5;
+/// Wrong codes a login challenge tolerates before it is dropped.
+const MAX_CHALLENGE_ATTEMPTS: i32 = 5;
+/// Wrong codes a user may enter, across all challenges, before codes are
+/// refused for [`LOCKOUT_MINUTES`].
+const MAX_FAILED_ATTEMPTS: i32 = 10;
+const LOCKOUT_MINUTES: i64 = 15;
+const RECOVERY_CODE_COUNT: usize = 10;
+
+/// A login challenge answered with a valid code.
+#[derive(Debug, Clone)]
+pub struct PassedChallenge {
+    pub user_id: Uuid,
+    /// Device the login was started from
+    pub device: Option<DeviceInfo>,
+}
+
+#[derive(Debug, FromRow)]
+struct StoredChallenge {
+    id: Uuid,
+    user_id: Uuid,
+    device_id: Option<Uuid>,
+    device_name: Option<String>,
+    device_platform: Option<String>,
+    device_client_version: Option<String>,
+    attempts: i32,
+    expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, FromRow)]
+struct StoredCredential {
+    secret: Vec<u8>,
+    last_used_step: Option<i64>,
+    confirmed_at: Option<DateTime<Utc>>,
+    failed_attempts: i32,
+    locked_until: Option<DateTime<Utc>>,
+}
+
+pub struct TwoFactorService;
+
+impl TwoFactorService {
+    pub async fn status(pool: &PgPool, user_id: Uuid) -> Result<TwoFactorStatus> {
+        let (enabled, recovery_codes_remaining) = sqlx::query_as::<_, (bool, i64)>(
+            r#"
+            SELECT
+                EXISTS (
+                    SELECT 1 FROM two_factor_credentials
+                    WHERE user_id = $1 AND confirmed_at IS NOT NULL
+                ),
+                (SELECT COUNT(*) FROM recovery_codes WHERE user_id = $1 AND used_at IS NULL)
+            "#,
+        )
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(TwoFactorStatus {
+            enabled,
+            recovery_codes_remaining,
+        })
+    }
+
+    pub async fn is_enabled(pool: &PgPool, user_id: Uuid) -> Result<bool> {
+        Ok(Self::status(pool, user_id).await?.enabled)
+    }
+
+    /// Starts enrolling with a fresh secret, replacing any unconfirmed one.
+    pub async fn begin_enrollment(pool: &PgPool, user: &User) -> Result<TwoFactorEnrollment> {
+        let secret = totp::generate_secret();
+
+        let started = sqlx::query(
+            r#"
+            INSERT INTO two_factor_credentials (user_id, secret)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id) DO UPDATE
+                SET secret = EXCLUDED.secret, last_used_step = NULL, created_at = NOW()
+                WHERE two_factor_credentials.confirmed_at IS NULL
+            "#,
+        )
+        .bind(user.id)
+        .bind(&secret)
+        .execute(pool)
+        .await?;
+
+        if started.rows_affected() == 0 {
+            return Err(AppError::Conflict(
+                "Two-factor authentication is already enabled".to_string(),
+            ));
+        }
+
+        Ok(TwoFactorEnrollment {
+            secret: totp::encode_secret(&secret),
+            otpauth_uri: totp::otpauth_uri(&secret, &user.email),
+        })
+    }
+
+    /// Enables two-factor authentication once the user proves their
+    /// authenticator works, handing out fresh recovery codes.
+    pub async fn confirm_enrollment(
+        pool: &PgPool,
+        user_id: Uuid,
+        code: &str,
+    ) -> Result<RecoveryCodes> {
+        let mut tx = pool.begin().await?;
+
+        let credential = Self::lock_credential(&mut tx, user_id)
+            .await?
+            .ok_or_else(|| {
+                AppError::UnprocessableEntity("Two-factor setup has not been started".to_string())
+            })?;
+
+        if credential.confirmed_at.is_some() {
+            return Err(AppError::Conflict(
+                "Two-factor authentication is already enabled".to_string(),
+            ));
+        }
+
+        let step = totp::verify(&credential.secret, code, Utc::now().timestamp(), None)
+            .ok_or_else(|| AppError::Validation("Invalid code".to_string()))?;
+
+        sqlx::query(
+            r#"
+            UPDATE two_factor_credentials SET confirmed_at = NOW(), last_used_step = $2
+            WHERE user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .bind(step)
+        .execute(&mut *tx)
+        .await?;
+
+        let recovery_codes = Self::replace_recovery_codes(&mut tx, user_id).await?;
+
+        tx.commit().await?;
+
+        tracing::info!(%user_id, "Two-factor authentication enabled");
+        Ok(RecoveryCodes { recovery_codes })
+    }
+
+    /// Turns two-factor authentication off after re-authenticating the user
+    /// with their password and a code.
+    pub async fn disable(pool: &PgPool, user_id: Uuid, password: &str, code: &str) -> Result<()> {
+        UserService::verify_password(pool, user_id, password).await?;
+
+        let mut tx = pool.begin().await?;
+
+        if !Self::check_code_in_tx(&mut tx, user_id, code).await? {
+            // Keep the failed attempt counted
+            tx.commit().await?;
+            return Err(AppError::InvalidCredentials);
+        }
+
+        sqlx::query("DELETE FROM two_factor_credentials WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM recovery_codes WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        tracing::info!(%user_id, "Two-factor authentication disabled");
+        Ok(())
+    }
+
+    /// Records a login whose password was verified; the returned token
+    /// completes it together with a code.
+    pub async fn start_login(
+        pool: &PgPool,
+        user_id: Uuid,
+        device: Option<DeviceInfo>,
+    ) -> Result<String> {
+        let token = generate_token();
+
+        sqlx::query(
+            r#"
+            INSERT INTO login_challenges
+                (id, user_id, token_hash, device_id, device_name, device_platform,
+                 device_client_version, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .bind(hash_token(&token))
+        .bind(device.as_ref().and_then(|device| device.id))
+        .bind(device.as_ref().map(|device| &device.name))
+        .bind(device.as_ref().and_then(|device| device.platform.as_ref()))
+        .bind(
+            device
+                .as_ref()
+                .and_then(|device| device.client_version.as_ref()),
+        )
+        .bind(Utc::now() + Self::challenge_ttl())
+        .execute(pool)
+        .await?;
+
+        Ok(token)
+    }
+
+    /// Answers a login challenge. Each challenge can be passed once, and is
+    /// dropped after too many wrong codes.
+    pub async fn complete_login(
+        pool: &PgPool,
+        challenge_token: &str,
+        code: &str,
+    ) -> Result<PassedChallenge> {
+        let mut tx = pool.begin().await?;
+
+        let challenge = sqlx::query_as::<_, StoredChallenge>(
+            r#"
+            SELECT id, user_id, device_id, device_name, device_platform,
+                   device_client_version, attempts, expires_at
+            FROM login_challenges
+            WHERE token_hash = $1
+            FOR UPDATE
+            "#,
+        )
+        .bind(hash_token(challenge_token))
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(AppError::Unauthorized)?;
+
+        if challenge.expires_at <= Utc::now() {
+            return Err(AppError::Unauthorized);
+        }
+
+        if !Self::check_code_in_tx(&mut tx, challenge.user_id, code).await? {
+            if challenge.attempts + 1 >= MAX_CHALLENGE_ATTEMPTS {
+                sqlx::query("DELETE FROM login_challenges WHERE id = $1")
+                    .bind(challenge.id)
+                    .execute(&mut *tx)
+                    .await?;
+                tracing::warn!(user_id = %challenge.user_id, "Login challenge dropped after too many wrong codes");
+            } else {
+                sqlx::query("UPDATE login_challenges SET attempts = attempts + 1 WHERE id = $1")
+                    .bind(challenge.id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            tx.commit().await?;
+
+            return Err(AppError::InvalidCredentials);
+        }
+
+        sqlx::query("DELETE FROM login_challenges WHERE id = $1")
+            .bind(challenge.id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        let device = challenge.device_name.map(|name| DeviceInfo {
+            id: challenge.device_id,
+            name,
+            platform: challenge.device_platform,
+            client_version: challenge.device_client_version,
+        });
+
+        Ok(PassedChallenge {
+            user_id: challenge.user_id,
+            device,
+        })
+    }
+
+    /// Seconds a login challenge can be answered.
+    pub fn challenge_expires_in() -> i64 {
+        Self::challenge_ttl().num_seconds()
+    }
+
+    /// Removes login challenges that expired before `expired_before`.
+    pub async fn purge_expired_challenges(
+        pool: &PgPool,
+        expired_before: DateTime<Utc>,
+    ) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM login_challenges WHERE expires_at < $1")
+            .bind(expired_before)
+            .execute(pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    fn challenge_ttl() -> Duration {
+        Duration::minutes(CHALLENGE_TTL_MINUTES)
+    }
+
+    /// Checks an authenticator code, or uses up a recovery code, of a user
+    /// with two-factor authentication enabled.
+    ///
+    /// Wrong codes are counted per user; too many in a row refuse all codes
+    /// for a while, however many login challenges they were spread over.
+    async fn check_code_in_tx(conn: &mut PgConnection, user_id: Uuid, code: &str) -> Result<bool> {
+        let credential = match Self::lock_credential(conn, user_id).await? {
+            Some(credential) if credential.confirmed_at.is_some() => credential,
+            _ => {
+                return Err(AppError::UnprocessableEntity(
+                    "Two-factor authentication is not enabled".to_string(),
+                ));
+            }
+        };
+
+        if credential
+            .locked_until
+            .is_some_and(|locked_until| locked_until > Utc::now())
+        {
+            return Err(AppError::TooManyRequests(
+                "Too many wrong codes, try again later".to_string(),
+            ));
+        }
+
+        let step = totp::verify(
+            &credential.secret,
+            code,
+            Utc::now().timestamp(),
+            credential.last_used_step,
+        );
+        let passed = step.is_some() || Self::use_recovery_code(conn, user_id, code).await?;
+
+        if passed {
+            sqlx::query(
+                r#"
+                UPDATE two_factor_credentials
+                SET last_used_step = COALESCE($2, last_used_step), failed_attempts = 0, locked_until = NULL
+                WHERE user_id = $1
+                "#,
+            )
+            .bind(user_id)
+            .bind(step)
+            .execute(conn)
+            .await?;
+        } else if credential.failed_attempts + 1 >= MAX_FAILED_ATTEMPTS {
+            sqlx::query(
+                r#"
+                UPDATE two_factor_credentials SET failed_attempts = 0, locked_until = $2
+                WHERE user_id = $1
+                "#,
+            )
+            .bind(user_id)
+            .bind(Utc::now() + Duration::minutes(LOCKOUT_MINUTES))
+            .execute(conn)
+            .await?;
+            tracing::warn!(%user_id, "Two-factor codes locked after too many wrong attempts");
+        } else {
+            sqlx::query(
+                "UPDATE two_factor_credentials SET failed_attempts = failed_attempts + 1 WHERE user_id = $1",
+            )
+            .bind(user_id)
+            .execute(conn)
+            .await?;
+        }
+
+        Ok(passed)
+    }
+
+    /// Uses up a matching recovery code, if the user has one left.
+    async fn use_recovery_code(conn: &mut PgConnection, user_id: Uuid, code: &str) -> Result<bool> {
+        let used = sqlx::query(
+            r#"
+            UPDATE recovery_codes SET used_at = NOW()
+            WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+            "#,
+        )
+        .bind(user_id)
+        .bind(hash_token(&totp::normalize_recovery_code(code)))
+        .execute(conn)
+        .await?;
+
+        if used.rows_affected() > 0 {
+            tracing::info!(%user_id, "Recovery code used");
+        }
+        Ok(used.rows_affected() > 0)
+    }
+
+    async fn lock_credential(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+    ) -> Result<Option<StoredCredential>> {
+        let credential = sqlx::query_as::<_, StoredCredential>(
+            r#"
+            SELECT secret, last_used_step, confirmed_at, failed_attempts, locked_until
+            FROM two_factor_credentials
+            WHERE user_id = $1
+            FOR UPDATE
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(conn)
+        .await?;
+
+        Ok(credential)
+    }
+
+    async fn replace_recovery_codes(conn: &mut PgConnection, user_id: Uuid) -> Result<Vec<String>> {
+        sqlx::query("DELETE FROM recovery_codes WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut *conn)
+            .await?;
+
+        let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+            .map(|_| totp::generate_recovery_code())
+            .collect();
+        let ids: Vec<Uuid> = codes.iter().map(|_| Uuid::new_v4()).collect();
+        let hashes: Vec<String> = codes
+            .iter()
+            .map(|code| hash_token(&totp::normalize_recovery_code(code)))
+            .collect();
+
+        sqlx::query(
+            r#"
+            INSERT INTO recovery_codes (id, user_id, code_hash)
+            SELECT id, $2, code_hash FROM UNNEST($1::UUID[], $3::CHAR(64)[]) AS t(id, code_hash)
+            "#,
+        )
+        .bind(&ids)
+        .bind(user_id)
+        .bind(&hashes)
+        .execute(conn)
+        .await?;
+
+        Ok(codes)
+    }
+}
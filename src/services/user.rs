@@ -52,12 +52,16 @@ impl UserService {
             .await?
             .ok_or(AppError::InvalidCredentials)?;
 
-        let parsed_hash = PasswordHash::new(&user.password_hash)
-            .map_err(|e| AppError::Internal(e.to_string()))?;
+        Self::check_password(&user, password)?;
 
-        Argon2::default()
-            .verify_password(password.as_bytes(), &parsed_hash)
-            .map_err(|_| AppError::InvalidCredentials)?;
+        Ok(user)
+    }
+
+    /// Re-authenticates a signed-in user before a sensitive change.
+    pub async fn verify_password(pool: &PgPool, user_id: Uuid, password: &str) -> Result<User> {
+        let user = Self::get_by_id(pool, user_id).await?;
+
+        Self::check_password(&user, password)?;
 
         Ok(user)
     }
@@ -69,4 +73,13 @@ impl UserService {
             .await?
             .ok_or_else(|| AppError::NotFound("User not found".to_string()))
     }
+
+    fn check_password(user: &User, password: &str) -> Result<()> {
+        let parsed_hash = PasswordHash::new(&user.password_hash)
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Argon2::default()
+            .verify_password(password.as_bytes(), &parsed_hash)
+            .map_err(|_| AppError::InvalidCredentials)
+    }
 }
@@ -2,6 +2,7 @@ use opentelemetry::KeyValue;
 use opentelemetry::trace::TracerProvider as _;
 use opentelemetry_otlp::WithExportConfig;
 use opentelemetry_sdk::{Resource, runtime, trace::TracerProvider};
+use tracing::Span;
 use tracing_subscriber::{EnvFilter, Layer, layer::SubscriberExt, util::SubscriberInitExt};
 
 use crate::Config;
@@ -42,6 +43,17 @@ pub fn init_telemetry(config: &Config) {
     }
 }
 
+/// Span for an HTTP request, recording the path but not the query string,
+/// which can carry credentials such as the WebSocket `token`.
+pub fn request_span<B>(request: &axum::http::Request<B>) -> Span {
+    tracing::debug_span!(
+        "request",
+        method = %request.method(),
+        path = %request.uri().path(),
+        version = ?request.version(),
+    )
+}
+
 fn init_tracer_provider(endpoint: &str, service_name: &str) -> TracerProvider {
     let exporter = opentelemetry_otlp::SpanExporter::builder()
         .with_tonic()
@@ -2,19 +2,30 @@ use axum::{
     Extension, Router,
     body::Body,
     http::{Method, Request, StatusCode, header},
-    routing::{get, post},
+    middleware,
+    routing::{delete, get, post},
 };
+use futures_util::StreamExt;
 use http_body_util::BodyExt;
 use serde_json::json;
 use sqlx::PgPool;
 use testcontainers::{ContainerAsync, runners::AsyncRunner};
 use testcontainers_modules::postgres::Postgres;
 use tokio::sync::OnceCell;
+use tokio_tungstenite::tungstenite;
 use tower::ServiceExt;
 
+use xync_server::AppError;
 use xync_server::AppState;
-use xync_server::auth::JwtManager;
+use xync_server::auth::{AuthUser, Credential, JwtManager, start_revocation_listener};
+use xync_server::blob::{BlobStorage, FsBlobStore};
+use xync_server::canonical_url::CanonicalUrlOptions;
+use xync_server::events::{EventBus, start_listener};
 use xync_server::handlers;
+use xync_server::idempotency::{Idempotency, idempotency};
+use xync_server::models::{ChangeEvent, ChangeOperation, EntityType, PageCursor};
+use xync_server::preview::{PreviewConfig, PreviewError, PreviewFetcher};
+use xync_server::services::{NoteService, SyncService};
 
 static TEST_CONTAINER: OnceCell<ContainerAsync<Postgres>> = OnceCell::const_new();
 static TEST_POOL: OnceCell<PgPool> = OnceCell::const_new();
@@ -41,29 +52,72 @@ async fn get_test_pool() -> &'static PgPool {
                 .expect("Failed to connect to test database");
 
             // Run migrations
-            sqlx::query(include_str!(
-                "../migrations/20240101000000_initial_schema.sql"
-            ))
-            .execute(&pool)
-            .await
-            .expect("Failed to run migrations");
+            sqlx::migrate!("./migrations")
+                .run(&pool)
+                .await
+                .expect("Failed to run migrations");
 
             pool
         })
         .await
 }
 
+/// Preview fetcher for tests; stub servers listen on loopback, so they need `allow_private_networks`.
+fn test_preview_fetcher(allow_private_networks: bool) -> PreviewFetcher {
+    PreviewFetcher::new(PreviewConfig {
+        timeout: std::time::Duration::from_secs(5),
+        max_bytes: 64 * 1024,
+        archive_max_bytes: 96 * 1024,
+        allow_private_networks,
+    })
+}
+
+/// Blob storage for tests; blobs are content-addressed, so every test can share one directory.
+fn test_blob_storage(quota_bytes: i64) -> BlobStorage {
+    let root = std::env::temp_dir().join("xync-test-blobs");
+    BlobStorage::new(FsBlobStore::new(root).unwrap(), quota_bytes)
+}
+
 fn create_test_app(pool: PgPool) -> Router {
-    let jwt = JwtManager::new("test-secret-key-for-testing", 24);
+    create_test_app_with_events(pool, EventBus::new())
+}
+
+fn create_test_app_with_events(pool: PgPool, events: EventBus) -> Router {
+    create_test_app_with(pool, events, test_blob_storage(1024 * 1024 * 1024))
+}
+
+fn create_test_app_with(pool: PgPool, events: EventBus, blobs: BlobStorage) -> Router {
+    let jwt = JwtManager::new("test-secret-key-for-testing", 15, 30);
     let state = AppState {
-        pool,
+        pool: pool.clone(),
         jwt: jwt.clone(),
+        events,
+        previews: test_preview_fetcher(true),
+        blobs,
+        canonical_urls: CanonicalUrlOptions::default(),
     };
 
     Router::new()
         .route("/api/auth/register", post(handlers::register))
         .route("/api/auth/login", post(handlers::login))
+        .route("/api/auth/login/2fa", post(handlers::login_two_factor))
+        .route("/api/auth/refresh", post(handlers::refresh))
+        .route("/api/auth/logout", post(handlers::logout))
+        .route("/api/auth/logout-all", post(handlers::logout_all))
         .route("/api/auth/me", get(handlers::me))
+        .route(
+            "/api/auth/tokens",
+            post(handlers::create_access_token).get(handlers::list_access_tokens),
+        )
+        .route(
+            "/api/auth/tokens/{id}",
+            delete(handlers::revoke_access_token),
+        )
+        .route("/api/auth/2fa", get(handlers::get_two_factor_status))
+        .route("/api/auth/2fa/setup", post(handlers::setup_two_factor))
+        .route("/api/auth/2fa/confirm", post(handlers::confirm_two_factor))
+        .route("/api/auth/2fa/disable", post(handlers::disable_two_factor))
+        .route("/.well-known/jwks.json", get(handlers::jwks))
         .route(
             "/api/bookmarks",
             post(handlers::create_bookmark).get(handlers::list_bookmarks),
@@ -74,6 +128,24 @@ fn create_test_app(pool: PgPool) -> Router {
                 .put(handlers::update_bookmark)
                 .delete(handlers::delete_bookmark),
         )
+        .route(
+            "/api/bookmarks/duplicates",
+            get(handlers::list_duplicate_bookmarks),
+        )
+        .route("/api/bookmarks/{id}/merge", post(handlers::merge_bookmarks))
+        .route("/api/bookmarks/preview", post(handlers::preview_bookmark))
+        .route(
+            "/api/bookmarks/{id}/preview",
+            post(handlers::refresh_bookmark_preview),
+        )
+        .route(
+            "/api/bookmarks/{id}/archive",
+            get(handlers::get_bookmark_archive),
+        )
+        .route(
+            "/api/bookmarks/{id}/archive/raw",
+            get(handlers::get_bookmark_archive_raw),
+        )
         .route(
             "/api/notes",
             post(handlers::create_note).get(handlers::list_notes),
@@ -84,6 +156,14 @@ fn create_test_app(pool: PgPool) -> Router {
                 .put(handlers::update_note)
                 .delete(handlers::delete_note),
         )
+        .route(
+            "/api/notes/{id}/conflicts",
+            get(handlers::list_note_conflicts),
+        )
+        .route(
+            "/api/notes/{id}/conflicts/{conflict_id}/resolve",
+            post(handlers::resolve_note_conflict),
+        )
         .route(
             "/api/tags",
             post(handlers::create_tag).get(handlers::list_tags),
@@ -104,6 +184,34 @@ fn create_test_app(pool: PgPool) -> Router {
                 .put(handlers::update_category)
                 .delete(handlers::delete_category),
         )
+        .route("/api/devices", get(handlers::list_devices))
+        .route("/api/devices/{id}", delete(handlers::revoke_device))
+        .route("/api/search", get(handlers::search))
+        .route("/api/search/suggest", get(handlers::suggest))
+        .route(
+            "/api/saved-searches",
+            post(handlers::create_saved_search).get(handlers::list_saved_searches),
+        )
+        .route(
+            "/api/saved-searches/{id}",
+            get(handlers::get_saved_search)
+                .put(handlers::update_saved_search)
+                .delete(handlers::delete_saved_search),
+        )
+        .route(
+            "/api/saved-searches/{id}/results",
+            get(handlers::get_saved_search_results),
+        )
+        .route("/api/storage/usage", get(handlers::get_storage_usage))
+        .route("/api/sync/changes", get(handlers::get_changes))
+        .route("/api/sync/tombstones", get(handlers::list_tombstones))
+        .route("/api/batch", post(handlers::run_batch))
+        .route("/api/ws", get(handlers::ws_handler))
+        .route("/api/events", get(handlers::stream_events))
+        .layer(middleware::from_fn_with_state(
+            Idempotency::new(pool, jwt.clone(), 24),
+            idempotency,
+        ))
         .layer(Extension(jwt))
         .with_state(state)
 }
@@ -113,6 +221,25 @@ async fn body_to_string(body: Body) -> String {
     String::from_utf8(bytes.to_vec()).unwrap()
 }
 
+/// Reads a streaming body until `needle` shows up, returning everything read so far.
+async fn read_stream_until(body: &mut Body, needle: &str) -> String {
+    let mut received = String::new();
+
+    while !received.contains(needle) {
+        let frame = tokio::time::timeout(std::time::Duration::from_secs(10), body.frame())
+            .await
+            .unwrap_or_else(|_| panic!("timed out waiting for {needle:?} in {received:?}"))
+            .expect("stream ended")
+            .unwrap();
+
+        if let Ok(data) = frame.into_data() {
+            received.push_str(std::str::from_utf8(&data).unwrap());
+        }
+    }
+
+    received
+}
+
 #[tokio::test]
 async fn test_user_registration() {
     let pool = get_test_pool().await.clone();
@@ -363,8 +490,8 @@ async fn test_create_and_list_bookmarks() {
     assert_eq!(list_response.status(), StatusCode::OK);
 
     let body = body_to_string(list_response.into_body()).await;
-    let bookmarks: Vec<serde_json::Value> = serde_json::from_str(&body).unwrap();
-    assert!(!bookmarks.is_empty());
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert!(!json["items"].as_array().unwrap().is_empty());
 }
 
 #[tokio::test]
@@ -424,8 +551,8 @@ async fn test_create_and_list_notes() {
     assert_eq!(list_response.status(), StatusCode::OK);
 
     let body = body_to_string(list_response.into_body()).await;
-    let notes: Vec<serde_json::Value> = serde_json::from_str(&body).unwrap();
-    assert!(!notes.is_empty());
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert!(!json["items"].as_array().unwrap().is_empty());
 }
 
 #[tokio::test]
@@ -470,3 +597,4062 @@ async fn test_validation_errors() {
     let response2 = app2.oneshot(request2).await.unwrap();
     assert_eq!(response2.status(), StatusCode::BAD_REQUEST);
 }
+
+#[tokio::test]
+async fn test_sync_changes_since_cursor() {
+    let pool = get_test_pool().await.clone();
+
+    // Register and get token
+    let app1 = create_test_app(pool.clone());
+    let register_request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/auth/register")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            json!({
+                "email": "synctest@example.com",
+                "password": "password123",
+                "name": "Sync Test"
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let register_response = app1.oneshot(register_request).await.unwrap();
+    let body = body_to_string(register_response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let token = json["token"].as_str().unwrap().to_string();
+
+    // Create a note
+    let app2 = create_test_app(pool.clone());
+    let create_request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/notes")
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::from(
+            json!({
+                "title": "Synced Note",
+                "content": "Shows up in the change feed"
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let create_response = app2.oneshot(create_request).await.unwrap();
+    let body = body_to_string(create_response.into_body()).await;
+    let note: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let note_id = note["id"].as_str().unwrap().to_string();
+
+    // Initial sync returns the note and a cursor
+    let app3 = create_test_app(pool.clone());
+    let sync_request = Request::builder()
+        .method(Method::GET)
+        .uri("/api/sync/changes")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap();
+
+    let sync_response = app3.oneshot(sync_request).await.unwrap();
+    assert_eq!(sync_response.status(), StatusCode::OK);
+
+    let body = body_to_string(sync_response.into_body()).await;
+    let changes: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(changes["notes"].as_array().unwrap().len(), 1);
+    assert_eq!(changes["has_more"], false);
+    let cursor = changes["cursor"].as_str().unwrap().to_string();
+
+    // Delete the note
+    let app4 = create_test_app(pool.clone());
+    let delete_request = Request::builder()
+        .method(Method::DELETE)
+        .uri(format!("/api/notes/{}", note_id))
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap();
+    app4.oneshot(delete_request).await.unwrap();
+
+    // Syncing from the cursor only reports the deletion
+    let app5 = create_test_app(pool.clone());
+    let sync_request = Request::builder()
+        .method(Method::GET)
+        .uri(format!("/api/sync/changes?since={}", cursor))
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap();
+
+    let sync_response = app5.oneshot(sync_request).await.unwrap();
+    assert_eq!(sync_response.status(), StatusCode::OK);
+
+    let body = body_to_string(sync_response.into_body()).await;
+    let changes: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert!(changes["notes"].as_array().unwrap().is_empty());
+    assert_eq!(changes["deleted"][0]["id"], note_id.as_str());
+    assert_eq!(changes["deleted"][0]["entity_type"], "note");
+    assert!(changes["deleted"][0]["deleted_at"].is_string());
+    assert_ne!(changes["cursor"], cursor.as_str());
+
+    // The deletion is also listed as a tombstone
+    let app6 = create_test_app(pool);
+    let tombstones_request = Request::builder()
+        .method(Method::GET)
+        .uri("/api/sync/tombstones")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap();
+
+    let tombstones_response = app6.oneshot(tombstones_request).await.unwrap();
+    assert_eq!(tombstones_response.status(), StatusCode::OK);
+
+    let body = body_to_string(tombstones_response.into_body()).await;
+    let tombstones: Vec<serde_json::Value> = serde_json::from_str(&body).unwrap();
+    assert_eq!(tombstones.len(), 1);
+    assert_eq!(tombstones[0]["id"], note_id.as_str());
+}
+
+#[tokio::test]
+async fn test_tombstone_gc_expires_old_cursors() {
+    let pool = get_test_pool().await.clone();
+
+    let send =
+        |method: Method, uri: &str, token: Option<String>, body: Option<serde_json::Value>| {
+            let app = create_test_app(pool.clone());
+            let uri = uri.to_string();
+            async move {
+                let mut request = Request::builder().method(method).uri(uri);
+                if let Some(token) = token {
+                    request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+                }
+                let body = match body {
+                    Some(body) => {
+                        request = request.header(header::CONTENT_TYPE, "application/json");
+                        Body::from(body.to_string())
+                    }
+                    None => Body::empty(),
+                };
+                let response = app.oneshot(request.body(body).unwrap()).await.unwrap();
+                let status = response.status();
+                let body = body_to_string(response.into_body()).await;
+                (
+                    status,
+                    serde_json::from_str::<serde_json::Value>(&body).unwrap_or_default(),
+                )
+            }
+        };
+
+    let (status, auth) = send(
+        Method::POST,
+        "/api/auth/register",
+        None,
+        Some(json!({
+            "email": "tombstonegc@example.com",
+            "password": "password123",
+            "name": "Tombstone GC"
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let token = Some(auth["token"].as_str().unwrap().to_string());
+    let user_id = uuid::Uuid::parse_str(auth["user"]["id"].as_str().unwrap()).unwrap();
+
+    let (_, kept) = send(
+        Method::POST,
+        "/api/notes",
+        token.clone(),
+        Some(json!({ "title": "Kept", "content": "" })),
+    )
+    .await;
+    let (_, removed) = send(
+        Method::POST,
+        "/api/notes",
+        token.clone(),
+        Some(json!({ "title": "Removed", "content": "" })),
+    )
+    .await;
+    let (_, changes) = send(Method::GET, "/api/sync/changes", token.clone(), None).await;
+    let cursor_before_delete = changes["cursor"].as_str().unwrap().to_string();
+
+    let uri = format!("/api/notes/{}", removed["id"].as_str().unwrap());
+    let (status, _) = send(Method::DELETE, &uri, token.clone(), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let uri = format!("/api/sync/changes?since={cursor_before_delete}");
+    let (_, changes) = send(Method::GET, &uri, token.clone(), None).await;
+    let cursor_after_delete = changes["cursor"].as_str().unwrap().to_string();
+
+    // Age this user's history past the retention window, leaving other tests' rows alone
+    sqlx::query(
+        "UPDATE sync_changes SET changed_at = changed_at - INTERVAL '60 days' WHERE user_id = $1",
+    )
+    .bind(user_id)
+    .execute(&pool)
+    .await
+    .unwrap();
+    sqlx::query(
+        "UPDATE tombstones SET deleted_at = deleted_at - INTERVAL '60 days' WHERE user_id = $1",
+    )
+    .bind(user_id)
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let purged =
+        SyncService::purge_tombstones(&pool, chrono::Utc::now() - chrono::Duration::days(30))
+            .await
+            .unwrap();
+    assert!(purged >= 1);
+
+    // A cursor from before the purged deletion can no longer be caught up
+    let uri = format!("/api/sync/changes?since={cursor_before_delete}");
+    let (status, body) = send(Method::GET, &uri, token.clone(), None).await;
+    assert_eq!(status, StatusCode::GONE);
+    assert_eq!(body["error"], "gone");
+
+    // One taken after it still works
+    let uri = format!("/api/sync/changes?since={cursor_after_delete}");
+    let (status, _) = send(Method::GET, &uri, token.clone(), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // A full resync returns the surviving state
+    let (status, changes) = send(Method::GET, "/api/sync/changes", token.clone(), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let notes = changes["notes"].as_array().unwrap();
+    assert_eq!(notes.len(), 1);
+    assert_eq!(notes[0]["id"], kept["id"]);
+    assert!(changes["deleted"].as_array().unwrap().is_empty());
+
+    let (_, tombstones) = send(Method::GET, "/api/sync/tombstones", token, None).await;
+    assert!(tombstones.as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_recreating_deleted_entity_clears_tombstone() {
+    let pool = get_test_pool().await.clone();
+
+    let send =
+        |method: Method, uri: &str, token: Option<String>, body: Option<serde_json::Value>| {
+            let app = create_test_app(pool.clone());
+            let uri = uri.to_string();
+            async move {
+                let mut request = Request::builder().method(method).uri(uri);
+                if let Some(token) = token {
+                    request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+                }
+                let body = match body {
+                    Some(body) => {
+                        request = request.header(header::CONTENT_TYPE, "application/json");
+                        Body::from(body.to_string())
+                    }
+                    None => Body::empty(),
+                };
+                let response = app.oneshot(request.body(body).unwrap()).await.unwrap();
+                let status = response.status();
+                let body = body_to_string(response.into_body()).await;
+                (
+                    status,
+                    serde_json::from_str::<serde_json::Value>(&body).unwrap_or_default(),
+                )
+            }
+        };
+
+    let (status, auth) = send(
+        Method::POST,
+        "/api/auth/register",
+        None,
+        Some(json!({
+            "email": "recreated@example.com",
+            "password": "password123",
+            "name": "Recreated"
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let token = Some(auth["token"].as_str().unwrap().to_string());
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let note = json!({ "id": id, "title": "Phoenix", "content": "" });
+    let (status, _) = send(
+        Method::POST,
+        "/api/notes",
+        token.clone(),
+        Some(note.clone()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let uri = format!("/api/notes/{id}");
+    let (status, _) = send(Method::DELETE, &uri, token.clone(), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (_, tombstones) = send(Method::GET, "/api/sync/tombstones", token.clone(), None).await;
+    assert_eq!(tombstones[0]["id"], id.as_str());
+
+    // Recreating it with the same client id brings it back to life
+    let (status, _) = send(Method::POST, "/api/notes", token.clone(), Some(note)).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let (_, tombstones) = send(Method::GET, "/api/sync/tombstones", token.clone(), None).await;
+    assert!(tombstones.as_array().unwrap().is_empty());
+
+    let (_, changes) = send(Method::GET, "/api/sync/changes", token, None).await;
+    assert!(changes["deleted"].as_array().unwrap().is_empty());
+    assert_eq!(changes["notes"][0]["id"], id.as_str());
+}
+
+#[tokio::test]
+async fn test_note_conditional_requests() {
+    let pool = get_test_pool().await.clone();
+
+    // Register and get token
+    let app1 = create_test_app(pool.clone());
+    let register_request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/auth/register")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            json!({
+                "email": "etagtest@example.com",
+                "password": "password123",
+                "name": "ETag Test"
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let register_response = app1.oneshot(register_request).await.unwrap();
+    let body = body_to_string(register_response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let token = json["token"].as_str().unwrap().to_string();
+
+    // Create a note and keep its ETag
+    let app2 = create_test_app(pool.clone());
+    let create_request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/notes")
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::from(
+            json!({
+                "title": "Versioned Note",
+                "content": "First draft"
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let create_response = app2.oneshot(create_request).await.unwrap();
+    let original_etag = create_response.headers()[header::ETAG]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let body = body_to_string(create_response.into_body()).await;
+    let note: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let note_id = note["id"].as_str().unwrap().to_string();
+
+    // Unchanged note is not re-sent
+    let app3 = create_test_app(pool.clone());
+    let get_request = Request::builder()
+        .method(Method::GET)
+        .uri(format!("/api/notes/{}", note_id))
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .header(header::IF_NONE_MATCH, &original_etag)
+        .body(Body::empty())
+        .unwrap();
+
+    let get_response = app3.oneshot(get_request).await.unwrap();
+    assert_eq!(get_response.status(), StatusCode::NOT_MODIFIED);
+
+    // First device updates from the original version
+    let app4 = create_test_app(pool.clone());
+    let update_request = Request::builder()
+        .method(Method::PUT)
+        .uri(format!("/api/notes/{}", note_id))
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .header(header::IF_MATCH, &original_etag)
+        .body(Body::from(
+            json!({ "content": "Device A edit" }).to_string(),
+        ))
+        .unwrap();
+
+    let update_response = app4.oneshot(update_request).await.unwrap();
+    assert_eq!(update_response.status(), StatusCode::OK);
+    assert_ne!(
+        update_response.headers()[header::ETAG],
+        original_etag.as_str()
+    );
+
+    // Second device is now stale and gets the server copy back
+    let app5 = create_test_app(pool);
+    let stale_request = Request::builder()
+        .method(Method::DELETE)
+        .uri(format!("/api/notes/{}", note_id))
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .header(header::IF_MATCH, &original_etag)
+        .body(Body::empty())
+        .unwrap();
+
+    let stale_response = app5.oneshot(stale_request).await.unwrap();
+    assert_eq!(stale_response.status(), StatusCode::PRECONDITION_FAILED);
+
+    let body = body_to_string(stale_response.into_body()).await;
+    let error: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(error["current"]["content"], "Device A edit");
+}
+
+#[tokio::test]
+async fn test_note_concurrent_edits_merge_or_conflict() {
+    let pool = get_test_pool().await.clone();
+
+    // Register and get token
+    let app1 = create_test_app(pool.clone());
+    let register_request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/auth/register")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            json!({
+                "email": "mergetest@example.com",
+                "password": "password123",
+                "name": "Merge Test"
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let register_response = app1.oneshot(register_request).await.unwrap();
+    let body = body_to_string(register_response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let token = json["token"].as_str().unwrap().to_string();
+
+    // Create a multi-line note both devices start from
+    let app2 = create_test_app(pool.clone());
+    let create_request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/notes")
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::from(
+            json!({
+                "title": "Shopping",
+                "content": "milk\nbread\neggs\n"
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let create_response = app2.oneshot(create_request).await.unwrap();
+    let base_etag = create_response.headers()[header::ETAG]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let body = body_to_string(create_response.into_body()).await;
+    let note: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let note_id = note["id"].as_str().unwrap().to_string();
+
+    // Device A edits the first line
+    let app3 = create_test_app(pool.clone());
+    let update_request = Request::builder()
+        .method(Method::PUT)
+        .uri(format!("/api/notes/{}", note_id))
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .header(header::IF_MATCH, &base_etag)
+        .body(Body::from(
+            json!({ "content": "oat milk\nbread\neggs\n" }).to_string(),
+        ))
+        .unwrap();
+
+    let update_response = app3.oneshot(update_request).await.unwrap();
+    assert_eq!(update_response.status(), StatusCode::OK);
+
+    // Device B edits the last line from the same base and is merged
+    let app4 = create_test_app(pool.clone());
+    let merge_request = Request::builder()
+        .method(Method::PUT)
+        .uri(format!("/api/notes/{}", note_id))
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .header(header::IF_MATCH, &base_etag)
+        .body(Body::from(
+            json!({ "content": "milk\nbread\nfree-range eggs\n" }).to_string(),
+        ))
+        .unwrap();
+
+    let merge_response = app4.oneshot(merge_request).await.unwrap();
+    assert_eq!(merge_response.status(), StatusCode::OK);
+    assert_eq!(merge_response.headers()[header::ETAG], "\"3\"");
+
+    let body = body_to_string(merge_response.into_body()).await;
+    let merged: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(merged["content"], "oat milk\nbread\nfree-range eggs\n");
+
+    // Device C rewrites the first line from the same base and conflicts
+    let app5 = create_test_app(pool.clone());
+    let conflict_request = Request::builder()
+        .method(Method::PUT)
+        .uri(format!("/api/notes/{}", note_id))
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .header(header::IF_MATCH, &base_etag)
+        .body(Body::from(
+            json!({ "content": "soy milk\nbread\neggs\n" }).to_string(),
+        ))
+        .unwrap();
+
+    let conflict_response = app5.oneshot(conflict_request).await.unwrap();
+    assert_eq!(conflict_response.status(), StatusCode::CONFLICT);
+
+    let body = body_to_string(conflict_response.into_body()).await;
+    let conflict: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let conflict_id = conflict["id"].as_str().unwrap().to_string();
+    assert_eq!(conflict["base_version"], 1);
+    assert_eq!(conflict["server_version"], 3);
+    assert_eq!(conflict["client_content"], "soy milk\nbread\neggs\n");
+
+    // Conflict is listed until resolved
+    let app6 = create_test_app(pool.clone());
+    let list_request = Request::builder()
+        .method(Method::GET)
+        .uri(format!("/api/notes/{}/conflicts", note_id))
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap();
+
+    let list_response = app6.oneshot(list_request).await.unwrap();
+    assert_eq!(list_response.status(), StatusCode::OK);
+
+    let body = body_to_string(list_response.into_body()).await;
+    let conflicts: Vec<serde_json::Value> = serde_json::from_str(&body).unwrap();
+    assert_eq!(conflicts.len(), 1);
+
+    // Resolve by keeping the client copy
+    let app7 = create_test_app(pool.clone());
+    let resolve_request = Request::builder()
+        .method(Method::POST)
+        .uri(format!(
+            "/api/notes/{}/conflicts/{}/resolve",
+            note_id, conflict_id
+        ))
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::from(json!({ "resolution": "client" }).to_string()))
+        .unwrap();
+
+    let resolve_response = app7.oneshot(resolve_request).await.unwrap();
+    assert_eq!(resolve_response.status(), StatusCode::OK);
+
+    let body = body_to_string(resolve_response.into_body()).await;
+    let resolved: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(resolved["content"], "soy milk\nbread\neggs\n");
+    assert_eq!(resolved["version"], 4);
+
+    let app8 = create_test_app(pool.clone());
+    let list_request = Request::builder()
+        .method(Method::GET)
+        .uri(format!("/api/notes/{}/conflicts", note_id))
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap();
+
+    let list_response = app8.oneshot(list_request).await.unwrap();
+    let body = body_to_string(list_response.into_body()).await;
+    let conflicts: Vec<serde_json::Value> = serde_json::from_str(&body).unwrap();
+    assert!(conflicts.is_empty());
+
+    // Only the latest revisions are kept as merge bases
+    for i in 0..NoteService::REVISION_LIMIT {
+        let app = create_test_app(pool.clone());
+        let update_request = Request::builder()
+            .method(Method::PUT)
+            .uri(format!("/api/notes/{}", note_id))
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .body(Body::from(
+                json!({ "content": format!("{i}\n") }).to_string(),
+            ))
+            .unwrap();
+        let update_response = app.oneshot(update_request).await.unwrap();
+        assert_eq!(update_response.status(), StatusCode::OK);
+    }
+
+    let versions: Vec<i64> = sqlx::query_scalar(
+        "SELECT version FROM note_revisions WHERE note_id = $1 ORDER BY version",
+    )
+    .bind(uuid::Uuid::parse_str(&note_id).unwrap())
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    let latest = 4 + NoteService::REVISION_LIMIT;
+    assert_eq!(versions.len() as i64, NoteService::REVISION_LIMIT);
+    assert_eq!(versions.last(), Some(&latest));
+
+    // A base that has been pruned can no longer be merged
+    let app9 = create_test_app(pool);
+    let stale_request = Request::builder()
+        .method(Method::PUT)
+        .uri(format!("/api/notes/{}", note_id))
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .header(header::IF_MATCH, &base_etag)
+        .body(Body::from(json!({ "content": "tea\n" }).to_string()))
+        .unwrap();
+    let stale_response = app9.oneshot(stale_request).await.unwrap();
+    assert_eq!(stale_response.status(), StatusCode::PRECONDITION_FAILED);
+}
+
+#[tokio::test]
+async fn test_websocket_pushes_changes() {
+    let pool = get_test_pool().await.clone();
+
+    // Register and get token
+    let app1 = create_test_app(pool.clone());
+    let register_request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/auth/register")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            json!({
+                "email": "wstest@example.com",
+                "password": "password123",
+                "name": "WebSocket Test"
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let register_response = app1.oneshot(register_request).await.unwrap();
+    let body = body_to_string(register_response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let token = json["token"].as_str().unwrap().to_string();
+
+    // Serve an instance that listens for change notifications
+    let events = EventBus::new();
+    start_listener(&pool, events.clone()).await.unwrap();
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server_app = create_test_app_with_events(pool.clone(), events);
+    tokio::spawn(async move { axum::serve(listener, server_app).await.unwrap() });
+
+    // Handshake without a token is rejected
+    let unauthorized = tokio_tungstenite::connect_async(format!("ws://{}/api/ws", addr)).await;
+    match unauthorized {
+        Err(tungstenite::Error::Http(response)) => {
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED)
+        }
+        other => panic!("expected 401 handshake, got {:?}", other.map(|_| ())),
+    }
+
+    let (mut socket, _) =
+        tokio_tungstenite::connect_async(format!("ws://{}/api/ws?token={}", addr, token))
+            .await
+            .unwrap();
+
+    // A change made through another instance is pushed to the socket
+    let app2 = create_test_app(pool);
+    let create_request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/notes")
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::from(
+            json!({
+                "title": "Pushed Note",
+                "content": "Hello from another device"
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let create_response = app2.oneshot(create_request).await.unwrap();
+    let body = body_to_string(create_response.into_body()).await;
+    let note: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    let message = tokio::time::timeout(std::time::Duration::from_secs(10), socket.next())
+        .await
+        .expect("no change event received")
+        .unwrap()
+        .unwrap();
+
+    let event: serde_json::Value = serde_json::from_str(message.to_text().unwrap()).unwrap();
+    assert_eq!(event["entity_type"], "note");
+    assert_eq!(event["id"], note["id"]);
+    assert_eq!(event["operation"], "upsert");
+    assert!(event["cursor"].as_str().unwrap().starts_with("v1."));
+    assert!(event.get("user_id").is_none());
+}
+
+#[tokio::test]
+async fn test_event_stream_replays_and_pushes_changes() {
+    let pool = get_test_pool().await.clone();
+
+    // Register and get token
+    let app1 = create_test_app(pool.clone());
+    let register_request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/auth/register")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            json!({
+                "email": "ssetest@example.com",
+                "password": "password123",
+                "name": "SSE Test"
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let register_response = app1.oneshot(register_request).await.unwrap();
+    let body = body_to_string(register_response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let token = json["token"].as_str().unwrap().to_string();
+    let user_id = uuid::Uuid::parse_str(json["user"]["id"].as_str().unwrap()).unwrap();
+
+    // A change made while the client was offline
+    let app2 = create_test_app(pool.clone());
+    let create_request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/tags")
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::from(json!({ "name": "offline" }).to_string()))
+        .unwrap();
+
+    let create_response = app2.oneshot(create_request).await.unwrap();
+    let body = body_to_string(create_response.into_body()).await;
+    let tag: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let tag_id = tag["id"].as_str().unwrap().to_string();
+
+    // Stream requires authentication
+    let app3 = create_test_app(pool.clone());
+    let unauthorized_request = Request::builder()
+        .method(Method::GET)
+        .uri("/api/events")
+        .body(Body::empty())
+        .unwrap();
+
+    let unauthorized_response = app3.oneshot(unauthorized_request).await.unwrap();
+    assert_eq!(unauthorized_response.status(), StatusCode::UNAUTHORIZED);
+
+    // Reconnecting from the start of the log replays the missed change
+    let events = EventBus::new();
+    start_listener(&pool, events.clone()).await.unwrap();
+
+    let app4 = create_test_app_with_events(pool.clone(), events);
+    let stream_request = Request::builder()
+        .method(Method::GET)
+        .uri("/api/events")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .header("Last-Event-ID", "v1.0000000000000000")
+        .body(Body::empty())
+        .unwrap();
+
+    let stream_response = app4.oneshot(stream_request).await.unwrap();
+    assert_eq!(stream_response.status(), StatusCode::OK);
+    assert_eq!(
+        stream_response.headers()[header::CONTENT_TYPE],
+        "text/event-stream"
+    );
+
+    let mut stream = stream_response.into_body();
+    let replayed = read_stream_until(&mut stream, &tag_id).await;
+    assert!(replayed.contains("event: change"));
+    assert!(replayed.contains("id: v1."));
+    assert!(replayed.contains("\"entity_type\":\"tag\""));
+    assert!(replayed.contains("\"version\":1"));
+
+    // Live changes follow the replay
+    let app5 = create_test_app(pool.clone());
+    let create_request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/notes")
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::from(
+            json!({
+                "title": "Live Note",
+                "content": "Streamed"
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let create_response = app5.oneshot(create_request).await.unwrap();
+    let body = body_to_string(create_response.into_body()).await;
+    let note: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    let live = read_stream_until(&mut stream, note["id"].as_str().unwrap()).await;
+    assert!(live.contains("\"entity_type\":\"note\""));
+    assert!(live.contains("\"operation\":\"upsert\""));
+
+    // Notifications lost or delivered out of order leave a gap in the
+    // sequence, which is filled from the change log
+    let quiet = EventBus::new();
+    let app6 = create_test_app_with_events(pool.clone(), quiet.clone());
+    let stream_request = Request::builder()
+        .method(Method::GET)
+        .uri("/api/events")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap();
+    let mut stream = app6.oneshot(stream_request).await.unwrap().into_body();
+
+    let mut note_ids = Vec::new();
+    for title in ["Missed Note", "Notified Note"] {
+        let app = create_test_app(pool.clone());
+        let create_request = Request::builder()
+            .method(Method::POST)
+            .uri("/api/notes")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .body(Body::from(
+                json!({ "title": title, "content": "" }).to_string(),
+            ))
+            .unwrap();
+        let body = body_to_string(app.oneshot(create_request).await.unwrap().into_body()).await;
+        let note: serde_json::Value = serde_json::from_str(&body).unwrap();
+        note_ids.push(note["id"].as_str().unwrap().to_string());
+    }
+
+    let (seq, version): (i64, i64) = sqlx::query_as(
+        "SELECT c.seq, n.version FROM sync_changes c JOIN notes n ON n.id = c.entity_id \
+         WHERE c.user_id = $1 AND c.entity_id = $2",
+    )
+    .bind(user_id)
+    .bind(uuid::Uuid::parse_str(&note_ids[1]).unwrap())
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    quiet.publish(ChangeEvent {
+        user_id,
+        seq,
+        entity_type: EntityType::Note,
+        entity_id: uuid::Uuid::parse_str(&note_ids[1]).unwrap(),
+        operation: ChangeOperation::Upsert,
+        version: Some(version),
+    });
+
+    let caught_up = read_stream_until(&mut stream, &note_ids[1]).await;
+    assert!(caught_up.contains(&note_ids[0]));
+}
+
+#[tokio::test]
+async fn test_streams_recheck_credentials() {
+    let pool = get_test_pool().await.clone();
+
+    // Plays another instance, learning of logouts through notifications
+    let jwt = JwtManager::new("test-secret-key-for-testing", 15, 30);
+    start_revocation_listener(&pool, jwt.revocations().clone())
+        .await
+        .unwrap();
+
+    let send =
+        |method: Method, uri: &str, token: Option<String>, body: Option<serde_json::Value>| {
+            let app = create_test_app(pool.clone());
+            let uri = uri.to_string();
+            async move {
+                let mut request = Request::builder().method(method).uri(uri);
+                if let Some(token) = token {
+                    request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+                }
+                let body = match body {
+                    Some(body) => {
+                        request = request.header(header::CONTENT_TYPE, "application/json");
+                        Body::from(body.to_string())
+                    }
+                    None => Body::empty(),
+                };
+                let response = app.oneshot(request.body(body).unwrap()).await.unwrap();
+                let status = response.status();
+                let body = body_to_string(response.into_body()).await;
+                (
+                    status,
+                    serde_json::from_str::<serde_json::Value>(&body).unwrap_or_default(),
+                )
+            }
+        };
+    let token = |auth: &serde_json::Value| auth["token"].as_str().unwrap().to_string();
+
+    let (status, laptop) = send(
+        Method::POST,
+        "/api/auth/register",
+        None,
+        Some(json!({
+            "email": "streamauth@example.com",
+            "password": "password123",
+            "name": "Stream Auth",
+            "device": { "name": "Laptop" }
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let sign_in = || {
+        send(
+            Method::POST,
+            "/api/auth/login",
+            None,
+            Some(json!({
+                "email": "streamauth@example.com",
+                "password": "password123",
+                "device": { "name": "Phone" }
+            })),
+        )
+    };
+
+    // A stream opened with a session stays open until it is logged out
+    let (_, phone) = sign_in().await;
+    let auth = AuthUser::from_token(&pool, &jwt, &token(&phone))
+        .await
+        .unwrap();
+    auth.revalidate(&pool, &jwt).await.unwrap();
+
+    let (status, _) = send(Method::POST, "/api/auth/logout", Some(token(&phone)), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    tokio::time::timeout(std::time::Duration::from_secs(10), async {
+        while auth.revalidate(&pool, &jwt).await.is_ok() {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .expect("logout never reached the stream's instance");
+    assert!(matches!(
+        auth.revalidate(&pool, &jwt).await,
+        Err(AppError::Unauthorized)
+    ));
+
+    // ...or its device is signed out remotely
+    let (_, phone) = sign_in().await;
+    let auth = AuthUser::from_token(&pool, &jwt, &token(&phone))
+        .await
+        .unwrap();
+    auth.revalidate(&pool, &jwt).await.unwrap();
+
+    let (status, _) = send(
+        Method::DELETE,
+        &format!("/api/devices/{}", phone["device"]["id"].as_str().unwrap()),
+        Some(token(&laptop)),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert!(matches!(
+        auth.revalidate(&pool, &jwt).await,
+        Err(AppError::Unauthorized)
+    ));
+
+    // ...or its token expires
+    let expired = AuthUser {
+        credential: Credential::Session {
+            jti: uuid::Uuid::new_v4(),
+            exp: chrono::Utc::now().timestamp() - 1,
+        },
+        device_id: None,
+        ..auth
+    };
+    assert!(matches!(
+        expired.revalidate(&pool, &jwt).await,
+        Err(AppError::Unauthorized)
+    ));
+
+    // A stream opened with a personal access token ends when the token is revoked
+    let (status, created) = send(
+        Method::POST,
+        "/api/auth/tokens",
+        Some(token(&laptop)),
+        Some(json!({ "name": "sync", "scopes": ["sync:read"] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let auth = AuthUser::from_token(&pool, &jwt, created["secret"].as_str().unwrap())
+        .await
+        .unwrap();
+    auth.revalidate(&pool, &jwt).await.unwrap();
+
+    let (status, _) = send(
+        Method::DELETE,
+        &format!("/api/auth/tokens/{}", created["id"].as_str().unwrap()),
+        Some(token(&laptop)),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert!(matches!(
+        auth.revalidate(&pool, &jwt).await,
+        Err(AppError::Unauthorized)
+    ));
+}
+
+#[tokio::test]
+async fn test_device_registry_and_remote_sign_out() {
+    let pool = get_test_pool().await.clone();
+
+    // Register the account from a laptop
+    let app1 = create_test_app(pool.clone());
+    let register_request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/auth/register")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            json!({
+                "email": "devicetest@example.com",
+                "password": "password123",
+                "name": "Device Test",
+                "device": { "name": "Laptop", "platform": "linux", "client_version": "1.4.0" }
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let register_response = app1.oneshot(register_request).await.unwrap();
+    let body = body_to_string(register_response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let laptop_token = json["token"].as_str().unwrap().to_string();
+    let laptop_id = json["device"]["id"].as_str().unwrap().to_string();
+    assert_eq!(json["device"]["name"], "Laptop");
+
+    // Sign in from a phone
+    let app2 = create_test_app(pool.clone());
+    let login_request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/auth/login")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            json!({
+                "email": "devicetest@example.com",
+                "password": "password123",
+                "device": { "name": "Phone", "platform": "ios", "client_version": "2.0.1" }
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let login_response = app2.oneshot(login_request).await.unwrap();
+    let body = body_to_string(login_response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let phone_token = json["token"].as_str().unwrap().to_string();
+    let phone_id = json["device"]["id"].as_str().unwrap().to_string();
+
+    // Phone syncs, acknowledging the cursor it syncs from
+    let app3 = create_test_app(pool.clone());
+    let sync_request = Request::builder()
+        .method(Method::GET)
+        .uri("/api/sync/changes?since=v1.0000000000000000")
+        .header(header::AUTHORIZATION, format!("Bearer {}", phone_token))
+        .body(Body::empty())
+        .unwrap();
+
+    let sync_response = app3.oneshot(sync_request).await.unwrap();
+    assert_eq!(sync_response.status(), StatusCode::OK);
+
+    // Both devices are listed
+    let app4 = create_test_app(pool.clone());
+    let list_request = Request::builder()
+        .method(Method::GET)
+        .uri("/api/devices")
+        .header(header::AUTHORIZATION, format!("Bearer {}", laptop_token))
+        .body(Body::empty())
+        .unwrap();
+
+    let list_response = app4.oneshot(list_request).await.unwrap();
+    assert_eq!(list_response.status(), StatusCode::OK);
+
+    let body = body_to_string(list_response.into_body()).await;
+    let devices: Vec<serde_json::Value> = serde_json::from_str(&body).unwrap();
+    assert_eq!(devices.len(), 2);
+
+    // Signing in again reuses the device, by name and platform or by id
+    let app = create_test_app(pool.clone());
+    let login_request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/auth/login")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            json!({
+                "email": "devicetest@example.com",
+                "password": "password123",
+                "device": { "name": "Phone", "platform": "ios", "client_version": "2.1.0" }
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let login_response = app.oneshot(login_request).await.unwrap();
+    let body = body_to_string(login_response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["device"]["id"], phone_id.as_str());
+    assert_eq!(json["device"]["client_version"], "2.1.0");
+
+    let app = create_test_app(pool.clone());
+    let login_request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/auth/login")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            json!({
+                "email": "devicetest@example.com",
+                "password": "password123",
+                "device": { "id": laptop_id, "name": "Work Laptop", "platform": "linux" }
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let login_response = app.oneshot(login_request).await.unwrap();
+    let body = body_to_string(login_response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["device"]["id"], laptop_id.as_str());
+    assert_eq!(json["device"]["name"], "Work Laptop");
+
+    let app = create_test_app(pool.clone());
+    let list_request = Request::builder()
+        .method(Method::GET)
+        .uri("/api/devices")
+        .header(header::AUTHORIZATION, format!("Bearer {}", laptop_token))
+        .body(Body::empty())
+        .unwrap();
+
+    let list_response = app.oneshot(list_request).await.unwrap();
+    let body = body_to_string(list_response.into_body()).await;
+    let devices: Vec<serde_json::Value> = serde_json::from_str(&body).unwrap();
+    assert_eq!(devices.len(), 2);
+
+    // The lost phone is signed out from the laptop
+    let app5 = create_test_app(pool.clone());
+    let revoke_request = Request::builder()
+        .method(Method::DELETE)
+        .uri(format!("/api/devices/{}", phone_id))
+        .header(header::AUTHORIZATION, format!("Bearer {}", laptop_token))
+        .body(Body::empty())
+        .unwrap();
+
+    let revoke_response = app5.oneshot(revoke_request).await.unwrap();
+    assert_eq!(revoke_response.status(), StatusCode::NO_CONTENT);
+
+    // Its token no longer works, while the laptop's still does
+    let app6 = create_test_app(pool.clone());
+    let phone_request = Request::builder()
+        .method(Method::GET)
+        .uri("/api/auth/me")
+        .header(header::AUTHORIZATION, format!("Bearer {}", phone_token))
+        .body(Body::empty())
+        .unwrap();
+
+    let phone_response = app6.oneshot(phone_request).await.unwrap();
+    assert_eq!(phone_response.status(), StatusCode::UNAUTHORIZED);
+
+    let app7 = create_test_app(pool);
+    let list_request = Request::builder()
+        .method(Method::GET)
+        .uri("/api/devices")
+        .header(header::AUTHORIZATION, format!("Bearer {}", laptop_token))
+        .body(Body::empty())
+        .unwrap();
+
+    let list_response = app7.oneshot(list_request).await.unwrap();
+    assert_eq!(list_response.status(), StatusCode::OK);
+
+    let body = body_to_string(list_response.into_body()).await;
+    let devices: Vec<serde_json::Value> = serde_json::from_str(&body).unwrap();
+    let phone = devices
+        .iter()
+        .find(|d| d["id"] == phone_id.as_str())
+        .unwrap();
+    assert!(phone["revoked_at"].is_string());
+    assert_eq!(phone["last_sync_cursor"], "v1.0000000000000000");
+    assert_eq!(phone["platform"], "ios");
+}
+
+#[tokio::test]
+async fn test_batch_operations_with_client_ids() {
+    let pool = get_test_pool().await.clone();
+
+    // Register and get token
+    let app1 = create_test_app(pool.clone());
+    let register_request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/auth/register")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            json!({
+                "email": "batchtest@example.com",
+                "password": "password123",
+                "name": "Batch Test"
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let register_response = app1.oneshot(register_request).await.unwrap();
+    let body = body_to_string(register_response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let token = json["token"].as_str().unwrap().to_string();
+
+    // Replay an offline queue where the bookmark references a tag and category created before it
+    let note_id = uuid::Uuid::new_v4().to_string();
+    let app2 = create_test_app(pool.clone());
+    let batch_request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/batch")
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::from(
+            json!({
+                "operations": [
+                    { "op": "create", "entity": "tag", "client_id": "tmp-tag", "data": { "name": "offline" } },
+                    { "op": "create", "entity": "category", "client_id": "tmp-cat", "data": { "name": "Reading" } },
+                    {
+                        "op": "create",
+                        "entity": "bookmark",
+                        "client_id": "tmp-bm",
+                        "data": {
+                            "url": "https://example.com/offline",
+                            "title": "Saved offline",
+                            "category_id": "tmp-cat",
+                            "tag_ids": ["tmp-tag"]
+                        }
+                    },
+                    { "op": "update", "entity": "bookmark", "id": "tmp-bm", "version": 1, "data": { "title": "Renamed offline" } },
+                    { "op": "create", "entity": "note", "id": note_id, "data": { "title": "Draft", "content": "Queued" } },
+                    { "op": "delete", "entity": "note", "id": note_id }
+                ]
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let batch_response = app2.oneshot(batch_request).await.unwrap();
+    assert_eq!(batch_response.status(), StatusCode::OK);
+
+    let body = body_to_string(batch_response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let results = json["results"].as_array().unwrap();
+    assert_eq!(results.len(), 6);
+    assert_eq!(results[0]["status"], 201);
+    assert_eq!(results[2]["data"]["category_id"], results[1]["id"]);
+    assert_eq!(results[3]["status"], 200);
+    assert_eq!(results[3]["data"]["title"], "Renamed offline");
+    assert_eq!(results[3]["id"], results[2]["id"]);
+    assert_eq!(results[4]["id"], note_id.as_str());
+    assert_eq!(results[5]["status"], 204);
+
+    // The bookmark is linked to the tag created in the same batch
+    let app3 = create_test_app(pool.clone());
+    let sync_request = Request::builder()
+        .method(Method::GET)
+        .uri("/api/sync/changes")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap();
+
+    let sync_response = app3.oneshot(sync_request).await.unwrap();
+    let body = body_to_string(sync_response.into_body()).await;
+    let changes: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(changes["bookmark_tags"][0]["tag_ids"][0], results[0]["id"]);
+
+    // A failing operation rolls back the whole batch
+    let app4 = create_test_app(pool.clone());
+    let failing_request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/batch")
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::from(
+            json!({
+                "operations": [
+                    { "op": "create", "entity": "tag", "data": { "name": "rolled-back" } },
+                    { "op": "delete", "entity": "note", "id": uuid::Uuid::new_v4() }
+                ]
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let failing_response = app4.oneshot(failing_request).await.unwrap();
+    assert_eq!(failing_response.status(), StatusCode::NOT_FOUND);
+
+    let body = body_to_string(failing_response.into_body()).await;
+    let error: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(error["operation"], 1);
+
+    let app5 = create_test_app(pool);
+    let tags_request = Request::builder()
+        .method(Method::GET)
+        .uri("/api/tags")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap();
+
+    let tags_response = app5.oneshot(tags_request).await.unwrap();
+    let body = body_to_string(tags_response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["items"].as_array().unwrap().len(), 1);
+    assert_eq!(json["items"][0]["name"], "offline");
+}
+
+#[tokio::test]
+async fn test_idempotency_key_replays_response() {
+    let pool = get_test_pool().await.clone();
+
+    // Register and get token
+    let app1 = create_test_app(pool.clone());
+    let register_request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/auth/register")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            json!({
+                "email": "idempotency@example.com",
+                "password": "password123",
+                "name": "Idempotency Test"
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let register_response = app1.oneshot(register_request).await.unwrap();
+    let body = body_to_string(register_response.into_body()).await;
+    let registered: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let token = registered["token"].as_str().unwrap().to_string();
+
+    let create_request = |title: &str| {
+        Request::builder()
+            .method(Method::POST)
+            .uri("/api/notes")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .header("Idempotency-Key", "create-note-1")
+            .body(Body::from(
+                json!({ "title": title, "content": "Sent twice" }).to_string(),
+            ))
+            .unwrap()
+    };
+
+    // First attempt creates the note
+    let app2 = create_test_app(pool.clone());
+    let first_response = app2.oneshot(create_request("Retried")).await.unwrap();
+    assert_eq!(first_response.status(), StatusCode::CREATED);
+    assert!(
+        first_response
+            .headers()
+            .get("idempotent-replayed")
+            .is_none()
+    );
+    let first_etag = first_response.headers()[header::ETAG].clone();
+    let first_body = body_to_string(first_response.into_body()).await;
+
+    // A retry gets the stored response back verbatim
+    let app3 = create_test_app(pool.clone());
+    let retry_response = app3.oneshot(create_request("Retried")).await.unwrap();
+    assert_eq!(retry_response.status(), StatusCode::CREATED);
+    assert_eq!(retry_response.headers()["idempotent-replayed"], "true");
+    assert_eq!(retry_response.headers()[header::ETAG], first_etag);
+    let retry_body = body_to_string(retry_response.into_body()).await;
+    assert_eq!(retry_body, first_body);
+
+    // Reusing the key for a different body is rejected
+    let app4 = create_test_app(pool.clone());
+    let mismatch_response = app4.oneshot(create_request("Different")).await.unwrap();
+    assert_eq!(mismatch_response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    // Only one note was created
+    let app5 = create_test_app(pool.clone());
+    let list_request = Request::builder()
+        .method(Method::GET)
+        .uri("/api/notes")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap();
+
+    let list_response = app5.oneshot(list_request).await.unwrap();
+    let body = body_to_string(list_response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["items"].as_array().unwrap().len(), 1);
+
+    // Routes issuing credentials run every time and are never stored
+    let refresh_request = |refresh_token: &str| {
+        Request::builder()
+            .method(Method::POST)
+            .uri("/api/auth/refresh")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .header("Idempotency-Key", "refresh-1")
+            .body(Body::from(
+                json!({ "refresh_token": refresh_token }).to_string(),
+            ))
+            .unwrap()
+    };
+
+    let app6 = create_test_app(pool.clone());
+    let refresh_response = app6
+        .oneshot(refresh_request(
+            registered["refresh_token"].as_str().unwrap(),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(refresh_response.status(), StatusCode::OK);
+    let body = body_to_string(refresh_response.into_body()).await;
+    let rotated: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    let app7 = create_test_app(pool.clone());
+    let retry_response = app7
+        .oneshot(refresh_request(rotated["refresh_token"].as_str().unwrap()))
+        .await
+        .unwrap();
+    assert_eq!(retry_response.status(), StatusCode::OK);
+    assert!(
+        retry_response
+            .headers()
+            .get("idempotent-replayed")
+            .is_none()
+    );
+
+    let stored: i64 = sqlx::query_scalar(
+        r#"
+        SELECT COUNT(*) FROM idempotency_keys k
+        JOIN users u ON u.id = k.user_id
+        WHERE u.email = 'idempotency@example.com' AND k.key = 'refresh-1'
+        "#,
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(stored, 0);
+}
+
+#[tokio::test]
+async fn test_create_with_client_generated_id() {
+    let pool = get_test_pool().await.clone();
+
+    // Register two users
+    let mut tokens = Vec::new();
+    for email in ["clientid1@example.com", "clientid2@example.com"] {
+        let app = create_test_app(pool.clone());
+        let register_request = Request::builder()
+            .method(Method::POST)
+            .uri("/api/auth/register")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(
+                json!({
+                    "email": email,
+                    "password": "password123",
+                    "name": "Client Id Test"
+                })
+                .to_string(),
+            ))
+            .unwrap();
+
+        let register_response = app.oneshot(register_request).await.unwrap();
+        let body = body_to_string(register_response.into_body()).await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        tokens.push(json["token"].as_str().unwrap().to_string());
+    }
+
+    let create_request = |token: &str, id: &str| {
+        Request::builder()
+            .method(Method::POST)
+            .uri("/api/tags")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .body(Body::from(
+                json!({ "id": id, "name": format!("tag-{}", &token[token.len() - 8..]) })
+                    .to_string(),
+            ))
+            .unwrap()
+    };
+
+    // The client-provided id is used as-is
+    let tag_id = uuid::Uuid::new_v4().to_string();
+    let app1 = create_test_app(pool.clone());
+    let create_response = app1
+        .oneshot(create_request(&tokens[0], &tag_id))
+        .await
+        .unwrap();
+    assert_eq!(create_response.status(), StatusCode::CREATED);
+    let body = body_to_string(create_response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["id"], tag_id.as_str());
+
+    // Another user cannot claim the same id
+    let app2 = create_test_app(pool.clone());
+    let collision_response = app2
+        .oneshot(create_request(&tokens[1], &tag_id))
+        .await
+        .unwrap();
+    assert_eq!(collision_response.status(), StatusCode::CONFLICT);
+
+    // Only random or time-ordered UUIDs are accepted
+    let app3 = create_test_app(pool.clone());
+    let v1_response = app3
+        .oneshot(create_request(
+            &tokens[1],
+            "c232ab00-9414-11ec-b3c8-9e6bdeced846",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(v1_response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_bookmark_responses_embed_tags() {
+    let pool = get_test_pool().await.clone();
+
+    // Register and get token
+    let app1 = create_test_app(pool.clone());
+    let register_request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/auth/register")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            json!({
+                "email": "bookmarktags@example.com",
+                "password": "password123",
+                "name": "Bookmark Tags Test"
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let register_response = app1.oneshot(register_request).await.unwrap();
+    let body = body_to_string(register_response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let token = json["token"].as_str().unwrap().to_string();
+
+    // Create two tags
+    let mut tag_ids = Vec::new();
+    for name in ["rust", "async"] {
+        let app = create_test_app(pool.clone());
+        let tag_request = Request::builder()
+            .method(Method::POST)
+            .uri("/api/tags")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .body(Body::from(json!({ "name": name }).to_string()))
+            .unwrap();
+
+        let tag_response = app.oneshot(tag_request).await.unwrap();
+        let body = body_to_string(tag_response.into_body()).await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        tag_ids.push(json["id"].as_str().unwrap().to_string());
+    }
+
+    // The created bookmark comes back with its tags, ordered by name
+    let app2 = create_test_app(pool.clone());
+    let create_request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/bookmarks")
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::from(
+            json!({
+                "url": "https://tokio.rs",
+                "title": "Tokio",
+                "tag_ids": tag_ids
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let create_response = app2.oneshot(create_request).await.unwrap();
+    assert_eq!(create_response.status(), StatusCode::CREATED);
+    let body = body_to_string(create_response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let bookmark_id = json["id"].as_str().unwrap().to_string();
+    assert_eq!(json["title"], "Tokio");
+    assert_eq!(json["tags"][0]["name"], "async");
+    assert_eq!(json["tags"][1]["name"], "rust");
+
+    // Listing embeds the same tags
+    let app3 = create_test_app(pool.clone());
+    let list_request = Request::builder()
+        .method(Method::GET)
+        .uri("/api/bookmarks")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap();
+
+    let list_response = app3.oneshot(list_request).await.unwrap();
+    let body = body_to_string(list_response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["items"][0]["tags"].as_array().unwrap().len(), 2);
+
+    // Updating the links is reflected in the response
+    let app4 = create_test_app(pool.clone());
+    let update_request = Request::builder()
+        .method(Method::PUT)
+        .uri(format!("/api/bookmarks/{}", bookmark_id))
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::from(json!({ "tag_ids": [tag_ids[0]] }).to_string()))
+        .unwrap();
+
+    let update_response = app4.oneshot(update_request).await.unwrap();
+    assert_eq!(update_response.status(), StatusCode::OK);
+    let body = body_to_string(update_response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["tags"].as_array().unwrap().len(), 1);
+    assert_eq!(json["tags"][0]["name"], "rust");
+
+    // A bookmark without tags gets an empty list
+    let app5 = create_test_app(pool.clone());
+    let update_request = Request::builder()
+        .method(Method::PUT)
+        .uri(format!("/api/bookmarks/{}", bookmark_id))
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::from(json!({ "tag_ids": [] }).to_string()))
+        .unwrap();
+
+    let update_response = app5.oneshot(update_request).await.unwrap();
+    let body = body_to_string(update_response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["tags"], json!([]));
+
+    let app6 = create_test_app(pool.clone());
+    let get_request = Request::builder()
+        .method(Method::GET)
+        .uri(format!("/api/bookmarks/{}", bookmark_id))
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap();
+
+    let get_response = app6.oneshot(get_request).await.unwrap();
+    assert_eq!(get_response.status(), StatusCode::OK);
+    let body = body_to_string(get_response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["id"], bookmark_id.as_str());
+    assert_eq!(json["tags"], json!([]));
+
+    // Renaming a tag changes the bookmarks embedding it
+    let app7 = create_test_app(pool.clone());
+    let update_request = Request::builder()
+        .method(Method::PUT)
+        .uri(format!("/api/bookmarks/{}", bookmark_id))
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::from(json!({ "tag_ids": [tag_ids[0]] }).to_string()))
+        .unwrap();
+
+    let update_response = app7.oneshot(update_request).await.unwrap();
+    assert_eq!(update_response.status(), StatusCode::OK);
+    let old_etag = update_response.headers()[header::ETAG].clone();
+
+    let app8 = create_test_app(pool.clone());
+    let rename_request = Request::builder()
+        .method(Method::PUT)
+        .uri(format!("/api/tags/{}", tag_ids[0]))
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::from(json!({ "name": "rustlang" }).to_string()))
+        .unwrap();
+
+    let rename_response = app8.oneshot(rename_request).await.unwrap();
+    assert_eq!(rename_response.status(), StatusCode::OK);
+
+    let app9 = create_test_app(pool);
+    let get_request = Request::builder()
+        .method(Method::GET)
+        .uri(format!("/api/bookmarks/{}", bookmark_id))
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .header(header::IF_NONE_MATCH, &old_etag)
+        .body(Body::empty())
+        .unwrap();
+
+    let get_response = app9.oneshot(get_request).await.unwrap();
+    assert_eq!(get_response.status(), StatusCode::OK);
+    assert_ne!(get_response.headers()[header::ETAG], old_etag);
+    let body = body_to_string(get_response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["tags"][0]["name"], "rustlang");
+}
+
+#[tokio::test]
+async fn test_bookmarks_only_link_own_tags() {
+    let pool = get_test_pool().await.clone();
+
+    let send =
+        |method: Method, uri: &str, token: Option<String>, body: Option<serde_json::Value>| {
+            let app = create_test_app(pool.clone());
+            let uri = uri.to_string();
+            async move {
+                let mut request = Request::builder().method(method).uri(uri);
+                if let Some(token) = token {
+                    request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+                }
+                let body = match body {
+                    Some(body) => {
+                        request = request.header(header::CONTENT_TYPE, "application/json");
+                        Body::from(body.to_string())
+                    }
+                    None => Body::empty(),
+                };
+                let response = app.oneshot(request.body(body).unwrap()).await.unwrap();
+                let status = response.status();
+                let body = body_to_string(response.into_body()).await;
+                (
+                    status,
+                    serde_json::from_str::<serde_json::Value>(&body).unwrap_or_default(),
+                )
+            }
+        };
+
+    let mut tokens = Vec::new();
+    for email in ["tagowner@example.com", "tagthief@example.com"] {
+        let (status, auth) = send(
+            Method::POST,
+            "/api/auth/register",
+            None,
+            Some(json!({ "email": email, "password": "password123", "name": "Tags" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        tokens.push(Some(auth["token"].as_str().unwrap().to_string()));
+    }
+    let (owner, thief) = (tokens[0].clone(), tokens[1].clone());
+
+    let (status, foreign_tag) = send(
+        Method::POST,
+        "/api/tags",
+        owner,
+        Some(json!({ "name": "private" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (_, own_tag) = send(
+        Method::POST,
+        "/api/tags",
+        thief.clone(),
+        Some(json!({ "name": "mine" })),
+    )
+    .await;
+
+    // Another user's tag cannot be linked when creating...
+    let (status, _) = send(
+        Method::POST,
+        "/api/bookmarks",
+        thief.clone(),
+        Some(json!({
+            "url": "https://example.com/tagged",
+            "title": "Tagged",
+            "tag_ids": [own_tag["id"], foreign_tag["id"]]
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, bookmark) = send(
+        Method::POST,
+        "/api/bookmarks",
+        thief.clone(),
+        Some(json!({ "url": "https://example.com/tagged", "title": "Tagged" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let uri = format!("/api/bookmarks/{}", bookmark["id"].as_str().unwrap());
+
+    // ...merging into a duplicate...
+    let (status, _) = send(
+        Method::POST,
+        "/api/bookmarks",
+        thief.clone(),
+        Some(json!({
+            "url": "https://example.com/tagged",
+            "title": "Tagged",
+            "on_duplicate": "merge",
+            "tag_ids": [foreign_tag["id"]]
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // ...or updating
+    let (status, _) = send(
+        Method::PUT,
+        &uri,
+        thief.clone(),
+        Some(json!({ "tag_ids": [foreign_tag["id"]] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, fetched) = send(Method::GET, &uri, thief.clone(), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(fetched["tags"], json!([]));
+
+    // Links made before ownership was checked never reveal the tag
+    sqlx::query("INSERT INTO bookmark_tags (bookmark_id, tag_id) VALUES ($1, $2)")
+        .bind(uuid::Uuid::parse_str(bookmark["id"].as_str().unwrap()).unwrap())
+        .bind(uuid::Uuid::parse_str(foreign_tag["id"].as_str().unwrap()).unwrap())
+        .execute(&pool)
+        .await
+        .unwrap();
+    let (_, fetched) = send(Method::GET, &uri, thief.clone(), None).await;
+    assert_eq!(fetched["tags"], json!([]));
+    let (_, found) = send(Method::GET, "/api/search?q=tag:private", thief, None).await;
+    assert_eq!(found, json!([]));
+}
+
+#[tokio::test]
+async fn test_list_pagination_and_filters() {
+    let pool = get_test_pool().await.clone();
+
+    // Register and get token
+    let app1 = create_test_app(pool.clone());
+    let register_request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/auth/register")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            json!({
+                "email": "pagination@example.com",
+                "password": "password123",
+                "name": "Pagination Test"
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let register_response = app1.oneshot(register_request).await.unwrap();
+    let body = body_to_string(register_response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let token = json["token"].as_str().unwrap().to_string();
+
+    let get = |uri: String| {
+        let app = create_test_app(pool.clone());
+        let token = token.clone();
+        async move {
+            let request = Request::builder()
+                .method(Method::GET)
+                .uri(uri)
+                .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap();
+            let response = app.oneshot(request).await.unwrap();
+            let status = response.status();
+            let body = body_to_string(response.into_body()).await;
+            (
+                status,
+                serde_json::from_str::<serde_json::Value>(&body).ok(),
+            )
+        }
+    };
+
+    // Two tags
+    let mut tag_ids = Vec::new();
+    for name in ["red", "blue"] {
+        let app = create_test_app(pool.clone());
+        let tag_request = Request::builder()
+            .method(Method::POST)
+            .uri("/api/tags")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .body(Body::from(json!({ "name": name }).to_string()))
+            .unwrap();
+
+        let tag_response = app.oneshot(tag_request).await.unwrap();
+        let body = body_to_string(tag_response.into_body()).await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        tag_ids.push(json["id"].as_str().unwrap().to_string());
+    }
+    let (red, blue) = (tag_ids[0].clone(), tag_ids[1].clone());
+
+    // Five bookmarks across domains and tag combinations
+    let bookmarks = [
+        (
+            "https://example.com/a",
+            "Echo",
+            vec![red.clone(), blue.clone()],
+        ),
+        ("https://docs.example.com/b", "Alpha", vec![red.clone()]),
+        ("https://user@EXAMPLE.com:8080/c", "Delta", vec![]),
+        ("https://notexample.com/d", "Charlie", vec![blue.clone()]),
+        ("https://other.org/e", "Bravo", vec![]),
+    ];
+    for (url, title, tags) in bookmarks {
+        let app = create_test_app(pool.clone());
+        let create_request = Request::builder()
+            .method(Method::POST)
+            .uri("/api/bookmarks")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .body(Body::from(
+                json!({ "url": url, "title": title, "tag_ids": tags }).to_string(),
+            ))
+            .unwrap();
+
+        let create_response = app.oneshot(create_request).await.unwrap();
+        assert_eq!(create_response.status(), StatusCode::CREATED);
+    }
+
+    // Walk the pages sorted by title
+    let mut titles = Vec::new();
+    let mut uri = "/api/bookmarks?sort=title&limit=2&include_total=true".to_string();
+    loop {
+        let (status, json) = get(uri.clone()).await;
+        assert_eq!(status, StatusCode::OK);
+        let json = json.unwrap();
+        assert_eq!(json["total"], 5);
+        for item in json["items"].as_array().unwrap() {
+            titles.push(item["title"].as_str().unwrap().to_string());
+        }
+        match json["next_cursor"].as_str() {
+            Some(cursor) => {
+                uri =
+                    format!("/api/bookmarks?sort=title&limit=2&include_total=true&cursor={cursor}")
+            }
+            None => break,
+        }
+    }
+    assert_eq!(titles, ["Alpha", "Bravo", "Charlie", "Delta", "Echo"]);
+
+    // Default order is newest first, without a total unless asked for
+    let (_, json) = get("/api/bookmarks?limit=2".to_string()).await;
+    let json = json.unwrap();
+    assert_eq!(json["items"][0]["title"], "Bravo");
+    assert!(json.get("total").is_none());
+
+    // A cursor only works with the sort it was issued for
+    let cursor = json["next_cursor"].as_str().unwrap().to_string();
+    let (status, _) = get(format!("/api/bookmarks?sort=title&cursor={cursor}")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // A tampered cursor is rejected rather than reaching the database
+    let mut tampered = PageCursor::decode(&cursor).unwrap();
+    tampered.key = "not a timestamp".to_string();
+    let (status, json) = get(format!("/api/bookmarks?cursor={}", tampered.encode())).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(json.unwrap()["error"], "validation_error");
+
+    // Domain filter covers subdomains but not lookalikes
+    let (_, json) = get("/api/bookmarks?url_domain=example.com&sort=title".to_string()).await;
+    let titles: Vec<_> = json.unwrap()["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["title"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(titles, ["Alpha", "Delta", "Echo"]);
+
+    // Tag filters match any or all of the given tags
+    let (_, json) = get(format!(
+        "/api/bookmarks?tag_id={red},{blue}&include_total=true"
+    ))
+    .await;
+    assert_eq!(json.unwrap()["total"], 3);
+
+    let (_, json) = get(format!("/api/bookmarks?tag_id={red},{blue}&tag_match=all")).await;
+    let json = json.unwrap();
+    assert_eq!(json["items"].as_array().unwrap().len(), 1);
+    assert_eq!(json["items"][0]["title"], "Echo");
+
+    // Other lists accept the same paging parameters
+    let (_, json) = get("/api/tags?limit=1".to_string()).await;
+    let json = json.unwrap();
+    assert_eq!(json["items"][0]["name"], "blue");
+    assert!(json["next_cursor"].is_string());
+
+    let (status, _) = get("/api/notes?limit=10&sort=title&order=desc".to_string()).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_full_text_search() {
+    let pool = get_test_pool().await.clone();
+
+    // Register two users
+    let mut tokens = Vec::new();
+    for email in ["search1@example.com", "search2@example.com"] {
+        let app = create_test_app(pool.clone());
+        let register_request = Request::builder()
+            .method(Method::POST)
+            .uri("/api/auth/register")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(
+                json!({
+                    "email": email,
+                    "password": "password123",
+                    "name": "Search Test"
+                })
+                .to_string(),
+            ))
+            .unwrap();
+
+        let register_response = app.oneshot(register_request).await.unwrap();
+        let body = body_to_string(register_response.into_body()).await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        tokens.push(json["token"].as_str().unwrap().to_string());
+    }
+
+    let post = |token: String, uri: &'static str, body: serde_json::Value| {
+        let app = create_test_app(pool.clone());
+        async move {
+            let request = Request::builder()
+                .method(Method::POST)
+                .uri(uri)
+                .header(header::CONTENT_TYPE, "application/json")
+                .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                .body(Body::from(body.to_string()))
+                .unwrap();
+            let response = app.oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::CREATED);
+        }
+    };
+
+    post(
+        tokens[0].clone(),
+        "/api/bookmarks",
+        json!({
+            "url": "https://example.com/tools",
+            "title": "Useful tools",
+            "description": "A list of <b>compilers</b>, including one for Rust"
+        }),
+    )
+    .await;
+    post(
+        tokens[0].clone(),
+        "/api/bookmarks",
+        json!({ "url": "https://www.rust-lang.org", "title": "The Rust Programming Language" }),
+    )
+    .await;
+    post(
+        tokens[0].clone(),
+        "/api/notes",
+        json!({ "title": "Weekend plans", "content": "Finish the rust ownership chapter" }),
+    )
+    .await;
+    post(
+        tokens[1].clone(),
+        "/api/notes",
+        json!({ "title": "Rust notes", "content": "Belongs to someone else" }),
+    )
+    .await;
+
+    let search = |uri: &'static str| {
+        let app = create_test_app(pool.clone());
+        let token = tokens[0].clone();
+        async move {
+            let request = Request::builder()
+                .method(Method::GET)
+                .uri(uri)
+                .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap();
+            let response = app.oneshot(request).await.unwrap();
+            let status = response.status();
+            let body = body_to_string(response.into_body()).await;
+            (
+                status,
+                serde_json::from_str::<serde_json::Value>(&body).ok(),
+            )
+        }
+    };
+
+    // Mixed results, title matches first, other users' data excluded
+    let (status, json) = search("/api/search?q=rust").await;
+    assert_eq!(status, StatusCode::OK);
+    let results = json.unwrap();
+    let results = results.as_array().unwrap();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0]["title"], "The Rust Programming Language");
+    assert_eq!(results[0]["entity_type"], "bookmark");
+    assert!(
+        results
+            .iter()
+            .any(|r| r["entity_type"] == "note" && r["title"] == "Weekend plans")
+    );
+
+    // Snippets highlight matches and escape the stored text
+    let tools = results
+        .iter()
+        .find(|r| r["title"] == "Useful tools")
+        .unwrap();
+    let snippet = tools["snippet"].as_str().unwrap();
+    assert!(snippet.contains("<mark>Rust</mark>"));
+    assert!(snippet.contains("&lt;b&gt;"));
+
+    // Stemming and type filtering
+    let (_, json) = search("/api/search?q=compiler&type=bookmark").await;
+    assert_eq!(json.unwrap().as_array().unwrap().len(), 1);
+
+    let (_, json) = search("/api/search?q=rust&type=note").await;
+    assert_eq!(json.unwrap().as_array().unwrap().len(), 1);
+
+    // A blank query is rejected
+    let (status, _) = search("/api/search?q=%20").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_search_query_language() {
+    let pool = get_test_pool().await.clone();
+
+    let app = create_test_app(pool.clone());
+    let register_request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/auth/register")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            json!({
+                "email": "searchlang@example.com",
+                "password": "password123",
+                "name": "Search Language"
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let register_response = app.oneshot(register_request).await.unwrap();
+    let body = body_to_string(register_response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let token = json["token"].as_str().unwrap().to_string();
+
+    let post = |uri: &'static str, body: serde_json::Value| {
+        let app = create_test_app(pool.clone());
+        let token = token.clone();
+        async move {
+            let request = Request::builder()
+                .method(Method::POST)
+                .uri(uri)
+                .header(header::CONTENT_TYPE, "application/json")
+                .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                .body(Body::from(body.to_string()))
+                .unwrap();
+            let response = app.oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::CREATED);
+        }
+    };
+
+    let rust_tag = uuid::Uuid::new_v4();
+    let archived_tag = uuid::Uuid::new_v4();
+    let work = uuid::Uuid::new_v4();
+    let infra = uuid::Uuid::new_v4();
+    let servers = uuid::Uuid::new_v4();
+
+    post("/api/tags", json!({ "id": rust_tag, "name": "Rust" })).await;
+    post(
+        "/api/tags",
+        json!({ "id": archived_tag, "name": "archived" }),
+    )
+    .await;
+    post("/api/categories", json!({ "id": work, "name": "Work" })).await;
+    post(
+        "/api/categories",
+        json!({ "id": infra, "name": "Infra", "parent_id": work }),
+    )
+    .await;
+    post(
+        "/api/categories",
+        json!({ "id": servers, "name": "Servers", "parent_id": infra }),
+    )
+    .await;
+
+    post(
+        "/api/bookmarks",
+        json!({
+            "url": "https://github.com/tokio-rs/tokio",
+            "title": "Tokio runtime",
+            "category_id": servers,
+            "tag_ids": [rust_tag],
+            "is_favorite": true
+        }),
+    )
+    .await;
+    post(
+        "/api/bookmarks",
+        json!({
+            "url": "https://docs.github.com/en",
+            "title": "GitHub docs",
+            "tag_ids": [rust_tag, archived_tag]
+        }),
+    )
+    .await;
+    post(
+        "/api/bookmarks",
+        json!({ "url": "https://notgithub.com", "title": "Lookalike site" }),
+    )
+    .await;
+    post(
+        "/api/notes",
+        json!({ "title": "Runtime notes", "content": "Comparing async runtime designs" }),
+    )
+    .await;
+
+    let search = |uri: String| {
+        let app = create_test_app(pool.clone());
+        let token = token.clone();
+        async move {
+            let request = Request::builder()
+                .method(Method::GET)
+                .uri(uri)
+                .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap();
+            let response = app.oneshot(request).await.unwrap();
+            let status = response.status();
+            let body = body_to_string(response.into_body()).await;
+            let json = serde_json::from_str::<serde_json::Value>(&body).unwrap();
+            (status, json)
+        }
+    };
+    let titles = |json: &serde_json::Value| {
+        let mut titles: Vec<String> = json
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["title"].as_str().unwrap().to_string())
+            .collect();
+        titles.sort();
+        titles
+    };
+
+    // Tags match case-insensitively and can be excluded
+    let (status, json) = search("/api/search?q=tag:rust".to_string()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(titles(&json), ["GitHub docs", "Tokio runtime"]);
+
+    let (_, json) = search("/api/search?q=tag:rust%20-tag:archived".to_string()).await;
+    assert_eq!(titles(&json), ["Tokio runtime"]);
+
+    // Category paths include subcategories and are read from the top level
+    let (_, json) = search("/api/search?q=category:%22work/infra%22".to_string()).await;
+    assert_eq!(titles(&json), ["Tokio runtime"]);
+
+    let (_, json) = search("/api/search?q=category:Work".to_string()).await;
+    assert_eq!(titles(&json), ["Tokio runtime"]);
+
+    let (_, json) = search("/api/search?q=category:Infra/Servers".to_string()).await;
+    assert_eq!(titles(&json), Vec::<String>::new());
+
+    let (_, json) = search("/api/search?q=category:Wo".to_string()).await;
+    assert_eq!(titles(&json), Vec::<String>::new());
+
+    let (_, json) = search("/api/search?q=category:Servers/Infra".to_string()).await;
+    assert_eq!(titles(&json), Vec::<String>::new());
+
+    // Sites include subdomains but not lookalikes
+    let (_, json) = search("/api/search?q=site:github.com".to_string()).await;
+    assert_eq!(titles(&json), ["GitHub docs", "Tokio runtime"]);
+
+    // Free text combines with qualifiers
+    let (_, json) = search("/api/search?q=runtime".to_string()).await;
+    assert_eq!(titles(&json), ["Runtime notes", "Tokio runtime"]);
+
+    let (_, json) = search("/api/search?q=runtime%20is:note".to_string()).await;
+    assert_eq!(titles(&json), ["Runtime notes"]);
+
+    let (_, json) = search("/api/search?q=is:untagged%20-is:note".to_string()).await;
+    assert_eq!(titles(&json), ["Lookalike site"]);
+
+    // Only bookmarks can be favorites
+    let (_, json) = search("/api/search?q=is:favorite".to_string()).await;
+    assert_eq!(titles(&json), ["Tokio runtime"]);
+
+    let (_, json) = search("/api/search?q=-is:favorite%20site:github.com".to_string()).await;
+    assert_eq!(titles(&json), ["GitHub docs"]);
+
+    // Dates compare against creation time
+    let (_, json) = search("/api/search?q=before:2000-01-01".to_string()).await;
+    assert_eq!(titles(&json).len(), 0);
+
+    let (_, json) = search("/api/search?q=after:2000-01-01".to_string()).await;
+    assert_eq!(titles(&json).len(), 4);
+
+    // Malformed queries are validation errors
+    for q in ["tag:", "is:starred", "before:soon", "%22unterminated"] {
+        let (status, json) = search(format!("/api/search?q={q}")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(
+            json["message"]
+                .as_str()
+                .unwrap()
+                .contains("Invalid search query")
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_saved_searches() {
+    let pool = get_test_pool().await.clone();
+
+    let app = create_test_app(pool.clone());
+    let register_request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/auth/register")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            json!({
+                "email": "savedsearch@example.com",
+                "password": "password123",
+                "name": "Saved Search"
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let register_response = app.oneshot(register_request).await.unwrap();
+    let body = body_to_string(register_response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let token = json["token"].as_str().unwrap().to_string();
+
+    let send = |method: Method, uri: String, body: Option<serde_json::Value>| {
+        let app = create_test_app(pool.clone());
+        let token = token.clone();
+        async move {
+            let mut request = Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(header::AUTHORIZATION, format!("Bearer {}", token));
+            let body = match body {
+                Some(body) => {
+                    request = request.header(header::CONTENT_TYPE, "application/json");
+                    Body::from(body.to_string())
+                }
+                None => Body::empty(),
+            };
+            let response = app.oneshot(request.body(body).unwrap()).await.unwrap();
+            let status = response.status();
+            let body = body_to_string(response.into_body()).await;
+            (
+                status,
+                serde_json::from_str::<serde_json::Value>(&body).unwrap_or_default(),
+            )
+        }
+    };
+
+    let (_, json) = send(Method::GET, "/api/sync/changes".to_string(), None).await;
+    let cursor = json["cursor"].as_str().unwrap().to_string();
+
+    let rust_tag = uuid::Uuid::new_v4();
+    let reading = uuid::Uuid::new_v4();
+    send(
+        Method::POST,
+        "/api/tags".to_string(),
+        Some(json!({ "id": rust_tag, "name": "rust" })),
+    )
+    .await;
+    send(
+        Method::POST,
+        "/api/categories".to_string(),
+        Some(json!({ "id": reading, "name": "Reading" })),
+    )
+    .await;
+    for title in ["First rust read", "Second rust read", "Third rust read"] {
+        let (status, _) = send(
+            Method::POST,
+            "/api/bookmarks".to_string(),
+            Some(json!({
+                "url": "https://example.com",
+                "title": title,
+                "category_id": reading,
+                "tag_ids": [rust_tag]
+            })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+    }
+    send(
+        Method::POST,
+        "/api/bookmarks".to_string(),
+        Some(json!({ "url": "https://example.com/other", "title": "Untagged read", "category_id": reading })),
+    )
+    .await;
+    send(
+        Method::POST,
+        "/api/notes".to_string(),
+        Some(json!({ "title": "A rust note", "content": "Not a bookmark" })),
+    )
+    .await;
+
+    // Queries are validated when saved
+    let (status, json) = send(
+        Method::POST,
+        "/api/saved-searches".to_string(),
+        Some(json!({ "name": "Broken", "query": "is:starred" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(json["message"].as_str().unwrap().contains("is:"));
+
+    let (status, saved) = send(
+        Method::POST,
+        "/api/saved-searches".to_string(),
+        Some(json!({
+            "name": "Rust reading",
+            "query": "is:bookmark tag:rust category:Reading",
+            "sort": "created_at"
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(saved["sort"], "created_at");
+    assert_eq!(saved["order"], serde_json::Value::Null);
+    let id = saved["id"].as_str().unwrap().to_string();
+
+    let (status, _) = send(
+        Method::POST,
+        "/api/saved-searches".to_string(),
+        Some(json!({ "name": "Rust reading", "query": "rust" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // Results are paginated newest first
+    let (status, page) = send(
+        Method::GET,
+        format!("/api/saved-searches/{id}/results?limit=2"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let items = page["items"].as_array().unwrap();
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0]["title"], "Third rust read");
+    assert_eq!(items[1]["title"], "Second rust read");
+
+    let next_cursor = page["next_cursor"].as_str().unwrap();
+    let (_, page) = send(
+        Method::GET,
+        format!("/api/saved-searches/{id}/results?limit=2&cursor={next_cursor}"),
+        None,
+    )
+    .await;
+    let items = page["items"].as_array().unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["title"], "First rust read");
+    assert!(page["next_cursor"].is_null());
+
+    // Updating the definition changes the results
+    let (status, updated) = send(
+        Method::PUT,
+        format!("/api/saved-searches/{id}"),
+        Some(json!({ "query": "rust", "sort": "title", "order": "asc" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(updated["version"], 2);
+
+    let (_, page) = send(
+        Method::GET,
+        format!("/api/saved-searches/{id}/results"),
+        None,
+    )
+    .await;
+    let titles: Vec<&str> = page["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["title"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        titles,
+        [
+            "A rust note",
+            "First rust read",
+            "Second rust read",
+            "Third rust read"
+        ]
+    );
+
+    let (_, page) = send(Method::GET, "/api/saved-searches".to_string(), None).await;
+    assert_eq!(page["items"].as_array().unwrap().len(), 1);
+    assert_eq!(page["items"][0]["name"], "Rust reading");
+
+    // Saved searches sync like other entities
+    let (_, changes) = send(
+        Method::GET,
+        format!("/api/sync/changes?since={cursor}"),
+        None,
+    )
+    .await;
+    assert_eq!(changes["saved_searches"][0]["id"], id.as_str());
+
+    let (status, _) = send(Method::DELETE, format!("/api/saved-searches/{id}"), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (status, _) = send(
+        Method::GET,
+        format!("/api/saved-searches/{id}/results"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_fuzzy_search_and_suggest() {
+    let pool = get_test_pool().await.clone();
+
+    let app = create_test_app(pool.clone());
+    let register_request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/auth/register")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            json!({
+                "email": "fuzzy@example.com",
+                "password": "password123",
+                "name": "Fuzzy Search"
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let register_response = app.oneshot(register_request).await.unwrap();
+    let body = body_to_string(register_response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let token = json["token"].as_str().unwrap().to_string();
+
+    let post = |uri: &'static str, body: serde_json::Value| {
+        let app = create_test_app(pool.clone());
+        let token = token.clone();
+        async move {
+            let request = Request::builder()
+                .method(Method::POST)
+                .uri(uri)
+                .header(header::CONTENT_TYPE, "application/json")
+                .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                .body(Body::from(body.to_string()))
+                .unwrap();
+            let response = app.oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::CREATED);
+        }
+    };
+
+    post(
+        "/api/bookmarks",
+        json!({ "url": "https://www.rust-lang.org", "title": "The Rust Programming Language" }),
+    )
+    .await;
+    post(
+        "/api/bookmarks",
+        json!({ "url": "https://kubernetes.io/docs", "title": "Kubernetes documentation" }),
+    )
+    .await;
+    post(
+        "/api/bookmarks",
+        json!({ "url": "https://example.com/100", "title": "100% coverage guide" }),
+    )
+    .await;
+    post(
+        "/api/notes",
+        json!({ "title": "Programming interview prep", "content": "Practice problems" }),
+    )
+    .await;
+
+    let get = |uri: &'static str| {
+        let app = create_test_app(pool.clone());
+        let token = token.clone();
+        async move {
+            let request = Request::builder()
+                .method(Method::GET)
+                .uri(uri)
+                .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap();
+            let response = app.oneshot(request).await.unwrap();
+            let status = response.status();
+            let body = body_to_string(response.into_body()).await;
+            (
+                status,
+                serde_json::from_str::<serde_json::Value>(&body).unwrap_or_default(),
+            )
+        }
+    };
+
+    // Misspellings only match in fuzzy mode
+    let (status, json) = get("/api/search?q=progamming%20langauge").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json.as_array().unwrap().len(), 0);
+
+    let (status, json) = get("/api/search?q=progamming%20langauge&fuzzy=true").await;
+    assert_eq!(status, StatusCode::OK);
+    let results = json.as_array().unwrap();
+    assert_eq!(results[0]["title"], "The Rust Programming Language");
+
+    // URLs are matched too, and exclusions still apply
+    let (_, json) = get("/api/search?q=kubernetis&fuzzy=true").await;
+    assert_eq!(json[0]["title"], "Kubernetes documentation");
+
+    let (_, json) = get("/api/search?q=kubernetis%20-documentation&fuzzy=true").await;
+    assert_eq!(json.as_array().unwrap().len(), 0);
+
+    // Suggestions list titles starting with the prefix first
+    let (status, json) = get("/api/search/suggest?prefix=progr").await;
+    assert_eq!(status, StatusCode::OK);
+    let suggestions = json.as_array().unwrap();
+    assert_eq!(suggestions.len(), 2);
+    assert_eq!(suggestions[0]["title"], "Programming interview prep");
+    assert_eq!(suggestions[0]["entity_type"], "note");
+    assert_eq!(suggestions[1]["title"], "The Rust Programming Language");
+
+    let (_, json) = get("/api/search/suggest?prefix=rust-lang").await;
+    assert_eq!(json[0]["url"], "https://www.rust-lang.org");
+
+    // Wildcards match literally
+    let (_, json) = get("/api/search/suggest?prefix=100%25").await;
+    assert_eq!(json.as_array().unwrap().len(), 1);
+
+    let (_, json) = get("/api/search/suggest?prefix=%25").await;
+    assert_eq!(json.as_array().unwrap().len(), 1);
+
+    let (status, _) = get("/api/search/suggest?prefix=%20").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_duplicate_bookmarks() {
+    let pool = get_test_pool().await.clone();
+
+    let app = create_test_app(pool.clone());
+    let register_request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/auth/register")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            json!({
+                "email": "duplicates@example.com",
+                "password": "password123",
+                "name": "Duplicates"
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let register_response = app.oneshot(register_request).await.unwrap();
+    let body = body_to_string(register_response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let token = json["token"].as_str().unwrap().to_string();
+
+    let send = |method: Method, uri: String, body: Option<serde_json::Value>| {
+        let app = create_test_app(pool.clone());
+        let token = token.clone();
+        async move {
+            let mut request = Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(header::AUTHORIZATION, format!("Bearer {}", token));
+            let body = match body {
+                Some(body) => {
+                    request = request.header(header::CONTENT_TYPE, "application/json");
+                    Body::from(body.to_string())
+                }
+                None => Body::empty(),
+            };
+            let response = app.oneshot(request.body(body).unwrap()).await.unwrap();
+            let status = response.status();
+            let body = body_to_string(response.into_body()).await;
+            (
+                status,
+                serde_json::from_str::<serde_json::Value>(&body).unwrap_or_default(),
+            )
+        }
+    };
+
+    // Duplicates are allowed by default and share a canonical URL
+    let (status, first) = send(
+        Method::POST,
+        "/api/bookmarks".to_string(),
+        Some(json!({ "url": "https://Example.com/a?utm_source=x", "title": "First" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(first["canonical_url"], "https://example.com/a");
+    let first_id = first["id"].as_str().unwrap().to_string();
+
+    let (status, second) = send(
+        Method::POST,
+        "/api/bookmarks".to_string(),
+        Some(json!({ "url": "https://example.com/a", "title": "Second" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let second_id = second["id"].as_str().unwrap().to_string();
+
+    let (status, json) = send(
+        Method::POST,
+        "/api/bookmarks".to_string(),
+        Some(json!({
+            "url": "https://example.com:443/a#top",
+            "title": "Third",
+            "on_duplicate": "reject"
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert!(json["message"].as_str().unwrap().contains(&first_id));
+
+    let (status, groups) = send(Method::GET, "/api/bookmarks/duplicates".to_string(), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(groups.as_array().unwrap().len(), 1);
+    assert_eq!(groups[0]["canonical_url"], "https://example.com/a");
+    assert_eq!(groups[0]["bookmarks"][0]["id"], first_id.as_str());
+    assert_eq!(groups[0]["bookmarks"][1]["id"], second_id.as_str());
+
+    // Merging on create folds the new data into the oldest bookmark
+    let tag_id = uuid::Uuid::new_v4();
+    send(
+        Method::POST,
+        "/api/tags".to_string(),
+        Some(json!({ "id": tag_id, "name": "merged" })),
+    )
+    .await;
+
+    let (status, merged) = send(
+        Method::POST,
+        "/api/bookmarks".to_string(),
+        Some(json!({
+            "url": "https://example.com/a?fbclid=1",
+            "title": "Ignored",
+            "description": "From the duplicate",
+            "tag_ids": [tag_id],
+            "on_duplicate": "merge"
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(merged["id"], first_id.as_str());
+    assert_eq!(merged["title"], "First");
+    assert_eq!(merged["description"], "From the duplicate");
+    assert_eq!(merged["tags"][0]["name"], "merged");
+    assert_eq!(merged["version"], 2);
+
+    // The merge endpoint folds and deletes the sources
+    let (status, merged) = send(
+        Method::POST,
+        format!("/api/bookmarks/{second_id}/merge"),
+        Some(json!({ "source_ids": [first_id] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(merged["title"], "Second");
+    assert_eq!(merged["description"], "From the duplicate");
+    assert_eq!(merged["tags"][0]["name"], "merged");
+
+    let (status, _) = send(Method::GET, format!("/api/bookmarks/{first_id}"), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (_, groups) = send(Method::GET, "/api/bookmarks/duplicates".to_string(), None).await;
+    assert_eq!(groups.as_array().unwrap().len(), 0);
+
+    let (status, _) = send(
+        Method::POST,
+        format!("/api/bookmarks/{second_id}/merge"),
+        Some(json!({ "source_ids": [second_id] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Updates can reject a URL that is already bookmarked
+    let (_, other) = send(
+        Method::POST,
+        "/api/bookmarks".to_string(),
+        Some(json!({ "url": "https://other.example.com", "title": "Other" })),
+    )
+    .await;
+    let other_id = other["id"].as_str().unwrap().to_string();
+
+    let (status, _) = send(
+        Method::PUT,
+        format!("/api/bookmarks/{other_id}"),
+        Some(json!({ "url": "https://EXAMPLE.com/a", "on_duplicate": "reject" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, _) = send(
+        Method::PUT,
+        format!("/api/bookmarks/{other_id}"),
+        Some(json!({ "url": "https://EXAMPLE.com/a", "on_duplicate": "merge" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, updated) = send(
+        Method::PUT,
+        format!("/api/bookmarks/{other_id}"),
+        Some(json!({ "url": "https://EXAMPLE.com/a" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(updated["canonical_url"], "https://example.com/a");
+}
+
+const STUB_STORY: &str = r#"<html><head><title>Field Notes</title></head><body>
+    <nav class="menu"><a href="/">Home</a> <a href="/wombats">Wombats</a></nav>
+    <article>
+        <h1>Field Notes</h1>
+        <p>Quokkas live on a few small islands off the coast of Western Australia, and they
+           are famous for appearing to smile.</p>
+        <p>They are mostly nocturnal, resting in dense vegetation during the day and
+           feeding on grasses and leaves at night.</p>
+    </article>
+    <div class="comments"><p>Nice write-up, would love to read more about their diet soon!</p></div>
+</body></html>"#;
+
+/// Serves a few pages on loopback for the preview fetcher, returning the base URL.
+async fn spawn_preview_stub() -> String {
+    use axum::response::{Html, Redirect};
+
+    let article = r#"<html><head>
+        <title>Stub Article | Stub Site</title>
+        <meta property="og:title" content="Stub Article">
+        <meta property="og:description" content="An article served by the stub">
+        <meta property="og:image" content="/cover.png">
+        <link rel="icon" href="/icon.png">
+    </head><body><p>Hello</p></body></html>"#;
+    let oversized = format!(
+        "<html><head><!-- {} --><title>Too Late</title></head></html>",
+        "x".repeat(100 * 1024)
+    );
+
+    let stub = Router::new()
+        .route("/article", get(move || async move { Html(article) }))
+        .route("/story", get(|| async { Html(STUB_STORY) }))
+        .route("/moved", get(|| async { Redirect::to("/article") }))
+        .route("/loop", get(|| async { Redirect::to("/loop") }))
+        .route("/oversized", get(move || async move { Html(oversized) }))
+        .route(
+            "/report.pdf",
+            get(|| async { ([(header::CONTENT_TYPE, "application/pdf")], "%PDF-1.7") }),
+        );
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, stub).await.unwrap() });
+
+    format!("http://{addr}")
+}
+
+#[tokio::test]
+async fn test_bookmark_previews() {
+    let pool = get_test_pool().await.clone();
+    let base = spawn_preview_stub().await;
+
+    let app = create_test_app(pool.clone());
+    let register_request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/auth/register")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            json!({
+                "email": "previews@example.com",
+                "password": "password123",
+                "name": "Previews"
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let register_response = app.oneshot(register_request).await.unwrap();
+    let body = body_to_string(register_response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let token = json["token"].as_str().unwrap().to_string();
+
+    let send = |method: Method, uri: String, body: Option<serde_json::Value>| {
+        let app = create_test_app(pool.clone());
+        let token = token.clone();
+        async move {
+            let mut request = Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(header::AUTHORIZATION, format!("Bearer {}", token));
+            let body = match body {
+                Some(body) => {
+                    request = request.header(header::CONTENT_TYPE, "application/json");
+                    Body::from(body.to_string())
+                }
+                None => Body::empty(),
+            };
+            let response = app.oneshot(request.body(body).unwrap()).await.unwrap();
+            let status = response.status();
+            let body = body_to_string(response.into_body()).await;
+            (
+                status,
+                serde_json::from_str::<serde_json::Value>(&body).unwrap_or_default(),
+            )
+        }
+    };
+    let preview = |url: String| {
+        send(
+            Method::POST,
+            "/api/bookmarks/preview".to_string(),
+            Some(json!({ "url": url })),
+        )
+    };
+
+    // Redirects are followed and relative URLs resolved against the final page
+    let (status, json) = preview(format!("{base}/moved")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["url"], format!("{base}/article"));
+    assert_eq!(json["title"], "Stub Article");
+    assert_eq!(json["description"], "An article served by the stub");
+    assert_eq!(json["image"], format!("{base}/cover.png"));
+    assert_eq!(json["favicon"], format!("{base}/icon.png"));
+
+    // Only the first bytes of a page are read
+    let (status, json) = preview(format!("{base}/oversized")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(json["title"].is_null());
+
+    let (status, _) = preview(format!("{base}/report.pdf")).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (status, _) = preview(format!("{base}/loop")).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (status, _) = preview(format!("{base}/missing")).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (status, _) = preview("ftp://example.com/file".to_string()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // With the default policy, loopback is refused whether given as an IP or a name
+    let fetcher = test_preview_fetcher(false);
+    let port = base.rsplit(':').next().unwrap();
+    for url in [
+        format!("{base}/article"),
+        format!("http://localhost:{port}/article"),
+        "http://169.254.169.254/latest/meta-data/".to_string(),
+        "http://[::1]/".to_string(),
+    ] {
+        let result = fetcher.fetch(&url).await;
+        assert!(
+            matches!(result, Err(PreviewError::BlockedAddress(_))),
+            "{url} should be blocked, got {result:?}"
+        );
+    }
+
+    // New bookmarks wait for the background worker; a refresh fetches right away
+    let (status, bookmark) = send(
+        Method::POST,
+        "/api/bookmarks".to_string(),
+        Some(json!({ "url": format!("{base}/article"), "title": "Stub" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(bookmark["preview_status"], "pending");
+    assert!(bookmark["preview_title"].is_null());
+    let id = bookmark["id"].as_str().unwrap().to_string();
+
+    let (status, refreshed) =
+        send(Method::POST, format!("/api/bookmarks/{id}/preview"), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(refreshed["preview_status"], "ready");
+    assert_eq!(refreshed["preview_title"], "Stub Article");
+    assert_eq!(
+        refreshed["preview_description"],
+        "An article served by the stub"
+    );
+    assert_eq!(refreshed["preview_image"], format!("{base}/cover.png"));
+    assert_eq!(refreshed["favicon"], format!("{base}/icon.png"));
+    assert!(refreshed["preview_fetched_at"].is_string());
+    assert_eq!(refreshed["version"], 2);
+    assert_eq!(refreshed["title"], "Stub");
+
+    // Changing the URL discards the old preview
+    let (status, updated) = send(
+        Method::PUT,
+        format!("/api/bookmarks/{id}"),
+        Some(json!({ "url": format!("{base}/moved") })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(updated["preview_status"], "pending");
+    assert!(updated["preview_title"].is_null());
+    assert!(updated["favicon"].is_null());
+
+    let (status, _) = send(
+        Method::POST,
+        format!("/api/bookmarks/{}/preview", uuid::Uuid::new_v4()),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_bookmark_archives() {
+    let pool = get_test_pool().await.clone();
+    let base = spawn_preview_stub().await;
+
+    let app = create_test_app(pool.clone());
+    let register_request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/auth/register")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            json!({
+                "email": "archives@example.com",
+                "password": "password123",
+                "name": "Archives"
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let register_response = app.oneshot(register_request).await.unwrap();
+    let body = body_to_string(register_response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let token = json["token"].as_str().unwrap().to_string();
+
+    let send = |method: Method, uri: String, body: Option<serde_json::Value>| {
+        let app = create_test_app(pool.clone());
+        let token = token.clone();
+        async move {
+            let mut request = Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(header::AUTHORIZATION, format!("Bearer {}", token));
+            let body = match body {
+                Some(body) => {
+                    request = request.header(header::CONTENT_TYPE, "application/json");
+                    Body::from(body.to_string())
+                }
+                None => Body::empty(),
+            };
+            let response = app.oneshot(request.body(body).unwrap()).await.unwrap();
+            let status = response.status();
+            let body = body_to_string(response.into_body()).await;
+            (
+                status,
+                serde_json::from_str::<serde_json::Value>(&body).unwrap_or_default(),
+            )
+        }
+    };
+
+    let (_, bookmark) = send(
+        Method::POST,
+        "/api/bookmarks".to_string(),
+        Some(json!({ "url": format!("{base}/story"), "title": "Marsupials" })),
+    )
+    .await;
+    let id = bookmark["id"].as_str().unwrap().to_string();
+
+    let (status, _) = send(Method::GET, format!("/api/bookmarks/{id}/archive"), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Fetching the preview archives the page too
+    let (status, _) = send(Method::POST, format!("/api/bookmarks/{id}/preview"), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, archive) = send(Method::GET, format!("/api/bookmarks/{id}/archive"), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(archive["url"], format!("{base}/story"));
+    assert_eq!(archive["title"], "Field Notes");
+    assert_eq!(archive["content_type"], "text/html");
+    assert_eq!(archive["size"], STUB_STORY.len());
+    assert_eq!(archive["truncated"], false);
+    let expected_hash = {
+        use sha2::{Digest, Sha256};
+        format!("{:x}", Sha256::digest(STUB_STORY.as_bytes()))
+    };
+    assert_eq!(archive["content_hash"], expected_hash);
+    let text = archive["text"].as_str().unwrap();
+    assert!(text.starts_with("Field Notes\n\nQuokkas live on a few small islands"));
+    assert!(!text.contains("Wombats"));
+    assert!(!text.contains("Nice write-up"));
+    assert!(archive["word_count"].as_i64().unwrap() > 30);
+
+    // The raw page is served as fetched, sandboxed
+    let app = create_test_app(pool.clone());
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/bookmarks/{id}/archive/raw"))
+                .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "text/html");
+    assert_eq!(
+        response.headers()[header::CONTENT_SECURITY_POLICY],
+        "sandbox"
+    );
+    assert_eq!(body_to_string(response.into_body()).await, STUB_STORY);
+
+    // Archived text is searchable, quoted in the snippet, and honours exclusions
+    let (status, results) = send(Method::GET, "/api/search?q=nocturnal".to_string(), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let results = results.as_array().unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["id"], id.as_str());
+    assert!(
+        results[0]["snippet"]
+            .as_str()
+            .unwrap()
+            .contains("<mark>nocturnal</mark>")
+    );
+
+    let (_, results) = send(
+        Method::GET,
+        "/api/search?q=marsupials%20-quokkas".to_string(),
+        None,
+    )
+    .await;
+    assert_eq!(results.as_array().unwrap().len(), 0);
+
+    // Pages over the archive limit are cut off
+    let (_, big) = send(
+        Method::POST,
+        "/api/bookmarks".to_string(),
+        Some(json!({ "url": format!("{base}/oversized"), "title": "Big" })),
+    )
+    .await;
+    let big_id = big["id"].as_str().unwrap().to_string();
+    send(
+        Method::POST,
+        format!("/api/bookmarks/{big_id}/preview"),
+        None,
+    )
+    .await;
+    let (_, archive) = send(
+        Method::GET,
+        format!("/api/bookmarks/{big_id}/archive"),
+        None,
+    )
+    .await;
+    assert_eq!(archive["truncated"], true);
+    assert_eq!(archive["size"], 96 * 1024);
+
+    // A new URL discards the old snapshot
+    let (status, _) = send(
+        Method::PUT,
+        format!("/api/bookmarks/{id}"),
+        Some(json!({ "url": format!("{base}/article") })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(Method::GET, format!("/api/bookmarks/{id}/archive"), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (_, results) = send(Method::GET, "/api/search?q=nocturnal".to_string(), None).await;
+    assert_eq!(results.as_array().unwrap().len(), 0);
+}
+
+#[tokio::test]
+async fn test_storage_usage() {
+    let pool = get_test_pool().await.clone();
+    let base = spawn_preview_stub().await;
+
+    let app = create_test_app(pool.clone());
+    let register_request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/auth/register")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            json!({
+                "email": "storage@example.com",
+                "password": "password123",
+                "name": "Storage"
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let register_response = app.oneshot(register_request).await.unwrap();
+    let body = body_to_string(register_response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let token = json["token"].as_str().unwrap().to_string();
+
+    let send =
+        |blobs: BlobStorage, method: Method, uri: String, body: Option<serde_json::Value>| {
+            let app = create_test_app_with(pool.clone(), EventBus::new(), blobs);
+            let token = token.clone();
+            async move {
+                let mut request = Request::builder()
+                    .method(method)
+                    .uri(uri)
+                    .header(header::AUTHORIZATION, format!("Bearer {}", token));
+                let body = match body {
+                    Some(body) => {
+                        request = request.header(header::CONTENT_TYPE, "application/json");
+                        Body::from(body.to_string())
+                    }
+                    None => Body::empty(),
+                };
+                let response = app.oneshot(request.body(body).unwrap()).await.unwrap();
+                let status = response.status();
+                let body = body_to_string(response.into_body()).await;
+                (
+                    status,
+                    serde_json::from_str::<serde_json::Value>(&body).unwrap_or_default(),
+                )
+            }
+        };
+    let blobs = test_blob_storage(1024 * 1024);
+
+    let (status, usage) = send(
+        blobs.clone(),
+        Method::GET,
+        "/api/storage/usage".to_string(),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(usage["used_bytes"], 0);
+    assert_eq!(usage["blob_count"], 0);
+    assert_eq!(usage["quota_bytes"], 1024 * 1024);
+
+    // Two bookmarks archiving the same page share one blob
+    let mut ids = Vec::new();
+    for url in [format!("{base}/story"), format!("{base}/story?copy=2")] {
+        let (_, bookmark) = send(
+            blobs.clone(),
+            Method::POST,
+            "/api/bookmarks".to_string(),
+            Some(json!({ "url": url, "title": "Marsupials" })),
+        )
+        .await;
+        let id = bookmark["id"].as_str().unwrap().to_string();
+        let (status, _) = send(
+            blobs.clone(),
+            Method::POST,
+            format!("/api/bookmarks/{id}/preview"),
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        ids.push(id);
+    }
+
+    let (_, usage) = send(
+        blobs.clone(),
+        Method::GET,
+        "/api/storage/usage".to_string(),
+        None,
+    )
+    .await;
+    assert_eq!(usage["used_bytes"], STUB_STORY.len());
+    assert_eq!(usage["blob_count"], 1);
+
+    // Refreshing keeps a single reference per archive
+    send(
+        blobs.clone(),
+        Method::POST,
+        format!("/api/bookmarks/{}/preview", ids[0]),
+        None,
+    )
+    .await;
+    let (status, _) = send(
+        blobs.clone(),
+        Method::DELETE,
+        format!("/api/bookmarks/{}", ids[0]),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, usage) = send(
+        blobs.clone(),
+        Method::GET,
+        "/api/storage/usage".to_string(),
+        None,
+    )
+    .await;
+    assert_eq!(usage["used_bytes"], STUB_STORY.len());
+
+    let (status, _) = send(
+        blobs.clone(),
+        Method::DELETE,
+        format!("/api/bookmarks/{}", ids[1]),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, usage) = send(
+        blobs.clone(),
+        Method::GET,
+        "/api/storage/usage".to_string(),
+        None,
+    )
+    .await;
+    assert_eq!(usage["used_bytes"], 0);
+    assert_eq!(usage["blob_count"], 0);
+
+    // Over the quota, the preview is still saved but the page is not archived
+    let small = test_blob_storage(16);
+    let (_, bookmark) = send(
+        small.clone(),
+        Method::POST,
+        "/api/bookmarks".to_string(),
+        Some(json!({ "url": format!("{base}/story?copy=3"), "title": "Too big" })),
+    )
+    .await;
+    let id = bookmark["id"].as_str().unwrap().to_string();
+    let (status, bookmark) = send(
+        small.clone(),
+        Method::POST,
+        format!("/api/bookmarks/{id}/preview"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(bookmark["preview_status"], "ready");
+    let (status, _) = send(
+        small.clone(),
+        Method::GET,
+        format!("/api/bookmarks/{id}/archive"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (_, usage) = send(small, Method::GET, "/api/storage/usage".to_string(), None).await;
+    assert_eq!(usage["used_bytes"], 0);
+}
+
+#[tokio::test]
+async fn test_refresh_token_rotation() {
+    let pool = get_test_pool().await.clone();
+
+    let send =
+        |method: Method, uri: &str, token: Option<String>, body: Option<serde_json::Value>| {
+            let app = create_test_app(pool.clone());
+            let uri = uri.to_string();
+            async move {
+                let mut request = Request::builder().method(method).uri(uri);
+                if let Some(token) = token {
+                    request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+                }
+                let body = match body {
+                    Some(body) => {
+                        request = request.header(header::CONTENT_TYPE, "application/json");
+                        Body::from(body.to_string())
+                    }
+                    None => Body::empty(),
+                };
+                let response = app.oneshot(request.body(body).unwrap()).await.unwrap();
+                let status = response.status();
+                let body = body_to_string(response.into_body()).await;
+                (
+                    status,
+                    serde_json::from_str::<serde_json::Value>(&body).unwrap_or_default(),
+                )
+            }
+        };
+    let refresh = |refresh_token: &serde_json::Value| {
+        send(
+            Method::POST,
+            "/api/auth/refresh",
+            None,
+            Some(json!({ "refresh_token": refresh_token })),
+        )
+    };
+
+    let (status, registered) = send(
+        Method::POST,
+        "/api/auth/register",
+        None,
+        Some(json!({
+            "email": "refresh@example.com",
+            "password": "password123",
+            "name": "Refresh"
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(registered["expires_in"], 15 * 60);
+    let first = registered["refresh_token"].clone();
+    assert!(first.is_string());
+
+    // Each refresh issues a working access token and a new refresh token
+    let (status, rotated) = refresh(&first).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(rotated["expires_in"], 15 * 60);
+    let second = rotated["refresh_token"].clone();
+    assert_ne!(second, first);
+
+    let token = rotated["token"].as_str().unwrap().to_string();
+    let (status, me) = send(Method::GET, "/api/auth/me", Some(token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(me["email"], "refresh@example.com");
+
+    let (status, rotated) = refresh(&second).await;
+    assert_eq!(status, StatusCode::OK);
+    let third = rotated["refresh_token"].clone();
+
+    // Replaying a used token revokes the whole family, including its newest token
+    let (status, _) = refresh(&first).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = refresh(&third).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Other sign-ins are separate families and keep working
+    let (status, login) = send(
+        Method::POST,
+        "/api/auth/login",
+        None,
+        Some(json!({ "email": "refresh@example.com", "password": "password123" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, rotated) = refresh(&login["refresh_token"]).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = refresh(&json!("not-a-refresh-token")).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = refresh(&json!("")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Signing a device out ends its refresh tokens too
+    let device_id = login["device"]["id"].as_str().unwrap();
+    let token = login["token"].as_str().unwrap().to_string();
+    let (status, _) = send(
+        Method::DELETE,
+        &format!("/api/devices/{device_id}"),
+        Some(token),
+        None,
+    )
+    .await;
+    assert!(status.is_success());
+    let (status, _) = refresh(&rotated["refresh_token"]).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_logout_revokes_tokens() {
+    let pool = get_test_pool().await.clone();
+
+    let send =
+        |method: Method, uri: &str, token: Option<String>, body: Option<serde_json::Value>| {
+            let app = create_test_app(pool.clone());
+            let uri = uri.to_string();
+            async move {
+                let mut request = Request::builder().method(method).uri(uri);
+                if let Some(token) = token {
+                    request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+                }
+                let body = match body {
+                    Some(body) => {
+                        request = request.header(header::CONTENT_TYPE, "application/json");
+                        Body::from(body.to_string())
+                    }
+                    None => Body::empty(),
+                };
+                let response = app.oneshot(request.body(body).unwrap()).await.unwrap();
+                let status = response.status();
+                let body = body_to_string(response.into_body()).await;
+                (
+                    status,
+                    serde_json::from_str::<serde_json::Value>(&body).unwrap_or_default(),
+                )
+            }
+        };
+    let credentials = json!({ "email": "logout@example.com", "password": "password123" });
+    let token = |auth: &serde_json::Value| Some(auth["token"].as_str().unwrap().to_string());
+
+    let (status, first) = send(
+        Method::POST,
+        "/api/auth/register",
+        None,
+        Some(json!({
+            "email": "logout@example.com",
+            "password": "password123",
+            "name": "Logout"
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (_, second) = send(
+        Method::POST,
+        "/api/auth/login",
+        None,
+        Some(credentials.clone()),
+    )
+    .await;
+    let (_, third) = send(
+        Method::POST,
+        "/api/auth/login",
+        None,
+        Some(credentials.clone()),
+    )
+    .await;
+
+    let (status, _) = send(Method::POST, "/api/auth/logout", None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Logging out kills the access token and its session's refresh token at once
+    let (status, _) = send(Method::POST, "/api/auth/logout", token(&first), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send(Method::GET, "/api/auth/me", token(&first), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = send(
+        Method::POST,
+        "/api/auth/refresh",
+        None,
+        Some(json!({ "refresh_token": first["refresh_token"] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Other sessions are unaffected
+    let (status, _) = send(Method::GET, "/api/auth/me", token(&second), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Logging out everywhere ends every session
+    let (status, _) = send(Method::POST, "/api/auth/logout-all", token(&second), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    for auth in [&second, &third] {
+        let (status, _) = send(Method::GET, "/api/auth/me", token(auth), None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = send(
+            Method::POST,
+            "/api/auth/refresh",
+            None,
+            Some(json!({ "refresh_token": auth["refresh_token"] })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    // Signing in again works right away
+    let (status, fourth) = send(Method::POST, "/api/auth/login", None, Some(credentials)).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, devices) = send(Method::GET, "/api/devices", token(&fourth), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let active = devices
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|device| device["revoked_at"].is_null())
+        .count();
+    assert_eq!(active, 1);
+}
+
+#[tokio::test]
+async fn test_jwks_endpoint() {
+    let pool = get_test_pool().await.clone();
+    let app = create_test_app(pool);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/.well-known/jwks.json")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CACHE_CONTROL],
+        "public, max-age=300"
+    );
+    let body: serde_json::Value =
+        serde_json::from_str(&body_to_string(response.into_body()).await).unwrap();
+    // The test app signs with a shared secret, which is never published
+    assert_eq!(body, json!({ "keys": [] }));
+}
+
+#[tokio::test]
+async fn test_personal_access_tokens() {
+    let pool = get_test_pool().await.clone();
+
+    let send = |method: Method, uri: &str, token: &str, body: Option<serde_json::Value>| {
+        let app = create_test_app(pool.clone());
+        let uri = uri.to_string();
+        let token = token.to_string();
+        async move {
+            let mut request = Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(header::AUTHORIZATION, format!("Bearer {}", token));
+            let body = match body {
+                Some(body) => {
+                    request = request.header(header::CONTENT_TYPE, "application/json");
+                    Body::from(body.to_string())
+                }
+                None => Body::empty(),
+            };
+            let response = app.oneshot(request.body(body).unwrap()).await.unwrap();
+            let status = response.status();
+            let body = body_to_string(response.into_body()).await;
+            (
+                status,
+                serde_json::from_str::<serde_json::Value>(&body).unwrap_or_default(),
+            )
+        }
+    };
+
+    let app = create_test_app(pool.clone());
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/api/auth/register")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(
+                    json!({
+                        "email": "pat@example.com",
+                        "password": "password123",
+                        "name": "Script Owner"
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let auth: serde_json::Value =
+        serde_json::from_str(&body_to_string(response.into_body()).await).unwrap();
+    let session = auth["token"].as_str().unwrap();
+
+    // Tokens need at least one scope
+    let (status, _) = send(
+        Method::POST,
+        "/api/auth/tokens",
+        session,
+        Some(json!({ "name": "empty", "scopes": [] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, created) = send(
+        Method::POST,
+        "/api/auth/tokens",
+        session,
+        Some(json!({
+            "name": "bookmark importer",
+            "scopes": ["bookmarks:write"],
+            "expires_in_days": 30
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(created["scopes"], json!(["bookmarks:write"]));
+    assert!(created["expires_at"].is_string());
+    assert!(created["last_used_at"].is_null());
+    let secret = created["secret"].as_str().unwrap();
+    assert!(secret.starts_with("xync_pat_"));
+
+    // Granted scopes work, and writing includes reading
+    let (status, _) = send(
+        Method::POST,
+        "/api/bookmarks",
+        secret,
+        Some(json!({ "url": "https://example.com/pat", "title": "Imported" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, _) = send(Method::GET, "/api/bookmarks", secret, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, me) = send(Method::GET, "/api/auth/me", secret, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(me["email"], "pat@example.com");
+
+    // Everything else is forbidden
+    let (status, _) = send(Method::GET, "/api/notes", secret, None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send(Method::GET, "/api/search?q=imported", secret, None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send(
+        Method::GET,
+        "/api/search?q=imported&type=bookmark",
+        secret,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(
+        Method::POST,
+        "/api/batch",
+        secret,
+        Some(json!({ "operations": [
+            { "op": "create", "entity": "note", "data": { "title": "Nope", "content": "" } }
+        ] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Tokens cannot manage accounts or mint more tokens
+    let (status, _) = send(Method::GET, "/api/devices", secret, None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send(Method::GET, "/api/auth/tokens", secret, None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send(Method::POST, "/api/auth/logout", secret, None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Listing shows use but never the secret
+    let (status, tokens) = send(Method::GET, "/api/auth/tokens", session, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let tokens = tokens.as_array().unwrap();
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(tokens[0]["id"], created["id"]);
+    assert!(tokens[0]["last_used_at"].is_string());
+    assert!(tokens[0].get("secret").is_none());
+
+    // Expired tokens are rejected
+    let (_, expiring) = send(
+        Method::POST,
+        "/api/auth/tokens",
+        session,
+        Some(json!({ "name": "expiring", "scopes": ["bookmarks:read"], "expires_in_days": 1 })),
+    )
+    .await;
+    sqlx::query("UPDATE personal_access_tokens SET expires_at = NOW() WHERE id = $1")
+        .bind(uuid::Uuid::parse_str(expiring["id"].as_str().unwrap()).unwrap())
+        .execute(&pool)
+        .await
+        .unwrap();
+    let (status, _) = send(
+        Method::GET,
+        "/api/bookmarks",
+        expiring["secret"].as_str().unwrap(),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // A token created with an Idempotency-Key is not kept for replay
+    let app = create_test_app(pool.clone());
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/api/auth/tokens")
+                .header(header::CONTENT_TYPE, "application/json")
+                .header(header::AUTHORIZATION, format!("Bearer {}", session))
+                .header("Idempotency-Key", "create-token-1")
+                .body(Body::from(
+                    json!({ "name": "retried", "scopes": ["notes:read"] }).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let retried: serde_json::Value =
+        serde_json::from_str(&body_to_string(response.into_body()).await).unwrap();
+    let stored: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM idempotency_keys WHERE key = 'create-token-1' OR position($1 IN body) > 0",
+    )
+    .bind(retried["secret"].as_str().unwrap().as_bytes())
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(stored, 0);
+
+    // Revoked tokens stop working at once
+    let uri = format!("/api/auth/tokens/{}", created["id"].as_str().unwrap());
+    let (status, _) = send(Method::DELETE, &uri, session, None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send(Method::GET, "/api/bookmarks", secret, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = send(Method::DELETE, &uri, session, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_two_factor_authentication() {
+    use data_encoding::BASE32_NOPAD;
+    use xync_server::auth::totp;
+
+    let pool = get_test_pool().await.clone();
+
+    // Every request carries an Idempotency-Key, as a client that retries would send
+    let send =
+        |method: Method, uri: &str, token: Option<String>, body: Option<serde_json::Value>| {
+            let app = create_test_app(pool.clone());
+            let uri = uri.to_string();
+            async move {
+                let mut request = Request::builder()
+                    .method(method)
+                    .uri(uri)
+                    .header("Idempotency-Key", uuid::Uuid::new_v4().to_string());
+                if let Some(token) = token {
+                    request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+                }
+                let body = match body {
+                    Some(body) => {
+                        request = request.header(header::CONTENT_TYPE, "application/json");
+                        Body::from(body.to_string())
+                    }
+                    None => Body::empty(),
+                };
+                let response = app.oneshot(request.body(body).unwrap()).await.unwrap();
+                let status = response.status();
+                let body = body_to_string(response.into_body()).await;
+                (
+                    status,
+                    serde_json::from_str::<serde_json::Value>(&body).unwrap_or_default(),
+                )
+            }
+        };
+    let credentials = json!({ "email": "2fa@example.com", "password": "password123" });
+
+    let (status, auth) = send(
+        Method::POST,
+        "/api/auth/register",
+        None,
+        Some(json!({
+            "email": "2fa@example.com",
+            "password": "password123",
+            "name": "Two Factor"
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let session = Some(auth["token"].as_str().unwrap().to_string());
+
+    let (status, state) = send(Method::GET, "/api/auth/2fa", session.clone(), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(state["enabled"], false);
+
+    // Confirming needs a started setup
+    let code = json!({ "code": "123456" });
+    let (status, _) = send(
+        Method::POST,
+        "/api/auth/2fa/confirm",
+        session.clone(),
+        Some(code),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (status, enrollment) =
+        send(Method::POST, "/api/auth/2fa/setup", session.clone(), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(
+        enrollment["otpauth_uri"]
+            .as_str()
+            .unwrap()
+            .starts_with("otpauth://totp/Xync%3A2fa%40example.com?secret=")
+    );
+    let secret = BASE32_NOPAD
+        .decode(enrollment["secret"].as_str().unwrap().as_bytes())
+        .unwrap();
+
+    // Nothing changes until a valid code confirms the setup
+    let (status, _) = send(
+        Method::POST,
+        "/api/auth/2fa/confirm",
+        session.clone(),
+        Some(json!({ "code": "abcdef" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = send(
+        Method::POST,
+        "/api/auth/login",
+        None,
+        Some(credentials.clone()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let confirmed_step = totp::step_at(chrono::Utc::now().timestamp());
+    let (status, recovery) = send(
+        Method::POST,
+        "/api/auth/2fa/confirm",
+        session.clone(),
+        Some(json!({ "code": totp::code_at(&secret, confirmed_step) })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let recovery_codes: Vec<String> = recovery["recovery_codes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|code| code.as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(recovery_codes.len(), 10);
+
+    let (status, _) = send(Method::POST, "/api/auth/2fa/setup", session.clone(), None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // The password alone now only yields a challenge
+    let (status, challenge) = send(
+        Method::POST,
+        "/api/auth/login",
+        None,
+        Some(credentials.clone()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert!(challenge.get("token").is_none());
+    assert!(challenge["expires_in"].as_i64().unwrap() > 0);
+    let challenge_token = challenge["challenge_token"].clone();
+
+    let answer = |code: String| json!({ "challenge_token": challenge_token, "code": code });
+    let (status, _) = send(
+        Method::POST,
+        "/api/auth/login/2fa",
+        None,
+        Some(answer("abcdef".to_string())),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    // The code that confirmed the setup cannot be replayed
+    let (status, _) = send(
+        Method::POST,
+        "/api/auth/login/2fa",
+        None,
+        Some(answer(totp::code_at(&secret, confirmed_step))),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, signed_in) = send(
+        Method::POST,
+        "/api/auth/login/2fa",
+        None,
+        Some(answer(totp::code_at(&secret, confirmed_step + 1))),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let second_session = Some(signed_in["token"].as_str().unwrap().to_string());
+    let (status, _) = send(Method::GET, "/api/auth/me", second_session, None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Challenges work once
+    let (status, _) = send(
+        Method::POST,
+        "/api/auth/login/2fa",
+        None,
+        Some(answer(totp::code_at(&secret, confirmed_step + 1))),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Recovery codes work once, however they are typed
+    let login_with = |code: String| {
+        let send = &send;
+        let credentials = credentials.clone();
+        async move {
+            let (_, challenge) =
+                send(Method::POST, "/api/auth/login", None, Some(credentials)).await;
+            send(
+                Method::POST,
+                "/api/auth/login/2fa",
+                None,
+                Some(json!({ "challenge_token": challenge["challenge_token"], "code": code })),
+            )
+            .await
+            .0
+        }
+    };
+    assert_eq!(
+        login_with(recovery_codes[0].to_uppercase()).await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        login_with(recovery_codes[0].clone()).await,
+        StatusCode::UNAUTHORIZED
+    );
+
+    // Too many wrong codes drop the challenge
+    let (_, challenge) = send(
+        Method::POST,
+        "/api/auth/login",
+        None,
+        Some(credentials.clone()),
+    )
+    .await;
+    for _ in 0..5 {
+        let (status, _) = send(
+            Method::POST,
+            "/api/auth/login/2fa",
+            None,
+            Some(json!({ "challenge_token": challenge["challenge_token"], "code": "abcdef" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+    let (status, _) = send(
+        Method::POST,
+        "/api/auth/login/2fa",
+        None,
+        Some(json!({ "challenge_token": challenge["challenge_token"], "code": recovery_codes[1] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (_, state) = send(Method::GET, "/api/auth/2fa", session.clone(), None).await;
+    assert_eq!(state["enabled"], true);
+    assert_eq!(state["recovery_codes_remaining"], 9);
+
+    // Fresh challenges don't reset the count: ten wrong codes in all lock
+    // out every code for a while, even a valid one
+    let challenge_with = |code: String| {
+        let send = &send;
+        let credentials = credentials.clone();
+        async move {
+            let (_, challenge) =
+                send(Method::POST, "/api/auth/login", None, Some(credentials)).await;
+            send(
+                Method::POST,
+                "/api/auth/login/2fa",
+                None,
+                Some(json!({ "challenge_token": challenge["challenge_token"], "code": code })),
+            )
+            .await
+        }
+    };
+    for _ in 0..4 {
+        let (status, _) = challenge_with("000000".to_string()).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+    let (status, locked) = challenge_with(recovery_codes[2].clone()).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(locked["error"], "too_many_requests");
+
+    // Once the lockout ends, a valid code works and resets the count
+    sqlx::query(
+        "UPDATE two_factor_credentials SET locked_until = NOW() WHERE user_id = (SELECT id FROM users WHERE email = '2fa@example.com')",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    let (status, _) = challenge_with(recovery_codes[2].clone()).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Disabling requires the password and a code
+    let (status, _) = send(
+        Method::POST,
+        "/api/auth/2fa/disable",
+        session.clone(),
+        Some(json!({ "password": "wrong-password", "code": recovery_codes[1] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = send(
+        Method::POST,
+        "/api/auth/2fa/disable",
+        session.clone(),
+        Some(json!({ "password": "password123", "code": "abcdef" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = send(
+        Method::POST,
+        "/api/auth/2fa/disable",
+        session.clone(),
+        Some(json!({ "password": "password123", "code": recovery_codes[1] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (_, state) = send(Method::GET, "/api/auth/2fa", session, None).await;
+    assert_eq!(state["enabled"], false);
+    assert_eq!(state["recovery_codes_remaining"], 0);
+    let (status, auth) = send(Method::POST, "/api/auth/login", None, Some(credentials)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(auth["token"].is_string());
+
+    // Neither the secret nor the recovery codes were kept for replay
+    for credential in recovery_codes
+        .iter()
+        .map(String::as_str)
+        .chain([enrollment["secret"].as_str().unwrap()])
+    {
+        let stored: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM idempotency_keys WHERE position($1 IN body) > 0",
+        )
+        .bind(credential.as_bytes())
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(stored, 0);
+    }
+}